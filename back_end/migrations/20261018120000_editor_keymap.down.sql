@@ -0,0 +1,2 @@
+ALTER TABLE editor_settings
+DROP COLUMN IF EXISTS keymap;
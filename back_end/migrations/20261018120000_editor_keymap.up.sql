@@ -0,0 +1,2 @@
+ALTER TABLE editor_settings
+ADD COLUMN keymap JSONB NOT NULL DEFAULT '{}';
@@ -0,0 +1,10 @@
+ALTER TABLE editor_settings
+DROP COLUMN IF EXISTS font_family,
+DROP COLUMN IF EXISTS font_size,
+DROP COLUMN IF EXISTS tab_width,
+DROP COLUMN IF EXISTS insert_spaces,
+DROP COLUMN IF EXISTS soft_wrap,
+DROP COLUMN IF EXISTS line_numbers,
+DROP COLUMN IF EXISTS highlight_current_line,
+DROP COLUMN IF EXISTS show_whitespace,
+DROP COLUMN IF EXISTS minimap;
@@ -0,0 +1,10 @@
+ALTER TABLE editor_settings
+ADD COLUMN font_family TEXT NOT NULL DEFAULT 'Monospace',
+ADD COLUMN font_size REAL NOT NULL DEFAULT 14,
+ADD COLUMN tab_width INT NOT NULL DEFAULT 4 CHECK (tab_width > 0),
+ADD COLUMN insert_spaces BOOLEAN NOT NULL DEFAULT TRUE,
+ADD COLUMN soft_wrap BOOLEAN NOT NULL DEFAULT FALSE,
+ADD COLUMN line_numbers BOOLEAN NOT NULL DEFAULT TRUE,
+ADD COLUMN highlight_current_line BOOLEAN NOT NULL DEFAULT TRUE,
+ADD COLUMN show_whitespace BOOLEAN NOT NULL DEFAULT FALSE,
+ADD COLUMN minimap BOOLEAN NOT NULL DEFAULT FALSE;
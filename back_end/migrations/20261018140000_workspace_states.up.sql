@@ -0,0 +1,6 @@
+CREATE TABLE workspace_states (
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    project VARCHAR(255) NOT NULL,
+    state TEXT NOT NULL,
+    PRIMARY KEY (user_id, project)
+);
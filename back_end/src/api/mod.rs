@@ -15,6 +15,7 @@ mod project;
 mod recs;
 mod search;
 mod user;
+mod webhook;
 
 // This module (back_end/src/api/) contains all of code to create the API routes as described in the Design section.
 
@@ -26,6 +27,7 @@ pub fn api_router(state: AppState) -> Router<AppState> {
         .merge(project::project_router(state.clone()))
         .merge(comment::comment_router(state.clone()))
         .merge(recs::rec_router(state))
+        .merge(webhook::webhook_router())
         .fallback(api_not_found)
 }
 
@@ -1,27 +1,32 @@
-use std::{fs, path::PathBuf};
+use std::path::{Component, PathBuf};
 
-use anyhow::anyhow;
 use axum::{
     Extension, Json, Router,
-    extract::{Path, State, WebSocketUpgrade, ws::WebSocket},
+    body::Body,
+    extract::{Path, Query, State, WebSocketUpgrade, ws::WebSocket},
+    http::header,
     middleware,
     response::{IntoResponse as _, Response},
     routing::{get, post, put},
 };
+use bollard::query_parameters::DownloadFromContainerOptions;
+use chrono::Local;
+use futures::{StreamExt as _, stream};
 use serde::{Deserialize, Serialize};
-use tempdir::TempDir;
-use tokio::process::Command;
 use tracing::{info, instrument};
-use walkdir::WalkDir;
+use ws_messages::GitBranches;
 
 use crate::{
     AppState,
     api::{ProjectResponse, search},
     auth::{ResponseTokenExt, TokenHeaders, middleware::{AuthUser, auth_middleware, optional_auth_middleware}},
     db::{DatabaseConnector, NewProject},
-    editor::{session::EditorSessionManager, websocket::WebSocketHandler},
+    editor::{
+        session::{EditorSessionManager, SessionHandle},
+        websocket::WebSocketHandler,
+    },
     error::AppError,
-    github::{CreateRepoResponse, access_tokens::{WithTokens, update_tokens}},
+    github::{CreateRepoResponse, GithubClient, access_tokens::{Tokens, WithTokens, update_tokens}},
     lang::ProjectLang,
 };
 
@@ -29,9 +34,15 @@ pub fn project_router(state: AppState) -> Router<AppState> {
     let auth = Router::new()
         .route("/project/{username}/{repo_name}/open", get(open_project))
         .route("/project/new", post(new_project))
+        .route("/project/import", get(list_importable_repos).post(import_project))
         .route("/project/{username}/{repo_name}/remix", post(remix_project))
         .route("/project/{username}/{repo_name}", put(update_project))
         .route("/project/github_save", post(github_save_project))
+        .route("/project/github_commit", post(github_commit_project))
+        .route("/project/branches", get(list_branches).post(create_branch))
+        .route("/project/checkout", post(checkout_branch))
+        .route("/project/merge", post(merge_remote))
+        .route("/project/download", get(download))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
@@ -51,6 +62,9 @@ fn project_page_router(state: AppState) -> Router<AppState> {
         .route("/liked", get(get_liked))
         .route("/like", post(like))
         .route("/unlike", post(unlike))
+        .route("/commits", get(list_commits))
+        .route("/commits/{sha}", get(get_commit))
+        .route("/commits/{sha}/file", get(get_file_at_commit))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
@@ -263,6 +277,181 @@ async fn new_project(
     }).into_response().with_tokens(tokens))
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportableRepo {
+    name: String,
+    description: Option<String>,
+    private: bool,
+    /// The language GitHub thinks the repo is mostly written in (if it is one that projects can use)
+    lang: Option<ProjectLang>,
+    /// Whether the repo is already one of the user's projects
+    imported: bool,
+}
+
+// Lists the user's GitHub repos, so that one can be chosen to import as a project
+#[instrument(skip(db, client, access_token, refresh_token))]
+async fn list_importable_repos(
+    State(AppState { db, client, .. }): State<AppState>,
+    Extension(AuthUser {
+        github_id,
+        access_token,
+        refresh_token,
+    }): Extension<AuthUser>,
+) -> Result<Response, AppError> {
+    let user_id = db.get_user_id(github_id).await?;
+    let project_repos = db.project_repo_names(user_id).await?;
+
+    let WithTokens(repos, tokens) = client
+        .list_user_repos(&access_token, &refresh_token)
+        .await?;
+
+    let repos: Vec<_> = repos
+        .into_iter()
+        .map(|repo| ImportableRepo {
+            imported: project_repos.contains(&repo.name),
+            lang: repo.language.as_deref().and_then(ProjectLang::from_github_name),
+            name: repo.name,
+            description: repo.description,
+            private: repo.private,
+        })
+        .collect();
+
+    Ok(Json(repos).into_response().with_tokens(tokens))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportProjectBody {
+    repo_name: String,
+    /// Title of the project (the name of the repo if not given)
+    title: Option<String>,
+    /// Language of the project (worked out from the repo's files if not given)
+    lang: Option<ProjectLang>,
+    /// Whether the project.toml should be added with a pull request, rather than committed to the default branch
+    #[serde(default)]
+    pull_request: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportProjectResponse {
+    username: String,
+    repo_name: String,
+    /// The pull request which adds the project.toml, if one was opened
+    pull_request_url: Option<String>,
+}
+
+// Importing one of the user's existing GitHub repos as a project
+// Repos which weren't created by the website don't have a project.toml, so one is generated for the repo's language
+// and added to the repo, so that the project can be run and formatted in the editor
+#[instrument(skip(db, client, access, refresh))]
+async fn import_project(
+    State(AppState { db, client, .. }): State<AppState>,
+    Extension(AuthUser {
+        github_id,
+        access_token: access,
+        refresh_token: refresh,
+    }): Extension<AuthUser>,
+    Json(ImportProjectBody {
+        repo_name,
+        title,
+        lang,
+        pull_request,
+    }): Json<ImportProjectBody>,
+) -> Result<Response, AppError> {
+    if !GithubClient::valid_repo_name(&repo_name) {
+        return Err(AppError::BadRequest(format!("{repo_name} isn't a valid repo name")));
+    }
+
+    let user_id = db.get_user_id(github_id).await?;
+    let title = title
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| repo_name.clone());
+    if db.project_exists(user_id, &title).await?
+        || db.project_repo_names(user_id).await?.contains(&repo_name)
+    {
+        return Err(AppError::ProjectExists);
+    }
+
+    let username = sqlx::query_scalar!("SELECT username FROM users WHERE id = $1", user_id)
+        .fetch_one(&*db)
+        .await?;
+
+    let WithTokens(repo, mut tokens) = client
+        .get_repo(&access, &refresh, &username, &repo_name)
+        .await?;
+
+    let (access_token, refresh_token) = tokens.as_ref().map_or((&*access, &*refresh), Tokens::unencrypted);
+    let WithTokens(files, new_tokens) = client
+        .list_files(access_token, refresh_token, &username, &repo_name, &repo.default_branch)
+        .await?;
+    tokens = new_tokens.or(tokens);
+
+    let Some(lang) = lang.or_else(|| ProjectLang::detect(&files)) else {
+        return Err(AppError::BadRequest(
+            "Couldn't work out the language of the repo, choose one to import it with".to_string(),
+        ));
+    };
+
+    // the readme is shown on the project's page
+    let has_readme = files
+        .iter()
+        .any(|path| !path.contains('/') && path.to_lowercase().starts_with("readme"));
+    let readme = if has_readme {
+        let (access_token, refresh_token) = tokens.as_ref().map_or((&*access, &*refresh), Tokens::unencrypted);
+        let WithTokens(readme, new_tokens) = client
+            .get_readme(access_token, refresh_token, &username, &repo_name)
+            .await?;
+        tokens = new_tokens.or(tokens);
+
+        readme
+    } else {
+        String::new()
+    };
+
+    let mut pull_request_url = None;
+    if !files.iter().any(|path| path == ".ide/project.toml") {
+        let project_toml = lang.generate_project_toml(&files).map_err(AppError::other)?;
+
+        // a pull request can't be opened on an empty repo, as there is no commit to branch from
+        let (access_token, refresh_token) = tokens.as_ref().map_or((&*access, &*refresh), Tokens::unencrypted);
+        let WithTokens(url, new_tokens) = client
+            .add_project_toml(
+                access_token,
+                refresh_token,
+                &username,
+                &repo_name,
+                &repo.default_branch,
+                &project_toml,
+                pull_request && !files.is_empty(),
+            )
+            .await?;
+        tokens = new_tokens.or(tokens);
+        pull_request_url = url;
+    }
+
+    let new_project = NewProject {
+        title,
+        lang,
+        user_id,
+        repo_name,
+        readme,
+        public: !repo.private,
+        tags: vec![],
+    };
+    db.add_project(&new_project).await?;
+
+    Ok(Json(ImportProjectResponse {
+        username,
+        repo_name: new_project.repo_name,
+        pull_request_url,
+    })
+    .into_response()
+    .with_tokens(tokens))
+}
+
 #[instrument(skip(db, client, access_token, refresh_token))]
 async fn remix_project(
     Path((username, repo_name)): Path<(String, String)>,
@@ -399,9 +588,131 @@ async fn update_project(
     Ok(())
 }
 
+#[derive(Debug, Deserialize)]
+struct CommitsQuery {
+    /// Branch, tag or commit to list the history of (the repo's default branch if not given)
+    #[serde(rename = "ref")]
+    git_ref: Option<String>,
+    /// Only list the commits which changed this file
+    path: Option<String>,
+    /// Page of commits to list, starting from 1
+    page: Option<u32>,
+}
+
+// Lists the commits in the history of a project's GitHub repo, newest first
+#[instrument(skip(db, client, access_token, refresh_token))]
+async fn list_commits(
+    Path((username, repo_name)): Path<(String, String)>,
+    State(AppState { db, client, .. }): State<AppState>,
+    Extension(AuthUser {
+        access_token,
+        refresh_token,
+        github_id,
+    }): Extension<AuthUser>,
+    Query(CommitsQuery {
+        git_ref,
+        path,
+        page,
+    }): Query<CommitsQuery>,
+) -> Result<Response, AppError> {
+    // this fails if the project doesn't exist, or is private and belongs to someone else
+    db.get_project(&username, &repo_name, Some(github_id), false)
+        .await?;
+
+    let WithTokens(commits, tokens) = client
+        .list_commits(
+            &access_token,
+            &refresh_token,
+            &username,
+            &repo_name,
+            git_ref.as_deref(),
+            path.as_deref(),
+            page.unwrap_or(1).max(1),
+        )
+        .await?;
+
+    Ok(Json(commits).into_response().with_tokens(tokens))
+}
+
+// Gets a commit in a project's GitHub repo, with the files it changed and their diffs
+#[instrument(skip(db, client, access_token, refresh_token))]
+async fn get_commit(
+    Path((username, repo_name, sha)): Path<(String, String, String)>,
+    State(AppState { db, client, .. }): State<AppState>,
+    Extension(AuthUser {
+        access_token,
+        refresh_token,
+        github_id,
+    }): Extension<AuthUser>,
+) -> Result<Response, AppError> {
+    db.get_project(&username, &repo_name, Some(github_id), false)
+        .await?;
+    check_sha(&sha)?;
+
+    let WithTokens(commit, tokens) = client
+        .get_commit(&access_token, &refresh_token, &username, &repo_name, &sha)
+        .await?;
+
+    Ok(Json(commit).into_response().with_tokens(tokens))
+}
+
+// Checks that a commit hash from a request is hexadecimal, so that it can be put into a GitHub URL
+fn check_sha(sha: &str) -> Result<(), AppError> {
+    if sha.is_empty() || sha.len() > 40 || !sha.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(AppError::BadRequest(format!("{sha} isn't a valid commit")));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct FileQuery {
+    path: String,
+}
+
+// Gets the contents of a file in a project's GitHub repo as it was at a commit
+#[instrument(skip(db, client, access_token, refresh_token))]
+async fn get_file_at_commit(
+    Path((username, repo_name, sha)): Path<(String, String, String)>,
+    State(AppState { db, client, .. }): State<AppState>,
+    Extension(AuthUser {
+        access_token,
+        refresh_token,
+        github_id,
+    }): Extension<AuthUser>,
+    Query(FileQuery { path }): Query<FileQuery>,
+) -> Result<Response, AppError> {
+    db.get_project(&username, &repo_name, Some(github_id), false)
+        .await?;
+    check_sha(&sha)?;
+    // the path is put into the GitHub URL, so it can't have parts which would move out of the repo's contents
+    if path.split('/').any(|part| matches!(part, "" | "." | "..")) {
+        return Err(AppError::BadRequest(format!("{path} isn't a valid file path")));
+    }
+
+    let WithTokens(contents, tokens) = client
+        .get_file_at(&access_token, &refresh_token, &username, &repo_name, &path, &sha)
+        .await?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/octet-stream")],
+        contents,
+    )
+        .into_response()
+        .with_tokens(tokens))
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenQuery {
+    /// Branch, tag or commit to open the project at (the repo's default branch if not given)
+    #[serde(rename = "ref")]
+    git_ref: Option<String>,
+}
+
 #[instrument(skip(db, ws, session_mgr, access_token, refresh_token))]
 async fn open_project(
     Path((username, repo_name)): Path<(String, String)>,
+    Query(OpenQuery { git_ref }): Query<OpenQuery>,
     State(AppState {
         db, session_mgr, ..
     }): State<AppState>,
@@ -423,6 +734,7 @@ async fn open_project(
             &username,
             &repo_name,
             project.lang,
+            git_ref.as_deref(),
             &access_token,
             &refresh_token,
         )
@@ -451,6 +763,8 @@ async fn handle_editor_ws(
     handler.handle(ws).await;
 }
 
+// Saves all of the changes made in the user's editor session to the current branch of the project's GitHub repo,
+// in a single commit containing only the files which have changed
 async fn github_save_project(
     State(AppState {
         db, session_mgr, ..
@@ -460,46 +774,65 @@ async fn github_save_project(
         refresh_token,
         github_id,
     }): Extension<AuthUser>,
-) -> Result<(), AppError> {
-    println!("saving project to github");
+) -> Result<Response, AppError> {
     let user_id = db.get_user_id(github_id).await?;
-    let session_handle = session_mgr.get_active_session(user_id).unwrap();
+    let session = session_mgr
+        .get_active_session(user_id)
+        .ok_or(AppError::NotFound)?;
+    let (username, repo_name) = session_repo(&db, &session).await?;
 
-    let temp_dir = TempDir::new("ide-export").map_err(AppError::other)?;
+    // every change is staged, so that the files on GitHub are the same as the files in the editor
+    session_mgr
+        .exec(&session, EditorSessionManager::git_command(&["add", "-A"]))
+        .await?;
 
-    let container_path = PathBuf::from(EditorSessionManager::WORKSPACE_PATH).join(&session_handle.directory);
-    let status = Command::new("docker")
-        .args(["cp", &format!("{}:{}", session_handle.container_id, container_path.to_string_lossy()), "."])
-        .current_dir(&temp_dir)
-        .status()
-        .await
-        .map_err(AppError::other)?;
+    let message = format!("Save from IDE {}", Local::now().to_rfc3339());
+    let WithTokens((), tokens) = session_mgr
+        .commit_staged(user_id, &access_token, &refresh_token, &username, &repo_name, &message)
+        .await?;
 
-    if !status.success() {
-        return Err(AppError::other(anyhow!("failed to export project")));
-    }
+    Ok(().into_response().with_tokens(tokens))
+}
 
-    let fs_path = temp_dir.path().to_path_buf().join(&session_handle.directory);
+#[derive(Deserialize)]
+struct CommitRequest {
+    message: String,
+}
 
-    println!("reading files");
-    let files = WalkDir::new(&temp_dir);
-        
-    let mut file_data = vec![];
-    for file in files {
-        let file = file.map_err(AppError::other)?;
-        if !file.file_type().is_file() {
-            continue;
-        }
+// Commits the changes staged in the source control panel of the user's editor session to the project's GitHub repo
+async fn github_commit_project(
+    State(AppState {
+        db, session_mgr, ..
+    }): State<AppState>,
+    Extension(AuthUser {
+        access_token,
+        refresh_token,
+        github_id,
+    }): Extension<AuthUser>,
+    Json(CommitRequest { message }): Json<CommitRequest>,
+) -> Result<Response, AppError> {
+    if message.trim().is_empty() {
+        return Err(AppError::BadRequest("The commit message is empty".to_string()));
+    }
 
-        println!("reading file: {file:?}, path: {:?}", file.path());
-        println!("trying to strip with prefix: {}", &*fs_path.to_string_lossy());
-        let path = file.path().to_string_lossy().to_string().strip_prefix(&*fs_path.to_string_lossy()).expect("invalid path").to_string();
+    let user_id = db.get_user_id(github_id).await?;
+    let session = session_mgr
+        .get_active_session(user_id)
+        .ok_or(AppError::NotFound)?;
+    let (username, repo_name) = session_repo(&db, &session).await?;
 
-        let contents = fs::read_to_string(file.path()).map_err(AppError::other)?;
+    let WithTokens((), tokens) = session_mgr
+        .commit_staged(user_id, &access_token, &refresh_token, &username, &repo_name, &message)
+        .await?;
 
-        file_data.push((path, contents));
-    }
+    Ok(().into_response().with_tokens(tokens))
+}
 
+// Gets the owner and name of the GitHub repo for the project open in an editor session
+async fn session_repo(
+    db: &DatabaseConnector,
+    session: &SessionHandle,
+) -> Result<(String, String), AppError> {
     let project = sqlx::query!(
         r#"
         SELECT pi.username, p.repo_name
@@ -507,19 +840,200 @@ async fn github_save_project(
         INNER JOIN project_info pi ON p.id = pi.id
         WHERE p.id = $1
         "#,
-        session_handle.project_id
+        session.project_id
     )
-    .fetch_one(&*db)
+    .fetch_one(&**db)
     .await?;
 
-    println!("adding files");
-    let _ = session_mgr.client().add_multiple_files(
-        &access_token,
-        &refresh_token,
-        &project.username.unwrap(),
-        &project.repo_name,
-        file_data,
-    ).await?;
+    Ok((project.username.ok_or(AppError::NotFound)?, project.repo_name))
+}
 
-    Ok(())
-}
\ No newline at end of file
+// Lists the branches of the repo for the project open in the user's editor session
+async fn list_branches(
+    State(AppState {
+        db, session_mgr, ..
+    }): State<AppState>,
+    Extension(AuthUser {
+        access_token,
+        refresh_token,
+        github_id,
+    }): Extension<AuthUser>,
+) -> Result<Response, AppError> {
+    let user_id = db.get_user_id(github_id).await?;
+    let session = session_mgr
+        .get_active_session(user_id)
+        .ok_or(AppError::NotFound)?;
+    let (username, repo_name) = session_repo(&db, &session).await?;
+
+    let WithTokens((branches, default), tokens) = session_mgr
+        .client()
+        .list_branches(&access_token, &refresh_token, &username, &repo_name)
+        .await?;
+
+    Ok(Json(GitBranches {
+        current: session.branch,
+        default: Some(default),
+        branches,
+    })
+    .into_response()
+    .with_tokens(tokens))
+}
+
+#[derive(Deserialize)]
+struct BranchRequest {
+    name: String,
+}
+
+// Creates a branch from the commit the user's editor session is based on, and saves changes to it from then on
+async fn create_branch(
+    State(AppState {
+        db, session_mgr, ..
+    }): State<AppState>,
+    Extension(AuthUser {
+        access_token,
+        refresh_token,
+        github_id,
+    }): Extension<AuthUser>,
+    Json(BranchRequest { name }): Json<BranchRequest>,
+) -> Result<Response, AppError> {
+    let user_id = db.get_user_id(github_id).await?;
+    let session = session_mgr
+        .get_active_session(user_id)
+        .ok_or(AppError::NotFound)?;
+
+    // git checks that the name is allowed for a branch
+    session_mgr
+        .exec(&session, EditorSessionManager::git_command(&["check-ref-format", "--branch", &name]))
+        .await
+        .map_err(|_| AppError::BadRequest(format!("{name} isn't a valid branch name")))?;
+
+    let (username, repo_name) = session_repo(&db, &session).await?;
+    let WithTokens((), tokens) = session_mgr
+        .client()
+        .create_branch(
+            &access_token,
+            &refresh_token,
+            &username,
+            &repo_name,
+            &name,
+            &session.base_commit,
+        )
+        .await?;
+    session_mgr.set_branch(user_id, &name);
+
+    Ok(().into_response().with_tokens(tokens))
+}
+
+// Switches the user's editor session to another branch, replacing the project's files
+async fn checkout_branch(
+    State(AppState {
+        db, session_mgr, ..
+    }): State<AppState>,
+    Extension(AuthUser {
+        access_token,
+        refresh_token,
+        github_id,
+    }): Extension<AuthUser>,
+    Json(BranchRequest { name }): Json<BranchRequest>,
+) -> Result<Response, AppError> {
+    let user_id = db.get_user_id(github_id).await?;
+    let session = session_mgr
+        .get_active_session(user_id)
+        .ok_or(AppError::NotFound)?;
+    let (username, repo_name) = session_repo(&db, &session).await?;
+
+    let WithTokens((), tokens) = session_mgr
+        .checkout(user_id, &access_token, &refresh_token, &username, &repo_name, &name)
+        .await?;
+
+    Ok(().into_response().with_tokens(tokens))
+}
+
+// Merges (pulls) the commits made on GitHub since the user's editor session was opened (or last saved) into its files,
+// so that its changes can be saved on top of them
+async fn merge_remote(
+    State(AppState {
+        db, session_mgr, ..
+    }): State<AppState>,
+    Extension(AuthUser {
+        access_token,
+        refresh_token,
+        github_id,
+    }): Extension<AuthUser>,
+) -> Result<Response, AppError> {
+    let user_id = db.get_user_id(github_id).await?;
+    let session = session_mgr
+        .get_active_session(user_id)
+        .ok_or(AppError::NotFound)?;
+    let (username, repo_name) = session_repo(&db, &session).await?;
+
+    let WithTokens(merge, tokens) = session_mgr
+        .merge_remote(user_id, &access_token, &refresh_token, &username, &repo_name)
+        .await?;
+
+    Ok(Json(merge).into_response().with_tokens(tokens))
+}
+
+#[derive(Deserialize)]
+struct DownloadQuery {
+    /// File or directory in the editor session to download (the whole project if not given)
+    path: Option<PathBuf>,
+}
+
+// Streams a file, folder or the whole project from the user's editor session back to them as a tar archive,
+// using the container's archive API
+async fn download(
+    State(AppState {
+        db, session_mgr, ..
+    }): State<AppState>,
+    Extension(AuthUser { github_id, .. }): Extension<AuthUser>,
+    Query(DownloadQuery { path }): Query<DownloadQuery>,
+) -> Result<Response, AppError> {
+    let user_id = db.get_user_id(github_id).await?;
+    let session = session_mgr
+        .get_active_session(user_id)
+        .ok_or(AppError::NotFound)?;
+
+    // only files in the project can be downloaded
+    let project_path =
+        PathBuf::from(EditorSessionManager::WORKSPACE_PATH).join(&session.directory);
+    let path = path.unwrap_or_else(|| project_path.clone());
+    if !path.starts_with(&project_path) || path.components().any(|c| c == Component::ParentDir) {
+        return Err(AppError::Unauthorized);
+    }
+
+    let mut archive = session_mgr
+        .docker()
+        .download_from_container(
+            &session.container_id,
+            Some(DownloadFromContainerOptions {
+                path: path.to_string_lossy().into_owned(),
+            }),
+        )
+        .boxed();
+
+    // the first chunk is read before responding, so that an error (e.g. the path not existing) can be sent back
+    let first_chunk = archive
+        .next()
+        .await
+        .ok_or(AppError::NotFound)?
+        .map_err(|_| AppError::NotFound)?;
+    let body = Body::from_stream(stream::once(async { Ok(first_chunk) }).chain(archive));
+
+    let name = path
+        .file_name()
+        .map_or_else(|| "project".into(), |name| name.to_string_lossy())
+        .replace('"', "_");
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-tar".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{name}.tar\""),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
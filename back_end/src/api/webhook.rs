@@ -0,0 +1,468 @@
+// Receives webhooks from the GitHub App, to keep projects up to date with changes made to their repos on GitHub
+// (e.g. pushing commits from outside of the editor, or renaming the repo)
+
+use axum::{
+    Router,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+};
+use chrono::{DateTime, FixedOffset, Utc};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use tracing::{info, instrument, warn};
+
+use crate::{AppState, CONFIG, db::DatabaseConnector, error::AppError, github::GithubClient};
+
+pub fn webhook_router() -> Router<AppState> {
+    Router::new().route("/github/webhook", post(github_webhook))
+}
+
+/// A repo that a webhook is about
+#[derive(Debug, PartialEq, Eq)]
+struct RepoId {
+    /// GitHub ID of the user who owns the repo
+    owner_id: i32,
+    /// GitHub username of the owner
+    owner: String,
+    name: String,
+}
+
+/// A change to make to a project, because of a webhook
+#[derive(Debug, PartialEq, Eq)]
+enum ProjectUpdate {
+    /// Commits were pushed to the repo's default branch
+    Pushed {
+        repo: RepoId,
+        time: DateTime<Utc>,
+        /// Whether the README needs to be downloaded again, because it was changed (only for public repos,
+        /// as the README of a private repo can't be read without one of the owner's tokens)
+        readme_changed: bool,
+    },
+    Renamed {
+        repo: RepoId,
+        /// The repo's old name
+        from: String,
+    },
+    Deleted(RepoId),
+    SetPublic {
+        repo: RepoId,
+        public: bool,
+    },
+}
+
+#[derive(Deserialize)]
+struct Account {
+    id: i32,
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct Repository {
+    name: String,
+    private: bool,
+    owner: Account,
+    default_branch: String,
+}
+
+impl Repository {
+    fn id(&self) -> RepoId {
+        RepoId {
+            owner_id: self.owner.id,
+            owner: self.owner.login.clone(),
+            name: self.name.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PushPayload {
+    #[serde(rename = "ref")]
+    git_ref: String,
+    repository: Repository,
+    /// `None` when a branch is deleted
+    head_commit: Option<PushCommit>,
+    commits: Vec<PushCommit>,
+}
+
+#[derive(Deserialize)]
+struct PushCommit {
+    timestamp: DateTime<FixedOffset>,
+    added: Vec<String>,
+    modified: Vec<String>,
+    removed: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct RepositoryPayload {
+    action: String,
+    repository: Repository,
+    changes: Option<RepositoryChanges>,
+}
+
+#[derive(Deserialize)]
+struct RepositoryChanges {
+    repository: Option<RepositoryNameChange>,
+}
+
+#[derive(Deserialize)]
+struct RepositoryNameChange {
+    name: Option<ChangedFrom>,
+}
+
+#[derive(Deserialize)]
+struct ChangedFrom {
+    from: String,
+}
+
+/// Payload of the `installation` event (when the App is installed on an account) and `installation_repositories`
+/// event (when the repos it can access are changed)
+#[derive(Deserialize)]
+struct InstallationPayload {
+    action: String,
+    installation: Installation,
+    /// The repos the App was installed on (`installation` events)
+    #[serde(default)]
+    repositories: Vec<InstallationRepo>,
+    /// The repos the App was given access to (`installation_repositories` events)
+    #[serde(default)]
+    repositories_added: Vec<InstallationRepo>,
+}
+
+#[derive(Deserialize)]
+struct Installation {
+    account: Account,
+}
+
+/// Repos in installation events don't have their owner, as it is the account the App is installed on
+#[derive(Deserialize)]
+struct InstallationRepo {
+    name: String,
+    private: bool,
+}
+
+// Checks that a webhook was sent by GitHub, using the `X-Hub-Signature-256` header, which is `sha256=` followed by
+// the hex encoded HMAC of the body, using the webhook secret as the key
+fn verify_signature(secret: &[u8], body: &[u8], signature: &str) -> bool {
+    let Some(signature) = signature
+        .strip_prefix("sha256=")
+        .and_then(|hex| hex::decode(hex).ok())
+    else {
+        return false;
+    };
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    // this compares in constant time, so the signature can't be guessed one byte at a time
+    mac.verify_slice(&signature).is_ok()
+}
+
+// Works out what needs to change in the database for a webhook, from the type of event (`X-GitHub-Event`) and its payload
+// Events that don't affect any projects give no updates
+fn parse_event(event: &str, body: &[u8]) -> serde_json::Result<Vec<ProjectUpdate>> {
+    let updates = match event {
+        "push" => {
+            let payload: PushPayload = serde_json::from_slice(body)?;
+            let repo = &payload.repository;
+
+            // only the default branch is shown on the website
+            let Some(head_commit) = payload.head_commit else {
+                return Ok(vec![]);
+            };
+            if payload.git_ref != format!("refs/heads/{}", repo.default_branch) {
+                return Ok(vec![]);
+            }
+
+            let is_readme = |path: &String| !path.contains('/') && path.to_lowercase().starts_with("readme");
+            let readme_changed = payload.commits.iter().any(|commit| {
+                commit
+                    .added
+                    .iter()
+                    .chain(&commit.modified)
+                    .chain(&commit.removed)
+                    .any(is_readme)
+            });
+
+            vec![ProjectUpdate::Pushed {
+                repo: repo.id(),
+                time: head_commit.timestamp.to_utc(),
+                readme_changed: readme_changed && !repo.private,
+            }]
+        }
+        "repository" => {
+            let payload: RepositoryPayload = serde_json::from_slice(body)?;
+            let repo = payload.repository.id();
+
+            match payload.action.as_str() {
+                "renamed" => {
+                    let from = payload
+                        .changes
+                        .and_then(|changes| changes.repository?.name)
+                        .map(|name| name.from);
+                    match from {
+                        Some(from) => vec![ProjectUpdate::Renamed { repo, from }],
+                        None => vec![],
+                    }
+                }
+                "deleted" => vec![ProjectUpdate::Deleted(repo)],
+                "privatized" | "publicized" => vec![ProjectUpdate::SetPublic {
+                    repo,
+                    public: !payload.repository.private,
+                }],
+                _ => vec![],
+            }
+        }
+        // the repos could have changed while the App couldn't see them, so their visibility is updated
+        "installation" | "installation_repositories" => {
+            let payload: InstallationPayload = serde_json::from_slice(body)?;
+            if !matches!(payload.action.as_str(), "created" | "unsuspend" | "added") {
+                return Ok(vec![]);
+            }
+
+            let account = payload.installation.account;
+            payload
+                .repositories
+                .into_iter()
+                .chain(payload.repositories_added)
+                .map(|repo| ProjectUpdate::SetPublic {
+                    repo: RepoId {
+                        owner_id: account.id,
+                        owner: account.login.clone(),
+                        name: repo.name,
+                    },
+                    public: !repo.private,
+                })
+                .collect()
+        }
+        _ => vec![],
+    };
+
+    Ok(updates)
+}
+
+// Makes the change to the project in the database (nothing happens if the repo isn't a project)
+async fn apply_update(
+    db: &DatabaseConnector,
+    client: &GithubClient,
+    update: ProjectUpdate,
+) -> Result<(), AppError> {
+    match update {
+        ProjectUpdate::Pushed {
+            repo,
+            time,
+            readme_changed,
+        } => {
+            db.set_project_last_modified(repo.owner_id, &repo.name, time)
+                .await?;
+            if readme_changed {
+                let readme = client
+                    .get_public_readme(&repo.owner, &repo.name)
+                    .await?
+                    .unwrap_or_default();
+                db.set_project_readme(repo.owner_id, &repo.name, &readme)
+                    .await?;
+            }
+        }
+        ProjectUpdate::Renamed { repo, from } => {
+            db.rename_project_repo(repo.owner_id, &from, &repo.name)
+                .await?;
+        }
+        ProjectUpdate::Deleted(repo) => db.delete_project_repo(repo.owner_id, &repo.name).await?,
+        ProjectUpdate::SetPublic { repo, public } => {
+            db.set_project_public(repo.owner_id, &repo.name, public)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[instrument(skip(db, client, headers, body))]
+async fn github_webhook(
+    State(AppState { db, client, .. }): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, AppError> {
+    let Some(secret) = &CONFIG.github_webhook_secret else {
+        warn!("received a webhook, but GITHUB_WEBHOOK_SECRET isn't set");
+        return Err(AppError::NotFound);
+    };
+
+    let signature = headers
+        .get("X-Hub-Signature-256")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if !verify_signature(secret.as_bytes(), &body, signature) {
+        return Err(AppError::Unauthorized);
+    }
+
+    let event = headers
+        .get("X-GitHub-Event")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let updates = parse_event(event, &body)
+        .map_err(|err| AppError::BadRequest(format!("invalid {event} payload: {err}")))?;
+
+    info!("received {event} webhook with {} updates", updates.len());
+    for update in updates {
+        apply_update(&db, &client, update).await?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // payloads recorded from deliveries of the GitHub App's webhooks (trimmed to the fields that are used)
+    const PUSH: &str = include_str!("../../tests/webhooks/push.json");
+    const PUSH_OTHER_BRANCH: &str = include_str!("../../tests/webhooks/push_other_branch.json");
+    const PUSH_PRIVATE: &str = include_str!("../../tests/webhooks/push_private.json");
+    const RENAMED: &str = include_str!("../../tests/webhooks/repository_renamed.json");
+    const DELETED: &str = include_str!("../../tests/webhooks/repository_deleted.json");
+    const PRIVATIZED: &str = include_str!("../../tests/webhooks/repository_privatized.json");
+    const INSTALLATION: &str = include_str!("../../tests/webhooks/installation_created.json");
+    const INSTALLATION_REPOS: &str = include_str!("../../tests/webhooks/installation_repositories_added.json");
+
+    fn repo(name: &str) -> RepoId {
+        RepoId {
+            owner_id: 58_131_023,
+            owner: "octo-student".to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    fn sign(secret: &[u8], body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn accepts_valid_signature() {
+        let signature = sign(b"secret", PUSH.as_bytes());
+        assert!(verify_signature(b"secret", PUSH.as_bytes(), &signature));
+    }
+
+    #[test]
+    fn matches_github_example_signature() {
+        // the example from GitHub's documentation on validating webhook deliveries
+        assert!(verify_signature(
+            b"It's a Secret to Everybody",
+            b"Hello, World!",
+            "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17"
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_signatures() {
+        let signature = sign(b"secret", PUSH.as_bytes());
+        assert!(!verify_signature(b"other secret", PUSH.as_bytes(), &signature));
+        assert!(!verify_signature(b"secret", b"{}", &signature));
+        assert!(!verify_signature(b"secret", PUSH.as_bytes(), &signature[7..]));
+        assert!(!verify_signature(b"secret", PUSH.as_bytes(), "sha256=not hex"));
+        assert!(!verify_signature(b"secret", PUSH.as_bytes(), ""));
+    }
+
+    #[test]
+    fn push_to_default_branch() {
+        let updates = parse_event("push", PUSH.as_bytes()).unwrap();
+        assert_eq!(
+            updates,
+            [ProjectUpdate::Pushed {
+                repo: repo("maze-solver"),
+                time: "2026-10-12T15:41:07Z".parse().unwrap(),
+                readme_changed: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn push_to_other_branch_is_ignored() {
+        let updates = parse_event("push", PUSH_OTHER_BRANCH.as_bytes()).unwrap();
+        assert!(updates.is_empty());
+    }
+
+    #[test]
+    fn push_to_private_repo_keeps_readme() {
+        let updates = parse_event("push", PUSH_PRIVATE.as_bytes()).unwrap();
+        assert!(matches!(
+            updates[..],
+            [ProjectUpdate::Pushed {
+                readme_changed: false,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn repository_renamed() {
+        let updates = parse_event("repository", RENAMED.as_bytes()).unwrap();
+        assert_eq!(
+            updates,
+            [ProjectUpdate::Renamed {
+                repo: repo("maze-solver-nea"),
+                from: "maze-solver".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn repository_deleted() {
+        let updates = parse_event("repository", DELETED.as_bytes()).unwrap();
+        assert_eq!(updates, [ProjectUpdate::Deleted(repo("maze-solver"))]);
+    }
+
+    #[test]
+    fn repository_privatized() {
+        let updates = parse_event("repository", PRIVATIZED.as_bytes()).unwrap();
+        assert_eq!(
+            updates,
+            [ProjectUpdate::SetPublic {
+                repo: repo("maze-solver"),
+                public: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn installation_updates_visibility() {
+        let updates = parse_event("installation", INSTALLATION.as_bytes()).unwrap();
+        assert_eq!(
+            updates,
+            [
+                ProjectUpdate::SetPublic {
+                    repo: repo("maze-solver"),
+                    public: true,
+                },
+                ProjectUpdate::SetPublic {
+                    repo: repo("coursework"),
+                    public: false,
+                },
+            ]
+        );
+
+        let updates = parse_event("installation_repositories", INSTALLATION_REPOS.as_bytes()).unwrap();
+        assert_eq!(
+            updates,
+            [ProjectUpdate::SetPublic {
+                repo: repo("tetris"),
+                public: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn other_events_are_ignored() {
+        let updates = parse_event("ping", br#"{"zen": "Keep it logically awesome."}"#).unwrap();
+        assert!(updates.is_empty());
+    }
+
+    #[test]
+    fn invalid_payload_fails() {
+        assert!(parse_event("push", b"{}").is_err());
+    }
+}
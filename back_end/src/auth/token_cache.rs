@@ -94,23 +94,27 @@ impl<K: Eq + Hash + Clone, V: Clone> LruCache<K, V> {
             return;
         }
 
-        // evict LRU
-        if self.map.len() == self.capacity {
+        let node = Node {
+            key: Some(key.clone()),
+            value: Some(value),
+            prev: 0,
+            next: 0,
+        };
+
+        // evict LRU, reusing its node so that its value is dropped
+        let idx = if self.map.len() == self.capacity {
             let lru = self.nodes[self.tail].prev;
             let lru_key = self.nodes[lru].key.clone().unwrap();
 
             self.remove(lru);
             self.map.remove(&lru_key);
-        }
-
-        // create new node
-        let idx = self.nodes.len();
-        self.nodes.push(Node {
-            key: Some(key.clone()),
-            value: Some(value),
-            prev: 0,
-            next: 0,
-        });
+            self.nodes[lru] = node;
+            lru
+        } else {
+            // create new node
+            self.nodes.push(node);
+            self.nodes.len() - 1
+        };
 
         self.map.insert(key, idx);
         self.insert_to_front(idx);
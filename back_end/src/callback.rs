@@ -54,9 +54,9 @@ pub async fn github_callback(
         } else {
             (
                 None,
-                Redirect::to(&format!(
-                    "https://github.com/apps/{GITHUB_APP_SLUG}/installations/new"
-                )),
+                Redirect::to(&client.web_url(&format!(
+                    "/apps/{GITHUB_APP_SLUG}/installations/new"
+                ))),
             )
         },
     )
@@ -1,8 +1,8 @@
 use std::ops::Deref;
 
 use chrono::{DateTime, Utc};
-use sqlx::PgPool;
-use ws_messages::{ColorScheme, EditorSettings};
+use sqlx::{PgPool, types::Json};
+use ws_messages::{ColorScheme, EditorSettings, FontFamily, Keymap};
 
 use crate::{api::ProjectInfo, error::AppError, github::GithubUser, lang::ProjectLang};
 
@@ -176,13 +176,132 @@ impl DatabaseConnector {
         Ok(exists)
     }
 
+    // The following are used to keep projects in sync with their GitHub repos when webhooks are received.
+    // Projects are found by the GitHub ID of the repo's owner and the repo's name, as that is what webhooks contain
+
+    pub async fn set_project_last_modified(
+        &self,
+        owner_github_id: i32,
+        repo_name: &str,
+        time: DateTime<Utc>,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE projects p
+            SET last_modified = $3
+            FROM users u
+            WHERE p.user_id = u.id AND u.github_id = $1 AND p.repo_name = $2
+            "#,
+            owner_github_id,
+            repo_name,
+            time
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_project_readme(
+        &self,
+        owner_github_id: i32,
+        repo_name: &str,
+        readme: &str,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE projects p
+            SET readme = $3
+            FROM users u
+            WHERE p.user_id = u.id AND u.github_id = $1 AND p.repo_name = $2
+            "#,
+            owner_github_id,
+            repo_name,
+            readme
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_project_public(
+        &self,
+        owner_github_id: i32,
+        repo_name: &str,
+        public: bool,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE projects p
+            SET public = $3
+            FROM users u
+            WHERE p.user_id = u.id AND u.github_id = $1 AND p.repo_name = $2
+            "#,
+            owner_github_id,
+            repo_name,
+            public
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn rename_project_repo(
+        &self,
+        owner_github_id: i32,
+        from: &str,
+        to: &str,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE projects p
+            SET repo_name = $3
+            FROM users u
+            WHERE p.user_id = u.id AND u.github_id = $1 AND p.repo_name = $2
+            "#,
+            owner_github_id,
+            from,
+            to
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_project_repo(&self, owner_github_id: i32, repo_name: &str) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            DELETE FROM projects p
+            USING users u
+            WHERE p.user_id = u.id AND u.github_id = $1 AND p.repo_name = $2
+            "#,
+            owner_github_id,
+            repo_name
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    // the names of the GitHub repos of all of a user's projects
+    pub async fn project_repo_names(&self, user_id: i32) -> sqlx::Result<Vec<String>> {
+        sqlx::query_scalar!("SELECT repo_name FROM projects WHERE user_id = $1", user_id)
+            .fetch_all(&self.0)
+            .await
+    }
+
     pub async fn get_editor_settings(&self, user_id: i32) -> Result<EditorSettings, AppError> {
-        let settings = sqlx::query_as!(
-            EditorSettings,
+        let row = sqlx::query!(
             r#"
-            SELECT s.auto_save, c.name as color_scheme, s.format_on_save 
+            SELECT s.auto_save, c.name as "color_scheme?", s.format_on_save, s.keymap as "keymap: Json<Keymap>",
+                s.font_family, s.font_size, s.tab_width, s.insert_spaces, s.soft_wrap, s.line_numbers,
+                s.highlight_current_line, s.show_whitespace, s.minimap
             FROM editor_settings s
-            INNER JOIN color_schemes c ON s.color_scheme = c.id
+            LEFT JOIN color_schemes c ON s.color_scheme = c.id
             WHERE user_id = $1
             "#,
             user_id
@@ -190,7 +309,24 @@ impl DatabaseConnector {
         .fetch_one(&self.0)
         .await?;
 
-        Ok(settings)
+        // settings that haven't been set yet are left as their defaults
+        let default = EditorSettings::default();
+
+        Ok(EditorSettings {
+            color_scheme: row.color_scheme,
+            auto_save: row.auto_save.unwrap_or(default.auto_save),
+            format_on_save: row.format_on_save.unwrap_or(default.format_on_save),
+            keymap: row.keymap.0,
+            font_family: FontFamily::from_name(&row.font_family).unwrap_or_default(),
+            font_size: row.font_size,
+            tab_width: row.tab_width.try_into().unwrap_or(default.tab_width),
+            insert_spaces: row.insert_spaces,
+            soft_wrap: row.soft_wrap,
+            line_numbers: row.line_numbers,
+            highlight_current_line: row.highlight_current_line,
+            show_whitespace: row.show_whitespace,
+            minimap: row.minimap,
+        })
     }
 
     pub async fn update_editor_settings(
@@ -201,16 +337,28 @@ impl DatabaseConnector {
         sqlx::query!(
             r#"
             UPDATE editor_settings
-            SET auto_save = $1, format_on_save = $2, color_scheme = (
+            SET auto_save = $1, format_on_save = $2, keymap = $3, color_scheme = (
                 SELECT id
                 FROM color_schemes
-                WHERE name = $3
-            )
-            WHERE user_id = $4
+                WHERE name = $4
+            ),
+            font_family = $5, font_size = $6, tab_width = $7, insert_spaces = $8, soft_wrap = $9,
+            line_numbers = $10, highlight_current_line = $11, show_whitespace = $12, minimap = $13
+            WHERE user_id = $14
             "#,
             settings.auto_save,
             settings.format_on_save,
+            Json(&settings.keymap) as _,
             settings.color_scheme,
+            settings.font_family.name(),
+            settings.font_size,
+            settings.tab_width.max(1) as i32,
+            settings.insert_spaces,
+            settings.soft_wrap,
+            settings.line_numbers,
+            settings.highlight_current_line,
+            settings.show_whitespace,
+            settings.minimap,
             user_id
         )
         .execute(&self.0)
@@ -219,6 +367,42 @@ impl DatabaseConnector {
         Ok(())
     }
 
+    pub async fn get_workspace_state(
+        &self,
+        user_id: i32,
+        project: &str,
+    ) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar!(
+            "SELECT state FROM workspace_states WHERE user_id = $1 AND project = $2",
+            user_id,
+            project
+        )
+        .fetch_optional(&self.0)
+        .await
+    }
+
+    pub async fn save_workspace_state(
+        &self,
+        user_id: i32,
+        project: &str,
+        state: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO workspace_states (user_id, project, state)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, project) DO UPDATE SET state = EXCLUDED.state
+            "#,
+            user_id,
+            project,
+            state
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
     pub async fn get_color_schemes(&self) -> anyhow::Result<Vec<ColorScheme>> {
         let color_schemes = sqlx::query!("SELECT name, palette FROM color_schemes")
             .fetch_all(&self.0)
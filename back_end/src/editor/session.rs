@@ -3,6 +3,7 @@
 use std::{
     collections::HashMap,
     ops::{Deref, DerefMut},
+    path::PathBuf,
     sync::{Arc, RwLock},
     time::Duration,
 };
@@ -10,22 +11,25 @@ use std::{
 use anyhow::anyhow;
 use bollard::{
     Docker, body_full,
+    container::LogOutput,
+    exec::{CreateExecOptions, StartExecResults},
     query_parameters::{
         CreateContainerOptions, CreateImageOptionsBuilder, StartContainerOptions,
         StopContainerOptions, UploadToContainerOptions,
     },
-    secret::{ContainerCreateBody, HostConfig, Mount, MountTypeEnum},
+    secret::{ContainerCreateBody, ExecInspectResponse, HostConfig, Mount, MountTypeEnum},
 };
 use bytes::Bytes;
 use flate2::read::GzDecoder;
 use futures::executor::block_on;
-use futures_util::StreamExt as _;
+use futures_util::{StreamExt as _, TryStreamExt as _};
 use tokio::task::JoinHandle;
 use tracing::{debug, info, instrument, warn};
+use ws_messages::{GitFileStatus, GitMerge};
 
 use crate::{
     error::AppError,
-    github::{GithubClient, access_tokens::WithTokens},
+    github::{FileChange, GithubClient, access_tokens::{Tokens, WithTokens}},
     lang::ProjectLang,
 };
 
@@ -36,10 +40,66 @@ pub struct SessionHandle {
     pub project_id: i32,
     pub container_id: String,
     pub directory: String,
+    /// The branch the project's files are from (`None` if they are from a tag or commit instead)
+    pub branch: Option<String>,
+    /// The branch, tag or commit the project's files are from
+    pub git_ref: String,
+    /// SHA hash of the commit on GitHub that the project's files were opened from (or last saved in),
+    /// which changes are saved on top of
+    pub base_commit: String,
     // code: Option<(String, DateTime<Utc>)>,
     // path: String,
 }
 
+impl SessionHandle {
+    // The branch that changes to the project are saved to, which is an error if a tag or commit was opened instead
+    pub fn save_branch(&self) -> Result<&str, AppError> {
+        self.branch.as_deref().ok_or_else(|| {
+            AppError::other(anyhow!(
+                "The project was opened at {}, which isn't a branch. Create a branch to save your changes",
+                self.git_ref
+            ))
+        })
+    }
+}
+
+/// A file staged in a session's git repository, read from `git diff --raw`
+struct StagedFile {
+    path: String,
+    /// The file's new mode (or its old mode, if it has been deleted)
+    mode: String,
+    deleted: bool,
+}
+
+impl StagedFile {
+    // Parses the output of `git diff --raw -z`, in which each file is
+    // `:<old mode> <new mode> <old hash> <new hash> <status>\0<path>\0`
+    fn parse_raw_diff(output: &str) -> Vec<Self> {
+        let mut parts = output.split('\0');
+        let mut files = vec![];
+        while let (Some(info), Some(path)) = (parts.next(), parts.next()) {
+            let mut fields = info.trim_start_matches(':').split(' ');
+            let old_mode = fields.next().unwrap_or_default();
+            let new_mode = fields.next().unwrap_or_default();
+            let deleted = fields.nth(2) == Some("D");
+            // deleted files have a new mode of 000000, but GitHub needs a valid mode to remove them from a tree
+            let mode = if deleted { old_mode } else { new_mode };
+
+            // submodules (mode 160000) aren't files, so they can't be uploaded
+            if mode == "160000" {
+                continue;
+            }
+            files.push(Self {
+                path: path.to_string(),
+                mode: mode.to_string(),
+                deleted,
+            });
+        }
+
+        files
+    }
+}
+
 // Implements wait logic as described in 'Container Lifecycle'   
 #[derive(Debug)]
 struct WaitingHandle {
@@ -152,6 +212,7 @@ impl EditorSessionManager {
 
     // called when the server receives a HTTP request to open a new session
     // may either start a new container or re-activate an already running one, depending on the state of SessionTable 
+    // `git_ref` is the branch, tag or commit to open the project at (the repo's default branch if `None`)
     pub async fn open(
         &self,
         user_id: i32,
@@ -159,6 +220,7 @@ impl EditorSessionManager {
         username: &str,
         repo_name: &str,
         lang: ProjectLang,
+        git_ref: Option<&str>,
         access_token: &str,
         refresh_token: &str,
     ) -> Result<WithTokens<String>, AppError> {
@@ -175,8 +237,10 @@ impl EditorSessionManager {
                 SessionMode::Active => return Err(AppError::SessionConflict),
                 // If the container is for a session that is waiting to be re-opened...
                 SessionMode::Waiting(_) => {
-                    // ... and the open request was for the same project...
-                    if state.handle.project_id == project_id {
+                    // ... and the open request was for the same project (at the same ref, if one was given)...
+                    if state.handle.project_id == project_id
+                        && git_ref.is_none_or(|git_ref| git_ref == state.handle.git_ref)
+                    {
                         // ... then re-activate the session
                         reactivate = true;
                     } else {
@@ -214,6 +278,7 @@ impl EditorSessionManager {
             username,
             repo_name,
             lang,
+            git_ref,
             access_token,
             refresh_token,
         )
@@ -227,6 +292,10 @@ impl EditorSessionManager {
     }
 
     pub const WORKSPACE_PATH: &'static str = "/home/workspace";
+    /// Message of the commit the project's files are added in when a session is created
+    const INITIAL_COMMIT_MESSAGE: &'static str = "Opened from GitHub";
+    /// Directory in the workspace which a branch's files are uploaded to when switching branch
+    const CHECKOUT_PATH: &'static str = "/home/workspace/.checkout";
 
     // create a new container via the Docker API and update the session table
     #[instrument(skip(self, access_token, refresh_token))]
@@ -237,11 +306,18 @@ impl EditorSessionManager {
         username: &str,
         repo_name: &str,
         lang: ProjectLang,
+        git_ref: Option<&str>,
         access_token: &str,
         refresh_token: &str,
     ) -> Result<WithTokens<String>, AppError> {
         let image = self.get_image(lang).await?;
 
+        // find out which branch is being opened, using the default branch from the repo's metadata if none was given
+        let WithTokens(resolved, tokens) = self
+            .client
+            .resolve_ref(access_token, refresh_token, username, repo_name, git_ref)
+            .await?;
+
         // defines configuration for an anonymous mount 
         // this describes how Docker will store the filesystem of the container
         let mount = Mount {
@@ -291,10 +367,14 @@ impl EditorSessionManager {
 
         // retrieve the files for the project from the GitHub repository
         debug!("fetching files");
-        let WithTokens((dir_name, tarball), headers) = self
+        let (access, refresh) = tokens
+            .as_ref()
+            .map_or((access_token, refresh_token), Tokens::unencrypted);
+        let WithTokens((dir_name, tarball), new_tokens) = self
             .client
-            .get_project_tarball(access_token, refresh_token, username, repo_name)
+            .get_project_tarball(access, refresh, username, repo_name, &resolved.sha)
             .await?;
+        let tokens = new_tokens.or(tokens);
 
         // add the files to the container
         debug!("adding files to container");
@@ -310,20 +390,345 @@ impl EditorSessionManager {
             .await
             .map_err(AppError::other)?;
 
+        let handle = SessionHandle {
+            project_id,
+            container_id: container_id.clone(),
+            directory: format!("{dir_name}/"),
+            branch: resolved.branch,
+            git_ref: resolved.git_ref,
+            base_commit: resolved.sha,
+        };
+
+        debug!("creating git repository");
+        self.init_repo(&handle).await?;
+
         // updating session table to add a new session
         self.table.write().unwrap().insert(
             user_id,
             SessionState {
-                handle: SessionHandle {
-                    project_id,
-                    container_id: container_id.clone(),
-                    directory: format!("{dir_name}/"),
-                },
+                handle,
                 mode: SessionMode::Active,
             },
         );
 
-        Ok(WithTokens(container_id, headers))
+        Ok(WithTokens(container_id, tokens))
+    }
+
+    // Commits the project's files to a new local git repository, so that the changes made to them in the editor
+    // can be listed and staged from the source control panel
+    async fn init_repo(&self, session: &SessionHandle) -> Result<(), AppError> {
+        let script = r#"g() { git -c safe.directory='*' "$@"; }
+            g init -q && g config user.name IDE && g config user.email ide@localhost \
+            && g add -A && g commit -q --allow-empty -m "$1""#;
+        self.exec(session, vec!["sh", "-c", script, "sh", Self::INITIAL_COMMIT_MESSAGE])
+            .await?;
+
+        Ok(())
+    }
+
+    // Replaces the files in the user's session with the files on another branch of the project's repo
+    // This fails if there are changes which haven't been committed, as they would be lost
+    pub async fn checkout(
+        &self,
+        user_id: i32,
+        access_token: &str,
+        refresh_token: &str,
+        username: &str,
+        repo_name: &str,
+        branch: &str,
+    ) -> Result<WithTokens<()>, AppError> {
+        let session = self
+            .get_active_session(user_id)
+            .ok_or(AppError::NotFound)?;
+
+        let status = self
+            .exec(&session, Self::git_command(&GitFileStatus::STATUS_ARGS))
+            .await?;
+        if !status.is_empty() {
+            return Err(AppError::BadRequest(
+                "Commit your changes before switching branch".to_string(),
+            ));
+        }
+
+        let WithTokens(resolved, tokens) = self
+            .client
+            .resolve_ref(access_token, refresh_token, username, repo_name, Some(branch))
+            .await?;
+        if resolved.branch.is_none() {
+            return Err(AppError::BadRequest(format!("There is no branch named {branch}")));
+        }
+        let (access, refresh) = tokens
+            .as_ref()
+            .map_or((access_token, refresh_token), Tokens::unencrypted);
+        let WithTokens((_, tarball), new_tokens) = self
+            .client
+            .get_project_tarball(access, refresh, username, repo_name, &resolved.sha)
+            .await?;
+        let tokens = new_tokens.or(tokens);
+
+        self.upload_checkout(&session, tarball).await?;
+
+        // the files are moved into the existing project directory (rather than using the directory from the tarball),
+        // so that the paths of files open in the editor stay the same
+        let script = r#"find . -mindepth 1 -delete && cp -a "$1"/*/. . && rm -rf "$1""#;
+        self.exec(&session, vec!["sh", "-c", script, "sh", Self::CHECKOUT_PATH])
+            .await?;
+        self.init_repo(&session).await?;
+
+        self.update_session(user_id, |handle| {
+            handle.branch = resolved.branch;
+            handle.git_ref = resolved.git_ref;
+            handle.base_commit = resolved.sha;
+        });
+
+        Ok(WithTokens((), tokens))
+    }
+
+    // Extracts a tarball of the project's repo into `CHECKOUT_PATH` in the session's container
+    async fn upload_checkout(&self, session: &SessionHandle, tarball: Bytes) -> Result<(), AppError> {
+        self.exec(session, vec!["mkdir", "-p", Self::CHECKOUT_PATH])
+            .await?;
+        self.docker
+            .upload_to_container(
+                &session.container_id,
+                Some(UploadToContainerOptions {
+                    path: Self::CHECKOUT_PATH.into(),
+                    ..Default::default()
+                }),
+                body_full(tarball),
+            )
+            .await
+            .map_err(AppError::other)?;
+
+        Ok(())
+    }
+
+    // Merges the commits made on GitHub since the session's base commit into the project's files.
+    // The changes made in the session are stashed, the session's repository is moved on to the new commit on GitHub
+    // (which becomes the base commit), then the changes are applied again. Files changed both in the session and on
+    // GitHub are merged, and where the same lines were changed they are left with conflict markers to resolve
+    pub async fn merge_remote(
+        &self,
+        user_id: i32,
+        access_token: &str,
+        refresh_token: &str,
+        username: &str,
+        repo_name: &str,
+    ) -> Result<WithTokens<GitMerge>, AppError> {
+        let session = self
+            .get_active_session(user_id)
+            .ok_or(AppError::NotFound)?;
+        let branch = session.save_branch()?;
+
+        let WithTokens(resolved, tokens) = self
+            .client
+            .resolve_ref(access_token, refresh_token, username, repo_name, Some(branch))
+            .await?;
+        if resolved.sha == session.base_commit {
+            return Ok(WithTokens(GitMerge::default(), tokens));
+        }
+        let (access, refresh) = tokens
+            .as_ref()
+            .map_or((access_token, refresh_token), Tokens::unencrypted);
+        let WithTokens((_, tarball), new_tokens) = self
+            .client
+            .get_project_tarball(access, refresh, username, repo_name, &resolved.sha)
+            .await?;
+        let tokens = new_tokens.or(tokens);
+        self.upload_checkout(&session, tarball).await?;
+
+        // the files on GitHub are committed on top of the session's last commit (using a separate index, so that
+        // the working tree isn't touched), then the working tree is fast-forwarded to that commit.
+        // Every change is staged before being stashed, so that new files are merged like any other file,
+        // and the stash is put back if anything fails before it has been applied
+        let script = r#"g() { git -c safe.directory='*' "$@"; }
+            remote=$(echo "$1"/*/)
+            old=$(g rev-parse HEAD) || exit 1
+            g add -A || exit 1
+            changed=; g diff --cached --quiet HEAD || changed=1
+            [ -z "$changed" ] || g stash push -q || exit 1
+            restore() { [ -z "$changed" ] || g stash pop -q; rm -rf "$1"; exit 1; }
+            tree=$(export GIT_INDEX_FILE="$1/index"
+                g --work-tree="$remote" add -A && g --work-tree="$remote" write-tree) || restore "$1"
+            commit=$(g commit-tree "$tree" -p HEAD -m "$2") && g merge -q --ff-only "$commit" || restore "$1"
+            rm -rf "$1"
+            # applying the stash only fails with conflicts, which are left in the files
+            if [ -n "$changed" ] && ! g stash pop -q; then
+                [ -n "$(g diff --name-only --diff-filter=U)" ] && g stash drop -q \
+                    || { echo "Your changes couldn't be applied again, so they have been kept in git stash" >&2; exit 1; }
+            fi
+            g diff --name-only -z "$old" HEAD"#;
+        let message = format!("Merge {branch} from GitHub");
+        let changed = self
+            .exec(&session, vec!["sh", "-c", script, "sh", Self::CHECKOUT_PATH, &message])
+            .await?;
+        let conflicts = self
+            .exec(&session, Self::git_command(&["diff", "--name-only", "-z", "--diff-filter=U"]))
+            .await?;
+
+        self.update_session(user_id, |handle| handle.base_commit = resolved.sha);
+
+        let paths = |output: &[u8]| -> Vec<PathBuf> {
+            String::from_utf8_lossy(output)
+                .split_terminator('\0')
+                .map(PathBuf::from)
+                .collect()
+        };
+        Ok(WithTokens(
+            GitMerge {
+                changed: paths(&changed),
+                conflicts: paths(&conflicts),
+            },
+            tokens,
+        ))
+    }
+
+    // Commits the changes staged in a session's git repository, then commits them to its branch of the project's
+    // GitHub repo on top of the commit the session is based on
+    // Only the changed files are uploaded, keeping their modes (e.g. whether they are executable)
+    pub async fn commit_staged(
+        &self,
+        user_id: i32,
+        access_token: &str,
+        refresh_token: &str,
+        username: &str,
+        repo_name: &str,
+        message: &str,
+    ) -> Result<WithTokens<()>, AppError> {
+        let session = self
+            .get_active_session(user_id)
+            .ok_or(AppError::NotFound)?;
+        let branch = session.save_branch()?;
+
+        let diff = self
+            .exec(
+                &session,
+                Self::git_command(&["diff", "--cached", "--raw", "-z", "--no-renames", "HEAD"]),
+            )
+            .await?;
+        let mut changes = vec![];
+        for file in StagedFile::parse_raw_diff(&String::from_utf8_lossy(&diff)) {
+            let contents = if file.deleted {
+                None
+            } else {
+                // the staged contents are committed, which may not be the same as the file in the working tree
+                let staged_path = format!(":{}", file.path);
+                Some(self.exec(&session, Self::git_command(&["show", &staged_path])).await?)
+            };
+            changes.push(FileChange {
+                path: file.path,
+                mode: file.mode,
+                contents,
+            });
+        }
+        if changes.is_empty() {
+            return Err(AppError::BadRequest("There are no changes to commit".to_string()));
+        }
+
+        // the local commit is made first, so that GitHub can't end up with a commit the session's repository doesn't have
+        self.exec(&session, Self::git_command(&["commit", "-q", "-m", message]))
+            .await?;
+
+        let result = self
+            .client
+            .commit_changes(
+                access_token,
+                refresh_token,
+                username,
+                repo_name,
+                branch,
+                &session.base_commit,
+                message,
+                changes,
+            )
+            .await;
+        let WithTokens(commit_sha, tokens) = match result {
+            Ok(commit) => commit,
+            Err(err) => {
+                // the local commit is undone, keeping its changes staged so that they can be committed again
+                if let Err(reset_err) = self
+                    .exec(&session, Self::git_command(&["reset", "-q", "--soft", "HEAD~1"]))
+                    .await
+                {
+                    warn!("failed to undo local commit: {reset_err:?}");
+                }
+                return Err(err);
+            }
+        };
+        self.update_session(user_id, |handle| handle.base_commit = commit_sha);
+
+        Ok(WithTokens((), tokens))
+    }
+
+    // Records that the files in the user's session are now on the given branch (which starts at the same commit)
+    pub fn set_branch(&self, user_id: i32, branch: &str) {
+        self.update_session(user_id, |handle| {
+            handle.branch = Some(branch.to_string());
+            handle.git_ref = branch.to_string();
+        });
+    }
+
+    fn update_session(&self, user_id: i32, update: impl FnOnce(&mut SessionHandle)) {
+        if let Some(state) = self.table.write().unwrap().get_mut(&user_id) {
+            update(&mut state.handle);
+        }
+    }
+
+    // Builds a command which runs git with the given arguments, trusting the repository whoever owns its files
+    pub fn git_command<'a>(args: &[&'a str]) -> Vec<&'a str> {
+        [["git", "-c", "safe.directory=*"].as_slice(), args].concat()
+    }
+
+    // Runs a command in a session's project directory until it finishes, returning what it wrote to stdout.
+    // If the command fails, the error contains what it wrote to stderr
+    pub async fn exec(&self, session: &SessionHandle, cmd: Vec<&str>) -> Result<Vec<u8>, AppError> {
+        let msg = self
+            .docker
+            .create_exec(
+                &session.container_id,
+                CreateExecOptions::<String> {
+                    cmd: Some(cmd.into_iter().map(String::from).collect()),
+                    working_dir: Some(format!("{}/{}", Self::WORKSPACE_PATH, session.directory)),
+                    attach_stdout: Some(true),
+                    attach_stderr: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await
+            .map_err(AppError::other)?;
+
+        let StartExecResults::Attached { mut output, .. } = self
+            .docker
+            .start_exec(&msg.id, None)
+            .await
+            .map_err(AppError::other)?
+        else {
+            unreachable!()
+        };
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        while let Some(chunk) = output.try_next().await.map_err(AppError::other)? {
+            match chunk {
+                LogOutput::StdOut { message } => stdout.extend_from_slice(&message),
+                LogOutput::StdErr { message } => stderr.extend_from_slice(&message),
+                _ => {}
+            }
+        }
+
+        let ExecInspectResponse { exit_code, .. } = self
+            .docker
+            .inspect_exec(&msg.id)
+            .await
+            .map_err(AppError::other)?;
+        if exit_code != Some(0) {
+            return Err(AppError::other(anyhow!(
+                "{}",
+                String::from_utf8_lossy(&stderr).trim()
+            )));
+        }
+
+        Ok(stdout)
     }
 
     // pub fn get_tarball_dir(tar_gz: &Bytes) -> Result<String, AppError> {
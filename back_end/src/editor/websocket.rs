@@ -1,21 +1,38 @@
 use std::{
+    collections::{HashMap, HashSet},
     io,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
+    pin::Pin,
+    time::SystemTime,
 };
 
+use anyhow::anyhow;
 use async_tar::Archive;
 use axum::extract::ws::{Message, WebSocket};
 use base64::{Engine as _, prelude::BASE64_STANDARD};
 use bollard::{
+    body_full,
+    container::LogOutput,
     exec::{CreateExecOptions, StartExecResults},
+    query_parameters::UploadToContainerOptions,
     secret::ExecInspectResponse,
 };
-use futures::{AsyncReadExt as _, StreamExt as _, TryStreamExt as _};
+use bytes::Bytes;
+use futures::{AsyncReadExt as _, StreamExt as _, TryStreamExt as _, stream::BoxStream};
+use regex::Regex;
+use regex_syntax::{
+    ParserBuilder,
+    hir::literal::{ExtractKind, Extractor, Seq},
+};
 use serde::Serialize;
-use tokio::io::AsyncWriteExt as _;
+use tokio::io::{AsyncWrite, AsyncWriteExt as _};
 use tokio_util::{compat::TokioAsyncReadCompatExt, io::StreamReader};
 use tracing::{info, warn};
-use ws_messages::{ClientMessage, Command, EditorSettings, ProjectTree, Response, ServerMessage};
+use uuid::Uuid;
+use ws_messages::{
+    ClientMessage, Command, EditorSettings, FormatInput, GitDiff, GitFileStatus, ProjectTree,
+    ReplaceChange, Response, SearchMatch, ServerMessage, UploadFile, search,
+};
 
 use crate::{DatabaseConnector, auth::crypto::Aes256Gcm, editor::session::EditorSessionManager};
 
@@ -27,6 +44,24 @@ pub struct WebSocketHandler {
     user_id: i32,
     running_pid: Option<i64>,
     project_dir: Option<String>,
+    /// Whether the client has polled for changed files yet
+    polled_changes: bool,
+}
+
+// Options for searching (and replacing) in a project, borrowed from a `Command::Search` or `Command::Replace`
+struct SearchQuery<'a> {
+    pattern: &'a str,
+    regex: bool,
+    case_sensitive: bool,
+    whole_word: bool,
+    glob: Option<&'a str>,
+}
+
+// Output of a command that has finished running in the container
+struct ExecOutput {
+    stdout: String,
+    stderr: String,
+    exit_code: Option<i64>,
 }
 
 impl WebSocketHandler {
@@ -43,6 +78,7 @@ impl WebSocketHandler {
             user_id,
             running_pid: None,
             project_dir: None,
+            polled_changes: false,
         }
     }
 
@@ -52,7 +88,7 @@ impl WebSocketHandler {
             match recv {
                 // if binary message received (as expected), try to execute the command and return a response message
                 Ok(Message::Binary(msg)) => {
-                    let msg = match self.create_response(&msg).await {
+                    let msg = match self.create_response(&msg, &mut ws).await {
                         Ok(msg) => msg,
                         Err(err) => {
                             eprintln!("failed to execute command on websocket: {err:#?}");
@@ -60,13 +96,7 @@ impl WebSocketHandler {
                         }
                     };
 
-                    let _ = ws
-                        .send(Message::Binary(
-                            msg.encode()
-                                .expect("failed to the encode the ws message")
-                                .into(),
-                        ))
-                        .await;
+                    let _ = Self::send(&mut ws, &msg).await;
                 }
                 // plaintext messages should not be received over the websocket
                 Ok(Message::Text(_)) => warn!("received text on websocket"),
@@ -82,16 +112,31 @@ impl WebSocketHandler {
         }
     }
 
-    async fn create_response(&mut self, msg: &[u8]) -> anyhow::Result<ServerMessage> {
+    async fn send(ws: &mut WebSocket, msg: &ServerMessage) -> Result<(), axum::Error> {
+        ws.send(Message::Binary(
+            msg.encode()
+                .expect("failed to the encode the ws message")
+                .into(),
+        ))
+        .await
+    }
+
+    async fn create_response(
+        &mut self,
+        msg: &[u8],
+        ws: &mut WebSocket,
+    ) -> anyhow::Result<ServerMessage> {
         let ClientMessage { id, cmd } = ClientMessage::decode(msg)?;
 
-        let resp = self.execute_cmd(cmd).await?;
+        let resp = self.execute_cmd(id, cmd, ws).await?;
 
         Ok(ServerMessage { id, resp })
     }
 
+    // The websocket is passed in so that commands can send back multiple responses (e.g. search results),
+    // before returning the final response
     #[rustfmt::skip]
-    async fn execute_cmd(&mut self, cmd: Command) -> anyhow::Result<Response> {
+    async fn execute_cmd(&mut self, id: Uuid, cmd: Command, ws: &mut WebSocket) -> anyhow::Result<Response> {
         println!("executing command: {cmd:?}");
         Ok(match cmd {
             Command::OpenProject                    => self.open_project().await?,
@@ -100,12 +145,33 @@ impl WebSocketHandler {
             Command::ColorSchemes                   => self.color_schemes().await?,
             Command::Run { command }                => self.run(&command).await?,
             Command::ReadFile { path }              => self.read_file(&path).await?,
+            Command::ReadBytes { path }             => self.read_bytes(&path).await?,
             Command::ReadDir { path }               => self.read_dir(&path).await?,
+            Command::ListFiles { ignore }           => self.list_files(ignore).await?,
             Command::WriteFile { path, contents }   => self.write_file(&path, &contents).await?,
-            Command::Format { command }             => self.format(&command).await?,
+            Command::Format { command, input }      => self.format(&command, input).await?,
             Command::Rename { from, to }            => self.rename(&from, &to).await?,
+            Command::NewFile { path }               => self.new_file(&path).await?,
+            Command::NewFolder { path }             => self.new_folder(&path).await?,
+            Command::Copy { from, to }              => self.copy(&from, &to).await?,
+            Command::Upload { dir, files }          => self.upload(&dir, files).await?,
             Command::Delete { path }                => self.delete(&path).await?,
             Command::StopRunning                    => self.stop_running().await?,
+            Command::PollChanges                    => self.poll_changes().await?,
+            Command::SaveWorkspace { state }        => self.save_workspace(&state).await?,
+            Command::LoadWorkspace                  => self.load_workspace().await?,
+            Command::GitStatus                      => self.git_status().await?,
+            Command::GitDiff { path }               => self.git_diff(&path).await?,
+            Command::GitStage { paths }             => self.git_stage(&paths).await?,
+            Command::GitUnstage { paths }           => self.git_unstage(&paths).await?,
+            Command::Search { pattern, regex, case_sensitive, whole_word, glob, .. } => {
+                let query = SearchQuery { pattern: &pattern, regex, case_sensitive, whole_word, glob: glob.as_deref() };
+                self.search(id, ws, &query).await?
+            }
+            Command::Replace { pattern, replacement, regex, case_sensitive, whole_word, glob, selected } => {
+                let query = SearchQuery { pattern: &pattern, regex, case_sensitive, whole_word, glob: glob.as_deref() };
+                self.replace(&query, &replacement, selected).await?
+            }
         })
     }
 
@@ -158,14 +224,151 @@ impl WebSocketHandler {
         let ExecInspectResponse { pid, .. } =
             self.session_mgr.docker().inspect_exec(&msg.id).await?;
 
+        // the output is read while stdin is written, as the command may fill its output pipe
+        // (and wait for it to be read) before it has read all of stdin
+        let (lines, ()): (Vec<String>, ()) = tokio::try_join!(
+            output.map_ok(|o| o.to_string()).try_collect(),
+            Self::write_stdin(&mut input, stdin),
+        )?;
+
+        Ok((lines.join("\n"), pid.unwrap()))
+    }
+
+    // Writes the input to a command's stdin, then closes it so that the command knows there is no more input
+    async fn write_stdin(
+        input: &mut Pin<Box<dyn AsyncWrite + Send>>,
+        stdin: Option<String>,
+    ) -> Result<(), bollard::errors::Error> {
         if let Some(stdin) = stdin {
             input.write_all(stdin.as_bytes()).await?;
             input.shutdown().await?;
         }
 
-        let lines: Vec<String> = output.map_ok(|o| o.to_string()).try_collect().await?;
+        Ok(())
+    }
 
-        Ok((lines.join("\n"), pid.unwrap()))
+    // Runs a command in the project directory until it finishes,
+    // keeping stdout and stderr separate so that the exit code can be checked for errors
+    async fn exec_docker_output<T>(
+        &self,
+        cmd: Vec<T>,
+        stdin: Option<String>,
+    ) -> Result<ExecOutput, bollard::errors::Error>
+    where
+        T: Into<String> + Default + Serialize,
+    {
+        let cmd = cmd.into_iter().map(Into::into).collect();
+        let msg = self
+            .session_mgr
+            .docker()
+            .create_exec(
+                &self.container_id,
+                CreateExecOptions::<String> {
+                    cmd: Some(cmd),
+                    working_dir: Some(format!(
+                        "{}/{}",
+                        EditorSessionManager::WORKSPACE_PATH,
+                        self.project_dir.as_ref().map_or("", |p| p),
+                    )),
+                    attach_stdin: Some(stdin.is_some()),
+                    attach_stdout: Some(true),
+                    attach_stderr: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        let StartExecResults::Attached { mut input, mut output } =
+            self.session_mgr.docker().start_exec(&msg.id, None).await?
+        else {
+            unreachable!()
+        };
+
+        // the output is read while stdin is written, as the command may fill its output pipe
+        // (and wait for it to be read) before it has read all of stdin
+        let read_output = async {
+            let mut stdout = Vec::new();
+            let mut stderr = Vec::new();
+            while let Some(chunk) = output.try_next().await? {
+                match chunk {
+                    LogOutput::StdOut { message } => stdout.extend_from_slice(&message),
+                    LogOutput::StdErr { message } => stderr.extend_from_slice(&message),
+                    _ => {}
+                }
+            }
+            Ok((stdout, stderr))
+        };
+        let ((stdout, stderr), ()) =
+            tokio::try_join!(read_output, Self::write_stdin(&mut input, stdin))?;
+
+        // the exit code is only available once the output has finished
+        let ExecInspectResponse { exit_code, .. } =
+            self.session_mgr.docker().inspect_exec(&msg.id).await?;
+
+        Ok(ExecOutput {
+            stdout: String::from_utf8_lossy(&stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&stderr).into_owned(),
+            exit_code,
+        })
+    }
+
+    // Starts executing a command in the container, and returns its output as it is produced
+    async fn exec_docker_stream<T>(
+        &self,
+        cmd: Vec<T>,
+    ) -> Result<BoxStream<'static, Result<Bytes, bollard::errors::Error>>, bollard::errors::Error>
+    where
+        T: Into<String> + Default + Serialize,
+    {
+        let cmd = cmd.into_iter().map(Into::into).collect();
+        let msg = self
+            .session_mgr
+            .docker()
+            .create_exec(
+                &self.container_id,
+                CreateExecOptions::<String> {
+                    cmd: Some(cmd),
+                    attach_stdout: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        let StartExecResults::Attached { output, .. } =
+            self.session_mgr.docker().start_exec(&msg.id, None).await?
+        else {
+            unreachable!()
+        };
+
+        Ok(output.map_ok(LogOutput::into_bytes).boxed())
+    }
+
+    // Reads the entire output of a command (unlike `exec_docker`, the output isn't split into lines)
+    async fn exec_docker_bytes<T>(&self, cmd: Vec<T>) -> Result<Vec<u8>, bollard::errors::Error>
+    where
+        T: Into<String> + Default + Serialize,
+    {
+        self.exec_docker_stream(cmd)
+            .await?
+            .try_fold(Vec::new(), |mut output, chunk| async move {
+                output.extend_from_slice(&chunk);
+                Ok(output)
+            })
+            .await
+    }
+
+    // Path to the project directory inside of the container
+    fn project_path(&self) -> anyhow::Result<String> {
+        let project_dir = self
+            .project_dir
+            .as_ref()
+            .ok_or_else(|| anyhow!("no project open"))?;
+
+        Ok(format!(
+            "{}/{}",
+            EditorSessionManager::WORKSPACE_PATH,
+            project_dir.trim_end_matches('/')
+        ))
     }
 
     async fn open_project(&mut self) -> anyhow::Result<Response> {
@@ -241,6 +444,23 @@ impl WebSocketHandler {
         Ok(Response::Success)
     }
 
+    // the workspace state is stored for each of the user's projects, by the name of the project's directory
+    async fn save_workspace(&self, state: &str) -> anyhow::Result<Response> {
+        let project = self.project_dir.as_deref().ok_or(anyhow!("no project open"))?;
+        self.db
+            .save_workspace_state(self.user_id, project, state)
+            .await?;
+
+        Ok(Response::Success)
+    }
+
+    async fn load_workspace(&self) -> anyhow::Result<Response> {
+        let project = self.project_dir.as_deref().ok_or(anyhow!("no project open"))?;
+        let state = self.db.get_workspace_state(self.user_id, project).await?;
+
+        Ok(Response::Workspace { state })
+    }
+
     async fn read_settings(&self) -> Result<Response, bollard::errors::Error> {
         let (contents, _) = self
             .exec_docker_with(vec!["cat", ".ide/project.toml"], None, true)
@@ -266,11 +486,22 @@ impl WebSocketHandler {
     }
 
     async fn read_file(&self, path: &Path) -> Result<Response, bollard::errors::Error> {
+        // the output is read as bytes so that the file's line endings are kept exactly
         let contents = self
-            .exec_docker(vec!["cat", &path.to_string_lossy()])
+            .exec_docker_bytes(vec!["cat", &path.to_string_lossy()])
             .await?;
 
-        Ok(Response::FileContents { contents })
+        Ok(Response::FileContents {
+            contents: String::from_utf8_lossy(&contents).into_owned(),
+        })
+    }
+
+    async fn read_bytes(&self, path: &Path) -> Result<Response, bollard::errors::Error> {
+        let contents = self
+            .exec_docker_bytes(vec!["cat", &path.to_string_lossy()])
+            .await?;
+
+        Ok(Response::FileBytes { contents })
     }
 
     async fn read_dir(&self, path: &Path) -> Result<Response, bollard::errors::Error> {
@@ -282,11 +513,73 @@ impl WebSocketHandler {
             ])
             .await?;
 
-        let contents_paths = contents.lines().map(PathBuf::from).collect();
+        // `ls` only lists the names of the entries, but the editor needs their full paths
+        let contents_paths = contents.lines().map(|name| path.join(name)).collect();
 
         Ok(Response::DirContents { contents_paths })
     }
 
+    // Lists every file in the project, apart from those in the `.git` directory, those ignored by a `.gitignore`
+    // and those matching one of the `ignore` globs (the same files the desktop editor skips).
+    // `git check-ignore` decides which files are ignored, with the globs given to it as an extra ignore file
+    async fn list_files(&self, ignore: Vec<String>) -> anyhow::Result<Response> {
+        let project = self.project_path()?;
+        let script = r#"p=$1; shift
+            ignore=$(mktemp) || exit 1
+            printf '%s\n' "$@" > "$ignore"
+            find "$p" -path "$p/.git" -prune -o -type f -print0 |
+                git -C "$p" -c safe.directory='*' -c core.excludesFile="$ignore" \
+                    check-ignore --no-index --stdin -z -n -v
+            status=$?
+            rm -f "$ignore"
+            # check-ignore exits with 1 if none of the files are ignored
+            [ "$status" -le 1 ]"#;
+
+        let mut cmd = vec!["sh".to_string(), "-c".into(), script.into(), "sh".into(), project];
+        cmd.extend(ignore);
+        let output = self.exec_docker_output(cmd, None).await?;
+        if output.exit_code != Some(0) {
+            return Err(anyhow!("failed to list files: {}", output.stderr));
+        }
+
+        // each file is listed as its source, line number and pattern (which are empty if nothing matched), then its path
+        let fields: Vec<_> = output.stdout.split('\0').collect();
+        let paths = fields
+            .chunks_exact(4)
+            .filter(|file| file[2].is_empty() || file[2].starts_with('!'))
+            .map(|file| PathBuf::from(file[3]))
+            .collect();
+
+        Ok(Response::FileList { paths })
+    }
+
+    // Lists the files in the project that have been modified since the last time this was called.
+    // Nothing is listed the first time, as the client has only just read the files
+    async fn poll_changes(&mut self) -> anyhow::Result<Response> {
+        let project = self.project_path()?;
+        // each poll touches a marker file, and the next poll lists the files modified after it
+        // (`-newer` is used as BusyBox `find` in the Alpine images doesn't support `-newermt`).
+        // BusyBox only compares whole seconds, so the marker is dated a second early to avoid missing changes
+        // made in the same second, which means a file can be listed by two polls in a row
+        let script = r#"m=/tmp/.ide-changes-marker
+            touch -d "@$(($(date +%s) - 1))" "$m.new" || exit 1
+            if [ "$2" = 1 ] && [ -f "$m" ]; then find "$1" -type f -newer "$m" -not -path '*/.git/*'; fi
+            mv "$m.new" "$m""#;
+        let polled = if self.polled_changes { "1" } else { "0" };
+
+        let output = self
+            .exec_docker_output(vec!["sh", "-c", script, "sh", project.as_str(), polled], None)
+            .await?;
+        if output.exit_code != Some(0) {
+            return Err(anyhow!("failed to check for changed files: {}", output.stderr));
+        }
+
+        self.polled_changes = true;
+        let paths = output.stdout.lines().map(PathBuf::from).collect();
+
+        Ok(Response::ChangedFiles { paths })
+    }
+
     async fn write_file(
         &self,
         path: &Path,
@@ -301,11 +594,32 @@ impl WebSocketHandler {
         .map(|_| Response::Success)
     }
 
-    async fn format(&self, command: &str) -> Result<Response, bollard::errors::Error> {
-        self.exec_docker_with(vec!["sh", "-c", command], None, true)
+    // Runs a formatter in the project directory. If `input` is given, its contents are formatted
+    // by passing them on stdin, and the formatted contents are sent back instead of being written to a file
+    async fn format(
+        &self,
+        command: &str,
+        input: Option<FormatInput>,
+    ) -> Result<Response, bollard::errors::Error> {
+        let formatting_stdin = input.is_some();
+        let output = self
+            .exec_docker_output(vec!["sh", "-c", command], input.map(|input| input.contents))
             .await?;
 
-        Ok(Response::Success)
+        // formatters print the reason they failed (e.g. a syntax error) to stderr
+        if output.exit_code != Some(0) {
+            return Ok(Response::Error {
+                msg: format!("`{command}` failed to format:\n{}", output.stderr),
+            });
+        }
+
+        Ok(if formatting_stdin {
+            Response::Formatted {
+                contents: output.stdout,
+            }
+        } else {
+            Response::Success
+        })
     }
 
     async fn rename(&self, from: &Path, to: &Path) -> Result<Response, bollard::errors::Error> {
@@ -314,6 +628,173 @@ impl WebSocketHandler {
             .map(|_| Response::Success)
     }
 
+    // Runs a command which changes the project's files, sending back its error message if it fails
+    async fn exec_change<T>(&self, cmd: Vec<T>) -> Result<Response, bollard::errors::Error>
+    where
+        T: Into<String> + Default + Serialize,
+    {
+        let output = self.exec_docker_output(cmd, None).await?;
+
+        Ok(Self::change_response(output))
+    }
+
+    // Sends back the error message of a command if it failed
+    fn change_response(output: ExecOutput) -> Response {
+        if output.exit_code == Some(0) {
+            Response::Success
+        } else {
+            Response::Error {
+                msg: output.stderr.trim().to_string(),
+            }
+        }
+    }
+
+    // Runs git in the project directory, with paths given relative to the project root
+    async fn exec_git(&self, args: &[&str]) -> Result<ExecOutput, bollard::errors::Error> {
+        self.exec_docker_output(EditorSessionManager::git_command(args), None)
+            .await
+    }
+
+    // Lists the files which have changed since the last commit
+    async fn git_status(&self) -> Result<Response, bollard::errors::Error> {
+        let output = self.exec_git(&GitFileStatus::STATUS_ARGS).await?;
+
+        Ok(if output.exit_code == Some(0) {
+            Response::GitFiles {
+                files: GitFileStatus::parse_status(&output.stdout),
+            }
+        } else {
+            Response::Error {
+                msg: output.stderr.trim().to_string(),
+            }
+        })
+    }
+
+    // Reads a file at the last commit and in the working tree, so that the changes to it can be shown
+    async fn git_diff(&self, path: &Path) -> Result<Response, bollard::errors::Error> {
+        let path = path.to_string_lossy();
+        let head = self.exec_git(&["show", &format!("HEAD:{path}")]).await?;
+        let working = self.exec_docker_output(vec!["cat", "--", &path], None).await?;
+
+        // a file which can't be read at one of them doesn't exist there
+        let contents = |output: ExecOutput| (output.exit_code == Some(0)).then_some(output.stdout);
+        Ok(Response::FileDiff {
+            diff: GitDiff {
+                head: contents(head),
+                working: contents(working),
+            },
+        })
+    }
+
+    async fn git_stage(&self, paths: &[PathBuf]) -> Result<Response, bollard::errors::Error> {
+        let paths: Vec<_> = paths.iter().map(|path| path.to_string_lossy()).collect();
+        // `-A` stages deleted files as well
+        let mut args = vec!["add", "-A", "--"];
+        args.extend(paths.iter().map(AsRef::as_ref));
+
+        let output = self.exec_git(&args).await?;
+        Ok(Self::change_response(output))
+    }
+
+    async fn git_unstage(&self, paths: &[PathBuf]) -> Result<Response, bollard::errors::Error> {
+        let paths: Vec<_> = paths.iter().map(|path| path.to_string_lossy()).collect();
+        let mut args = vec!["reset", "-q", "--"];
+        args.extend(paths.iter().map(AsRef::as_ref));
+
+        let output = self.exec_git(&args).await?;
+        Ok(Self::change_response(output))
+    }
+
+    async fn new_file(&self, path: &Path) -> Result<Response, bollard::errors::Error> {
+        // `set -C` stops the redirect from overwriting an existing file
+        self.exec_change(vec!["sh", "-c", r#"set -C; : > "$1""#, "sh", &path.to_string_lossy()])
+            .await
+    }
+
+    async fn new_folder(&self, path: &Path) -> Result<Response, bollard::errors::Error> {
+        self.exec_change(vec!["mkdir", "--", &path.to_string_lossy()])
+            .await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<Response, bollard::errors::Error> {
+        // `cp` would copy into `to` if it is an existing directory, rather than failing
+        let script = r#"if [ -e "$2" ]; then echo "$2 already exists" >&2; exit 1; fi; cp -R -- "$1" "$2""#;
+
+        self.exec_change(vec![
+            "sh",
+            "-c",
+            script,
+            "sh",
+            &from.to_string_lossy(),
+            &to.to_string_lossy(),
+        ])
+        .await
+    }
+
+    // Adds files uploaded from the user's computer to a directory in the project, using the container's archive API.
+    // The files' paths are relative to the directory, so that whole folders can be uploaded
+    async fn upload(&self, dir: &Path, files: Vec<UploadFile>) -> anyhow::Result<Response> {
+        let project = self.project_path()?;
+        if !dir.starts_with(&project) || dir.components().any(|c| c == Component::ParentDir) {
+            return Ok(Response::Error {
+                msg: "Files can only be uploaded into the project".to_string(),
+            });
+        }
+
+        let mtime = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs();
+        let mut archive = tar::Builder::new(Vec::new());
+        // directories in the archive that have already been added
+        let mut dirs = HashSet::new();
+
+        for file in &files {
+            // the parent directories of each file are added first, so that folders can be uploaded
+            for parent in file.path.ancestors().skip(1).collect::<Vec<_>>().into_iter().rev() {
+                if parent.as_os_str().is_empty() || !dirs.insert(parent.to_path_buf()) {
+                    continue;
+                }
+
+                let mut header = tar::Header::new_gnu();
+                header.set_entry_type(tar::EntryType::Directory);
+                header.set_size(0);
+                header.set_mode(0o755);
+                header.set_mtime(mtime);
+                header.set_cksum();
+                archive.append_data(&mut header, parent, io::empty())?;
+            }
+
+            let mut header = tar::Header::new_gnu();
+            header.set_size(file.contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(mtime);
+            header.set_cksum();
+            // this fails for paths outside of the directory (i.e. absolute paths, or paths containing `..`)
+            if let Err(err) = archive.append_data(&mut header, &file.path, file.contents.as_slice()) {
+                return Ok(Response::Error {
+                    msg: format!("Can't upload {}: {err}", file.path.display()),
+                });
+            }
+        }
+        let archive = archive.into_inner()?;
+
+        self.session_mgr
+            .docker()
+            .upload_to_container(
+                &self.container_id,
+                Some(UploadToContainerOptions {
+                    path: dir.to_string_lossy().into_owned(),
+                    // the files are owned by the container's user, so that they can be edited from the terminal
+                    copy_uidgid: Some("true".to_string()),
+                    ..Default::default()
+                }),
+                body_full(archive.into()),
+            )
+            .await?;
+
+        Ok(Response::Success)
+    }
+
     async fn delete(&self, path: &Path) -> Result<Response, bollard::errors::Error> {
         // check if path is directory
         if self
@@ -345,4 +826,202 @@ impl WebSocketHandler {
             Ok(Response::Success)
         }
     }
+
+    // The maximum number of search results sent back, and how many are sent back at a time
+    const MAX_SEARCH_RESULTS: usize = 2000;
+    const SEARCH_BATCH_SIZE: usize = 100;
+
+    // Builds the regex used to find the position of matches in each line that `grep` finds
+    fn search_regex(query: &SearchQuery) -> Result<Regex, regex::Error> {
+        search::search_regex(query.pattern, query.regex, query.case_sensitive, query.whole_word)
+    }
+
+    // Finds the literals which every match of a search has to start (or else end) with, so that grep can look for them
+    // instead of the regex. None if there aren't any, e.g. for `\d+`
+    fn grep_literals(query: &SearchQuery) -> Option<Vec<String>> {
+        let pattern = if query.regex {
+            query.pattern.to_string()
+        } else {
+            regex::escape(query.pattern)
+        };
+        let hir = ParserBuilder::new()
+            .case_insensitive(!query.case_sensitive)
+            .build()
+            .parse(&pattern)
+            .ok()?;
+        let seq = [ExtractKind::Prefix, ExtractKind::Suffix]
+            .into_iter()
+            .map(|kind| Extractor::new().kind(kind).extract(&hir))
+            .find(Seq::is_finite)?;
+
+        // an empty literal would match every line anyway, and grep can't be given null bytes
+        let literals = seq
+            .literals()?
+            .iter()
+            .map(|literal| String::from_utf8(literal.as_bytes().to_vec()).ok())
+            .collect::<Option<Vec<_>>>()?;
+        (!literals.is_empty() && literals.iter().all(|literal| !literal.is_empty() && !literal.contains('\0')))
+            .then_some(literals)
+    }
+
+    // Builds a `grep` command to find the lines of the (non-binary) files in the project which could match.
+    // The lines are only a rough filter, as which parts of them match is decided by `search_regex`:
+    // grep's regex syntax and its idea of a word differ from the `regex` crate's, so regex searches look for the
+    // literals from `grep_literals`, and whole word searches list every line containing the pattern
+    fn grep_cmd(&self, query: &SearchQuery) -> anyhow::Result<Vec<String>> {
+        let mut cmd: Vec<String> = vec![
+            "grep".into(),
+            // search recursively, skipping binary files, and separate paths from the rest of the output with a null byte
+            "-rnIHZ".into(),
+            "--color=never".into(),
+            "--exclude-dir=.git".into(),
+            "-F".into(),
+        ];
+        if let Some(glob) = query.glob.filter(|glob| !glob.is_empty()) {
+            cmd.push(format!("--include={glob}"));
+        }
+        // grep may only ignore the case of ASCII letters (depending on the container's locale), so other
+        // case-insensitive patterns look for each way of writing them, like regex searches do
+        if !query.regex && (query.case_sensitive || query.pattern.is_ascii()) {
+            if !query.case_sensitive {
+                cmd.push("-i".into());
+            }
+            cmd.extend(["-e".into(), query.pattern.into()]);
+        } else if let Some(literals) = Self::grep_literals(query) {
+            cmd.extend(literals.into_iter().flat_map(|literal| ["-e".into(), literal]));
+        } else {
+            // an empty pattern matches every line
+            cmd.extend(["-e".into(), String::new()]);
+        }
+        cmd.extend(["--".into(), self.project_path()?]);
+
+        Ok(cmd)
+    }
+
+    // Parses a line of output from `grep` (formatted as `path\0line:contents`) into the matches on that line
+    fn parse_grep_line(line: &str, re: &Regex) -> Vec<SearchMatch> {
+        let Some((path, rest)) = line.split_once('\0') else {
+            return vec![];
+        };
+        let Some((line_num, contents)) = rest.split_once(':') else {
+            return vec![];
+        };
+        let Ok(line_num) = line_num.parse() else {
+            return vec![];
+        };
+        let contents = contents.trim_end_matches('\r');
+
+        re.find_iter(contents)
+            .map(|m| SearchMatch {
+                path: path.into(),
+                line: line_num,
+                col: contents[..m.start()].chars().count() + 1,
+                preview: contents.to_string(),
+            })
+            .collect()
+    }
+
+    // Searches through the project using `grep`, sending back the matches in batches while they are found
+    async fn search(
+        &self,
+        id: Uuid,
+        ws: &mut WebSocket,
+        query: &SearchQuery<'_>,
+    ) -> anyhow::Result<Response> {
+        let re = match Self::search_regex(query) {
+            Ok(re) => re,
+            Err(err) => return Ok(Response::Error { msg: err.to_string() }),
+        };
+
+        let mut output = self.exec_docker_stream(self.grep_cmd(query)?).await?;
+
+        let mut matches = vec![];
+        let mut sent = 0;
+        // bytes of the line of output currently being received
+        let mut line = vec![];
+
+        while let Some(chunk) = output.try_next().await? {
+            line.extend_from_slice(&chunk);
+
+            while let Some(end) = line.iter().position(|&b| b == b'\n') {
+                let complete: Vec<u8> = line.drain(..=end).collect();
+                matches.extend(Self::parse_grep_line(
+                    &String::from_utf8_lossy(&complete[..end]),
+                    &re,
+                ));
+            }
+
+            if sent + matches.len() >= Self::MAX_SEARCH_RESULTS {
+                break;
+            }
+
+            if matches.len() >= Self::SEARCH_BATCH_SIZE {
+                sent += matches.len();
+                let resp = Response::SearchResults {
+                    matches: std::mem::take(&mut matches),
+                    finished: false,
+                };
+                Self::send(ws, &ServerMessage { id, resp }).await?;
+            }
+        }
+        matches.extend(Self::parse_grep_line(&String::from_utf8_lossy(&line), &re));
+        matches.truncate(Self::MAX_SEARCH_RESULTS.saturating_sub(sent));
+
+        Ok(Response::SearchResults {
+            matches,
+            finished: true,
+        })
+    }
+
+    // Replaces the selected matches in the project, or if there are none, returns a preview of every match being replaced
+    async fn replace(
+        &self,
+        query: &SearchQuery<'_>,
+        replacement: &str,
+        selected: Option<Vec<ReplaceChange>>,
+    ) -> anyhow::Result<Response> {
+        let re = match Self::search_regex(query) {
+            Ok(re) => re,
+            Err(err) => return Ok(Response::Error { msg: err.to_string() }),
+        };
+
+        let Some(selected) = selected else {
+            let output = self.exec_docker_bytes(self.grep_cmd(query)?).await?;
+            let changes = String::from_utf8_lossy(&output)
+                .lines()
+                .flat_map(|line| Self::parse_grep_line(line, &re))
+                .map(|m| ReplaceChange {
+                    after: search::replace_matches(&re, &m.preview, replacement, query.regex, &[m.col]),
+                    path: m.path,
+                    line: m.line,
+                    col: m.col,
+                    before: m.preview,
+                })
+                .collect();
+
+            return Ok(Response::ReplacePreview { changes });
+        };
+
+        // the columns of the selected matches on each line of each file
+        let mut files: HashMap<&Path, HashMap<usize, Vec<usize>>> = HashMap::new();
+        for change in &selected {
+            files
+                .entry(&change.path)
+                .or_default()
+                .entry(change.line)
+                .or_default()
+                .push(change.col);
+        }
+
+        for (path, lines) in files {
+            let path_str = path.to_string_lossy();
+            let contents = self.exec_docker_bytes(vec!["cat", &*path_str]).await?;
+            let contents = String::from_utf8_lossy(&contents);
+            let new_contents = search::replace_in_contents(&re, &contents, replacement, query.regex, &lines);
+
+            self.write_file(path, &new_contents).await?;
+        }
+
+        Ok(Response::Success)
+    }
 }
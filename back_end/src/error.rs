@@ -1,12 +1,13 @@
 use anyhow::anyhow;
 use axum::{
     Json,
-    http::StatusCode,
+    http::{StatusCode, header},
     response::{IntoResponse, Response},
 };
 use serde::Deserialize;
 use serde_json::json;
 use thiserror::Error;
+use tracing::error;
 
 use crate::auth::crypto;
 
@@ -28,6 +29,17 @@ pub enum AppError {
     Unauthorized,
     #[error("project already exists")]
     ProjectExists,
+    #[error("invalid request: {0}")]
+    BadRequest(String),
+    #[error("branch {0} has changed on GitHub")]
+    RemoteChanged(String),
+    #[error("GitHub rate limit exceeded")]
+    GithubRateLimited {
+        /// Seconds until requests can be made again (if known)
+        retry_after: Option<u64>,
+    },
+    #[error("unexpected response from GitHub: {0}")]
+    GithubResponse(String),
     #[error("{0}")]
     Other(anyhow::Error),
 }
@@ -57,6 +69,11 @@ impl AppError {
     pub fn other(err: impl Into<anyhow::Error>) -> Self {
         Self::Other(err.into())
     }
+
+    // for responses from GitHub that couldn't be read (e.g. JSON which is missing fields)
+    pub fn malformed(err: impl std::fmt::Display) -> Self {
+        Self::GithubResponse(err.to_string())
+    }
 }
 
 impl IntoResponse for AppError {
@@ -69,6 +86,36 @@ impl IntoResponse for AppError {
             Unauthorized => return StatusCode::UNAUTHORIZED.into_response(),
             SessionConflict => return StatusCode::CONFLICT.into_response(),
             ProjectExists => return StatusCode::UNPROCESSABLE_ENTITY.into_response(),
+            BadRequest(msg) => {
+                return (StatusCode::BAD_REQUEST, Json(json!({ "error": msg }))).into_response();
+            }
+            // the message is always sent, so that the editor can tell the user what to do
+            RemoteChanged(branch) => {
+                let msg = format!(
+                    "{branch} has new commits on GitHub since the project was opened, \
+                    which would be overwritten by saving. Merge them into the project first"
+                );
+                return (StatusCode::CONFLICT, Json(json!({ "error": msg }))).into_response();
+            }
+            GithubRateLimited { retry_after } => {
+                let msg = match retry_after {
+                    Some(secs) => format!("Too many requests to GitHub, try again in {secs} seconds"),
+                    None => "Too many requests to GitHub, try again later".to_string(),
+                };
+                let mut resp = (StatusCode::SERVICE_UNAVAILABLE, Json(json!({ "error": msg }))).into_response();
+                if let Some(secs) = retry_after {
+                    resp.headers_mut().insert(header::RETRY_AFTER, secs.into());
+                }
+                return resp;
+            }
+            GithubResponse(msg) => {
+                error!("unexpected response from GitHub: {msg}");
+                return (
+                    StatusCode::BAD_GATEWAY,
+                    Json(json!({ "error": "GitHub sent an unexpected response" })),
+                )
+                    .into_response();
+            }
             InvalidAuth(e) => e.into(),
             Database(e) => e.into(),
             GithubAuth(e) => anyhow!("Github auth failed: {}", e.message),
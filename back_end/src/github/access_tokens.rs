@@ -130,7 +130,7 @@ impl GithubClient {
         // Send HTTP request for access token API request, and store HTTP response in `text`
         let text = self
             .client
-            .post("https://github.com/login/oauth/access_token")
+            .post(self.web_url("/login/oauth/access_token"))
             .form(&params)
             .send()
             .await
@@ -0,0 +1,1084 @@
+// A fake GitHub API which keeps repos in memory, so that the GitHub client can be tested without the real GitHub
+// It covers the endpoints used for users, repos, contents, git data (refs, commits, trees and blobs), forks and tarballs,
+// and responds in the same way as GitHub does for the fields the client reads
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    io::Write as _,
+    iter,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    Json, Router,
+    body::{Body, to_bytes},
+    extract::{Path, Query, Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use base64::{Engine as _, prelude::BASE64_STANDARD};
+use flate2::{Compression, write::GzEncoder};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sha2::{Digest as _, Sha256};
+use tokio::net::TcpListener;
+
+/// A user who can make requests with their access token
+#[derive(Clone)]
+struct MockUser {
+    id: i32,
+    login: String,
+}
+
+struct MockRepo {
+    private: bool,
+    default_branch: String,
+    /// SHA hash of the commit each branch points to (empty until the first file is added)
+    branches: BTreeMap<String, String>,
+}
+
+struct MockCommit {
+    tree: String,
+    parents: Vec<String>,
+    message: String,
+}
+
+/// Files in a tree by their path, with their mode and the SHA hash of their blob
+/// (trees are stored flat, rather than with a tree for each directory)
+type Tree = BTreeMap<String, (String, String)>;
+
+#[derive(Default)]
+struct Store {
+    /// Users by their access token
+    users: HashMap<String, MockUser>,
+    /// Repos by their owner's username and their name
+    repos: HashMap<(String, String), MockRepo>,
+    blobs: HashMap<String, Vec<u8>>,
+    trees: HashMap<String, Tree>,
+    commits: HashMap<String, MockCommit>,
+    /// Responses to give instead of handling the next requests, to test errors from GitHub
+    failures: VecDeque<(StatusCode, Vec<(&'static str, String)>)>,
+    /// How many requests have been received
+    requests: usize,
+    /// How many requests have been answered with 304 Not Modified, because their ETag matched
+    not_modified: usize,
+    /// Most entries listed for a recursive tree before it is truncated, like GitHub does for big repos
+    max_tree_entries: Option<usize>,
+}
+
+// gives an ID for an object from its contents, like git does (but with SHA-256 cut down to the length of a SHA-1 hash)
+fn object_sha(kind: &str, data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(kind.as_bytes());
+    hasher.update(data);
+    hex::encode(hasher.finalize())[..40].to_string()
+}
+
+impl Store {
+    fn user(&self, headers: &HeaderMap) -> Option<&MockUser> {
+        let token = headers
+            .get(header::AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")?;
+        self.users.get(token)
+    }
+
+    // finds a repo, if the user can see it
+    fn repo(&self, user: Option<&MockUser>, owner: &str, name: &str) -> Option<&MockRepo> {
+        let repo = self.repos.get(&(owner.to_string(), name.to_string()))?;
+        let can_see = !repo.private || user.is_some_and(|user| user.login == owner);
+
+        can_see.then_some(repo)
+    }
+
+    // finds the commit a branch name or commit SHA (or the start of one) refers to
+    fn resolve(&self, repo: &MockRepo, git_ref: &str) -> Option<String> {
+        if let Some(sha) = repo.branches.get(git_ref) {
+            return Some(sha.clone());
+        }
+        if git_ref.len() < 7 {
+            return None;
+        }
+        self.commits
+            .keys()
+            .find(|sha| sha.starts_with(git_ref))
+            .cloned()
+    }
+
+    fn add_blob(&mut self, contents: Vec<u8>) -> String {
+        let sha = object_sha("blob", &contents);
+        self.blobs.insert(sha.clone(), contents);
+        sha
+    }
+
+    fn add_tree(&mut self, tree: Tree) -> String {
+        let sha = object_sha("tree", format!("{tree:?}").as_bytes());
+        self.trees.insert(sha.clone(), tree);
+        sha
+    }
+
+    fn add_commit(&mut self, commit: MockCommit) -> String {
+        // the number of commits is included, so that commits with the same tree and parents are still different
+        let data = format!("{} {:?} {} {}", commit.tree, commit.parents, commit.message, self.commits.len());
+        let sha = object_sha("commit", data.as_bytes());
+        self.commits.insert(sha.clone(), commit);
+        sha
+    }
+
+    fn is_ancestor(&self, ancestor: &str, sha: &str) -> bool {
+        sha == ancestor
+            || self.commits[sha]
+                .parents
+                .iter()
+                .any(|parent| self.is_ancestor(ancestor, parent))
+    }
+
+    // the files of the commit that a branch or commit SHA refers to
+    fn files_at(&self, repo: &MockRepo, git_ref: &str) -> Option<(String, &Tree)> {
+        let sha = self.resolve(repo, git_ref)?;
+        let tree = &self.trees[&self.commits[&sha].tree];
+        Some((sha, tree))
+    }
+
+    // commits changes to files on a branch (creating the branch if the repo is empty), returning the new commit's SHA
+    fn commit_files(
+        &mut self,
+        owner: &str,
+        name: &str,
+        branch: &str,
+        message: &str,
+        changes: Vec<(String, Option<Vec<u8>>)>,
+    ) -> String {
+        let key = (owner.to_string(), name.to_string());
+        let parent = self.repos[&key].branches.get(branch).cloned();
+        let mut tree = parent
+            .as_ref()
+            .map(|sha| self.trees[&self.commits[sha].tree].clone())
+            .unwrap_or_default();
+
+        for (path, contents) in changes {
+            match contents {
+                Some(contents) => {
+                    let sha = self.add_blob(contents);
+                    tree.insert(path, ("100644".to_string(), sha));
+                }
+                None => {
+                    tree.remove(&path);
+                }
+            }
+        }
+
+        let tree = self.add_tree(tree);
+        let sha = self.add_commit(MockCommit {
+            tree,
+            parents: parent.into_iter().collect(),
+            message: message.to_string(),
+        });
+        self.repos
+            .get_mut(&key)
+            .unwrap()
+            .branches
+            .insert(branch.to_string(), sha.clone());
+
+        sha
+    }
+}
+
+/// A mock GitHub server, running in the background on a local port
+#[derive(Clone)]
+pub struct MockGithub {
+    /// The URL of the server (for both the API and the website)
+    pub url: String,
+    store: Arc<Mutex<Store>>,
+}
+
+impl MockGithub {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let mock = Self {
+            url,
+            store: Arc::default(),
+        };
+
+        let app = router(mock.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        mock
+    }
+
+    pub fn add_user(&self, access_token: &str, id: i32, login: &str) {
+        self.store.lock().unwrap().users.insert(
+            access_token.to_string(),
+            MockUser {
+                id,
+                login: login.to_string(),
+            },
+        );
+    }
+
+    // creates a repo on GitHub without using the API (e.g. one the user made themselves), with one commit of the files
+    pub fn add_repo(&self, owner: &str, name: &str, private: bool, files: &[(&str, &str)]) {
+        let mut store = self.store.lock().unwrap();
+        store.repos.insert(
+            (owner.to_string(), name.to_string()),
+            MockRepo {
+                private,
+                default_branch: "main".to_string(),
+                branches: BTreeMap::new(),
+            },
+        );
+        if !files.is_empty() {
+            let changes = files
+                .iter()
+                .map(|(path, contents)| ((*path).to_string(), Some(contents.as_bytes().to_vec())))
+                .collect();
+            store.commit_files(owner, name, "main", "Initial commit", changes);
+        }
+    }
+
+    // commits a change to a file on GitHub without using the API (e.g. a change made on the GitHub website)
+    pub fn push_file(&self, owner: &str, name: &str, branch: &str, path: &str, contents: Option<&str>) -> String {
+        self.store.lock().unwrap().commit_files(
+            owner,
+            name,
+            branch,
+            &format!("Update {path}"),
+            vec![(path.to_string(), contents.map(|contents| contents.as_bytes().to_vec()))],
+        )
+    }
+
+    // whether the repo exists
+    pub fn has_repo(&self, owner: &str, name: &str) -> bool {
+        self.store
+            .lock()
+            .unwrap()
+            .repos
+            .contains_key(&(owner.to_string(), name.to_string()))
+    }
+
+    // the contents of a file on a branch (`None` if it doesn't exist)
+    pub fn file(&self, owner: &str, name: &str, branch: &str, path: &str) -> Option<Vec<u8>> {
+        let store = self.store.lock().unwrap();
+        let repo = store.repos.get(&(owner.to_string(), name.to_string()))?;
+        let (_, tree) = store.files_at(repo, branch)?;
+        let (_, blob) = tree.get(path)?;
+
+        Some(store.blobs[blob].clone())
+    }
+
+    // the paths of the files on a branch
+    pub fn paths(&self, owner: &str, name: &str, branch: &str) -> Vec<String> {
+        let store = self.store.lock().unwrap();
+        let repo = &store.repos[&(owner.to_string(), name.to_string())];
+        store
+            .files_at(repo, branch)
+            .map(|(_, tree)| tree.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    // the SHA hash of the commit a branch points to
+    pub fn head(&self, owner: &str, name: &str, branch: &str) -> Option<String> {
+        let store = self.store.lock().unwrap();
+        store.repos[&(owner.to_string(), name.to_string())]
+            .branches
+            .get(branch)
+            .cloned()
+    }
+
+    // the SHA hashes of the parents of a commit
+    pub fn parents(&self, sha: &str) -> Vec<String> {
+        self.store.lock().unwrap().commits[sha].parents.clone()
+    }
+
+    // makes the next request fail with the status and headers, instead of being handled
+    pub fn fail_next(&self, status: StatusCode, headers: &[(&'static str, String)]) {
+        self.store
+            .lock()
+            .unwrap()
+            .failures
+            .push_back((status, headers.to_vec()));
+    }
+
+    pub fn truncate_trees(&self, max_entries: usize) {
+        self.store.lock().unwrap().max_tree_entries = Some(max_entries);
+    }
+
+    pub fn request_count(&self) -> usize {
+        self.store.lock().unwrap().requests
+    }
+
+    pub fn not_modified_count(&self) -> usize {
+        self.store.lock().unwrap().not_modified
+    }
+}
+
+fn router(mock: MockGithub) -> Router {
+    Router::new()
+        .route("/user", get(get_user))
+        .route("/user/installations", get(get_installations))
+        .route("/user/repos", get(list_repos).post(create_repo))
+        .route("/repos/{owner}/{repo}", get(get_repo))
+        .route("/repos/{owner}/{repo}/forks", post(fork_repo))
+        .route("/repos/{owner}/{repo}/readme", get(get_readme))
+        .route("/repos/{owner}/{repo}/contents/{*path}", get(get_contents).put(put_contents))
+        .route("/repos/{owner}/{repo}/branches", get(list_branches))
+        .route("/repos/{owner}/{repo}/branches/{*branch}", get(get_branch))
+        .route("/repos/{owner}/{repo}/commits", get(list_commits))
+        .route("/repos/{owner}/{repo}/commits/{git_ref}", get(get_commit))
+        .route("/repos/{owner}/{repo}/tarball/{git_ref}", get(get_tarball))
+        .route("/repos/{owner}/{repo}/pulls", post(create_pull))
+        .route("/repos/{owner}/{repo}/git/ref/heads/{*branch}", get(get_ref))
+        .route("/repos/{owner}/{repo}/git/refs", post(create_ref))
+        .route("/repos/{owner}/{repo}/git/refs/heads/{*branch}", axum::routing::patch(update_ref))
+        .route("/repos/{owner}/{repo}/git/commits", post(create_commit))
+        .route("/repos/{owner}/{repo}/git/commits/{sha}", get(get_git_commit))
+        .route("/repos/{owner}/{repo}/git/trees", post(create_tree))
+        .route("/repos/{owner}/{repo}/git/trees/{git_ref}", get(get_tree))
+        .route("/repos/{owner}/{repo}/git/blobs", post(create_blob))
+        .route("/raw/{owner}/{repo}/{git_ref}/{*path}", get(get_raw))
+        .layer(middleware::from_fn_with_state(mock.clone(), handle_request))
+        .with_state(mock)
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "message": message }))).into_response()
+}
+
+fn not_found() -> Response {
+    error(StatusCode::NOT_FOUND, "Not Found")
+}
+
+// counts requests, gives any failures that have been queued, rejects unknown access tokens,
+// and adds ETags to successful GET responses (answering with 304 Not Modified if the ETag was sent back)
+async fn handle_request(State(mock): State<MockGithub>, req: Request, next: Next) -> Response {
+    {
+        let mut store = mock.store.lock().unwrap();
+        store.requests += 1;
+
+        if let Some((status, headers)) = store.failures.pop_front() {
+            let mut resp = error(status, "Mock failure");
+            for (name, value) in headers {
+                resp.headers_mut().insert(name, value.parse().unwrap());
+            }
+            return resp;
+        }
+        if req.headers().contains_key(header::AUTHORIZATION) && store.user(req.headers()).is_none() {
+            return error(StatusCode::UNAUTHORIZED, "Bad credentials");
+        }
+    }
+
+    let is_get = req.method() == axum::http::Method::GET;
+    let if_none_match = req.headers().get(header::IF_NONE_MATCH).cloned();
+    let resp = next.run(req).await;
+    if !is_get || resp.status() != StatusCode::OK {
+        return resp;
+    }
+
+    let (mut parts, body) = resp.into_parts();
+    let body = to_bytes(body, usize::MAX).await.unwrap();
+    let etag = HeaderValue::from_str(&format!("\"{}\"", object_sha("etag", &body))).unwrap();
+    if if_none_match.as_ref() == Some(&etag) {
+        mock.store.lock().unwrap().not_modified += 1;
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+    parts.headers.insert(header::ETAG, etag);
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+fn repo_json(owner: &MockUser, name: &str, repo: &MockRepo) -> Value {
+    json!({
+        "name": name,
+        "full_name": format!("{}/{name}", owner.login),
+        "owner": { "login": owner.login, "id": owner.id },
+        "private": repo.private,
+        "description": null,
+        "language": null,
+        "default_branch": repo.default_branch,
+    })
+}
+
+async fn get_user(State(mock): State<MockGithub>, headers: HeaderMap) -> Response {
+    let store = mock.store.lock().unwrap();
+    let Some(user) = store.user(&headers) else {
+        return error(StatusCode::UNAUTHORIZED, "Requires authentication");
+    };
+
+    Json(json!({
+        "login": user.login,
+        "id": user.id,
+        "avatar_url": format!("{}/avatars/{}", mock.url, user.login),
+    }))
+    .into_response()
+}
+
+async fn get_installations() -> Response {
+    Json(json!({ "total_count": 1, "installations": [] })).into_response()
+}
+
+#[derive(Deserialize)]
+struct PageQuery {
+    page: Option<u32>,
+}
+
+async fn list_repos(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Query(PageQuery { page }): Query<PageQuery>,
+) -> Response {
+    let store = mock.store.lock().unwrap();
+    let Some(user) = store.user(&headers) else {
+        return error(StatusCode::UNAUTHORIZED, "Requires authentication");
+    };
+
+    // every repo is on the first page
+    if page.unwrap_or(1) > 1 {
+        return Json(json!([])).into_response();
+    }
+    let repos: Vec<_> = store
+        .repos
+        .iter()
+        .filter(|((owner, _), _)| *owner == user.login)
+        .map(|((_, name), repo)| repo_json(user, name, repo))
+        .collect();
+
+    Json(repos).into_response()
+}
+
+#[derive(Deserialize)]
+struct CreateRepoBody {
+    name: String,
+    #[serde(default)]
+    private: bool,
+}
+
+async fn create_repo(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Json(body): Json<CreateRepoBody>,
+) -> Response {
+    let mut store = mock.store.lock().unwrap();
+    let Some(user) = store.user(&headers).cloned() else {
+        return error(StatusCode::UNAUTHORIZED, "Requires authentication");
+    };
+    let key = (user.login.clone(), body.name.clone());
+    if store.repos.contains_key(&key) {
+        return error(StatusCode::UNPROCESSABLE_ENTITY, "name already exists on this account");
+    }
+
+    let repo = MockRepo {
+        private: body.private,
+        default_branch: "main".to_string(),
+        branches: BTreeMap::new(),
+    };
+    let json = repo_json(&user, &body.name, &repo);
+    store.repos.insert(key, repo);
+
+    (StatusCode::CREATED, Json(json)).into_response()
+}
+
+async fn get_repo(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name)): Path<(String, String)>,
+) -> Response {
+    let store = mock.store.lock().unwrap();
+    let user = store.user(&headers);
+    let Some(repo) = store.repo(user, &owner, &name) else {
+        return not_found();
+    };
+    let owner = store
+        .users
+        .values()
+        .find(|user| user.login == owner)
+        .cloned()
+        .unwrap_or(MockUser { id: 0, login: owner });
+
+    Json(repo_json(&owner, &name, repo)).into_response()
+}
+
+async fn fork_repo(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name)): Path<(String, String)>,
+) -> Response {
+    let mut store = mock.store.lock().unwrap();
+    let Some(user) = store.user(&headers).cloned() else {
+        return error(StatusCode::UNAUTHORIZED, "Requires authentication");
+    };
+    let Some(repo) = store.repo(Some(&user), &owner, &name) else {
+        return not_found();
+    };
+
+    // the fork has the same branches as the original, pointing to the same commits
+    let fork = MockRepo {
+        private: repo.private,
+        default_branch: repo.default_branch.clone(),
+        branches: repo.branches.clone(),
+    };
+    let json = repo_json(&user, &name, &fork);
+    store.repos.entry((user.login, name)).or_insert(fork);
+
+    (StatusCode::ACCEPTED, Json(json)).into_response()
+}
+
+#[derive(Deserialize)]
+struct RefQuery {
+    #[serde(rename = "ref")]
+    git_ref: Option<String>,
+}
+
+// whether the request asked for the file itself, rather than JSON describing it
+fn wants_raw(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("raw"))
+}
+
+async fn get_readme(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name)): Path<(String, String)>,
+    Query(RefQuery { git_ref }): Query<RefQuery>,
+) -> Response {
+    let store = mock.store.lock().unwrap();
+    let user = store.user(&headers);
+    let Some(repo) = store.repo(user, &owner, &name) else {
+        return not_found();
+    };
+    let git_ref = git_ref.unwrap_or_else(|| repo.default_branch.clone());
+    let Some((_, tree)) = store.files_at(repo, &git_ref) else {
+        return not_found();
+    };
+    let Some((path, (_, blob))) = tree
+        .iter()
+        .find(|(path, _)| !path.contains('/') && path.to_lowercase().starts_with("readme"))
+    else {
+        return not_found();
+    };
+
+    if wants_raw(&headers) {
+        return store.blobs[blob].clone().into_response();
+    }
+    Json(json!({
+        "name": path,
+        "path": path,
+        "sha": blob,
+        "download_url": format!("{}/raw/{owner}/{name}/{git_ref}/{path}", mock.url),
+    }))
+    .into_response()
+}
+
+async fn get_raw(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name, git_ref, path)): Path<(String, String, String, String)>,
+) -> Response {
+    let store = mock.store.lock().unwrap();
+    let user = store.user(&headers);
+    let Some(repo) = store.repo(user, &owner, &name) else {
+        return not_found();
+    };
+    match store.files_at(repo, &git_ref).and_then(|(_, tree)| tree.get(&path)) {
+        Some((_, blob)) => store.blobs[blob].clone().into_response(),
+        None => not_found(),
+    }
+}
+
+async fn get_contents(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name, path)): Path<(String, String, String)>,
+    Query(RefQuery { git_ref }): Query<RefQuery>,
+) -> Response {
+    let store = mock.store.lock().unwrap();
+    let user = store.user(&headers);
+    let Some(repo) = store.repo(user, &owner, &name) else {
+        return not_found();
+    };
+    let git_ref = git_ref.unwrap_or_else(|| repo.default_branch.clone());
+    let Some((_, blob)) = store.files_at(repo, &git_ref).and_then(|(_, tree)| tree.get(&path)) else {
+        return not_found();
+    };
+
+    let contents = store.blobs[blob].clone();
+    if wants_raw(&headers) {
+        return contents.into_response();
+    }
+    Json(json!({
+        "type": "file",
+        "path": path,
+        "sha": blob,
+        "encoding": "base64",
+        "content": BASE64_STANDARD.encode(contents),
+    }))
+    .into_response()
+}
+
+#[derive(Deserialize)]
+struct PutContentsBody {
+    message: String,
+    content: String,
+    branch: Option<String>,
+    /// SHA hash of the blob being replaced, which is needed to change an existing file
+    sha: Option<String>,
+}
+
+async fn put_contents(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name, path)): Path<(String, String, String)>,
+    Json(body): Json<PutContentsBody>,
+) -> Response {
+    let mut store = mock.store.lock().unwrap();
+    let user = store.user(&headers).cloned();
+    if user.as_ref().is_none_or(|user| user.login != owner) {
+        return not_found();
+    }
+    let Some(repo) = store.repo(user.as_ref(), &owner, &name) else {
+        return not_found();
+    };
+    let branch = body.branch.unwrap_or_else(|| repo.default_branch.clone());
+
+    let existing = store
+        .files_at(repo, &branch)
+        .and_then(|(_, tree)| tree.get(&path).cloned());
+    if let Some((_, blob)) = existing
+        && body.sha.as_ref() != Some(&blob)
+    {
+        return error(StatusCode::UNPROCESSABLE_ENTITY, "\"sha\" wasn't supplied");
+    }
+    let Ok(contents) = BASE64_STANDARD.decode(body.content) else {
+        return error(StatusCode::UNPROCESSABLE_ENTITY, "content is not valid Base64");
+    };
+
+    let sha = store.commit_files(&owner, &name, &branch, &body.message, vec![(path, Some(contents))]);
+
+    (StatusCode::CREATED, Json(json!({ "commit": { "sha": sha } }))).into_response()
+}
+
+async fn list_branches(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name)): Path<(String, String)>,
+    Query(PageQuery { page }): Query<PageQuery>,
+) -> Response {
+    let store = mock.store.lock().unwrap();
+    let user = store.user(&headers);
+    let Some(repo) = store.repo(user, &owner, &name) else {
+        return not_found();
+    };
+
+    // every branch is on the first page
+    if page.unwrap_or(1) > 1 {
+        return Json(json!([])).into_response();
+    }
+    let branches: Vec<_> = repo
+        .branches
+        .iter()
+        .map(|(name, sha)| json!({ "name": name, "commit": { "sha": sha } }))
+        .collect();
+
+    Json(branches).into_response()
+}
+
+async fn get_branch(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name, branch)): Path<(String, String, String)>,
+) -> Response {
+    let store = mock.store.lock().unwrap();
+    let user = store.user(&headers);
+    let Some(sha) = store
+        .repo(user, &owner, &name)
+        .and_then(|repo| repo.branches.get(&branch))
+    else {
+        return not_found();
+    };
+
+    Json(json!({ "name": branch, "commit": { "sha": sha } })).into_response()
+}
+
+// a commit in the format of the REST API (rather than the git data API), without the files it changed
+fn commit_json(store: &Store, owner: &str, sha: &str) -> Value {
+    json!({
+        "sha": sha,
+        "commit": {
+            "message": store.commits[sha].message,
+            "author": { "name": owner, "date": "2026-10-18T12:00:00Z" },
+        },
+        "author": { "login": owner },
+        "files": [],
+    })
+}
+
+#[derive(Deserialize)]
+struct CommitsQuery {
+    sha: Option<String>,
+    page: Option<u32>,
+}
+
+async fn list_commits(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name)): Path<(String, String)>,
+    Query(CommitsQuery { sha, page }): Query<CommitsQuery>,
+) -> Response {
+    let store = mock.store.lock().unwrap();
+    let user = store.user(&headers);
+    let Some(repo) = store.repo(user, &owner, &name) else {
+        return not_found();
+    };
+    let git_ref = sha.unwrap_or_else(|| repo.default_branch.clone());
+    let Some(head) = store.resolve(repo, &git_ref) else {
+        return not_found();
+    };
+
+    // every commit is on the first page, following the first parent of each commit back from the newest
+    if page.unwrap_or(1) > 1 {
+        return Json(json!([])).into_response();
+    }
+    let history: Vec<_> = iter::successors(Some(head), |sha| store.commits[sha].parents.first().cloned())
+        .map(|sha| commit_json(&store, &owner, &sha))
+        .collect();
+
+    Json(history).into_response()
+}
+
+async fn get_commit(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name, git_ref)): Path<(String, String, String)>,
+) -> Response {
+    let store = mock.store.lock().unwrap();
+    let user = store.user(&headers);
+    let Some(sha) = store
+        .repo(user, &owner, &name)
+        .and_then(|repo| store.resolve(repo, &git_ref))
+    else {
+        return error(StatusCode::UNPROCESSABLE_ENTITY, "No commit found for SHA");
+    };
+
+    Json(commit_json(&store, &owner, &sha)).into_response()
+}
+
+async fn get_tarball(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name, git_ref)): Path<(String, String, String)>,
+) -> Response {
+    let store = mock.store.lock().unwrap();
+    let user = store.user(&headers);
+    let Some((sha, tree)) = store
+        .repo(user, &owner, &name)
+        .and_then(|repo| store.files_at(repo, &git_ref))
+    else {
+        return not_found();
+    };
+
+    // like GitHub, the files are in a directory named after the repo and commit
+    let root = format!("{owner}-{name}-{}", &sha[..7]);
+    let mut archive = tar::Builder::new(GzEncoder::new(vec![], Compression::default()));
+    for (path, (mode, blob)) in tree {
+        let contents = &store.blobs[blob];
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(if mode == "100755" { 0o755 } else { 0o644 });
+        header.set_cksum();
+        archive
+            .append_data(&mut header, format!("{root}/{path}"), contents.as_slice())
+            .unwrap();
+    }
+    let mut encoder = archive.into_inner().unwrap();
+    encoder.flush().unwrap();
+    let tarball = encoder.finish().unwrap();
+
+    (
+        [(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename={root}.tar.gz"),
+        )],
+        tarball,
+    )
+        .into_response()
+}
+
+async fn create_pull(
+    State(mock): State<MockGithub>,
+    Path((owner, name)): Path<(String, String)>,
+) -> Response {
+    let url = format!("{}/{owner}/{name}/pull/1", mock.url);
+    (StatusCode::CREATED, Json(json!({ "number": 1, "html_url": url }))).into_response()
+}
+
+fn ref_json(branch: &str, sha: &str) -> Value {
+    json!({
+        "ref": format!("refs/heads/{branch}"),
+        "object": { "type": "commit", "sha": sha },
+    })
+}
+
+async fn get_ref(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name, branch)): Path<(String, String, String)>,
+) -> Response {
+    let store = mock.store.lock().unwrap();
+    let user = store.user(&headers);
+    match store
+        .repo(user, &owner, &name)
+        .and_then(|repo| repo.branches.get(&branch))
+    {
+        Some(sha) => Json(ref_json(&branch, sha)).into_response(),
+        None => not_found(),
+    }
+}
+
+#[derive(Deserialize)]
+struct CreateRefBody {
+    #[serde(rename = "ref")]
+    git_ref: String,
+    sha: String,
+}
+
+// gets the repo for a request which changes it, which only the owner can make
+fn owned_repo<'a>(
+    store: &'a mut Store,
+    headers: &HeaderMap,
+    owner: &str,
+    name: &str,
+) -> Option<&'a mut MockRepo> {
+    if store.user(headers)?.login != owner {
+        return None;
+    }
+    store.repos.get_mut(&(owner.to_string(), name.to_string()))
+}
+
+async fn create_ref(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name)): Path<(String, String)>,
+    Json(body): Json<CreateRefBody>,
+) -> Response {
+    let mut store = mock.store.lock().unwrap();
+    let commit_exists = store.commits.contains_key(&body.sha);
+    let Some(repo) = owned_repo(&mut store, &headers, &owner, &name) else {
+        return not_found();
+    };
+    let Some(branch) = body.git_ref.strip_prefix("refs/heads/") else {
+        return error(StatusCode::UNPROCESSABLE_ENTITY, "Reference name is invalid");
+    };
+    if !commit_exists {
+        return error(StatusCode::UNPROCESSABLE_ENTITY, "Object does not exist");
+    }
+    if repo.branches.contains_key(branch) {
+        return error(StatusCode::UNPROCESSABLE_ENTITY, "Reference already exists");
+    }
+    repo.branches.insert(branch.to_string(), body.sha.clone());
+
+    (StatusCode::CREATED, Json(ref_json(branch, &body.sha))).into_response()
+}
+
+#[derive(Deserialize)]
+struct UpdateRefBody {
+    sha: String,
+    #[serde(default)]
+    force: bool,
+}
+
+async fn update_ref(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name, branch)): Path<(String, String, String)>,
+    Json(body): Json<UpdateRefBody>,
+) -> Response {
+    let mut store = mock.store.lock().unwrap();
+    if !store.commits.contains_key(&body.sha) {
+        return error(StatusCode::UNPROCESSABLE_ENTITY, "Object does not exist");
+    }
+    let Some(head) = owned_repo(&mut store, &headers, &owner, &name)
+        .and_then(|repo| repo.branches.get(&branch).cloned())
+    else {
+        return error(StatusCode::UNPROCESSABLE_ENTITY, "Reference does not exist");
+    };
+    if !body.force && !store.is_ancestor(&head, &body.sha) {
+        return error(StatusCode::UNPROCESSABLE_ENTITY, "Update is not a fast forward");
+    }
+
+    let repo = owned_repo(&mut store, &headers, &owner, &name).unwrap();
+    repo.branches.insert(branch.clone(), body.sha.clone());
+
+    Json(ref_json(&branch, &body.sha)).into_response()
+}
+
+#[derive(Deserialize)]
+struct CreateCommitBody {
+    message: String,
+    tree: String,
+    parents: Vec<String>,
+}
+
+async fn create_commit(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name)): Path<(String, String)>,
+    Json(body): Json<CreateCommitBody>,
+) -> Response {
+    let mut store = mock.store.lock().unwrap();
+    if owned_repo(&mut store, &headers, &owner, &name).is_none() {
+        return not_found();
+    }
+    let objects_exist = store.trees.contains_key(&body.tree)
+        && body.parents.iter().all(|parent| store.commits.contains_key(parent));
+    if !objects_exist {
+        return error(StatusCode::UNPROCESSABLE_ENTITY, "Tree or parent SHA does not exist");
+    }
+
+    let sha = store.add_commit(MockCommit {
+        tree: body.tree.clone(),
+        parents: body.parents,
+        message: body.message,
+    });
+
+    (StatusCode::CREATED, Json(json!({ "sha": sha, "tree": { "sha": body.tree } }))).into_response()
+}
+
+async fn get_git_commit(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name, sha)): Path<(String, String, String)>,
+) -> Response {
+    let store = mock.store.lock().unwrap();
+    let user = store.user(&headers);
+    if store.repo(user, &owner, &name).is_none() {
+        return not_found();
+    }
+    let Some(commit) = store.commits.get(&sha) else {
+        return not_found();
+    };
+    let parents: Vec<_> = commit.parents.iter().map(|sha| json!({ "sha": sha })).collect();
+
+    Json(json!({
+        "sha": sha,
+        "message": commit.message,
+        "tree": { "sha": commit.tree },
+        "parents": parents,
+    }))
+    .into_response()
+}
+
+/// Modes GitHub accepts for tree entries (files, executable files, directories, submodules and symlinks)
+const VALID_MODES: [&str; 5] = ["100644", "100755", "040000", "160000", "120000"];
+
+#[derive(Deserialize)]
+struct TreeEntry {
+    path: String,
+    mode: String,
+    /// `None` to remove the file from the tree
+    sha: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CreateTreeBody {
+    base_tree: Option<String>,
+    tree: Vec<TreeEntry>,
+}
+
+async fn create_tree(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name)): Path<(String, String)>,
+    Json(body): Json<CreateTreeBody>,
+) -> Response {
+    let mut store = mock.store.lock().unwrap();
+    if owned_repo(&mut store, &headers, &owner, &name).is_none() {
+        return not_found();
+    }
+
+    let mut tree = match &body.base_tree {
+        Some(sha) => match store.trees.get(sha) {
+            Some(tree) => tree.clone(),
+            None => return error(StatusCode::UNPROCESSABLE_ENTITY, "base_tree does not exist"),
+        },
+        None => Tree::new(),
+    };
+    for entry in body.tree {
+        // GitHub refuses entries without a valid mode, even when they remove a file
+        if !VALID_MODES.contains(&entry.mode.as_str()) {
+            return error(StatusCode::UNPROCESSABLE_ENTITY, "Invalid tree mode");
+        }
+        match entry.sha {
+            Some(sha) if store.blobs.contains_key(&sha) => {
+                tree.insert(entry.path, (entry.mode, sha));
+            }
+            Some(_) => return error(StatusCode::UNPROCESSABLE_ENTITY, "Blob does not exist"),
+            None => {
+                tree.remove(&entry.path);
+            }
+        }
+    }
+    let sha = store.add_tree(tree);
+
+    (StatusCode::CREATED, Json(json!({ "sha": sha }))).into_response()
+}
+
+async fn get_tree(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name, git_ref)): Path<(String, String, String)>,
+) -> Response {
+    let store = mock.store.lock().unwrap();
+    let user = store.user(&headers);
+    let Some(repo) = store.repo(user, &owner, &name) else {
+        return not_found();
+    };
+    if repo.branches.is_empty() {
+        return error(StatusCode::CONFLICT, "Git Repository is empty.");
+    }
+    let Some((_, tree)) = store.files_at(repo, &git_ref) else {
+        return not_found();
+    };
+
+    let max_entries = store.max_tree_entries.unwrap_or(usize::MAX);
+    let entries: Vec<_> = tree
+        .iter()
+        .take(max_entries)
+        .map(|(path, (mode, sha))| json!({ "path": path, "mode": mode, "type": "blob", "sha": sha }))
+        .collect();
+
+    Json(json!({ "tree": entries, "truncated": tree.len() > max_entries })).into_response()
+}
+
+#[derive(Deserialize)]
+struct CreateBlobBody {
+    content: String,
+    encoding: String,
+}
+
+async fn create_blob(
+    State(mock): State<MockGithub>,
+    headers: HeaderMap,
+    Path((owner, name)): Path<(String, String)>,
+    Json(body): Json<CreateBlobBody>,
+) -> Response {
+    let mut store = mock.store.lock().unwrap();
+    if owned_repo(&mut store, &headers, &owner, &name).is_none() {
+        return not_found();
+    }
+
+    let contents = match body.encoding.as_str() {
+        "base64" => match BASE64_STANDARD.decode(&body.content) {
+            Ok(contents) => contents,
+            Err(_) => return error(StatusCode::UNPROCESSABLE_ENTITY, "content is not valid Base64"),
+        },
+        _ => body.content.into_bytes(),
+    };
+    let sha = store.add_blob(contents);
+
+    (StatusCode::CREATED, Json(json!({ "sha": sha }))).into_response()
+}
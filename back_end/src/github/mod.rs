@@ -4,29 +4,56 @@ use anyhow::anyhow;
 use axum::http::HeaderValue;
 use base64::{Engine as _, prelude::BASE64_STANDARD};
 use bytes::Bytes;
-use chrono::Local;
-use reqwest::{RequestBuilder, Response, StatusCode, header::USER_AGENT};
-use serde::Deserialize;
+use reqwest::{
+    RequestBuilder, Response, StatusCode,
+    header::{CONTENT_DISPOSITION, USER_AGENT},
+};
+use serde::{Deserialize, de::DeserializeOwned};
 use serde_json::json;
+use tracing::debug;
+use ws_messages::{CommitDetails, CommitFile, CommitInfo};
 
 use crate::{
+    CONFIG,
     error::{AppError, GithubUserError},
-    github::access_tokens::{TokenRequestType, WithTokens, update_tokens},
+    github::access_tokens::{TokenRequestType, Tokens, WithTokens, update_tokens},
     lang::ProjectLang,
 };
 
 pub mod access_tokens;
+#[cfg(test)]
+mod mock;
+mod requests;
+#[cfg(test)]
+mod tests;
+
+use requests::{EtagCache, RateLimits};
 
 // Class that provides a wrapper pattern around GitHub API requests
 #[derive(Clone, Debug)]
 pub struct GithubClient {
     // HTTP client is stored as a field for making requests
     client: reqwest::Client,
+    // Base URL of the GitHub API, e.g. `https://api.github.com`
+    api_base: String,
+    // Base URL of the GitHub website, e.g. `https://github.com`
+    web_base: String,
+    // Responses to GET requests, which are used again if GitHub says they haven't changed
+    etags: EtagCache,
+    // When each access token can be used again after reaching a rate limit
+    rate_limits: RateLimits,
 }
 
 
 impl Default for GithubClient {
     fn default() -> Self {
+        Self::new(&CONFIG.github_api_url, &CONFIG.github_url)
+    }
+}
+
+impl GithubClient {
+    // Creates a client for the GitHub instance at the given URLs (GitHub itself, GitHub Enterprise or a mock server)
+    pub fn new(api_base: &str, web_base: &str) -> Self {
         Self {
             client: reqwest::Client::builder()
                 .default_headers(
@@ -34,32 +61,87 @@ impl Default for GithubClient {
                 )
                 .build()
                 .unwrap(),
+            api_base: api_base.to_string(),
+            web_base: web_base.to_string(),
+            etags: requests::new_etag_cache(),
+            rate_limits: RateLimits::default(),
         }
     }
 }
 
 impl GithubClient {
-    const API_BASE: &str = "https://api.github.com";
     const USER_AGENT: &str = "nea-website";
+    /// Branch that the project.toml of an imported repo is added on, when it is added with a pull request
+    /// (with a number after it if the repo already has a branch with the name)
+    const SETUP_BRANCH: &str = "ide-setup";
+    /// How many names are tried for the setup branch before giving up
+    const SETUP_BRANCH_ATTEMPTS: u32 = 10;
+    /// How many commits are listed on each page of a repo's history
+    const COMMITS_PER_PAGE: u32 = 30;
 
     // Convert an API path into a GitHub URL
-    fn api_url(path: &str) -> String {
-        format!("{}{}", Self::API_BASE, path)
+    fn api_url(&self, path: &str) -> String {
+        format!("{}{}", self.api_base, path)
+    }
+
+    // Convert a path on the GitHub website into a URL
+    pub fn web_url(&self, path: &str) -> String {
+        format!("{}{}", self.web_base, path)
+    }
+
+    // Checks that a branch, tag or commit name follows git's rules for ref names (see `git check-ref-format`),
+    // so that it can't change which part of the API is used when it is put into a URL
+    pub fn valid_ref(name: &str) -> bool {
+        !name.is_empty()
+            && name != "@"
+            && !name.starts_with(['-', '/'])
+            && !name.ends_with(['/', '.'])
+            && !name.contains("..")
+            && !name.contains("//")
+            && !name.contains("@{")
+            && !name
+                .chars()
+                .any(|c| c.is_ascii_control() || " ~^:?*[\\".contains(c))
+            && name
+                .split('/')
+                .all(|part| !part.starts_with('.') && !part.ends_with(".lock"))
+    }
+
+    // Checks that a repo name only has the characters GitHub allows in them, so that it can't point the API at
+    // another account's repo when it is put into a URL (e.g. `../someone/repo`)
+    pub fn valid_repo_name(name: &str) -> bool {
+        !name.is_empty()
+            && name != "."
+            && name != ".."
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "._-".contains(c))
+    }
+
+    // Percent-encodes text to be put into the path of a URL, keeping the slashes between its segments
+    pub fn encode_path(path: &str) -> String {
+        path.bytes()
+            .map(|b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                    (b as char).to_string()
+                }
+                _ => format!("%{b:02X}"),
+            })
+            .collect()
     }
 
     // Sends a request to the GitHub API using the given access token for authentication
     // If authentication fails, the refresh token is used to retrieve a new access token and retry the request
     async fn send_authenticated(
         &self,
-        mut req: RequestBuilder,
+        req: RequestBuilder,
         access_token: &str,
         refresh_token: Option<&str>,
     ) -> Result<WithTokens<Response>, AppError> {
+        let req = req.build().map_err(AppError::other)?;
         let req_clone = req.try_clone();
 
-        req = req.header("Authorization", format!("Bearer {access_token}"));
-
-        let mut resp = req.send().await.map_err(AppError::other)?;
+        let mut resp = self.send(req, Some(access_token)).await?;
 
         let mut new_tokens = None;
 
@@ -77,27 +159,49 @@ impl GithubClient {
 
             // retry request with new credentials
             if let Some(req) = req_clone {
-                resp = req
-                    .header(
-                        "Authorization",
-                        format!("Bearer {}", tokens.access_unencrypted),
-                    )
-                    .send()
-                    .await
-                    .map_err(AppError::other)?;
+                resp = self.send(req, Some(&tokens.access_unencrypted)).await?;
             }
             new_tokens = Some(tokens);
         }
 
-        // display an error if request failed
+        // log the request if it failed
         if !resp.status().is_success() {
-            println!("{resp:#?}");
+            debug!("GitHub request failed: {resp:?}");
         }
 
         // return new tokens if any
         Ok(WithTokens(resp, new_tokens))
     }
 
+    // Sends a request to the GitHub API and parses its JSON response, failing if the request wasn't successful
+    // `tokens` holds the newest tokens, and is updated if the access token had to be refreshed
+    async fn send_json<T: DeserializeOwned>(
+        &self,
+        req: RequestBuilder,
+        access_token: &str,
+        refresh_token: &str,
+        tokens: &mut Option<Tokens>,
+    ) -> Result<T, AppError> {
+        let (access_token, refresh_token) = tokens
+            .as_ref()
+            .map_or((access_token, refresh_token), Tokens::unencrypted);
+        let WithTokens(resp, new_tokens) = self
+            .send_authenticated(req, access_token, Some(refresh_token))
+            .await?;
+        if new_tokens.is_some() {
+            *tokens = new_tokens;
+        }
+
+        if !resp.status().is_success() {
+            return Err(AppError::other(anyhow!(
+                "GitHub request failed: {}",
+                resp.text().await.unwrap_or_default()
+            )));
+        }
+
+        resp.json().await.map_err(AppError::malformed)
+    }
+
     // Fetches information about the Github user using the access token, and caches the user's id with the encrypted token
     // Returns the user info on a successful fetch
     pub async fn get_user(
@@ -107,7 +211,7 @@ impl GithubClient {
     ) -> Result<WithTokens<GithubUser>, AppError> {
         let WithTokens(resp, tokens) = self
             .send_authenticated(
-                self.client.get(Self::api_url("/user")),
+                self.client.get(self.api_url("/user")),
                 access_token,
                 refresh_token,
             )
@@ -124,7 +228,7 @@ impl GithubClient {
         }
     }
 
-    // Fetches the files and directories of a given project
+    // Fetches the files and directories of a given project at a branch, tag or commit
     // returned as a TAR archive compressed using Gzip
     pub async fn get_project_tarball(
         &self,
@@ -132,34 +236,43 @@ impl GithubClient {
         refresh_token: &str,
         username: &str,
         repo_name: &str,
+        git_ref: &str,
     ) -> Result<WithTokens<(String, Bytes)>, AppError> {
         // fetch the tarball
         let WithTokens(resp, tokens) = self
             .send_authenticated(
-                self.client.get(Self::api_url(&format!(
-                    "/repos/{username}/{repo_name}/tarball"
+                self.client.get(self.api_url(&format!(
+                    "/repos/{username}/{repo_name}/tarball/{}",
+                    Self::encode_path(git_ref)
                 ))),
                 access_token,
                 Some(refresh_token),
             )
             .await?;
 
+        if !resp.status().is_success() {
+            return Err(AppError::other(anyhow!(
+                "failed to fetch project files at {git_ref}: {}",
+                resp.text().await.unwrap_or_default()
+            )));
+        }
+
         // get the name of the tarball
-        let disposition_header = resp.headers().get("content-disposition").expect("missing content-disposition").to_str().unwrap();
-        let tarball_name = disposition_header
-            .split(";")
-            .find_map(|s| {
-                let s = s.trim();
-                if s.starts_with("filename=") {
-                    Some(s.trim_start_matches("filename=").trim_matches('"').split('.').next().unwrap())
-                } else {
-                    None
-                }
+        let tarball_name = resp
+            .headers()
+            .get(CONTENT_DISPOSITION)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| {
+                header.split(';').find_map(|s| {
+                    let name = s.trim().strip_prefix("filename=")?.trim_matches('"');
+                    name.split('.').next()
+                })
             })
-            .unwrap();
+            .map(str::to_string)
+            .ok_or_else(|| AppError::malformed("tarball response has no file name"))?;
 
         Ok(WithTokens(
-            (tarball_name.to_string(), resp.bytes().await.map_err(AppError::other)?),
+            (tarball_name, resp.bytes().await.map_err(AppError::other)?),
             tokens,
         ))
     }
@@ -209,7 +322,7 @@ impl GithubClient {
         let WithTokens(resp, new_tokens) = self
             .send_authenticated(
                 self.client
-                    .post(Self::api_url("/user/repos"))
+                    .post(self.api_url("/user/repos"))
                     .json(&json!({ "name": repo_name, "private": private })),
                 access_token,
                 Some(refresh_token),
@@ -225,7 +338,7 @@ impl GithubClient {
         if !resp.status().is_success() {
             return Err(AppError::other(anyhow!(
                 "failed to create project: {}",
-                resp.text().await.unwrap()
+                resp.text().await.unwrap_or_default()
             )));
         }
 
@@ -255,7 +368,7 @@ impl GithubClient {
         let WithTokens(resp, tokens) = self
             .send_authenticated(
                 self.client
-                    .get(Self::api_url(&format!("/repos/{username}/{repo_name}"))),
+                    .get(self.api_url(&format!("/repos/{username}/{repo_name}"))),
                 access_token,
                 Some(refresh_token),
             )
@@ -318,7 +431,7 @@ impl GithubClient {
         let WithTokens(resp, tokens) = self
             .send_authenticated(
                 self.client
-                    .put(Self::api_url(&format!(
+                    .put(self.api_url(&format!(
                         "/repos/{username}/{repo_name}/contents/{path}"
                     )))
                     .json(&json!({
@@ -335,136 +448,572 @@ impl GithubClient {
         } else {
             Err(AppError::other(anyhow!(
                 "failed to add file: {}",
-                resp.text().await.unwrap()
+                resp.text().await.unwrap_or_default()
             )))
         }
     }
 
-    // Add multiple files to the GitHub repo with a single `git` commit
-    // Requires interfacing with the Git Database API, as described in this article: 
-    pub async fn add_multiple_files(&self, mut access_token: &str, mut refresh_token: &str, username: &str, repo_name: &str, files: Vec<(String, String)>) -> Result<WithTokens<()>, AppError> {
-        // create binary objects (blobs) that represent each file that will be added in the commit
-        println!("creating blobs");
-        let mut blob_shas = vec![];
-        for (_, content) in &files {
-            let WithTokens(resp, _) = self.send_authenticated(
+    // Commits changes to some of the files in a GitHub repo, on top of the commit `base_sha` on the given branch
+    // Only the changed files are uploaded, as the new tree is based on the tree of the base commit
+    // This fails if the branch has moved on from the base commit, as the commits made since would be overwritten
+    // Returns the SHA hash of the new commit
+    #[allow(clippy::too_many_arguments)]
+    pub async fn commit_changes(
+        &self,
+        access_token: &str,
+        refresh_token: &str,
+        username: &str,
+        repo_name: &str,
+        branch: &str,
+        base_sha: &str,
+        message: &str,
+        changes: Vec<FileChange>,
+    ) -> Result<WithTokens<String>, AppError> {
+        let repo_url = |path: &str| self.api_url(&format!("/repos/{username}/{repo_name}{path}"));
+        let mut tokens = None;
+
+        let head_sha = self
+            .send_json::<GithubBranchResponse>(
                 self.client
-                    .post(Self::api_url(&format!("/repos/{username}/{repo_name}/git/blobs")))
-                    .json(&json!({
-                        "content": content,
-                        "encoding": "utf-8"
-                    })),
+                    .get(repo_url(&format!("/git/ref/heads/{}", Self::encode_path(branch)))),
                 access_token,
-                Some(refresh_token),
+                refresh_token,
+                &mut tokens,
             )
-            .await?;
+            .await?
+            .object
+            .sha;
+        if head_sha != base_sha {
+            return Err(AppError::RemoteChanged(branch.to_string()));
+        }
 
-            let sha_hash = resp
-                .json::<GithubShaResponse>()
-                .await
-                .map_err(AppError::other)?
-                .sha;
+        let base_tree_sha = self
+            .send_json::<GithubCommitResponse>(
+                self.client.get(repo_url(&format!("/git/commits/{base_sha}"))),
+                access_token,
+                refresh_token,
+                &mut tokens,
+            )
+            .await?
+            .tree
+            .sha;
 
-            // add the SHA hash for each file into list so that they can be add to the Git tree later
-            blob_shas.push(sha_hash);
+        // upload a blob for each changed file (as base64, so that binary files are kept as they are)
+        // deleted files are removed from the tree by giving them no SHA hash
+        let mut tree = vec![];
+        for change in changes {
+            let sha = match change.contents {
+                Some(contents) => Some(
+                    self.send_json::<GithubShaResponse>(
+                        self.client.post(repo_url("/git/blobs")).json(&json!({
+                            "content": BASE64_STANDARD.encode(contents),
+                            "encoding": "base64"
+                        })),
+                        access_token,
+                        refresh_token,
+                        &mut tokens,
+                    )
+                    .await?
+                    .sha,
+                ),
+                None => None,
+            };
+
+            tree.push(json!({
+                "path": change.path,
+                "mode": change.mode,
+                "type": "blob",
+                "sha": sha
+            }));
         }
 
-        // create a git tree which represents the file tree structure of the entire contents of the new commit
-        println!("creating tree");
-        let json = json!({
-                    "tree": files.iter().zip(blob_shas).map(|((path, _), sha_hash)| json!({
-                        "path": path,
-                        "mode": "100644",
-                        "type": "blob",
-                        "sha": sha_hash
-                    })).collect::<Vec<_>>(),
-                });
-
-        println!("send tree json: {:#?}", &json);
-
-        // add the tree using the git db api
-        let WithTokens(resp, tokens) = self.send_authenticated(
-            self.client
-                .post(Self::api_url(&format!("/repos/{username}/{repo_name}/git/trees")))
-                .json(&json),
-            access_token,
-            Some(refresh_token),
-        )
-        .await?;
-        update_tokens!(access_token, refresh_token, tokens);
+        let tree_sha = self
+            .send_json::<GithubShaResponse>(
+                self.client.post(repo_url("/git/trees")).json(&json!({
+                    "base_tree": base_tree_sha,
+                    "tree": tree,
+                })),
+                access_token,
+                refresh_token,
+                &mut tokens,
+            )
+            .await?
+            .sha;
 
-        let tree_sha = resp
-            .json::<GithubShaResponse>()
-            .await
-            .map_err(AppError::other)?
+        let commit_sha = self
+            .send_json::<GithubShaResponse>(
+                self.client.post(repo_url("/git/commits")).json(&json!({
+                    "message": message,
+                    "tree": tree_sha,
+                    "parents": [base_sha],
+                })),
+                access_token,
+                refresh_token,
+                &mut tokens,
+            )
+            .await?
             .sha;
 
-        // get the tree for the previous (parent) commit
-        println!("getting parent tree");
-        let WithTokens(resp, tokens) = self.send_authenticated(
+        // move the branch on to the new commit
+        // GitHub also refuses this if another commit was added to the branch since it was checked above
+        self.send_json::<GithubBranchResponse>(
             self.client
-                .get(Self::api_url(&format!("/repos/{username}/{repo_name}/git/refs/heads/main"))),
+                .patch(repo_url(&format!("/git/refs/heads/{}", Self::encode_path(branch))))
+                .json(&json!({ "sha": commit_sha, "force": false })),
             access_token,
-            Some(refresh_token),
+            refresh_token,
+            &mut tokens,
         )
         .await?;
-        update_tokens!(access_token, refresh_token, tokens);
 
-        let parent_sha = resp
-            .json::<GithubBranchResponse>()
-            .await
-            .map_err(AppError::other)?
-            .object
+        Ok(WithTokens(commit_sha, tokens))
+    }
+
+    // Fetches information about a GitHub repo, including its default branch
+    pub async fn get_repo(
+        &self,
+        access_token: &str,
+        refresh_token: &str,
+        username: &str,
+        repo_name: &str,
+    ) -> Result<WithTokens<GithubRepo>, AppError> {
+        let mut tokens = None;
+        let repo = self
+            .send_json(
+                self.client
+                    .get(self.api_url(&format!("/repos/{username}/{repo_name}"))),
+                access_token,
+                refresh_token,
+                &mut tokens,
+            )
+            .await?;
+
+        Ok(WithTokens(repo, tokens))
+    }
+
+    // Lists the names of the branches in a GitHub repo, along with the name of its default branch
+    pub async fn list_branches(
+        &self,
+        access_token: &str,
+        refresh_token: &str,
+        username: &str,
+        repo_name: &str,
+    ) -> Result<WithTokens<(Vec<String>, String)>, AppError> {
+        let WithTokens(repo, mut tokens) = self
+            .get_repo(access_token, refresh_token, username, repo_name)
+            .await?;
+
+        // GitHub returns at most 100 branches per page
+        let mut branches = vec![];
+        for page in 1.. {
+            let names = self
+                .send_json::<Vec<GithubBranchName>>(
+                    self.client
+                        .get(self.api_url(&format!("/repos/{username}/{repo_name}/branches")))
+                        .query(&[("per_page", 100), ("page", page)]),
+                    access_token,
+                    refresh_token,
+                    &mut tokens,
+                )
+                .await?;
+            let last_page = names.len() < 100;
+            branches.extend(names.into_iter().map(|branch| branch.name));
+            if last_page {
+                break;
+            }
+        }
+
+        Ok(WithTokens((branches, repo.default_branch), tokens))
+    }
+
+    // Finds out which branch, tag or commit to open a GitHub repo at (its default branch if none is given),
+    // and the commit it points to
+    pub async fn resolve_ref(
+        &self,
+        access_token: &str,
+        refresh_token: &str,
+        username: &str,
+        repo_name: &str,
+        git_ref: Option<&str>,
+    ) -> Result<WithTokens<ResolvedRef>, AppError> {
+        let repo_url = |path: &str| self.api_url(&format!("/repos/{username}/{repo_name}{path}"));
+
+        let WithTokens(repo, mut tokens) = self
+            .get_repo(access_token, refresh_token, username, repo_name)
+            .await?;
+        let (git_ref, branch) = match git_ref {
+            Some(git_ref) if git_ref != repo.default_branch => {
+                if !Self::valid_ref(git_ref) {
+                    return Err(AppError::BadRequest(format!("{git_ref} isn't a valid branch, tag or commit")));
+                }
+
+                // GitHub only finds the branch if the ref is the name of one (rather than a tag or commit)
+                let (access, refresh) = tokens
+                    .as_ref()
+                    .map_or((access_token, refresh_token), Tokens::unencrypted);
+                let WithTokens(resp, new_tokens) = self
+                    .send_authenticated(
+                        self.client
+                            .get(repo_url(&format!("/branches/{}", Self::encode_path(git_ref)))),
+                        access,
+                        Some(refresh),
+                    )
+                    .await?;
+                tokens = new_tokens.or(tokens);
+
+                // any error other than the branch not existing (e.g. GitHub being unavailable) is passed on,
+                // rather than treating the ref as a tag or commit
+                let is_branch = resp.status() != StatusCode::NOT_FOUND;
+                if is_branch && !resp.status().is_success() {
+                    return Err(AppError::other(anyhow!(
+                        "failed to check for branch {git_ref}: {}",
+                        resp.text().await.unwrap_or_default()
+                    )));
+                }
+
+                (git_ref.to_string(), is_branch.then(|| git_ref.to_string()))
+            }
+            _ => (repo.default_branch.clone(), Some(repo.default_branch)),
+        };
+
+        let sha = self
+            .send_json::<GithubShaResponse>(
+                self.client
+                    .get(repo_url(&format!("/commits/{}", Self::encode_path(&git_ref)))),
+                access_token,
+                refresh_token,
+                &mut tokens,
+            )
+            .await?
             .sha;
 
-        // add the new commit, with the new tree and parent commit's tree
-        println!("creating commit");
-        let WithTokens(resp, tokens) = self.send_authenticated(
+        Ok(WithTokens(
+            ResolvedRef {
+                git_ref,
+                branch,
+                sha,
+            },
+            tokens,
+        ))
+    }
+
+    // Creates a new branch in a GitHub repo, starting at the given commit
+    pub async fn create_branch(
+        &self,
+        access_token: &str,
+        refresh_token: &str,
+        username: &str,
+        repo_name: &str,
+        branch: &str,
+        sha: &str,
+    ) -> Result<WithTokens<()>, AppError> {
+        let mut tokens = None;
+        self.send_json::<GithubBranchResponse>(
             self.client
-                .post(Self::api_url(&format!("/repos/{username}/{repo_name}/git/commits")))
+                .post(self.api_url(&format!("/repos/{username}/{repo_name}/git/refs")))
                 .json(&json!({
-                    "message": format!("Save from IDE {}", Local::now().to_rfc3339()),
-                    "tree": tree_sha,
-                    "parents": [parent_sha],
+                    "ref": format!("refs/heads/{branch}"),
+                    "sha": sha,
                 })),
             access_token,
-            Some(refresh_token),
+            refresh_token,
+            &mut tokens,
         )
         .await?;
-        update_tokens!(access_token, refresh_token, tokens);
 
-        let commit_sha = resp
-            .json::<GithubShaResponse>()
+        Ok(WithTokens((), tokens))
+    }
+
+    // Lists the commits on a branch of a GitHub repo (or before a tag or commit), newest first, a page at a time
+    // If a path is given, only the commits which changed that file are listed
+    #[allow(clippy::too_many_arguments)]
+    pub async fn list_commits(
+        &self,
+        access_token: &str,
+        refresh_token: &str,
+        username: &str,
+        repo_name: &str,
+        git_ref: Option<&str>,
+        path: Option<&str>,
+        page: u32,
+    ) -> Result<WithTokens<Vec<CommitInfo>>, AppError> {
+        let mut query = vec![
+            ("per_page", Self::COMMITS_PER_PAGE.to_string()),
+            ("page", page.to_string()),
+        ];
+        if let Some(git_ref) = git_ref {
+            query.push(("sha", git_ref.to_string()));
+        }
+        if let Some(path) = path {
+            query.push(("path", path.to_string()));
+        }
+
+        let mut tokens = None;
+        let commits = self
+            .send_json::<Vec<GithubCommit>>(
+                self.client
+                    .get(self.api_url(&format!("/repos/{username}/{repo_name}/commits")))
+                    .query(&query),
+                access_token,
+                refresh_token,
+                &mut tokens,
+            )
+            .await?;
+
+        Ok(WithTokens(
+            commits.into_iter().map(|commit| commit.into_details().commit).collect(),
+            tokens,
+        ))
+    }
+
+    // Fetches a commit from a GitHub repo, with the files it changed and the diff of each file
+    pub async fn get_commit(
+        &self,
+        access_token: &str,
+        refresh_token: &str,
+        username: &str,
+        repo_name: &str,
+        sha: &str,
+    ) -> Result<WithTokens<CommitDetails>, AppError> {
+        let mut tokens = None;
+        let commit = self
+            .send_json::<GithubCommit>(
+                self.client
+                    .get(self.api_url(&format!("/repos/{username}/{repo_name}/commits/{sha}"))),
+                access_token,
+                refresh_token,
+                &mut tokens,
+            )
+            .await?;
+
+        Ok(WithTokens(commit.into_details(), tokens))
+    }
+
+    // Fetches the contents of a file in a GitHub repo as it was at a commit (or on a branch or tag)
+    pub async fn get_file_at(
+        &self,
+        access_token: &str,
+        refresh_token: &str,
+        username: &str,
+        repo_name: &str,
+        path: &str,
+        git_ref: &str,
+    ) -> Result<WithTokens<Bytes>, AppError> {
+        // the raw media type makes GitHub return the file itself, rather than JSON with the contents in base64
+        let WithTokens(resp, tokens) = self
+            .send_authenticated(
+                self.client
+                    .get(self.api_url(&format!(
+                        "/repos/{username}/{repo_name}/contents/{}",
+                        Self::encode_path(path)
+                    )))
+                    .query(&[("ref", git_ref)])
+                    .header("Accept", "application/vnd.github.raw+json"),
+                access_token,
+                Some(refresh_token),
+            )
+            .await?;
+
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Err(AppError::NotFound);
+        }
+        if !resp.status().is_success() {
+            return Err(AppError::other(anyhow!(
+                "failed to get {path} at {git_ref}: {}",
+                resp.text().await.unwrap_or_default()
+            )));
+        }
+        let contents = resp.bytes().await.map_err(AppError::other)?;
+
+        Ok(WithTokens(contents, tokens))
+    }
+
+    // Lists the repos owned by the user, most recently updated first
+    pub async fn list_user_repos(
+        &self,
+        access_token: &str,
+        refresh_token: &str,
+    ) -> Result<WithTokens<Vec<UserRepo>>, AppError> {
+        let mut tokens = None;
+
+        // GitHub returns at most 100 repos per page
+        let mut repos = vec![];
+        for page in 1.. {
+            let page_repos = self
+                .send_json::<Vec<UserRepo>>(
+                    self.client.get(self.api_url("/user/repos")).query(&[
+                        ("affiliation", "owner".to_string()),
+                        ("sort", "updated".to_string()),
+                        ("per_page", "100".to_string()),
+                        ("page", page.to_string()),
+                    ]),
+                    access_token,
+                    refresh_token,
+                    &mut tokens,
+                )
+                .await?;
+            let last_page = page_repos.len() < 100;
+            repos.extend(page_repos);
+            if last_page {
+                break;
+            }
+        }
+
+        Ok(WithTokens(repos, tokens))
+    }
+
+    // Lists the paths of every file in a GitHub repo at a branch, tag or commit (which is empty if the repo has no commits)
+    pub async fn list_files(
+        &self,
+        access_token: &str,
+        refresh_token: &str,
+        username: &str,
+        repo_name: &str,
+        git_ref: &str,
+    ) -> Result<WithTokens<Vec<String>>, AppError> {
+        let WithTokens(resp, tokens) = self
+            .send_authenticated(
+                self.client
+                    .get(self.api_url(&format!(
+                        "/repos/{username}/{repo_name}/git/trees/{}",
+                        Self::encode_path(git_ref)
+                    )))
+                    .query(&[("recursive", "1")]),
+                access_token,
+                Some(refresh_token),
+            )
+            .await?;
+
+        // GitHub responds with 409 Conflict for a repo without any commits
+        if resp.status() == StatusCode::CONFLICT {
+            return Ok(WithTokens(vec![], tokens));
+        }
+        if !resp.status().is_success() {
+            return Err(AppError::other(anyhow!(
+                "failed to list files: {}",
+                resp.text().await.unwrap_or_default()
+            )));
+        }
+
+        let tree = resp
+            .json::<GithubTreeResponse>()
             .await
-            .map_err(AppError::other)?
-            .sha;
+            .map_err(AppError::malformed)?;
+        // a partial list could make the repo look like another language, or miss an existing project.toml
+        if tree.truncated {
+            return Err(AppError::BadRequest(format!(
+                "{repo_name} has too many files for them all to be listed"
+            )));
+        }
+        let files = tree
+            .tree
+            .into_iter()
+            .filter(|entry| entry.kind == "blob")
+            .map(|entry| entry.path)
+            .collect();
+
+        Ok(WithTokens(files, tokens))
+    }
+
+    // Adds a project.toml to a repo which was created outside of the website, so that it can be run in the editor
+    // It is either committed straight to the default branch, or added on a new branch with a pull request
+    // to merge it (e.g. if the default branch is protected), in which case the URL of the pull request is returned
+    #[allow(clippy::too_many_arguments)]
+    pub async fn add_project_toml(
+        &self,
+        access_token: &str,
+        refresh_token: &str,
+        username: &str,
+        repo_name: &str,
+        default_branch: &str,
+        project_toml: &str,
+        pull_request: bool,
+    ) -> Result<WithTokens<Option<String>>, AppError> {
+        let repo_url = |path: &str| self.api_url(&format!("/repos/{username}/{repo_name}{path}"));
+        let mut tokens = None;
+
+        let branch = if pull_request {
+            let sha = self
+                .send_json::<GithubShaResponse>(
+                    self.client
+                        .get(repo_url(&format!("/commits/{}", Self::encode_path(default_branch)))),
+                    access_token,
+                    refresh_token,
+                    &mut tokens,
+                )
+                .await?
+                .sha;
 
-        // update the main branch to point to the newly created commit as being the newest commit
-        let WithTokens(resp, tokens) = self.send_authenticated(
+            // the branch may be left over from importing the repo before,
+            // so a new branch is made with a number after the name rather than changing it
+            let mut attempt = 1;
+            loop {
+                let branch = match attempt {
+                    1 => Self::SETUP_BRANCH.to_string(),
+                    _ => format!("{}-{attempt}", Self::SETUP_BRANCH),
+                };
+                let (access, refresh) = tokens
+                    .as_ref()
+                    .map_or((access_token, refresh_token), Tokens::unencrypted);
+                let WithTokens(resp, new_tokens) = self
+                    .send_authenticated(
+                        self.client.post(repo_url("/git/refs")).json(&json!({
+                            "ref": format!("refs/heads/{branch}"),
+                            "sha": sha,
+                        })),
+                        access,
+                        Some(refresh),
+                    )
+                    .await?;
+                tokens = new_tokens.or(tokens);
+
+                if resp.status().is_success() {
+                    break branch;
+                }
+                let error = resp.text().await.unwrap_or_default();
+                if !error.contains("already exists") || attempt == Self::SETUP_BRANCH_ATTEMPTS {
+                    return Err(AppError::other(anyhow!("failed to create branch {branch}: {error}")));
+                }
+                attempt += 1;
+            }
+        } else {
+            default_branch.to_string()
+        };
+
+        self.send_json::<serde_json::Value>(
             self.client
-                .patch(Self::api_url(&format!("/repos/{username}/{repo_name}/git/refs/heads/main")))
+                .put(repo_url("/contents/.ide/project.toml"))
                 .json(&json!({
-                    "sha": commit_sha
+                    "message": "Add .ide/project.toml",
+                    "content": BASE64_STANDARD.encode(project_toml),
+                    "branch": branch,
                 })),
             access_token,
-            Some(refresh_token),
+            refresh_token,
+            &mut tokens,
         )
         .await?;
-        let status = resp.status();
-
-        let updated_branch_sha = resp
-            .json::<GithubBranchResponse>()
-            .await
-            .map_err(AppError::other)?
-            .object
-            .sha;
 
-        // check whether the commit succeeded or not
-        if !status.is_success() || updated_branch_sha != commit_sha {
-            return Err(AppError::other(anyhow!("failed to update branch")));
+        if !pull_request {
+            return Ok(WithTokens(None, tokens));
         }
 
-        Ok(WithTokens((), tokens))
+        let pull = self
+            .send_json::<GithubPullResponse>(
+                self.client.post(repo_url("/pulls")).json(&json!({
+                    "title": "Add project settings for the online IDE",
+                    "head": branch,
+                    "base": default_branch,
+                    "body": "Adds `.ide/project.toml`, which sets the commands used to run and format the project \
+                        in the online IDE. Merge this to be able to run the project from the editor.",
+                })),
+                access_token,
+                refresh_token,
+                &mut tokens,
+            )
+            .await?;
+
+        Ok(WithTokens(Some(pull.html_url), tokens))
     }
 
     // Get the README.md file from a given GitHub repo
@@ -477,7 +1026,7 @@ impl GithubClient {
     ) -> Result<WithTokens<String>, AppError> {
         let WithTokens(resp, tokens) = self
             .send_authenticated(
-                self.client.get(Self::api_url(&format!(
+                self.client.get(self.api_url(&format!(
                     "/repos/{username}/{repo_name}/readme"
                 ))),
                 access_token,
@@ -488,7 +1037,7 @@ impl GithubClient {
         let readme_resp = resp
             .json::<GithubReadmeResponse>()
             .await
-            .map_err(AppError::other)?;
+            .map_err(AppError::malformed)?;
         update_tokens!(access_token, refresh_token, tokens);
 
         let WithTokens(resp, tokens) = self
@@ -504,6 +1053,35 @@ impl GithubClient {
         Ok(WithTokens(readme, tokens))
     }
 
+    // Gets the README of a public repo without any user's tokens (for webhooks, which aren't sent on behalf of a user)
+    // Returns `None` if the repo doesn't have a README
+    pub async fn get_public_readme(
+        &self,
+        username: &str,
+        repo_name: &str,
+    ) -> Result<Option<String>, AppError> {
+        let req = self
+            .client
+            .get(self.api_url(&format!("/repos/{username}/{repo_name}/readme")))
+            .header("Accept", "application/vnd.github.raw+json")
+            .build()
+            .map_err(AppError::other)?;
+        let resp = self.send(req, None).await?;
+
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !resp.status().is_success() {
+            return Err(AppError::other(anyhow!(
+                "failed to get the readme of {username}/{repo_name}: {}",
+                resp.text().await.unwrap_or_default()
+            )));
+        }
+        let readme = resp.text().await.map_err(AppError::other)?;
+
+        Ok(Some(readme))
+    }
+
     // Create a fork of a GitHub repo (to allow for remixing projects)
     pub async fn fork_repo(
         &self,
@@ -514,7 +1092,7 @@ impl GithubClient {
     ) -> Result<WithTokens<()>, AppError> {
         let WithTokens(resp, tokens) = self
             .send_authenticated(
-                self.client.post(Self::api_url(&format!(
+                self.client.post(self.api_url(&format!(
                     "/repos/{username}/{repo_name}/forks"
                 ))),
                 access_token,
@@ -525,7 +1103,7 @@ impl GithubClient {
         if !resp.status().is_success() {
             return Err(AppError::other(anyhow!(
                 "failed to fork repo: {}",
-                resp.text().await.unwrap()
+                resp.text().await.unwrap_or_default()
             )));
         }
 
@@ -536,7 +1114,7 @@ impl GithubClient {
     pub async fn user_installed(&self, access_token: &str) -> Result<bool, AppError> {
         let WithTokens(resp, tokens) = self
             .send_authenticated(
-                self.client.get(Self::api_url("/user/installations")),
+                self.client.get(self.api_url("/user/installations")),
                 access_token,
                 None,
             )
@@ -547,7 +1125,7 @@ impl GithubClient {
         let installation_count = resp
             .json::<InstallationsResponse>()
             .await
-            .map_err(AppError::other)?
+            .map_err(AppError::malformed)?
             .total_count;
 
         Ok(installation_count > 0)
@@ -595,3 +1173,131 @@ struct GithubShaResponse {
 struct GithubBranchResponse {
     object: GithubShaResponse,
 }
+
+#[derive(Deserialize)]
+pub struct GithubRepo {
+    pub default_branch: String,
+    pub private: bool,
+}
+
+/// A repo owned by the user, from [`GithubClient::list_user_repos`]
+#[derive(Deserialize)]
+pub struct UserRepo {
+    pub name: String,
+    pub description: Option<String>,
+    pub private: bool,
+    /// The language most of the repo is written in, as named by GitHub (e.g. "C++")
+    pub language: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GithubTreeResponse {
+    tree: Vec<GithubTreeEntry>,
+    /// Whether GitHub left out some of the entries, as the tree is too big to list at once
+    #[serde(default)]
+    truncated: bool,
+}
+
+#[derive(Deserialize)]
+struct GithubTreeEntry {
+    path: String,
+    /// `blob` for files, `tree` for directories and `commit` for submodules
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Deserialize)]
+struct GithubPullResponse {
+    html_url: String,
+}
+
+#[derive(Deserialize)]
+struct GithubBranchName {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct GithubCommitResponse {
+    tree: GithubShaResponse,
+}
+
+#[derive(Deserialize)]
+struct GithubCommit {
+    sha: String,
+    commit: GithubCommitData,
+    /// The GitHub account of the commit's author (`None` if their email isn't linked to an account)
+    author: Option<GithubLogin>,
+    /// Only included when a single commit is fetched
+    #[serde(default)]
+    files: Vec<GithubCommitFile>,
+}
+
+impl GithubCommit {
+    fn into_details(self) -> CommitDetails {
+        CommitDetails {
+            commit: CommitInfo {
+                sha: self.sha,
+                message: self.commit.message,
+                // the author's GitHub username is used if they have an account, as it is shown on the website
+                author: self.author.map_or(self.commit.author.name, |author| author.login),
+                date: self.commit.author.date,
+            },
+            files: self
+                .files
+                .into_iter()
+                .map(|file| CommitFile {
+                    path: file.filename.into(),
+                    status: file.status,
+                    additions: file.additions,
+                    deletions: file.deletions,
+                    patch: file.patch,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GithubCommitData {
+    message: String,
+    author: GithubCommitAuthor,
+}
+
+#[derive(Deserialize)]
+struct GithubCommitAuthor {
+    name: String,
+    date: String,
+}
+
+#[derive(Deserialize)]
+struct GithubLogin {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct GithubCommitFile {
+    filename: String,
+    status: String,
+    additions: u32,
+    deletions: u32,
+    patch: Option<String>,
+}
+
+/// A branch, tag or commit of a repo, found by [`GithubClient::resolve_ref`]
+pub struct ResolvedRef {
+    pub git_ref: String,
+    /// Name of the branch, if the ref is a branch
+    pub branch: Option<String>,
+    /// SHA hash of the commit the ref points to
+    pub sha: String,
+}
+
+/// A file to change in a commit made with [`GithubClient::commit_changes`]
+pub struct FileChange {
+    /// Path of the file relative to the root of the repo
+    pub path: String,
+    /// Git file mode of the file (e.g. `100755` for executable files)
+    pub mode: String,
+    /// New contents of the file (`None` if the file has been deleted)
+    pub contents: Option<Vec<u8>>,
+}
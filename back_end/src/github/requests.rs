@@ -0,0 +1,280 @@
+// Sending requests to the GitHub API, which are retried when GitHub fails or a rate limit is hit, and use ETags
+// so that responses which haven't changed aren't downloaded again (and don't count towards the rate limit)
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::http;
+use bytes::{Bytes, BytesMut};
+use chrono::{DateTime, Utc};
+use futures::{StreamExt as _, future, stream};
+use reqwest::{
+    Body, Method, Request, Response, StatusCode,
+    header::{self, HeaderMap, HeaderValue},
+};
+use tracing::{debug, warn};
+
+use super::GithubClient;
+use crate::{auth::LruCache, error::AppError};
+
+// how many times a request is sent before giving up
+const MAX_ATTEMPTS: u32 = 4;
+// how long to wait before the first retry (which doubles for each retry after)
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+// rate limits which reset later than this fail the request, rather than waiting for them
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(10);
+// how many responses are cached with their ETags
+const ETAG_CACHE_SIZE: usize = 50;
+// larger responses (e.g. tarballs of big repos) aren't cached, to limit the memory used
+const MAX_CACHED_BODY: usize = 4 * 1024 * 1024;
+// a warning is logged when a token has fewer requests than this left before it is rate limited
+const LOW_RATE_LIMIT: u64 = 100;
+
+/// A response that was cached with its ETag, which is used again if GitHub says that it hasn't changed
+#[derive(Clone, Debug)]
+pub struct CachedResponse {
+    etag: HeaderValue,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl CachedResponse {
+    fn to_response(&self) -> Response {
+        let mut resp = http::Response::new(self.body.clone());
+        *resp.status_mut() = self.status;
+        resp.headers_mut().clone_from(&self.headers);
+
+        resp.into()
+    }
+}
+
+/// Responses to GET requests, by the access token and URL of the request
+/// (so that responses for private repos are only given to the users who can see them)
+pub type EtagCache = Arc<Mutex<LruCache<String, CachedResponse>>>;
+
+pub fn new_etag_cache() -> EtagCache {
+    Arc::new(Mutex::new(LruCache::new(ETAG_CACHE_SIZE)))
+}
+
+/// The time at which each access token can make requests again, after hitting a rate limit
+/// (requests without a token are under the key "")
+pub type RateLimits = Arc<Mutex<HashMap<String, DateTime<Utc>>>>;
+
+/// What to do after receiving a response
+enum Retry {
+    /// The response is the final one
+    No,
+    /// Send the request again after waiting
+    After {
+        wait: Duration,
+        /// Whether the request was refused because of a rate limit (so it fails if it can't be sent again)
+        rate_limited: bool,
+    },
+    /// A rate limit was hit that resets too far in the future to wait for (after the given time, if known)
+    RateLimited(Option<Duration>),
+}
+
+// exponential backoff, with some randomness so that requests which failed together aren't all retried together
+fn backoff(attempt: u32) -> Duration {
+    BASE_BACKOFF * 2u32.pow(attempt - 1) + Duration::from_millis(rand::random_range(0..250))
+}
+
+// gets the value of a header which is a number (e.g. `X-RateLimit-Remaining`)
+fn number_header(resp: &Response, name: &str) -> Option<u64> {
+    resp.headers().get(name)?.to_str().ok()?.parse().ok()
+}
+
+impl GithubClient {
+    // Sends a request to the GitHub API, authenticated with the access token if one is given
+    // GET requests use the ETag of the last response to the same request, and requests are retried with backoff
+    // for server errors (if sending the request again can't repeat a change) and rate limits that reset soon
+    pub(super) async fn send(
+        &self,
+        mut req: Request,
+        access_token: Option<&str>,
+    ) -> Result<Response, AppError> {
+        let token_key = access_token.unwrap_or_default().to_string();
+
+        // GitHub isn't sent requests that would be refused because of a rate limit which hasn't reset yet
+        if let Some(wait) = self.rate_limit_wait(&token_key) {
+            return Err(AppError::GithubRateLimited {
+                retry_after: Some(wait.as_secs().max(1)),
+            });
+        }
+
+        if let Some(token) = access_token {
+            let auth = HeaderValue::from_str(&format!("Bearer {token}")).map_err(AppError::other)?;
+            req.headers_mut().insert(header::AUTHORIZATION, auth);
+        }
+
+        let cache_key = (req.method() == Method::GET).then(|| format!("{token_key} {}", req.url()));
+        let cached = cache_key
+            .as_ref()
+            .and_then(|key| self.etags.lock().unwrap().get(key).cloned());
+        if let Some(cached) = &cached {
+            req.headers_mut().insert(header::IF_NONE_MATCH, cached.etag.clone());
+        }
+
+        // sending one of these requests twice has the same effect as sending it once
+        let idempotent = matches!(
+            *req.method(),
+            Method::GET | Method::HEAD | Method::PUT | Method::DELETE
+        );
+
+        let mut attempt = 1;
+        let mut resp = loop {
+            // requests with a streamed body can't be cloned, so they are only sent once
+            let next_req = if attempt < MAX_ATTEMPTS { req.try_clone() } else { None };
+            let result = self.client.execute(req).await;
+
+            let retry = match &result {
+                Ok(resp) => Self::check_response(resp, attempt, idempotent),
+                Err(err) if idempotent && (err.is_connect() || err.is_timeout()) => Retry::After {
+                    wait: backoff(attempt),
+                    rate_limited: false,
+                },
+                Err(_) => Retry::No,
+            };
+            let wait = match retry {
+                Retry::No => break result.map_err(AppError::other)?,
+                Retry::RateLimited(wait) => return Err(self.rate_limited(&token_key, wait)),
+                Retry::After { wait, rate_limited } => match next_req {
+                    Some(next_req) => {
+                        req = next_req;
+                        wait
+                    }
+                    None if rate_limited => return Err(self.rate_limited(&token_key, Some(wait))),
+                    None => break result.map_err(AppError::other)?,
+                },
+            };
+
+            debug!("retrying GitHub request in {wait:?} (attempt {attempt} failed)");
+            tokio::time::sleep(wait).await;
+            attempt += 1;
+        };
+
+        if resp.status() == StatusCode::NOT_MODIFIED
+            && let Some(cached) = cached
+        {
+            return Ok(cached.to_response());
+        }
+
+        if let Some(key) = cache_key
+            && resp.status().is_success()
+            && let Some(etag) = resp.headers().get(header::ETAG).cloned()
+            // bodies which say they are too big to cache aren't read into memory
+            && resp.content_length().is_none_or(|len| len <= MAX_CACHED_BODY as u64)
+        {
+            // the body has to be read to be cached, so the response is rebuilt from it
+            let status = resp.status();
+            let headers = resp.headers().clone();
+            let mut body = BytesMut::new();
+            while let Some(chunk) = resp.chunk().await.map_err(AppError::other)? {
+                body.extend_from_slice(&chunk);
+
+                // a body without a length is only read until it turns out to be too big,
+                // then the rest of it is streamed after the part which has been read
+                if body.len() > MAX_CACHED_BODY {
+                    let read = stream::once(future::ready(Ok(body.freeze())));
+                    let mut rebuilt = http::Response::new(Body::wrap_stream(read.chain(resp.bytes_stream())));
+                    *rebuilt.status_mut() = status;
+                    *rebuilt.headers_mut() = headers;
+
+                    return Ok(rebuilt.into());
+                }
+            }
+
+            let cached = CachedResponse {
+                etag,
+                status,
+                headers,
+                body: body.freeze(),
+            };
+            self.etags.lock().unwrap().put(key, cached.clone());
+
+            return Ok(cached.to_response());
+        }
+
+        Ok(resp)
+    }
+
+    // works out whether a request needs to be sent again from its response
+    fn check_response(resp: &Response, attempt: u32, idempotent: bool) -> Retry {
+        let remaining = number_header(resp, "x-ratelimit-remaining");
+        if let Some(remaining) = remaining
+            && remaining < LOW_RATE_LIMIT
+        {
+            warn!("{remaining} GitHub API requests left before the rate limit is reached");
+        }
+
+        let status = resp.status();
+        if status == StatusCode::FORBIDDEN || status == StatusCode::TOO_MANY_REQUESTS {
+            let wait = if let Some(secs) = number_header(resp, "retry-after") {
+                // secondary rate limits (e.g. for making too many requests at once) say how long to wait
+                Some(Duration::from_secs(secs))
+            } else if remaining == Some(0) {
+                // the primary rate limit resets at the given time (in seconds since the Unix epoch)
+                let now = u64::try_from(Utc::now().timestamp()).unwrap_or_default();
+                number_header(resp, "x-ratelimit-reset").map(|reset| Duration::from_secs(reset.saturating_sub(now)))
+            } else if status == StatusCode::TOO_MANY_REQUESTS {
+                None
+            } else {
+                // forbidden for another reason (e.g. the user can't access the repo)
+                return Retry::No;
+            };
+
+            return match wait {
+                Some(wait) if wait > MAX_RETRY_WAIT => Retry::RateLimited(Some(wait)),
+                wait => Retry::After {
+                    wait: wait.unwrap_or_else(|| backoff(attempt)),
+                    rate_limited: true,
+                },
+            };
+        }
+
+        if status.is_server_error() && idempotent {
+            return Retry::After {
+                wait: backoff(attempt),
+                rate_limited: false,
+            };
+        }
+
+        Retry::No
+    }
+
+    // how long until requests can be made with the token again, if it has hit a rate limit
+    fn rate_limit_wait(&self, token_key: &str) -> Option<Duration> {
+        let mut rate_limits = self.rate_limits.lock().unwrap();
+        let until = *rate_limits.get(token_key)?;
+
+        match (until - Utc::now()).to_std() {
+            Ok(wait) => Some(wait),
+            // the rate limit has reset
+            Err(_) => {
+                rate_limits.remove(token_key);
+                None
+            }
+        }
+    }
+
+    // remembers that the token has hit a rate limit, and gives the error for the request that hit it
+    fn rate_limited(&self, token_key: &str, wait: Option<Duration>) -> AppError {
+        warn!("GitHub rate limit reached, resetting in {wait:?}");
+        if let Some(wait) = wait
+            && let Ok(wait) = chrono::Duration::from_std(wait)
+        {
+            self.rate_limits
+                .lock()
+                .unwrap()
+                .insert(token_key.to_string(), Utc::now() + wait);
+        }
+
+        AppError::GithubRateLimited {
+            retry_after: wait.map(|wait| wait.as_secs().max(1)),
+        }
+    }
+}
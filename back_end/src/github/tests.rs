@@ -0,0 +1,522 @@
+// Tests of the project, remix and save flows, which make requests to a mock GitHub server
+
+use std::io::Read as _;
+
+use axum::http::StatusCode;
+use flate2::read::GzDecoder;
+
+use super::{FileChange, GithubClient, access_tokens::WithTokens, mock::MockGithub};
+use crate::{error::AppError, lang::ProjectLang};
+
+const ALICE: &str = "alice-token";
+const BOB: &str = "bob-token";
+
+// starts a mock GitHub with two users, and a client which sends requests to it
+async fn setup() -> (MockGithub, GithubClient) {
+    let mock = MockGithub::start().await;
+    mock.add_user(ALICE, 1, "alice");
+    mock.add_user(BOB, 2, "bob");
+    let client = GithubClient::new(&mock.url, &mock.url);
+
+    (mock, client)
+}
+
+// the files in a tarball from GitHub, by their path within the repo
+fn unpack_tarball(dir_name: &str, tarball: &[u8]) -> Vec<(String, String)> {
+    let mut archive = tar::Archive::new(GzDecoder::new(tarball));
+    archive
+        .entries()
+        .unwrap()
+        .map(|entry| {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().to_string_lossy().to_string();
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents).unwrap();
+            let path = path.strip_prefix(&format!("{dir_name}/")).unwrap().to_string();
+            (path, contents)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn get_user() {
+    let (_mock, client) = setup().await;
+
+    let WithTokens(user, tokens) = client.get_user(ALICE, None).await.unwrap();
+    assert_eq!(user.id, 1);
+    assert_eq!(user.username, "alice");
+    assert!(tokens.is_none());
+
+    assert!(client.user_installed(ALICE).await.unwrap());
+}
+
+#[tokio::test]
+async fn create_project() {
+    let (mock, client) = setup().await;
+
+    let WithTokens(created, _) = client
+        .create_repo(ALICE, "", "alice", "My Project!", ProjectLang::Python, false)
+        .await
+        .unwrap();
+    assert_eq!(created.repo_name, "my_project");
+    assert!(!created.already_exists);
+
+    assert_eq!(mock.paths("alice", "my_project", "main"), [".ide/project.toml", "main.py"]);
+    let project_toml = mock.file("alice", "my_project", "main", ".ide/project.toml").unwrap();
+    assert_eq!(
+        String::from_utf8(project_toml).unwrap(),
+        ProjectLang::Python.get_project_toml().unwrap()
+    );
+
+    // a project with the same name isn't created again
+    let WithTokens(created, _) = client
+        .create_repo(ALICE, "", "alice", "my project", ProjectLang::Rust, false)
+        .await
+        .unwrap();
+    assert!(created.already_exists);
+    assert!(mock.file("alice", "my_project", "main", "README.md").is_none());
+}
+
+#[tokio::test]
+async fn open_project() {
+    let (mock, client) = setup().await;
+    mock.add_repo("alice", "game", false, &[("main.py", "print('hi')"), ("src/board.py", "")]);
+    mock.push_file("alice", "game", "main", "main.py", Some("print('hello')"));
+
+    let WithTokens(resolved, _) = client
+        .resolve_ref(ALICE, "", "alice", "game", None)
+        .await
+        .unwrap();
+    assert_eq!(resolved.git_ref, "main");
+    assert_eq!(resolved.branch.as_deref(), Some("main"));
+    assert_eq!(Some(&resolved.sha), mock.head("alice", "game", "main").as_ref());
+
+    let WithTokens((dir_name, tarball), _) = client
+        .get_project_tarball(ALICE, "", "alice", "game", &resolved.sha)
+        .await
+        .unwrap();
+    assert_eq!(dir_name, format!("alice-game-{}", &resolved.sha[..7]));
+    assert_eq!(
+        unpack_tarball(&dir_name, &tarball),
+        [
+            ("main.py".to_string(), "print('hello')".to_string()),
+            ("src/board.py".to_string(), String::new()),
+        ]
+    );
+
+    // an older commit is opened without a branch
+    let first = mock.parents(&resolved.sha)[0].clone();
+    let WithTokens(resolved, _) = client
+        .resolve_ref(ALICE, "", "alice", "game", Some(&first[..7]))
+        .await
+        .unwrap();
+    assert_eq!(resolved.branch, None);
+    assert_eq!(resolved.sha, first);
+
+    let WithTokens(file, _) = client
+        .get_file_at(ALICE, "", "alice", "game", "main.py", &first)
+        .await
+        .unwrap();
+    assert_eq!(file.as_ref(), b"print('hi')");
+}
+
+#[tokio::test]
+async fn history() {
+    let (mock, client) = setup().await;
+    mock.add_repo("alice", "game", false, &[("main.py", "")]);
+    let latest = mock.push_file("alice", "game", "main", "main.py", Some("print(1)"));
+
+    let WithTokens(commits, _) = client
+        .list_commits(ALICE, "", "alice", "game", None, None, 1)
+        .await
+        .unwrap();
+    let messages: Vec<_> = commits.iter().map(|commit| commit.message.as_str()).collect();
+    assert_eq!(messages, ["Update main.py", "Initial commit"]);
+    assert_eq!(commits[0].sha, latest);
+    assert_eq!(commits[0].author, "alice");
+
+    let WithTokens(details, _) = client
+        .get_commit(ALICE, "", "alice", "game", &latest)
+        .await
+        .unwrap();
+    assert_eq!(details.commit.sha, latest);
+}
+
+#[tokio::test]
+async fn private_repos_are_hidden() {
+    let (mock, client) = setup().await;
+    mock.add_repo("alice", "secret", true, &[("main.py", "")]);
+
+    let WithTokens(exists, _) = client.repo_exists(ALICE, "", "alice", "secret").await.unwrap();
+    assert!(exists);
+    let WithTokens(exists, _) = client.repo_exists(BOB, "", "alice", "secret").await.unwrap();
+    assert!(!exists);
+
+    assert!(client.get_repo(BOB, "", "alice", "secret").await.is_err());
+    assert!(matches!(client.get_public_readme("alice", "secret").await, Ok(None)));
+}
+
+#[tokio::test]
+async fn remix_project() {
+    let (mock, client) = setup().await;
+    let _ = client
+        .create_repo(ALICE, "", "alice", "Rusty", ProjectLang::Rust, false)
+        .await
+        .unwrap();
+
+    let _ = client.fork_repo(BOB, "", "alice", "rusty").await.unwrap();
+    assert!(mock.has_repo("bob", "rusty"));
+    assert_eq!(mock.head("bob", "rusty", "main"), mock.head("alice", "rusty", "main"));
+
+    // the readme of the remix is the starter readme of a Rust project
+    let (_, starter_readme) = ProjectLang::Rust.get_initial_file().unwrap();
+    let WithTokens(readme, _) = client.get_readme(BOB, "", "bob", "rusty").await.unwrap();
+    assert_eq!(readme, starter_readme);
+    assert_eq!(
+        client.get_public_readme("bob", "rusty").await.unwrap(),
+        Some(starter_readme)
+    );
+
+    // the remix can be changed without changing the original
+    let base = mock.head("bob", "rusty", "main").unwrap();
+    let _ = client
+        .commit_changes(
+            BOB,
+            "",
+            "bob",
+            "rusty",
+            "main",
+            &base,
+            "Add main.rs",
+            vec![FileChange {
+                path: "src/main.rs".to_string(),
+                mode: "100644".to_string(),
+                contents: Some(b"fn main() {}".to_vec()),
+            }],
+        )
+        .await
+        .unwrap();
+    assert!(mock.file("bob", "rusty", "main", "src/main.rs").is_some());
+    assert!(mock.file("alice", "rusty", "main", "src/main.rs").is_none());
+}
+
+#[tokio::test]
+async fn save_project() {
+    let (mock, client) = setup().await;
+    mock.add_repo(
+        "alice",
+        "game",
+        false,
+        &[("main.py", "print('hi')"), ("old.py", ""), ("run.sh", "python main.py")],
+    );
+    let base = mock.head("alice", "game", "main").unwrap();
+
+    let WithTokens(sha, _) = client
+        .commit_changes(
+            ALICE,
+            "",
+            "alice",
+            "game",
+            "main",
+            &base,
+            "Save changes",
+            vec![
+                FileChange {
+                    path: "main.py".to_string(),
+                    mode: "100644".to_string(),
+                    contents: Some(b"print('bye')".to_vec()),
+                },
+                FileChange {
+                    path: "old.py".to_string(),
+                    mode: "100644".to_string(),
+                    contents: None,
+                },
+                FileChange {
+                    path: "lib/utils.py".to_string(),
+                    mode: "100644".to_string(),
+                    contents: Some(vec![0, 159, 146, 150]),
+                },
+            ],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(mock.head("alice", "game", "main"), Some(sha.clone()));
+    assert_eq!(mock.parents(&sha), [base]);
+    assert_eq!(mock.paths("alice", "game", "main"), ["lib/utils.py", "main.py", "run.sh"]);
+    assert_eq!(mock.file("alice", "game", "main", "main.py").unwrap(), b"print('bye')");
+    assert_eq!(mock.file("alice", "game", "main", "lib/utils.py").unwrap(), [0, 159, 146, 150]);
+}
+
+#[tokio::test]
+async fn save_to_branch_with_url_characters() {
+    let (mock, client) = setup().await;
+    mock.add_repo("alice", "game", false, &[("main.py", "")]);
+    let base = mock.head("alice", "game", "main").unwrap();
+    let _ = client
+        .create_branch(ALICE, "", "alice", "game", "fix#1?", &base)
+        .await
+        .unwrap();
+
+    let WithTokens(sha, _) = client
+        .commit_changes(
+            ALICE,
+            "",
+            "alice",
+            "game",
+            "fix#1?",
+            &base,
+            "Save changes",
+            vec![FileChange {
+                path: "main.py".to_string(),
+                mode: "100644".to_string(),
+                contents: Some(b"print('hi')".to_vec()),
+            }],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(mock.head("alice", "game", "fix#1?"), Some(sha));
+    assert_eq!(mock.head("alice", "game", "main"), Some(base));
+}
+
+#[tokio::test]
+async fn save_after_remote_change() {
+    let (mock, client) = setup().await;
+    mock.add_repo("alice", "game", false, &[("main.py", "")]);
+    let base = mock.head("alice", "game", "main").unwrap();
+    let remote = mock.push_file("alice", "game", "main", "README.md", Some("# Game"));
+
+    let result = client
+        .commit_changes(
+            ALICE,
+            "",
+            "alice",
+            "game",
+            "main",
+            &base,
+            "Save changes",
+            vec![FileChange {
+                path: "main.py".to_string(),
+                mode: "100644".to_string(),
+                contents: Some(b"print(1)".to_vec()),
+            }],
+        )
+        .await;
+
+    assert!(matches!(result, Err(AppError::RemoteChanged(branch)) if branch == "main"));
+    assert_eq!(mock.head("alice", "game", "main"), Some(remote));
+}
+
+#[tokio::test]
+async fn save_with_invalid_mode() {
+    let (mock, client) = setup().await;
+    mock.add_repo("alice", "game", false, &[("main.py", "")]);
+    let base = mock.head("alice", "game", "main").unwrap();
+
+    // GitHub needs a mode for every file in the tree, including deleted ones
+    let result = client
+        .commit_changes(
+            ALICE,
+            "",
+            "alice",
+            "game",
+            "main",
+            &base,
+            "Delete main.py",
+            vec![FileChange {
+                path: "main.py".to_string(),
+                mode: String::new(),
+                contents: None,
+            }],
+        )
+        .await;
+
+    assert!(result.is_err());
+    assert_eq!(mock.head("alice", "game", "main"), Some(base));
+}
+
+#[tokio::test]
+async fn branches() {
+    let (mock, client) = setup().await;
+    mock.add_repo("alice", "game", false, &[("main.py", "")]);
+    let head = mock.head("alice", "game", "main").unwrap();
+
+    let _ = client
+        .create_branch(ALICE, "", "alice", "game", "feature", &head)
+        .await
+        .unwrap();
+    let WithTokens((branches, default_branch), _) = client
+        .list_branches(ALICE, "", "alice", "game")
+        .await
+        .unwrap();
+    assert_eq!(branches, ["feature", "main"]);
+    assert_eq!(default_branch, "main");
+
+    let WithTokens(resolved, _) = client
+        .resolve_ref(ALICE, "", "alice", "game", Some("feature"))
+        .await
+        .unwrap();
+    assert_eq!(resolved.branch.as_deref(), Some("feature"));
+    assert_eq!(resolved.sha, head);
+
+    // a commit isn't a branch
+    let WithTokens(resolved, _) = client
+        .resolve_ref(ALICE, "", "alice", "game", Some(&head))
+        .await
+        .unwrap();
+    assert_eq!(resolved.branch, None);
+    assert_eq!(resolved.sha, head);
+
+    // refs which aren't allowed by git aren't put into the URL
+    for git_ref in ["../../users/bob", "feature?x", "-x", "a//b", ""] {
+        let result = client
+            .resolve_ref(ALICE, "", "alice", "game", Some(git_ref))
+            .await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))), "{git_ref}");
+    }
+}
+
+#[tokio::test]
+async fn import_project() {
+    let (mock, client) = setup().await;
+    mock.add_repo("alice", "empty", false, &[]);
+    mock.add_repo("alice", "tool", false, &[("Cargo.toml", ""), ("src/main.rs", "")]);
+    mock.add_repo("bob", "other", false, &[("main.py", "")]);
+
+    let WithTokens(repos, _) = client.list_user_repos(ALICE, "").await.unwrap();
+    let mut names: Vec<_> = repos.into_iter().map(|repo| repo.name).collect();
+    names.sort();
+    assert_eq!(names, ["empty", "tool"]);
+
+    let WithTokens(files, _) = client
+        .list_files(ALICE, "", "alice", "empty", "main")
+        .await
+        .unwrap();
+    assert!(files.is_empty());
+
+    let WithTokens(files, _) = client
+        .list_files(ALICE, "", "alice", "tool", "main")
+        .await
+        .unwrap();
+    assert_eq!(ProjectLang::detect(&files), Some(ProjectLang::Rust));
+
+    // a partial list of a big repo's files isn't used
+    mock.truncate_trees(1);
+    let result = client.list_files(ALICE, "", "alice", "tool", "main").await;
+    assert!(matches!(result, Err(AppError::BadRequest(_))));
+    mock.truncate_trees(usize::MAX);
+
+    // with a pull request, the default branch isn't changed
+    let head = mock.head("alice", "tool", "main");
+    let WithTokens(pull_url, _) = client
+        .add_project_toml(ALICE, "", "alice", "tool", "main", "run_command = \"cargo run\"", true)
+        .await
+        .unwrap();
+    assert_eq!(pull_url, Some(format!("{}/alice/tool/pull/1", mock.url)));
+    assert_eq!(mock.head("alice", "tool", "main"), head);
+    assert!(mock.file("alice", "tool", GithubClient::SETUP_BRANCH, ".ide/project.toml").is_some());
+
+    // importing again doesn't change the branch from the first pull request
+    let WithTokens(pull_url, _) = client
+        .add_project_toml(ALICE, "", "alice", "tool", "main", "run_command = \"cargo test\"", true)
+        .await
+        .unwrap();
+    assert!(pull_url.is_some());
+    assert_eq!(
+        mock.file("alice", "tool", GithubClient::SETUP_BRANCH, ".ide/project.toml").unwrap(),
+        b"run_command = \"cargo run\""
+    );
+    assert_eq!(
+        mock.file("alice", "tool", "ide-setup-2", ".ide/project.toml").unwrap(),
+        b"run_command = \"cargo test\""
+    );
+
+    let WithTokens(pull_url, _) = client
+        .add_project_toml(ALICE, "", "alice", "tool", "main", "run_command = \"cargo run\"", false)
+        .await
+        .unwrap();
+    assert_eq!(pull_url, None);
+    assert_eq!(
+        mock.file("alice", "tool", "main", ".ide/project.toml").unwrap(),
+        b"run_command = \"cargo run\""
+    );
+}
+
+#[test]
+fn repo_names() {
+    assert!(GithubClient::valid_repo_name("my-game_2.0"));
+    for name in ["", ".", "..", "../bob/game", "game?x", "game name"] {
+        assert!(!GithubClient::valid_repo_name(name), "{name}");
+    }
+}
+
+#[tokio::test]
+async fn retries_server_errors() {
+    let (mock, client) = setup().await;
+    mock.add_repo("alice", "game", false, &[("main.py", "")]);
+    mock.fail_next(StatusCode::BAD_GATEWAY, &[]);
+
+    let WithTokens(repo, _) = client.get_repo(ALICE, "", "alice", "game").await.unwrap();
+    assert_eq!(repo.default_branch, "main");
+    assert_eq!(mock.request_count(), 2);
+}
+
+#[tokio::test]
+async fn rate_limit() {
+    let (mock, client) = setup().await;
+    mock.add_repo("alice", "game", false, &[("main.py", "")]);
+    let reset = chrono::Utc::now().timestamp() + 3600;
+    mock.fail_next(
+        StatusCode::FORBIDDEN,
+        &[
+            ("x-ratelimit-remaining", "0".to_string()),
+            ("x-ratelimit-reset", reset.to_string()),
+        ],
+    );
+
+    let result = client.get_repo(ALICE, "", "alice", "game").await;
+    assert!(matches!(result, Err(AppError::GithubRateLimited { retry_after: Some(_) })));
+
+    // requests with the same token aren't sent until the rate limit resets, but other tokens can still be used
+    let result = client.get_repo(ALICE, "", "alice", "game").await;
+    assert!(matches!(result, Err(AppError::GithubRateLimited { .. })));
+    assert_eq!(mock.request_count(), 1);
+    assert!(client.get_repo(BOB, "", "alice", "game").await.is_ok());
+}
+
+#[tokio::test]
+async fn etag_cache() {
+    let (mock, client) = setup().await;
+    mock.add_repo("alice", "game", false, &[("main.py", "print('hi')")]);
+
+    for _ in 0..2 {
+        let WithTokens(file, _) = client
+            .get_file_at(ALICE, "", "alice", "game", "main.py", "main")
+            .await
+            .unwrap();
+        assert_eq!(file.as_ref(), b"print('hi')");
+    }
+    assert_eq!(mock.not_modified_count(), 1);
+
+    // a changed file is downloaded again
+    mock.push_file("alice", "game", "main", "main.py", Some("print('bye')"));
+    let WithTokens(file, _) = client
+        .get_file_at(ALICE, "", "alice", "game", "main.py", "main")
+        .await
+        .unwrap();
+    assert_eq!(file.as_ref(), b"print('bye')");
+    assert_eq!(mock.not_modified_count(), 1);
+
+    // files too big to cache are still downloaded whole, but aren't cached
+    let big = "a".repeat(5 * 1024 * 1024);
+    mock.push_file("alice", "game", "main", "big.txt", Some(&big));
+    for _ in 0..2 {
+        let WithTokens(file, _) = client
+            .get_file_at(ALICE, "", "alice", "game", "big.txt", "main")
+            .await
+            .unwrap();
+        assert_eq!(file.len(), big.len());
+    }
+    assert_eq!(mock.not_modified_count(), 1);
+}
@@ -1,11 +1,11 @@
-use std::{fmt::Display, fs, io, str::FromStr};
+use std::{collections::HashMap, fmt::Display, fs, io, str::FromStr};
 
 use anyhow::bail;
 use serde::{Deserialize, Serialize};
 use sqlx::{Decode, Postgres, error::BoxDynError, postgres::PgValueRef};
 
 // an enum representing all the available languages for projects
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ProjectLang {
     #[serde(rename = "py")]
     Python,
@@ -73,7 +73,11 @@ impl<'r> Decode<'r, Postgres> for ProjectLang {
 
 impl ProjectLang {
     // File path of where configuration files are stored for all of the languages 
+    #[cfg(not(test))]
     const LANG_PATH: &'static str = "./back_end/languages";
+    // tests are run from the crate's directory rather than the root of the repo
+    #[cfg(test)]
+    const LANG_PATH: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/languages");
 
     // Get the project.toml file for a given language
     // Stores the default run/format commands
@@ -99,4 +103,161 @@ impl ProjectLang {
 
         Ok((name, content))
     }
+
+    // Works out the language of a project from the paths of the files in its repo (relative to the root).
+    // Files which only a project in one language would have (e.g. `Cargo.toml`) are looked for first,
+    // then the language with the most source files is chosen
+    pub fn detect(paths: &[String]) -> Option<Self> {
+        let has_root_file = |name: &str| paths.iter().any(|path| path == name);
+        let has_root_extension = |ext: &str| {
+            paths
+                .iter()
+                .any(|path| !path.contains('/') && path.ends_with(&format!(".{ext}")))
+        };
+
+        if has_root_file("Cargo.toml") {
+            return Some(Self::Rust);
+        }
+        if has_root_file("tsconfig.json") {
+            return Some(Self::TypeScript);
+        }
+        if has_root_file("package.json") {
+            return Some(Self::JavaScript);
+        }
+        if has_root_extension("csproj") || has_root_extension("sln") {
+            return Some(Self::CSharp);
+        }
+        if ["pom.xml", "build.gradle", "build.gradle.kts"].into_iter().any(has_root_file) {
+            return Some(Self::Java);
+        }
+        if ["pyproject.toml", "requirements.txt", "setup.py"].into_iter().any(has_root_file) {
+            return Some(Self::Python);
+        }
+
+        let mut counts = HashMap::new();
+        for path in paths {
+            let Some((_, ext)) = path.rsplit_once('.') else {
+                continue;
+            };
+            let lang = match ext {
+                "py" => Self::Python,
+                "js" | "mjs" | "cjs" => Self::JavaScript,
+                "ts" => Self::TypeScript,
+                "rs" => Self::Rust,
+                "c" | "h" => Self::C,
+                "cpp" | "cc" | "cxx" | "hpp" => Self::CPlusPlus,
+                "cs" => Self::CSharp,
+                "sh" => Self::Bash,
+                "java" => Self::Java,
+                _ => continue,
+            };
+            *counts.entry(lang).or_insert(0) += 1;
+        }
+
+        // ties are broken by the name of the language, so that the same language is always chosen
+        counts
+            .into_iter()
+            .max_by_key(|(lang, count)| (*count, lang.to_string()))
+            .map(|(lang, _)| lang)
+    }
+
+    // Gets the language from the name GitHub gives to a repo's main language (e.g. "C++")
+    pub fn from_github_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "Python" => Self::Python,
+            "JavaScript" => Self::JavaScript,
+            "TypeScript" => Self::TypeScript,
+            "Rust" => Self::Rust,
+            "C" => Self::C,
+            "C++" => Self::CPlusPlus,
+            "C#" => Self::CSharp,
+            "Shell" => Self::Bash,
+            "Java" => Self::Java,
+            _ => return None,
+        })
+    }
+
+    // Gets the project.toml for an existing repo with the given files, which is the language's default project.toml
+    // with a run command that suits the files (e.g. running the repo's main file, or using its build tool)
+    pub fn generate_project_toml(self, paths: &[String]) -> io::Result<String> {
+        let template = self.get_project_toml()?;
+        let Some(run_command) = self.detect_run_command(paths) else {
+            return Ok(template);
+        };
+
+        Ok(template
+            .lines()
+            .map(|line| {
+                if line.starts_with("run_command") {
+                    // the debug representation of a string is quoted and escaped the same way as in TOML
+                    format!("run_command = {run_command:?}")
+                } else {
+                    line.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+            + "\n")
+    }
+
+    // Chooses the command to run a project with the given files (`None` if the default command should be used)
+    fn detect_run_command(self, paths: &[String]) -> Option<String> {
+        let has_root_file = |name: &str| paths.iter().any(|path| path == name);
+        // the first of the files which is in the root of the repo,
+        // or any file in the root with the extension if none of them are
+        let entry_point = |names: &[&str], ext: &str| {
+            names
+                .iter()
+                .find(|name| has_root_file(name))
+                .map(|name| name.to_string())
+                .or_else(|| {
+                    let mut files: Vec<_> = paths
+                        .iter()
+                        .filter(|path| !path.contains('/') && path.ends_with(&format!(".{ext}")))
+                        .collect();
+                    files.sort();
+                    files.first().map(|path| path.to_string())
+                })
+        };
+
+        // file names are quoted, as they are put into a shell command
+        match self {
+            Self::Python => {
+                entry_point(&["main.py", "app.py", "__main__.py"], "py").map(|file| format!("python3 {}", shell_quote(&file)))
+            }
+            Self::JavaScript | Self::TypeScript if has_root_file("package.json") => {
+                Some("npm install && npm start".to_string())
+            }
+            Self::JavaScript => {
+                entry_point(&["main.js", "index.js", "app.js"], "js").map(|file| format!("node {}", shell_quote(&file)))
+            }
+            Self::TypeScript => {
+                entry_point(&["main.ts", "index.ts", "app.ts"], "ts").map(|file| format!("ts-node {}", shell_quote(&file)))
+            }
+            Self::C | Self::CPlusPlus if has_root_file("Makefile") => Some("make".to_string()),
+            Self::C => Some("gcc *.c -o main && ./main".to_string()),
+            Self::CPlusPlus => Some("g++ *.cpp -o main && ./main".to_string()),
+            Self::Java => entry_point(&["Main.java", "App.java"], "java").map(|file| {
+                let class = shell_quote(file.trim_end_matches(".java"));
+                format!("javac *.java && java {class}")
+            }),
+            Self::Bash => entry_point(&["main.sh", "run.sh"], "sh").map(|file| format!("bash {}", shell_quote(&file))),
+            // cargo and dotnet find the project themselves
+            Self::Rust | Self::CSharp => None,
+        }
+    }
+}
+
+// Quotes text to be used as a single argument in a shell command, if it has any characters the shell would interpret
+fn shell_quote(text: &str) -> String {
+    if !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./".contains(c))
+    {
+        return text.to_string();
+    }
+
+    // nothing is special inside single quotes, so only the single quotes themselves need to be escaped
+    format!("'{}'", text.replace('\'', r"'\''"))
 }
@@ -39,6 +39,9 @@ const FRONT_PUBLIC: &str = "./front_end/dist";
 const GITHUB_APP_SLUG: &str = "nea-website";
 // Path to the compiled HTML/CSS/JS for the editor frontend
 const EDITOR_PATH: &str = "./editor/dist";
+// GitHub URLs used unless others are given in the GITHUB_API_URL and GITHUB_URL env vars
+const DEFAULT_GITHUB_API_URL: &str = "https://api.github.com";
+const DEFAULT_GITHUB_URL: &str = "https://github.com";
 // Socket on which the server listens for incoming requests (port 8080)
 const SOCKET_ADDRESS: &str = "0.0.0.0:8080";
 
@@ -55,6 +58,12 @@ struct Config {
     database_url: String,
     // Private key used for AES encryption/decryption
     aes_key: Vec<u8>,
+    // Secret that GitHub signs webhook deliveries with (webhooks are rejected if it isn't set)
+    github_webhook_secret: Option<String>,
+    // Base URL of the GitHub REST API (which is different for GitHub Enterprise, or a mock server when testing)
+    github_api_url: String,
+    // Base URL of the GitHub website, which users are sent to for signing in and installing the App
+    github_url: String,
 }
 
 impl Config {
@@ -72,6 +81,11 @@ impl Config {
             aes_key: BASE64_STANDARD
                 .decode(dotenv::var("AES_KEY").context("missing AES_KEY")?)
                 .context("invalid base64 AES_KEY")?,
+            github_webhook_secret: dotenv::var("GITHUB_WEBHOOK_SECRET").ok(),
+            github_api_url: dotenv::var("GITHUB_API_URL")
+                .map_or_else(|_| DEFAULT_GITHUB_API_URL.to_string(), |url| url.trim_end_matches('/').to_string()),
+            github_url: dotenv::var("GITHUB_URL")
+                .map_or_else(|_| DEFAULT_GITHUB_URL.to_string(), |url| url.trim_end_matches('/').to_string()),
         })
     }
 }
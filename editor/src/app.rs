@@ -1,26 +1,40 @@
 use crate::{
-    buffer::{Buffer, BufferError, Buffers, FileData},
+    buffer::{
+        Buffer, BufferError, Buffers, CursorPosition, FileData, Revision, SavedLayout,
+        SplitDirection,
+    },
     color_scheme::AvailableColorSchemes,
+    commands::{self, CommandRegistry, EditorCommand},
+    diff::{self, DiffLine},
     explorer::{Explorer, ExplorerAction},
-    platform::{self, FileSystemTrait as _, RunnerTrait as _, SearchResult},
+    fuzzy,
+    history::{FileHistory, HistoryAction},
+    platform::{
+        self, FileSystemTrait as _, FileWatcherTrait as _, GitOutput, GitTrait as _,
+        ReplaceChange, RunnerTrait as _, SearchQuery, SearchResult,
+    },
+    source_control::{SourceControl, SourceControlAction},
 };
 
 use core::f32;
 use std::{
+    collections::{HashMap, HashSet},
+    io::ErrorKind,
     path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
 
 use eframe::egui;
 use egui::{
-    Align, Button, CentralPanel, ComboBox, Grid, Id, Key, KeyboardShortcut, Layout, MenuBar, Modifiers, RichText, ScrollArea, SidePanel, Style, TopBottomPanel, ViewportCommand, containers::modal::Modal
+    Align, Button, CentralPanel, Color32, ComboBox, DragValue, Event, Grid, Id, Key, KeyboardShortcut, Layout, MenuBar, Modifiers, RichText, ScrollArea, SidePanel, Style, TextEdit, TopBottomPanel, ViewportCommand, containers::modal::Modal
 };
 use egui_extras::syntax_highlighting;
 #[cfg(not(target_arch = "wasm32"))]
 use egui_term::{TerminalBackend, TerminalView};
 use eyre::OptionExt;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use ws_messages::{ColorScheme, EditorSettings};
+use ws_messages::{ColorScheme, EditorSettings, FontFamily, Keymap, KeymapPreset};
 
 #[macro_export]
 macro_rules! dbg_frame {
@@ -73,15 +87,117 @@ struct SearchModalState {
     search_text: String,
     replace_text: String,
     is_replace: bool,
+    regex: bool,
+    case_sensitive: bool,
+    whole_word: bool,
+    /// Glob used to filter which files are searched (all files are searched if empty)
+    glob: String,
     search_results: Vec<SearchResult>,
+    /// Error with the search query (e.g. an invalid regex)
+    search_error: Option<String>,
+    /// Whether search results are still being received from the server
+    searching: bool,
+    /// Number of the latest search (from `App::last_search_id`),
+    /// so that results still being received from earlier searches are ignored
+    #[cfg(target_arch = "wasm32")]
+    search_id: u32,
+    /// Preview of each match that will be replaced, along with whether the user has ticked it to be replaced
+    replace_preview: Option<Vec<(ReplaceChange, bool)>>,
+}
+
+impl SearchModalState {
+    fn query(&self) -> SearchQuery {
+        SearchQuery {
+            #[cfg(target_arch = "wasm32")]
+            search_id: self.search_id,
+            pattern: self.search_text.clone(),
+            regex: self.regex,
+            case_sensitive: self.case_sensitive,
+            whole_word: self.whole_word,
+            glob: Some(self.glob.trim().to_string()).filter(|glob| !glob.is_empty()),
+        }
+    }
+}
+
+/// State of the modal for quickly finding and opening any file in the project
+#[derive(Default, Debug)]
+struct FileFinderState {
+    query: String,
+    /// Paths of every file in the project (`None` while they are being received from the server)
+    files: Option<Vec<PathBuf>>,
+    /// Index of the highlighted result, which is opened when Enter is pressed
+    selected: usize,
+    /// Indices of the files matching the query, best first (`None` until they have been found)
+    matches: Option<Vec<usize>>,
+}
+
+/// State of the command palette, which lists every command so that it can be found and run
+#[derive(Default, Debug)]
+struct CommandPaletteState {
+    query: String,
+    /// Index of the highlighted item, which is chosen when Enter is pressed
+    selected: usize,
+    /// Indices of the items matching the query, best first (`None` until they have been found)
+    matches: Option<Vec<usize>>,
+    /// Whether a colour scheme is being chosen (after "Change colour scheme"), rather than a command
+    picking_color_scheme: bool,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 enum BottomPanelState {
     Output,
     Terminal,
 }
 
+/// The state of the editor for a project, which is restored when the project is next opened
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
+struct WorkspaceState {
+    /// Open buffers, in the order they were opened
+    buffers: Vec<BufferState>,
+    bottom_panel: Option<BottomPanelState>,
+    /// Directories open in the explorer (relative to the project root)
+    expanded_dirs: Vec<PathBuf>,
+    /// Layout of the panes and the files open in each of them (`None` if it hasn't been saved yet)
+    layout: Option<SavedLayout>,
+}
+
+/// The state of an open buffer in a [`WorkspaceState`]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct BufferState {
+    /// Path of the buffer's file relative to the project root (`None` for untitled buffers)
+    path: Option<PathBuf>,
+    cursor: CursorPosition,
+    /// Vertical scroll offset of the buffer's text
+    scroll: f32,
+    /// Unsaved contents of the buffer (`None` if it has no unsaved changes)
+    draft: Option<String>,
+    /// Whether the buffer's tab is selected in its pane
+    selected: bool,
+    /// Whether the buffer is the current buffer (the selected tab in the active pane)
+    active: bool,
+}
+
+/// A file which has been changed outside of the editor while its buffer has unsaved changes
+struct FileConflict {
+    path: PathBuf,
+    /// Current contents of the file on disk
+    disk_contents: String,
+    /// Lines of the diff between the buffer and the file on disk, each starting with ` `, `-` or `+`
+    /// (`None` unless the user is comparing the changes)
+    comparison: Option<Vec<(char, String)>>,
+}
+
+/// How the user chose to resolve a [`FileConflict`]
+enum ConflictResolution {
+    /// Combine the changes in the buffer with the changes on disk
+    Merge,
+    /// Keep the buffer's contents, which overwrite the file when it is next saved
+    KeepBuffer,
+    /// Replace the buffer's contents with the file on disk
+    LoadFromDisk,
+}
+
 #[derive(Default)]
 pub struct App {
     editor_settings: EditorSettings,
@@ -94,6 +210,12 @@ pub struct App {
     available_color_schemes: AvailableColorSchemes,
     /// [`Explorer`] side panel
     explorer: Option<Explorer>,
+    /// [`SourceControl`] side panel (`None` if it isn't shown)
+    source_control: Option<SourceControl>,
+    /// The project's git repository
+    git: Option<platform::Git>,
+    /// Window listing the commits which changed a file (`None` if it isn't shown)
+    history: Option<FileHistory>,
     bottom_panel_state: Option<BottomPanelState>,
     /// Contents of the output panel
     /// This must be wrapped in an `Arc<Mutex<_>>` so that it can be shared to and modified across threads, including the `running_command` thread.
@@ -107,18 +229,67 @@ pub struct App {
     settings_modal_state: Option<EditorSettings>,
     /// Current state of the modal for search and replace
     search_modal_state: Option<SearchModalState>,
+    /// Text entered into the go to line modal (`None` if the modal isn't shown)
+    goto_line_modal_state: Option<String>,
+    /// State of the file finder modal (`None` if the modal isn't shown)
+    file_finder_state: Option<FileFinderState>,
+    /// State of the command palette (`None` if it isn't shown)
+    command_palette_state: Option<CommandPaletteState>,
+    /// Every command that can be run in the editor
+    commands: CommandRegistry<App>,
+    /// Id of the command whose keybinding is being changed in the settings modal (waiting for a key to be pressed)
+    recording_keybinding: Option<&'static str>,
+    /// Files that have been opened most recently, starting with the most recent
+    recent_files: Vec<PathBuf>,
     /// Whether the help modal is currently shown
     help_modal_shown: bool,
     /// Action to be completed once the current modal is closed.
     modal_action: Option<ModalAction>,
     /// Whether unsaved changes should be ignored when closing the editor
     ignore_dirty: bool,
+    /// Watches the open project for files changed outside of the editor
+    watcher: Option<platform::FileWatcher>,
+    /// Time (from `egui::InputState::time`) when the watcher was last checked for changes
+    last_change_check: f64,
+    /// Files changed outside of the editor which the user needs to choose how to handle
+    file_conflicts: Vec<FileConflict>,
+    /// Files and directories the user has been asked to confirm deleting (`None` if the modal isn't shown)
+    delete_modal_state: Option<Vec<PathBuf>>,
+    /// Why saving to GitHub failed because the branch has new commits, shown in a modal offering to merge them
+    /// into the project (`None` if the modal isn't shown)
+    #[cfg(target_arch = "wasm32")]
+    merge_modal_state: Option<String>,
+    /// The workspace state last saved for the project, serialized as yaml.
+    /// This is `None` until the saved state has been restored, so that it isn't overwritten before then
+    saved_workspace: Option<String>,
+    /// Time (from `egui::InputState::time`) when the workspace state was last saved
+    last_workspace_save: f64,
+    /// Number of the last project search, which is counted across every time the search modal is opened
+    #[cfg(target_arch = "wasm32")]
+    last_search_id: u32,
+    /// States of buffers from the previous session, to be applied once their files have been opened
+    pending_buffer_states: HashMap<PathBuf, BufferState>,
+    /// Workspace state of every project opened on desktop, by the path of the project
+    #[cfg(not(target_arch = "wasm32"))]
+    workspaces: HashMap<PathBuf, WorkspaceState>,
     /// Handle to the backend when in the web editor
     #[cfg(target_arch = "wasm32")]
     backend_handle: platform::BackendHandle,
+    /// Cursor positions to move to once the contents of files being opened are received
+    #[cfg(target_arch = "wasm32")]
+    pending_cursors: HashMap<PathBuf, CursorPosition>,
 }
 
 impl eframe::App for App {
+    // the settings and workspaces are only saved locally on desktop, since the web editor stores them on the server
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, Self::SETTINGS_KEY, &self.editor_settings);
+
+        self.store_workspace();
+        eframe::set_value(storage, Self::WORKSPACES_KEY, &self.workspaces);
+    }
+
     // perform all the editor logic by updating it each frame
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // check whether the user has requested to close
@@ -133,7 +304,13 @@ impl eframe::App for App {
             ctx.send_viewport_cmd(ViewportCommand::CancelClose);
         }
 
-        self.handle_shortcuts(ctx);
+        // keybindings aren't used while a new one is being recorded in the settings
+        if self.recording_keybinding.is_none() {
+            self.handle_shortcuts(ctx);
+        }
+
+        self.check_for_changes(ctx);
+        self.handle_dropped_files(ctx);
 
         // display menu bar
         TopBottomPanel::top("top_menu_panel").show(ctx, |ui| {
@@ -152,7 +329,7 @@ impl eframe::App for App {
                 .show(ctx, |ui| explorer.show(ui, &self.fs));
 
             if response.response.clicked_elsewhere() {
-                explorer.highlighted = None;
+                explorer.selection.paths.clear();
             }
 
             match response.inner {
@@ -160,9 +337,16 @@ impl eframe::App for App {
                     if let Some(action) = explorer_response.action {
                         match action {
                             ExplorerAction::OpenFile(path) => self.open_file(path),
-                            // TODO: the 2 below
-                            ExplorerAction::NewFolder(path) => todo!(), //self.new_folder(path),
-                            ExplorerAction::Delete(path) => todo!(),    // self.delete_file(path),
+                            ExplorerAction::NewFile(path) => self.new_file(&path),
+                            ExplorerAction::NewFolder(path) => self.new_folder(&path),
+                            ExplorerAction::Delete(paths) => self.delete_modal_state = Some(paths),
+                            ExplorerAction::Move { paths, to } => self.move_files(&paths, &to),
+                            ExplorerAction::Rename { from, to } => self.rename_entry(&from, &to),
+                            ExplorerAction::Copy { paths, to } => self.copy_files(&paths, &to),
+                            #[cfg(target_arch = "wasm32")]
+                            ExplorerAction::Upload { dir, folder } => self.upload(dir, folder),
+                            #[cfg(target_arch = "wasm32")]
+                            ExplorerAction::Download(path) => self.download(Some(&path)),
                         }
                     }
                 }
@@ -170,25 +354,51 @@ impl eframe::App for App {
             }
         }
 
-        // if Delete key pressed while selecting a file in the explorer, then delete the file
-        if let Some(path) = self.explorer.as_ref().and_then(|e| e.highlighted.clone())
+        // display side panel for source control, next to the explorer
+        if let Some(source_control) = self.source_control.as_mut() {
+            let action = SidePanel::left("source_control_panel")
+                .resizable(true)
+                .max_width(max_left_panel_width * ctx.available_rect().width())
+                .show(ctx, |ui| source_control.show(ui))
+                .inner;
+            source_control.show_diff(ctx);
+
+            if let Some(action) = action {
+                self.source_control_action(action);
+            }
+        }
+        self.show_history(ctx);
+        self.check_git_request();
+
+        // if Delete key pressed while selecting files in the explorer (and not typing), then ask to delete the files
+        if let Some(explorer) = &self.explorer
+            && !explorer.selection.paths.is_empty()
+            && ctx.memory(|m| m.focused().is_none())
             && ctx.input(|i| i.key_pressed(Key::Delete))
         {
-            self.delete(path.as_path());
+            self.delete_modal_state = Some(explorer.selection.paths.clone());
         }
 
         if let Some(bottom_panel_state) = self.bottom_panel_state {
             let max_bottom_panel_height = 0.8;
-            TopBottomPanel::bottom("bottom_panel")
+            let clicked_location = TopBottomPanel::bottom("bottom_panel")
                 .resizable(true)
                 .max_height(max_bottom_panel_height * ctx.available_rect().height())
                 .show(ctx, |ui| {
                     let size = ui.available_size();
                     match bottom_panel_state {
                         BottomPanelState::Output => self.output(ui, size),
-                        BottomPanelState::Terminal => self.terminal(ui, size),
+                        BottomPanelState::Terminal => {
+                            self.terminal(ui, size);
+                            None
+                        }
                     }
-                });
+                })
+                .inner;
+
+            if let Some((path, position)) = clicked_location {
+                self.open_file_at(path, position);
+            }
         }
 
         let buffers_response = CentralPanel::default()
@@ -204,8 +414,17 @@ impl eframe::App for App {
         if let Some(err) = buffers_response.error_message {
             self.error_message = Some(err);
         }
+        self.format_saved(&buffers_response.saved);
+        if !buffers_response.saved.is_empty() {
+            self.refresh_git_status();
+        }
+        if let Some(id) = buffers_response.restore_id {
+            self.restore_revision(id);
+        }
+        self.save_workspace(ctx);
 
         let mut changed = false;
+        let mut previewed = false;
         let mut replaced = false;
         let mut done = false;
         let mut opened = None;
@@ -215,34 +434,25 @@ impl eframe::App for App {
                 search_state,
                 &mut opened,
                 &mut changed,
+                &mut previewed,
                 &mut replaced,
                 &mut done,
             );
-
-            if let Some(ref loc) = opened
-                && let Some(buffer) = self.buffers.get_by_path(&loc.path)
-            {
-                self.buffers.select(buffer.id());
-            }
         }
 
-        if let Some(search_state) = &self.search_modal_state {
-            if replaced {
-                let paths: Vec<_> = search_state
-                    .search_results
-                    .iter()
-                    .map(|r| r.path.as_path())
-                    .collect();
-                let _ = self.fs.replace(
-                    &paths,
-                    &search_state.search_text,
-                    &search_state.replace_text,
-                );
-            }
+        // open the file for the clicked search result at the location of the match
+        if let Some(res) = &opened {
+            self.open_file_at(res.path.clone(), CursorPosition::new(res.line, res.col));
+        }
 
-            if changed {
-                self.update_search_results(&search_state.search_text.clone());
-            }
+        if previewed {
+            self.preview_replace();
+        }
+        if replaced {
+            self.replace();
+        }
+        if changed {
+            self.update_search_results();
         }
         if done || opened.is_some() {
             self.search_modal_state = None;
@@ -252,6 +462,18 @@ impl eframe::App for App {
             self.show_settings_modal(ctx);
         }
 
+        if self.goto_line_modal_state.is_some() {
+            self.show_goto_line_modal(ctx);
+        }
+
+        if self.file_finder_state.is_some() {
+            self.show_file_finder_modal(ctx);
+        }
+
+        if self.command_palette_state.is_some() {
+            self.show_command_palette(ctx);
+        }
+
         if self.help_modal_shown {
             self.show_help_modal(ctx);
         }
@@ -270,6 +492,19 @@ impl eframe::App for App {
             }
         }
 
+        if !self.file_conflicts.is_empty() {
+            self.show_file_conflict_modal(ctx);
+        }
+
+        if self.delete_modal_state.is_some() {
+            self.show_delete_modal(ctx);
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        if self.merge_modal_state.is_some() {
+            self.show_merge_modal(ctx);
+        }
+
         if self.error_message.is_some() {
             self.show_error_modal(ctx);
         }
@@ -281,20 +516,78 @@ impl eframe::App for App {
     }
 }
 
+impl Default for CommandRegistry<App> {
+    fn default() -> Self {
+        App::commands()
+    }
+}
+
 impl App {
+    const MAX_RECENT_FILES: usize = 20;
+    const MAX_PICKER_RESULTS: usize = 50;
+    /// How many of the files changed by pulling from GitHub are listed
+    #[cfg(target_arch = "wasm32")]
+    const MAX_LISTED_FILES: usize = 20;
+    /// How often to check for files changed outside of the editor, in seconds
+    const CHANGE_CHECK_INTERVAL: f64 = 2.0;
+    /// How often the state of the workspace is saved, in seconds
+    const WORKSPACE_SAVE_INTERVAL: f64 = 5.0;
+    /// Key used to store the editor settings on desktop
+    #[cfg(not(target_arch = "wasm32"))]
+    const SETTINGS_KEY: &str = "editor_settings";
+    /// Key used to store the workspace state of each project on desktop
+    #[cfg(not(target_arch = "wasm32"))]
+    const WORKSPACES_KEY: &str = "workspaces";
+
+    // create the desktop editor, restoring the settings from when it was last closed
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new(cc: &eframe::CreationContext) -> Self {
+        let mut app = Self::default();
+        egui_extras::install_image_loaders(&cc.egui_ctx);
+
+        let settings = cc
+            .storage
+            .and_then(|storage| eframe::get_value::<EditorSettings>(storage, Self::SETTINGS_KEY));
+        if let Some(settings) = settings {
+            if let Some(scheme) = settings
+                .color_scheme
+                .as_ref()
+                .and_then(|name| app.available_color_schemes.get_scheme(name))
+            {
+                cc.egui_ctx
+                    .set_style(AvailableColorSchemes::scheme_to_style(scheme));
+            }
+            app.commands.apply_keymap(&settings.keymap);
+            app.editor_settings = settings;
+        }
+
+        if let Some(workspaces) = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, Self::WORKSPACES_KEY))
+        {
+            app.workspaces = workspaces;
+        }
+
+        app
+    }
+
     #[cfg(target_arch = "wasm32")]
-    pub async fn new(user: String, repo: String) -> Self {
-        let project = platform::Project::new(user, repo)
+    pub async fn new(user: String, repo: String, git_ref: Option<String>) -> Self {
+        let project = platform::Project::new(user, repo, git_ref)
             .await
             .expect("failed to create project");
         let fs = platform::FileSystem::new(project.handle().clone());
         let runner = platform::Runner::new(project.handle().clone());
+        let watcher = platform::FileWatcher::new(project.handle().clone());
+        let git = platform::Git::new(project.handle().clone(), &user, &repo, git_ref.clone());
         let backend_handle = project.handle().clone();
 
         Self {
             project: Some(project),
             fs,
             runner,
+            watcher: Some(watcher),
+            git: Some(git),
             backend_handle,
             ..Self::default()
         }
@@ -317,28 +610,6 @@ impl App {
                         self.open_folder(ctx);
                     }
 
-                    #[cfg(not(target_arch = "wasm32"))]
-                    {
-                        ui.separator();
-
-                        // Enable search and replace if there is a currently open project
-                        if ui
-                            .add_enabled(self.project.is_some(), Button::new("Search"))
-                            .clicked()
-                        {
-                            self.search_modal_state = Some(SearchModalState::default());
-                        }
-                        if ui
-                            .add_enabled(self.project.is_some(), Button::new("Replace"))
-                            .clicked()
-                        {
-                            self.search_modal_state = Some(SearchModalState {
-                                is_replace: true,
-                                ..Default::default()
-                            });
-                        }
-                    }
-
                     ui.separator();
 
                     // only show "Save file"/"Save as..." if there is a currently selected buffer
@@ -370,13 +641,60 @@ impl App {
                     {
                         self.save_all();
                     }
+
+                    ui.separator();
+                }
+
+                // Enable search and replace if there is a currently open project
+                if ui
+                    .add_enabled(self.project.is_some(), Button::new("Search"))
+                    .clicked()
+                {
+                    self.search_modal_state = Some(SearchModalState::default());
+                }
+                if ui
+                    .add_enabled(self.project.is_some(), Button::new("Replace"))
+                    .clicked()
+                {
+                    self.search_modal_state = Some(SearchModalState {
+                        is_replace: true,
+                        ..Default::default()
+                    });
+                }
+                if ui
+                    .add_enabled(self.project.is_some(), Button::new("Go to file..."))
+                    .clicked()
+                {
+                    self.open_file_finder();
                 }
-                // Save to GitHub is only available on the web
+
+                // Save to GitHub, uploads and downloads are only available on the web
                 #[cfg(target_arch = "wasm32")]
                 {
                     if ui.button("Save to GitHub").clicked() {
                         self.save_to_github();
                     }
+                    if ui.button("Pull from GitHub").clicked() {
+                        self.merge_remote();
+                    }
+
+                    ui.separator();
+                    let has_explorer = self.explorer.is_some();
+                    if ui
+                        .add_enabled(has_explorer, Button::new("Upload files..."))
+                        .clicked()
+                    {
+                        self.upload_to_selected(false);
+                    }
+                    if ui
+                        .add_enabled(has_explorer, Button::new("Upload folder..."))
+                        .clicked()
+                    {
+                        self.upload_to_selected(true);
+                    }
+                    if ui.button("Download project").clicked() {
+                        self.download(None);
+                    }
                 }
             });
             ui.menu_button("Edit", |ui| {
@@ -408,6 +726,25 @@ impl App {
                         self.bottom_panel_state = Some(BottomPanelState::Terminal)
                     }
                 }
+                if ui
+                    .add_enabled(self.git.is_some(), Button::new("Show source control"))
+                    .clicked()
+                {
+                    self.toggle_source_control();
+                }
+                ui.separator();
+                if ui.button("Split right").clicked() {
+                    self.buffers.split_active_pane(SplitDirection::Horizontal);
+                }
+                if ui.button("Split down").clicked() {
+                    self.buffers.split_active_pane(SplitDirection::Vertical);
+                }
+                if ui
+                    .add_enabled(self.buffers.pane_count() > 1, Button::new("Close pane"))
+                    .clicked()
+                {
+                    self.buffers.close_active_pane();
+                }
             });
 
             ui.menu_button("Run", |ui| {
@@ -422,6 +759,9 @@ impl App {
                 if ui.button("Help").clicked() {
                     self.help_modal_shown = true;
                 }
+                if ui.button("Show all commands").clicked() {
+                    self.command_palette_state = Some(CommandPaletteState::default());
+                }
             });
 
             #[cfg(target_arch = "wasm32")]
@@ -444,17 +784,50 @@ impl App {
     }
 
     // display program output in a scrollable monospaced text box
-    fn output(&self, ui: &mut egui::Ui, size: egui::Vec2) {
-        ScrollArea::vertical().show(ui, |ui| {
-            ui.add_sized(
-                size,
-                egui::TextEdit::multiline(
-                    &mut self.output.lock().expect("failed to get output").as_str(),
-                )
-                .desired_width(f32::INFINITY)
-                .code_editor(),
-            );
-        });
+    //
+    // Ctrl+clicking a file location in the output (e.g. an error at `src/main.rs:4:10`)
+    // returns the location so that the file can be opened there
+    fn output(&self, ui: &mut egui::Ui, size: egui::Vec2) -> Option<(PathBuf, CursorPosition)> {
+        ScrollArea::vertical()
+            .show(ui, |ui| {
+                let output = self.output.lock().expect("failed to get output");
+                let text_output = egui::TextEdit::multiline(&mut output.as_str())
+                    .desired_width(f32::INFINITY)
+                    .min_size(size)
+                    .code_editor()
+                    .show(ui);
+
+                if !text_output.response.clicked() || !ui.input(|i| i.modifiers.command) {
+                    return None;
+                }
+
+                // find the line of output that was clicked on
+                let char_index = text_output.cursor_range?.primary.index;
+                let byte_index = output
+                    .char_indices()
+                    .nth(char_index)
+                    .map_or(output.len(), |(i, _)| i);
+                let line_start = output[..byte_index].rfind('\n').map_or(0, |i| i + 1);
+                let line_end = output[byte_index..]
+                    .find('\n')
+                    .map_or(output.len(), |i| byte_index + i);
+
+                let (path, position) = Self::parse_location(&output[line_start..line_end])?;
+                let root = self.explorer.as_ref()?.root_path();
+
+                Some((root.join(path), position))
+            })
+            .inner
+    }
+
+    // finds the first file location (written as `path:line` or `path:line:col`) in a line of output
+    fn parse_location(line: &str) -> Option<(PathBuf, CursorPosition)> {
+        line.split_whitespace().find_map(|word| {
+            let word = word.trim_matches(|c: char| matches!(c, '"' | '\'' | '(' | ')' | ',' | ':'));
+            let (path, position) = word.split_once(':')?;
+
+            Some((PathBuf::from(path), position.parse().ok()?))
+        })
     }
 
     // display terminal panel
@@ -466,170 +839,649 @@ impl App {
         }
     }
 
+    // run the command whose keybinding has been pressed (if any)
     fn handle_shortcuts(&mut self, ctx: &egui::Context) {
-        ctx.input_mut(|i| {
-            let allow_save = self.buffers.current_buffer().is_some();
-            // CTRL+SHIFT+S => Save as
-            if allow_save
-                && i.consume_shortcut(&KeyboardShortcut {
-                    modifiers: Modifiers::COMMAND | Modifiers::SHIFT,
-                    logical_key: Key::S,
-                })
-            {
-                let _ = self.save_as();
-            }
-            // CTRL+ALT+S => Save all
-            else if self.buffers.is_dirty()
-                && i.consume_shortcut(&KeyboardShortcut {
-                    modifiers: Modifiers::COMMAND | Modifiers::ALT,
-                    logical_key: Key::S,
-                })
-            {
-                self.save_all();
-            }
-            // CTRL+SHIFT+O => Open folder
-            else if i.consume_shortcut(&KeyboardShortcut {
-                modifiers: Modifiers::COMMAND | Modifiers::SHIFT,
-                logical_key: Key::O,
-            }) {
-                self.open_folder(ctx);
-            }
-            // CTRL+SHIFT+U => Toggle output
-            else if self.explorer.is_some()
-                && i.consume_shortcut(&KeyboardShortcut {
-                    modifiers: Modifiers::COMMAND | Modifiers::SHIFT,
-                    logical_key: Key::U,
-                })
-            {
-                self.bottom_panel_state = match self.bottom_panel_state {
-                    Some(BottomPanelState::Output) => None,
-                    _ => Some(BottomPanelState::Output),
-                };
-            }
-            // CTRL+S => Save file
-            else if allow_save
-                && i.consume_shortcut(&KeyboardShortcut {
-                    modifiers: Modifiers::COMMAND,
-                    logical_key: Key::S,
-                })
-            {
-                let _ = self.save_file();
-            }
-            // CTRL+O => Open file
-            else if i.consume_shortcut(&KeyboardShortcut {
-                modifiers: Modifiers::COMMAND,
-                logical_key: Key::O,
-            }) {
-                self.open_file_dialog();
-            }
-            // CTRL+N => New file
-            else if i.consume_shortcut(&KeyboardShortcut {
-                modifiers: Modifiers::COMMAND,
-                logical_key: Key::N,
-            }) {
-                self.buffers.add(Buffer::empty());
-            }
-            // CTRL+F => Search
-            else if self.project.is_some()
-                && i.consume_shortcut(&KeyboardShortcut {
-                    modifiers: Modifiers::COMMAND,
-                    logical_key: Key::F,
-                })
-            {
-                self.search_modal_state = Some(SearchModalState::default());
-            }
-            // CTRL+H => Replace
-            else if self.project.is_some()
-                && i.consume_shortcut(&KeyboardShortcut {
-                    modifiers: Modifiers::COMMAND,
-                    logical_key: Key::H,
-                })
-            {
-                self.search_modal_state = Some(SearchModalState {
-                    is_replace: true,
-                    ..Default::default()
-                });
-            }
-            // CTRL+, => Settings
-            else if i.consume_shortcut(&KeyboardShortcut {
-                modifiers: Modifiers::COMMAND,
-                logical_key: Key::Comma,
-            }) {
-                self.settings_modal_state = Some(self.editor_settings.clone());
-            }
-            // CTRL+` => Toggle terminal
-            else if self.explorer.is_some()
-                && i.consume_shortcut(&KeyboardShortcut {
-                    modifiers: Modifiers::COMMAND,
-                    logical_key: Key::Backtick,
-                })
-            {
-                self.bottom_panel_state = match self.bottom_panel_state {
-                    Some(BottomPanelState::Terminal) => None,
-                    _ => Some(BottomPanelState::Terminal),
-                };
-            }
-            // F5 => Run
-            else if i.consume_key(Modifiers::NONE, Key::F5)
-                && self.project.is_some()
-                && let Err(e) = self.run()
-            {
-                self.error_message = Some(e.to_string());
-            }
-        });
+        if let Some(command) = self.commands.triggered(self, ctx) {
+            let run = command.run;
+            run(self, ctx);
+        }
     }
 
-    // Saves the current contents of the code buffer to a file.
-    //
-    // If no file is currently associated with the `App`, it prompts the user
-    // to select a save location. Once a file is chosen or if a file already
-    // exists, it writes the contents of the code buffer to the file.
-    // It updates the `file` field with the latest contents after saving.
-    //
-    // Returns `true` if the save was completed.
-    #[cfg(not(target_arch = "wasm32"))]
-    fn save_file(&mut self) -> Result<(), SaveError> {
-        if let Some(project) = &mut self.project {
-            let _ = self.runner.format(project);
-        }
+    // every command available in the editor, which are shown in the command palette and help modal
+    fn commands() -> CommandRegistry<Self> {
+        let shortcut = |modifiers, logical_key| Some(KeyboardShortcut::new(modifiers, logical_key));
+        let has_buffer = |app: &Self| app.buffers.current_buffer().is_some();
+        let has_project = |app: &Self| app.project.is_some();
+        let has_explorer = |app: &Self| app.explorer.is_some();
+        let always = |_: &Self| true;
+
+        let mut commands = vec![];
+
+        #[cfg(not(target_arch = "wasm32"))]
+        commands.extend([
+            EditorCommand {
+                id: "file.new",
+                name: "New file",
+                default_keybinding: shortcut(Modifiers::COMMAND, Key::N),
+                enabled: always,
+                run: |app, _| app.buffers.add(Buffer::empty()),
+            },
+            EditorCommand {
+                id: "file.open",
+                name: "Open file",
+                default_keybinding: shortcut(Modifiers::COMMAND, Key::O),
+                enabled: always,
+                run: |app, _| app.open_file_dialog(),
+            },
+            EditorCommand {
+                id: "file.open_folder",
+                name: "Open folder",
+                default_keybinding: shortcut(Modifiers::COMMAND | Modifiers::SHIFT, Key::O),
+                enabled: always,
+                run: |app, ctx| app.open_folder(ctx),
+            },
+        ]);
+
+        commands.push(EditorCommand {
+            id: "file.save",
+            name: "Save file",
+            default_keybinding: shortcut(Modifiers::COMMAND, Key::S),
+            enabled: has_buffer,
+            run: |app, _| {
+                let _ = app.save_file();
+            },
+        });
+
+        #[cfg(not(target_arch = "wasm32"))]
+        commands.push(EditorCommand {
+            id: "file.save_as",
+            name: "Save as",
+            default_keybinding: shortcut(Modifiers::COMMAND | Modifiers::SHIFT, Key::S),
+            enabled: has_buffer,
+            run: |app, _| {
+                let _ = app.save_as();
+            },
+        });
+
+        commands.push(EditorCommand {
+            id: "file.save_all",
+            name: "Save all changes",
+            default_keybinding: shortcut(Modifiers::COMMAND | Modifiers::ALT, Key::S),
+            enabled: |app| app.buffers.is_dirty(),
+            run: |app, _| app.save_all(),
+        });
 
-        match self.buffers.current_buffer_mut() {
-            Some(buffer) => match buffer.save(&self.fs) {
-                Ok(_) => Ok(()),
-                Err(_) => self.save_as(),
+        #[cfg(target_arch = "wasm32")]
+        commands.extend([
+            EditorCommand {
+                id: "file.save_to_github",
+                name: "Save to GitHub",
+                default_keybinding: None,
+                enabled: always,
+                run: |app, _| app.save_to_github(),
+            },
+            EditorCommand {
+                id: "file.pull_from_github",
+                name: "Pull from GitHub",
+                default_keybinding: None,
+                enabled: always,
+                run: |app, _| app.merge_remote(),
+            },
+            EditorCommand {
+                id: "file.upload",
+                name: "Upload files",
+                default_keybinding: None,
+                enabled: has_explorer,
+                run: |app, _| app.upload_to_selected(false),
+            },
+            EditorCommand {
+                id: "file.upload_folder",
+                name: "Upload folder",
+                default_keybinding: None,
+                enabled: has_explorer,
+                run: |app, _| app.upload_to_selected(true),
+            },
+            EditorCommand {
+                id: "file.download_project",
+                name: "Download project",
+                default_keybinding: None,
+                enabled: always,
+                run: |app, _| app.download(None),
+            },
+        ]);
+
+        commands.extend([
+            EditorCommand {
+                id: "search.find",
+                name: "Search",
+                default_keybinding: shortcut(Modifiers::COMMAND, Key::F),
+                enabled: has_project,
+                run: |app, _| app.search_modal_state = Some(SearchModalState::default()),
+            },
+            EditorCommand {
+                id: "search.replace",
+                name: "Replace",
+                default_keybinding: shortcut(Modifiers::COMMAND, Key::H),
+                enabled: has_project,
+                run: |app, _| {
+                    app.search_modal_state = Some(SearchModalState {
+                        is_replace: true,
+                        ..Default::default()
+                    });
+                },
+            },
+            EditorCommand {
+                id: "go.line",
+                name: "Go to line",
+                default_keybinding: shortcut(Modifiers::COMMAND, Key::G),
+                enabled: has_buffer,
+                run: |app, _| app.goto_line_modal_state = Some(String::new()),
+            },
+            EditorCommand {
+                id: "go.file",
+                name: "Go to file",
+                default_keybinding: shortcut(Modifiers::COMMAND, Key::P),
+                enabled: has_project,
+                run: |app, _| app.open_file_finder(),
+            },
+            EditorCommand {
+                id: "palette",
+                name: "Show all commands",
+                default_keybinding: shortcut(Modifiers::COMMAND | Modifiers::SHIFT, Key::P),
+                enabled: always,
+                run: |app, _| app.command_palette_state = Some(CommandPaletteState::default()),
+            },
+            EditorCommand {
+                id: "settings",
+                name: "Settings",
+                default_keybinding: shortcut(Modifiers::COMMAND, Key::Comma),
+                enabled: always,
+                run: |app, _| app.settings_modal_state = Some(app.editor_settings.clone()),
+            },
+            EditorCommand {
+                id: "settings.color_scheme",
+                name: "Change colour scheme",
+                default_keybinding: None,
+                enabled: |app| !app.available_color_schemes.schemes.is_empty(),
+                run: |app, _| {
+                    app.command_palette_state = Some(CommandPaletteState {
+                        picking_color_scheme: true,
+                        ..Default::default()
+                    });
+                },
+            },
+            EditorCommand {
+                id: "view.split_right",
+                name: "Split editor right",
+                default_keybinding: shortcut(Modifiers::COMMAND, Key::Backslash),
+                enabled: |_| true,
+                run: |app, _| app.buffers.split_active_pane(SplitDirection::Horizontal),
+            },
+            EditorCommand {
+                id: "view.split_down",
+                name: "Split editor down",
+                default_keybinding: None,
+                enabled: |_| true,
+                run: |app, _| app.buffers.split_active_pane(SplitDirection::Vertical),
             },
-            None => Err(SaveError::NoBufferSelected),
+            EditorCommand {
+                id: "view.close_pane",
+                name: "Close pane",
+                default_keybinding: None,
+                enabled: |app| app.buffers.pane_count() > 1,
+                run: |app, _| app.buffers.close_active_pane(),
+            },
+            EditorCommand {
+                id: "view.terminal",
+                name: "Toggle terminal",
+                default_keybinding: shortcut(Modifiers::COMMAND, Key::Backtick),
+                enabled: has_explorer,
+                run: |app, _| app.toggle_bottom_panel(BottomPanelState::Terminal),
+            },
+            EditorCommand {
+                id: "view.output",
+                name: "Toggle output",
+                default_keybinding: shortcut(Modifiers::COMMAND | Modifiers::SHIFT, Key::U),
+                enabled: has_explorer,
+                run: |app, _| app.toggle_bottom_panel(BottomPanelState::Output),
+            },
+            EditorCommand {
+                id: "view.source_control",
+                name: "Toggle source control",
+                default_keybinding: shortcut(Modifiers::COMMAND | Modifiers::SHIFT, Key::G),
+                enabled: |app| app.git.is_some(),
+                run: |app, _| app.toggle_source_control(),
+            },
+            EditorCommand {
+                id: "git.file_history",
+                name: "Show file history",
+                default_keybinding: None,
+                enabled: |app| {
+                    app.git.is_some()
+                        && app.buffers.current_buffer().is_some_and(|b| b.file_data().is_some())
+                },
+                run: |app, _| app.show_file_history(),
+            },
+            EditorCommand {
+                id: "run.run",
+                name: "Run",
+                default_keybinding: shortcut(Modifiers::NONE, Key::F5),
+                enabled: has_project,
+                run: |app, _| {
+                    if let Err(e) = app.run() {
+                        app.error_message = Some(e.to_string());
+                    }
+                },
+            },
+            EditorCommand {
+                id: "run.stop",
+                name: "Stop running",
+                default_keybinding: None,
+                enabled: |app| app.runner.is_running(),
+                run: |app, _| app.runner.stop(),
+            },
+            EditorCommand {
+                id: "run.format",
+                name: "Format project",
+                default_keybinding: None,
+                enabled: has_project,
+                run: |app, _| app.format(&[]),
+            },
+            EditorCommand {
+                id: "run.format_file",
+                name: "Format file",
+                default_keybinding: shortcut(Modifiers::ALT | Modifiers::SHIFT, Key::F),
+                enabled: |app| app.project.is_some() && app.buffers.current_buffer().is_some(),
+                run: |app, _| app.format_current_buffer(),
+            },
+            EditorCommand {
+                id: "help",
+                name: "Help",
+                default_keybinding: None,
+                enabled: always,
+                run: |app, _| app.help_modal_shown = true,
+            },
+        ]);
+
+        CommandRegistry::new(commands)
+    }
+
+    // show or hide the given bottom panel
+    fn toggle_bottom_panel(&mut self, panel: BottomPanelState) {
+        self.bottom_panel_state = if self.bottom_panel_state == Some(panel) {
+            None
+        } else {
+            Some(panel)
+        };
+    }
+
+    fn toggle_source_control(&mut self) {
+        if self.source_control.take().is_none() {
+            self.source_control = Some(SourceControl::default());
+            self.refresh_git_status();
+            self.refresh_branches();
         }
     }
 
-    // Saves the selected buffer into a new file.
-    // Prompts the user to select the new location of the file.
-    //
-    // Returns `true` if the save was completed.
-    #[cfg(not(target_arch = "wasm32"))]
-    fn save_as(&mut self) -> Result<(), SaveError> {
-        let Some(buffer) = self.buffers.current_buffer() else {
-            return Err(SaveError::NoBufferSelected);
+    // Reloads the branches shown in the source control panel (if it is shown)
+    fn refresh_branches(&mut self) {
+        let (Some(git), Some(_)) = (&mut self.git, &self.source_control) else {
+            return;
         };
 
-        let Some(path) = rfd::FileDialog::new().save_file() else {
-            return Err(SaveError::NoFileSelected);
+        let result = git.branches().map(GitOutput::Branches);
+        self.git_result(result);
+    }
+
+    // Reloads the files shown in the source control panel (if it is shown)
+    fn refresh_git_status(&mut self) {
+        let (Some(git), Some(source_control)) = (&self.git, &mut self.source_control) else {
+            return;
         };
 
-        // If the current buffer has no file, associate it with the file
-        // Otherwise, create a new buffer with the new file (unless the file hasn't changed)
-        match buffer.file_data() {
-            Some(FileData { path: old_path, .. }) => {
-                if &path != old_path {
-                    self.buffers.add(Buffer::new(
-                        buffer.contents().into(),
-                        Some(FileData {
-                            path,
-                            contents: buffer.contents().into(),
-                        }),
-                    ));
-                }
-            }
+        match git.status() {
+            Ok(files) => source_control.set_files(files),
+            // the status is received later on the web
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+            Err(err) => source_control.set_error(err.to_string()),
+        }
+    }
+
+    fn source_control_action(&mut self, action: SourceControlAction) {
+        let Some(git) = &mut self.git else {
+            return;
+        };
+
+        let result = match action {
+            SourceControlAction::Refresh => {
+                self.refresh_branches();
+                Ok(())
+            }
+            SourceControlAction::Stage(paths) => git.stage(&paths),
+            SourceControlAction::Unstage(paths) => git.unstage(&paths),
+            SourceControlAction::Commit(message) => {
+                let result = git.commit(&message).map(|()| GitOutput::Committed);
+                if let Some(source_control) = &mut self.source_control {
+                    source_control.committing = true;
+                }
+                self.git_result(result);
+                return;
+            }
+            SourceControlAction::SwitchBranch(name) => {
+                if self.buffers.is_dirty() {
+                    self.error_message = Some("Save your changes before switching branch".into());
+                    return;
+                }
+                let result = git.switch_branch(&name).map(|()| GitOutput::SwitchedBranch(name));
+                self.git_result(result);
+                return;
+            }
+            SourceControlAction::CreateBranch(name) => {
+                let result = git.create_branch(&name).map(|()| GitOutput::CreatedBranch(name));
+                self.git_result(result);
+                return;
+            }
+            SourceControlAction::ShowDiff(path) => {
+                match git.diff(&path) {
+                    Ok(diff) => {
+                        if let Some(source_control) = &mut self.source_control {
+                            source_control.set_diff(path, diff);
+                        }
+                    }
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+                    Err(err) => self.error_message = Some(format!("Failed to compare file: {err}")),
+                }
+                return;
+            }
+            SourceControlAction::OpenFile(path) => {
+                if let Some(explorer) = &self.explorer {
+                    self.open_file(explorer.root_path().join(path));
+                }
+                return;
+            }
+        };
+
+        match result {
+            // on the web, the status is refreshed once the server has staged the files
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+            Err(err) => self.error_message = Some(err.to_string()),
+            Ok(()) => self.refresh_git_status(),
+        }
+    }
+
+    // checks whether a git request made on the web has finished
+    fn check_git_request(&mut self) {
+        if let Some(result) = self.git.as_mut().and_then(|git| git.finished()) {
+            self.git_result(result);
+        }
+    }
+
+    // Updates the editor once a git operation has finished (which is straight away on desktop)
+    fn git_result(&mut self, result: std::io::Result<GitOutput>) {
+        match result {
+            Ok(GitOutput::Committed) => {
+                if let Some(source_control) = &mut self.source_control {
+                    source_control.committing = false;
+                    source_control.message.clear();
+                }
+                self.refresh_git_status();
+            }
+            #[cfg(target_arch = "wasm32")]
+            Ok(GitOutput::Saved) => self.refresh_git_status(),
+            Ok(GitOutput::Branches(branches)) => {
+                if let Some(source_control) = &mut self.source_control {
+                    source_control.set_branches(branches);
+                }
+            }
+            Ok(GitOutput::CreatedBranch(branch)) => {
+                if let Some(source_control) = &mut self.source_control {
+                    source_control.set_current_branch(branch);
+                }
+                self.refresh_branches();
+            }
+            Ok(GitOutput::SwitchedBranch(branch)) => {
+                if let Some(source_control) = &mut self.source_control {
+                    source_control.set_current_branch(branch);
+                }
+                self.branch_switched();
+                self.refresh_git_status();
+                self.refresh_branches();
+            }
+            Ok(GitOutput::History(commits)) => {
+                if let Some(history) = &mut self.history {
+                    history.set_commits(commits);
+                }
+            }
+            Ok(GitOutput::FileAtCommit {
+                sha,
+                path,
+                contents,
+            }) => self.open_revision(sha, path, contents),
+            #[cfg(target_arch = "wasm32")]
+            Ok(GitOutput::Merged(merge)) => self.remote_merged(merge),
+            // the result is received later on the web
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+            // the changes on GitHub have to be merged into the project before it can be saved
+            #[cfg(target_arch = "wasm32")]
+            Err(err) if err.get_ref().is_some_and(|err| err.is::<platform::RemoteChanged>()) => {
+                if let Some(source_control) = &mut self.source_control {
+                    source_control.committing = false;
+                }
+                self.merge_modal_state = Some(err.to_string());
+            }
+            Err(err) => {
+                if let Some(source_control) = &mut self.source_control {
+                    source_control.committing = false;
+                }
+                // the history window would be stuck loading otherwise
+                if self.history.as_ref().is_some_and(FileHistory::is_loading) {
+                    self.history = None;
+                }
+                self.error_message = Some(err.to_string());
+            }
+        }
+    }
+
+    // Opens a window listing the commits which changed the file in the selected buffer
+    fn show_file_history(&mut self) {
+        let Some(path) = self
+            .buffers
+            .current_buffer()
+            .and_then(|buffer| Some(buffer.file_data()?.path.clone()))
+        else {
+            return;
+        };
+        let (Some(git), Some(explorer)) = (&mut self.git, &self.explorer) else {
+            return;
+        };
+        let Ok(relative) = path.strip_prefix(explorer.root_path()) else {
+            self.error_message = Some("Only files in the project have a history".into());
+            return;
+        };
+
+        let result = git.history(relative).map(GitOutput::History);
+        self.history = Some(FileHistory::new(path));
+        self.git_result(result);
+    }
+
+    fn show_history(&mut self, ctx: &egui::Context) {
+        let Some(history) = &mut self.history else {
+            return;
+        };
+
+        let (open, action) = history.show(ctx);
+        let path = history.path.clone();
+        if !open {
+            self.history = None;
+        }
+
+        match action {
+            Some(HistoryAction::ViewFile(sha)) => {
+                let (Some(git), Some(explorer)) = (&mut self.git, &self.explorer) else {
+                    return;
+                };
+                let Ok(relative) = path.strip_prefix(explorer.root_path()) else {
+                    return;
+                };
+                let relative = relative.to_path_buf();
+
+                let result = git.file_at(&sha, &relative).map(|contents| GitOutput::FileAtCommit {
+                    sha,
+                    path: relative,
+                    contents,
+                });
+                self.git_result(result);
+            }
+            None => {}
+        }
+    }
+
+    // Opens a read-only buffer showing a file (relative to the project root) as it was at a commit,
+    // or selects it if it is already open
+    fn open_revision(&mut self, sha: String, path: PathBuf, contents: Vec<u8>) {
+        let Some(explorer) = &self.explorer else {
+            return;
+        };
+        let revision = Revision {
+            path: explorer.root_path().join(path),
+            sha,
+        };
+
+        let open = self
+            .buffers
+            .iter()
+            .find(|buffer| buffer.revision() == Some(&revision))
+            .map(Buffer::id);
+        match open {
+            Some(id) => self.buffers.select(id),
+            None => self.buffers.add(Buffer::from_revision(revision, contents)),
+        }
+    }
+
+    // Replaces a file with the old version of it shown in a buffer.
+    // If the file is open, its buffer is changed to the old version, so that it can be checked before being saved
+    fn restore_revision(&mut self, id: Uuid) {
+        let Some(buffer) = self.buffers.get_by_id(id) else {
+            return;
+        };
+        let Some(revision) = buffer.revision() else {
+            return;
+        };
+        if !buffer.kind().is_text() {
+            self.error_message = Some("Only text files can be restored".into());
+            return;
+        }
+        let (path, contents) = (revision.path.clone(), buffer.contents().to_string());
+
+        match self.buffers.get_mut_by_path(&path) {
+            Some(current) => {
+                current.set_contents(contents);
+                let id = current.id();
+                self.buffers.select(id);
+            }
+            None => {
+                match self.fs.write(&path, &contents) {
+                    // on the web, the file is written before it is read to be opened
+                    Err(err) if err.kind() != ErrorKind::WouldBlock => {
+                        self.error_message =
+                            Some(format!("Failed to restore {}: {err}", path.display()));
+                        return;
+                    }
+                    _ => {}
+                }
+                self.open_file(path);
+                self.refresh_git_status();
+            }
+        }
+    }
+
+    // Reopens the project's files after switching branch, as any of them may have changed.
+    // The open buffers don't have unsaved changes (as this is checked before switching), so they are reopened
+    // from the new branch
+    fn branch_switched(&mut self) {
+        self.store_workspace();
+        self.buffers = Buffers::default();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if let Some(explorer) = &mut self.explorer
+                && let Err(err) = explorer.refresh(&self.fs)
+            {
+                self.error_message = Some(err.to_string());
+            }
+            self.load_workspace();
+        }
+
+        // the project is opened again, which rebuilds the explorer and restores the workspace
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.saved_workspace = None;
+            self.fs = platform::FileSystem::new(self.backend_handle.clone());
+            self.backend_handle.send(ws_messages::Command::OpenProject);
+        }
+    }
+
+    // Saves the current contents of the code buffer to a file.
+    //
+    // If no file is currently associated with the `App`, it prompts the user
+    // to select a save location. Once a file is chosen or if a file already
+    // exists, it writes the contents of the code buffer to the file.
+    // It updates the `file` field with the latest contents after saving.
+    //
+    // Returns `true` if the save was completed.
+    fn save_file(&mut self) -> Result<(), SaveError> {
+        let buffer = self
+            .buffers
+            .current_buffer_mut()
+            .ok_or(SaveError::NoBufferSelected)?;
+
+        match buffer.save(&self.fs) {
+            Ok(_) => {
+                // can unwrap as the buffer has been saved to its file
+                let path = buffer.file_data().unwrap().path.clone();
+                self.format_saved(&[path]);
+                Ok(())
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Err(_) => self.save_as(),
+            // there is no file dialog on the web to choose where to save a new file
+            #[cfg(target_arch = "wasm32")]
+            Err(_) => Err(SaveError::NoFileSelected),
+        }
+    }
+
+    // Saves the selected buffer into a new file.
+    // Prompts the user to select the new location of the file.
+    //
+    // Returns `true` if the save was completed.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_as(&mut self) -> Result<(), SaveError> {
+        let Some(buffer) = self.buffers.current_buffer() else {
+            return Err(SaveError::NoBufferSelected);
+        };
+        // only text can be saved into a new file
+        if !buffer.kind().is_text() {
+            return Ok(());
+        }
+
+        let Some(path) = rfd::FileDialog::new().save_file() else {
+            return Err(SaveError::NoFileSelected);
+        };
+
+        // If the current buffer has no file, associate it with the file
+        // Otherwise, create a new buffer with the new file (unless the file hasn't changed)
+        match buffer.file_data() {
+            Some(FileData { path: old_path, .. }) => {
+                if &path != old_path {
+                    self.buffers.add(Buffer::new(
+                        buffer.contents().into(),
+                        Some(FileData {
+                            path,
+                            contents: buffer.contents().into(),
+                        }),
+                    ));
+                }
+            }
             None => {
                 let buffer = self.buffers.current_buffer_mut().unwrap();
                 buffer.set_file_data(FileData {
@@ -638,53 +1490,504 @@ impl App {
                 });
             }
         }
-        self.save_file()?;
+        self.save_file()?;
+
+        Ok(())
+    }
+
+    fn save_all(&mut self) {
+        // collect list of buffers with unsaved changes
+        let dirty_buffers: Vec<_> = self
+            .buffers
+            .iter()
+            .filter_map(|buf| buf.is_dirty().then_some(buf.id()))
+            .collect();
+
+        // loop through unsaved buffers, and prompt the user to save each one
+        let mut saved = vec![];
+        for id in dirty_buffers {
+            // unwrap is safe here as `id` is guaranteed to be associated with a buffer
+            let buffer = self.buffers.get_mut_by_id(id).unwrap();
+            
+            match buffer.save(&self.fs) {
+                Ok(()) => saved.push(buffer.file_data().unwrap().path.clone()),
+                // new files are formatted when they are saved by `save_as`
+                #[cfg(not(target_arch = "wasm32"))]
+                Err(BufferError::NoAssociatedFile) => {
+                    self.buffers.select(id);
+                    // if no file is selected, ignore it and continue saving all
+                    let _ = self.save_as();
+                }
+                Err(_) => {}
+            }
+        }
+
+        self.format_saved(&saved);
+    }
+
+    // Formats the files that have just been saved if format on save is turned on.
+    // Their buffers' contents are passed through the project's stdin formatter if it has one,
+    // otherwise the files are formatted on disk and their buffers are reloaded
+    fn format_saved(&mut self, files: &[PathBuf]) {
+        if !self.editor_settings.format_on_save || files.is_empty() {
+            return;
+        }
+        let Some(project) = &mut self.project else {
+            return;
+        };
+
+        let mut formatted = vec![];
+        for path in files {
+            let Some(buffer) = self
+                .buffers
+                .get_by_path(path)
+                .filter(|buffer| buffer.kind().is_text())
+            else {
+                continue;
+            };
+            match self.runner.format_contents(project, path, buffer.contents()) {
+                Ok(Some(contents)) => formatted.push((path.clone(), contents)),
+                Ok(None) => {}
+                Err(err) => {
+                    self.error_message = Some(format!("Failed to format: {err}"));
+                    return;
+                }
+            }
+        }
+
+        // on the web, the runner formats the files itself if there is no stdin formatter
+        #[cfg(not(target_arch = "wasm32"))]
+        if formatted.is_empty() {
+            self.format(files);
+        }
+
+        for (path, contents) in formatted {
+            self.apply_formatted(&path, contents);
+        }
+    }
+
+    // Passes the contents of the current buffer through the project's stdin formatter,
+    // replacing them with the formatted contents
+    fn format_current_buffer(&mut self) {
+        let (Some(project), Some(buffer)) = (&mut self.project, self.buffers.current_buffer())
+        else {
+            return;
+        };
+        // the path is needed to read the project settings for the file
+        let Some(path) = buffer.file_data().map(|f| f.path.clone()) else {
+            self.error_message = Some("Save the file before formatting it".into());
+            return;
+        };
+
+        match self.runner.format_contents(project, &path, buffer.contents()) {
+            Ok(Some(contents)) => self.apply_formatted(&path, contents),
+            #[cfg(not(target_arch = "wasm32"))]
+            Ok(None) => {
+                self.error_message = Some(
+                    "No stdin formatter set\n\nAdd `format_stdin` to project.toml to format files in the editor"
+                        .into(),
+                )
+            }
+            // on the web, the formatted contents are received later on (in `handle_pending`)
+            #[cfg(target_arch = "wasm32")]
+            Ok(None) => {}
+            Err(err) => self.error_message = Some(format!("Failed to format: {err}")),
+        }
+    }
+
+    // Replaces the contents of a buffer with its formatted contents,
+    // saving them as well if the buffer didn't have any unsaved changes
+    fn apply_formatted(&mut self, path: &Path, contents: String) {
+        let Some(buffer) = self.buffers.get_mut_by_path(path) else {
+            return;
+        };
+
+        let was_dirty = buffer.is_dirty();
+        buffer.set_contents(contents);
+        if !was_dirty && let Err(BufferError::IoError(err)) = buffer.save(&self.fs) {
+            self.error_message = Some(format!("Failed to save {}: {err}", path.display()));
+        }
+    }
+
+    // Formats the given files (or the whole project if `files` is empty) with the project's formatter
+    fn format(&mut self, files: &[PathBuf]) {
+        let Some(project) = &mut self.project else {
+            return;
+        };
+
+        if let Err(err) = self.runner.format(project, files) {
+            self.error_message = Some(format!("Failed to format: {err}"));
+            return;
+        }
+
+        // on the web, the files are reloaded once the server has finished formatting them (in `handle_pending`)
+        #[cfg(not(target_arch = "wasm32"))]
+        self.reload_buffers();
+    }
+
+    // Reloads the buffers that don't have unsaved changes from their files, after the files have been changed
+    fn reload_buffers(&mut self) {
+        let paths: Vec<_> = self
+            .buffers
+            .iter()
+            .filter(|buffer| !buffer.is_dirty())
+            .filter_map(|buffer| Some(buffer.file_data()?.path.clone()))
+            .collect();
+
+        for path in paths {
+            self.read_changed_file(&path);
+        }
+    }
+
+    // Checks the watcher for files that have been changed outside of the editor (every `CHANGE_CHECK_INTERVAL` seconds)
+    fn check_for_changes(&mut self, ctx: &egui::Context) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+
+        // keep checking even when nothing else causes the editor to be redrawn
+        ctx.request_repaint_after(std::time::Duration::from_secs_f64(
+            Self::CHANGE_CHECK_INTERVAL,
+        ));
+        let time = ctx.input(|i| i.time);
+        if time - self.last_change_check < Self::CHANGE_CHECK_INTERVAL {
+            return;
+        }
+        self.last_change_check = time;
+
+        match watcher.changed_files() {
+            Ok(paths) => self.files_changed(&paths),
+            // on the web, the changed files are received later on (in `handle_pending`)
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+            Err(err) => {
+                self.error_message = Some(format!("Failed to check for changed files: {err}"))
+            }
+        }
+    }
+
+    // Updates the open buffers and the explorer after files have been changed outside of the editor
+    fn files_changed(&mut self, paths: &[PathBuf]) {
+        for path in paths {
+            self.read_changed_file(path);
+        }
+
+        // new files need to be added to the explorer
+        let new_dirs: Vec<_> = paths
+            .iter()
+            .filter(|path| self.explorer.as_ref().is_some_and(|e| e.is_missing(path)))
+            .filter_map(|path| path.parent())
+            .collect();
+        if !new_dirs.is_empty() {
+            self.refresh_explorer(new_dirs.into_iter());
+        }
+    }
+
+    // Reads a file that has been changed outside of the editor, if it is open in a buffer
+    fn read_changed_file(&mut self, path: &Path) {
+        let Some(buffer) = self.buffers.get_mut_by_path(path) else {
+            return;
+        };
+
+        // buffers which aren't text can't have unsaved changes, so they are always reloaded
+        if !buffer.kind().is_text() {
+            match self.fs.read_bytes(path) {
+                Ok(bytes) => buffer.reload_bytes(bytes),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+                Err(err) => {
+                    self.error_message = Some(format!("Failed to reload {}: {err}", path.display()))
+                }
+            }
+            return;
+        }
+
+        match self.fs.read_file(path) {
+            Ok(contents) => self.file_changed(path, contents),
+            // on the web, the file contents are received later on (in `handle_pending`)
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+            Err(err) => {
+                self.error_message = Some(format!("Failed to reload {}: {err}", path.display()))
+            }
+        }
+    }
+
+    // Updates the buffer for a file whose contents have changed outside of the editor.
+    // Buffers without unsaved changes are reloaded, otherwise the user is asked what to do with the changes
+    fn file_changed(&mut self, path: &Path, contents: String) {
+        let Some(buffer) = self.buffers.get_mut_by_path(path) else {
+            return;
+        };
+        // the editor's own saves also show up as changes, but the file will match what was saved
+        if buffer.file_data().is_some_and(|file| file.contents == contents) {
+            return;
+        }
+
+        if !buffer.is_dirty() {
+            buffer.reload(contents);
+        } else if buffer.contents() == contents {
+            // the file was changed to match the buffer, so it no longer has unsaved changes
+            buffer.set_file_data(FileData {
+                contents,
+                path: path.to_path_buf(),
+            });
+        } else {
+            match self.file_conflicts.iter_mut().find(|c| c.path == path) {
+                Some(conflict) => {
+                    conflict.disk_contents = contents;
+                    conflict.comparison = None;
+                }
+                None => self.file_conflicts.push(FileConflict {
+                    path: path.to_path_buf(),
+                    disk_contents: contents,
+                    comparison: None,
+                }),
+            }
+        }
+    }
+
+    // Shows a modal for a file changed outside of the editor while its buffer has unsaved changes,
+    // letting the user compare the two versions and choose which to keep (or merge them)
+    fn show_file_conflict_modal(&mut self, ctx: &egui::Context) {
+        let Some(conflict) = self.file_conflicts.first_mut() else {
+            return;
+        };
+        // the buffer may have been closed since the file changed
+        let Some(buffer) = self.buffers.get_by_path(&conflict.path) else {
+            self.file_conflicts.remove(0);
+            return;
+        };
+
+        let mut resolution = None;
+        Modal::new(Id::new("file_conflict_modal")).show(ctx, |ui| {
+            let name = conflict
+                .path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+            ui.heading(format!("{name} has changed on disk"));
+            ui.label(
+                "The file was changed outside of the editor, but its buffer has unsaved changes.",
+            );
+
+            let mut comparing = conflict.comparison.is_some();
+            if ui.checkbox(&mut comparing, "Compare changes").changed() {
+                conflict.comparison = comparing.then(|| {
+                    diff::diff_lines(buffer.contents(), &conflict.disk_contents)
+                        .into_iter()
+                        .map(|line| match line {
+                            DiffLine::Same(text) => (' ', text.to_string()),
+                            DiffLine::Removed(text) => ('-', text.to_string()),
+                            DiffLine::Added(text) => ('+', text.to_string()),
+                        })
+                        .collect()
+                });
+            }
+
+            if let Some(comparison) = &conflict.comparison {
+                ui.label("Lines only in the buffer are marked with -, and lines only on disk with +");
+                let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+                ScrollArea::both().max_height(400.0).show_rows(
+                    ui,
+                    row_height,
+                    comparison.len(),
+                    |ui, rows| {
+                        for (kind, text) in &comparison[rows] {
+                            let color = match kind {
+                                '-' => ui.visuals().error_fg_color,
+                                '+' => Color32::from_rgb(0x4c, 0xaf, 0x50),
+                                _ => ui.visuals().weak_text_color(),
+                            };
+                            let line = format!("{kind} {}", text.trim_end_matches(['\r', '\n']));
+                            ui.label(RichText::new(line).monospace().color(color));
+                        }
+                    },
+                );
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Merge")
+                    .on_hover_text("Keep both sets of changes, marking any lines changed in both")
+                    .clicked()
+                {
+                    resolution = Some(ConflictResolution::Merge);
+                }
+                if ui.button("Keep my changes").clicked() {
+                    resolution = Some(ConflictResolution::KeepBuffer);
+                }
+                if ui.button("Load from disk").clicked() {
+                    resolution = Some(ConflictResolution::LoadFromDisk);
+                }
+            });
+        });
+
+        let Some(resolution) = resolution else {
+            return;
+        };
+        let conflict = self.file_conflicts.remove(0);
+        let Some(buffer) = self.buffers.get_mut_by_path(&conflict.path) else {
+            return;
+        };
+        // the buffer's snapshot of the file is what both sets of changes were made to
+        let base = buffer
+            .file_data()
+            .map(|file| file.contents.clone())
+            .unwrap_or_default();
+
+        match resolution {
+            ConflictResolution::Merge => {
+                let merged = diff::merge(
+                    &base,
+                    buffer.contents(),
+                    &conflict.disk_contents,
+                    "unsaved changes",
+                    "changes on disk",
+                );
+                buffer.set_contents(merged.text);
+                if merged.conflicts > 0 {
+                    self.error_message = Some(format!(
+                        "{} conflicting change(s) in {} were kept between <<<<<<< and >>>>>>> markers",
+                        merged.conflicts,
+                        conflict.path.display()
+                    ));
+                }
+            }
+            ConflictResolution::KeepBuffer => {}
+            ConflictResolution::LoadFromDisk => {
+                buffer.set_contents(conflict.disk_contents.clone());
+            }
+        }
 
-        Ok(())
+        // the buffer is now based on the file on disk, so saving it only overwrites the chosen changes
+        buffer.set_file_data(FileData {
+            contents: conflict.disk_contents,
+            path: conflict.path,
+        });
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
-    fn save_all(&mut self) {
-        if let Some(project) = &mut self.project {
-            let _ = self.runner.format(project);
+    // send a request to the backend API at /project/github_save
+    // to commit the changes to the project's files to GitHub
+    #[cfg(target_arch = "wasm32")]
+    fn save_to_github(&mut self) {
+        if let Some(git) = &mut self.git {
+            let result = git.save_to_github().map(|()| GitOutput::Saved);
+            self.git_result(result);
         }
+    }
 
-        // collect list of buffers with unsaved changes
-        let dirty_buffers: Vec<_> = self
-            .buffers
-            .iter()
-            .filter_map(|buf| buf.is_dirty().then_some(buf.id()))
-            .collect();
+    // Merges the commits made on GitHub since the project was opened (or last saved) into the project's files
+    // (pulling them into the project). Buffers with unsaved changes to files that changed are kept, and the user
+    // is asked how to handle them like any other file changed outside of the editor
+    #[cfg(target_arch = "wasm32")]
+    fn merge_remote(&mut self) {
+        if let Some(git) = &mut self.git
+            && let Err(err) = git.merge_remote()
+        {
+            self.git_result(Err(err));
+        }
+    }
 
-        // loop through unsaved buffers, and prompt the user to save each one
-        for id in dirty_buffers {
-            // unwrap is safe here as `id` is guaranteed to be associated with a buffer
-            let buffer = self.buffers.get_mut_by_id(id).unwrap();
-            
-            if let Err(BufferError::NoAssociatedFile) = buffer.save(&self.fs) {
-                self.buffers.select(id);
-                // if no file is selected, ignore it and continue saving all
-                let _ = self.save_as();
+    // Updates the editor once the changes on GitHub have been merged into the project, and lists the files which changed.
+    // The files are reloaded, and files with conflicts are opened so that they can be resolved
+    #[cfg(target_arch = "wasm32")]
+    fn remote_merged(&mut self, merge: ws_messages::GitMerge) {
+        let Some(root) = self.explorer.as_ref().map(|explorer| explorer.root_path().to_path_buf()) else {
+            return;
+        };
+
+        let changed: Vec<_> = merge.changed.iter().map(|path| root.join(path)).collect();
+        for path in &changed {
+            self.read_changed_file(path);
+        }
+        // files may have been added or deleted on GitHub
+        let mut dirs: Vec<_> = changed.iter().filter_map(|path| path.parent()).collect();
+        dirs.sort();
+        dirs.dedup();
+        self.refresh_explorer(dirs.into_iter());
+        self.refresh_git_status();
+
+        if merge.changed.is_empty() {
+            self.error_message = Some("The project is already up to date with GitHub".into());
+            return;
+        }
+        let mut message = format!("{} file(s) changed on GitHub:", merge.changed.len());
+        // a long list wouldn't fit in the modal
+        for path in merge.changed.iter().take(Self::MAX_LISTED_FILES) {
+            message.push_str(&format!("\n{}", path.display()));
+        }
+        if merge.changed.len() > Self::MAX_LISTED_FILES {
+            message.push_str(&format!("\n...and {} more", merge.changed.len() - Self::MAX_LISTED_FILES));
+        }
+
+        if !merge.conflicts.is_empty() {
+            for path in &merge.conflicts {
+                self.open_file(root.join(path));
             }
+            message.push_str(&format!(
+                "\n\n{} of them were also changed in the same places here. Both versions are kept in each file \
+                between <<<<<<< and >>>>>>> markers. Choose what to keep before saving to GitHub",
+                merge.conflicts.len()
+            ));
         }
+        self.error_message = Some(message);
     }
 
-    fn format_files(&self) {
-        if self.editor_settings.format_on_save && let Some(project) = &self.project {
+    // Lets the user choose files (or a folder) from their computer, and uploads them into the directory
+    #[cfg(target_arch = "wasm32")]
+    fn upload(&mut self, dir: PathBuf, folder: bool) {
+        if let Err(err) = platform::pick_and_upload(&self.backend_handle, dir, folder) {
+            self.error_message = Some(format!("Failed to upload: {err}"));
+        }
+    }
 
+    // uploads files (or a folder) into the directory selected in the explorer
+    #[cfg(target_arch = "wasm32")]
+    fn upload_to_selected(&mut self, folder: bool) {
+        if let Some(explorer) = &self.explorer {
+            self.upload(explorer.target_dir(), folder);
         }
     }
 
-    // send a request to the backend API at /project/github_save
-    // to commit the contents of the project to GitHub
+    // Downloads a file, folder or the whole project (if `path` is `None`) as an archive
     #[cfg(target_arch = "wasm32")]
-    fn save_to_github(&mut self) {
-        wasm_bindgen_futures::spawn_local(async move {
-            gloo_net::http::Request::post("/api/project/github_save")
-                .send()
-                .await
-                .expect("failed to save project to github");
-        });
+    fn download(&mut self, path: Option<&Path>) {
+        if let Err(err) = platform::download(path) {
+            self.error_message = Some(format!("Failed to download: {err}"));
+        }
+    }
+
+    // Adds files dropped onto the editor from the user's computer to the directory selected in the explorer
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        if dropped.is_empty() {
+            return;
+        }
+        let Some(explorer) = &self.explorer else {
+            return;
+        };
+        let dir = explorer.target_dir();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let paths: Vec<_> = dropped.into_iter().filter_map(|file| file.path).collect();
+            self.copy_files(&paths, &dir);
+        }
+
+        // in the browser, only the names and contents of the files are available
+        #[cfg(target_arch = "wasm32")]
+        {
+            let files = dropped
+                .into_iter()
+                .filter_map(|file| {
+                    Some(ws_messages::UploadFile {
+                        path: file.name.into(),
+                        contents: file.bytes?.to_vec(),
+                    })
+                })
+                .collect();
+            self.fs.upload(&dir, files);
+            self.refresh_explorer([dir.as_path()].into_iter());
+        }
     }
 
     // open OS-provided dialog to select a file to open
@@ -700,6 +2003,11 @@ impl App {
 
     // open a new buffer for the file at the provided path
     fn open_file(&mut self, path: PathBuf) {
+        // move the file to the front of the recently opened files
+        self.recent_files.retain(|recent| recent != &path);
+        self.recent_files.insert(0, path.clone());
+        self.recent_files.truncate(Self::MAX_RECENT_FILES);
+
         // Don't open a new tab if the file is already open
         if let Some(buffer) = self.buffers.get_by_path(&path) {
             self.buffers.select(buffer.id());
@@ -715,7 +2023,24 @@ impl App {
         }
         #[cfg(target_arch = "wasm32")]
         {
-            let _ = self.fs.read_file(&path);
+            let _ = self.fs.read_bytes(&path);
+        }
+    }
+
+    // open the file at the provided path, and move the cursor to the given position in it
+    fn open_file_at(&mut self, path: PathBuf, position: CursorPosition) {
+        self.open_file(path.clone());
+
+        match self.buffers.get_mut_by_path(&path) {
+            Some(buffer) => buffer.set_cursor(position),
+            // on the web, the file contents haven't been received from the server yet,
+            // so the cursor is moved once they have been (in `handle_pending`)
+            #[cfg(target_arch = "wasm32")]
+            None => {
+                self.pending_cursors.insert(path, position);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            None => {}
         }
     }
 
@@ -748,6 +2073,9 @@ impl App {
             panic!("path must be a dir");
         }
 
+        // keep the state of the project being closed, so that it is restored if it is opened again
+        self.store_workspace();
+
         // load the project settings from the .ide directory
         let settings = match ProjectSettings::read_from(&path) {
             Ok(settings) => settings,
@@ -760,6 +2088,23 @@ impl App {
         // instantiate a new `Project` object
         self.project = Some(Project::new(path.clone(), settings));
 
+        // source control uses the git repository in the project's directory (if there is one)
+        self.git = Some(platform::Git::new(&path));
+        if let Some(source_control) = &mut self.source_control {
+            *source_control = SourceControl::default();
+        }
+        self.refresh_git_status();
+
+        // watch the project's files so that buffers can be updated when they are changed outside of the editor
+        self.file_conflicts.clear();
+        self.watcher = match platform::FileWatcher::new(&path) {
+            Ok(watcher) => Some(watcher),
+            Err(err) => {
+                self.error_message = Some(format!("Failed to watch project for changes: {err}"));
+                None
+            }
+        };
+
         // initialise the interactive terminal backend
         // requires using an MPSC (Multiple Producer, Single Consumer) channel to send data between
         // the process running the terminal shell and the widget displaying the terminal in the UI
@@ -789,20 +2134,443 @@ impl App {
             Ok(explorer) => {
                 self.explorer = Some(explorer);
                 self.buffers = Buffers::default();
+                self.load_workspace();
             }
             // display error message to user if loading file tree failed
             Err(err) => self.error_message = Some(err.to_string()),
         }
     }
 
-    // delete the file for the path, and remove the buffer in the UI
-    fn delete(&mut self, path: &Path) {
-        if let Some(buffer) = self.buffers.get_by_path(path) {
-            self.buffers.delete_buffer(buffer.id());
-        }
+    // Reads the workspace state saved for the project in its previous session, and restores it
+    fn load_workspace(&mut self) {
+        self.saved_workspace = None;
+        self.pending_buffer_states.clear();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            // the state is kept in the map, as it is only stored again if it changes
+            let root = self.explorer.as_ref().map(|e| e.root_path());
+            let state = root
+                .and_then(|root| self.workspaces.get(root).cloned())
+                .unwrap_or_default();
+            self.restore_workspace(state);
+        }
+        // on the web, the state is restored once it has been received (in `handle_pending`)
+        #[cfg(target_arch = "wasm32")]
+        self.backend_handle.send(ws_messages::Command::LoadWorkspace);
+    }
+
+    // Splits the editor into the panes from the project's previous session and reopens the buffers
+    // (with their unsaved changes), then restores the panels around them
+    fn restore_workspace(&mut self, mut state: WorkspaceState) {
+        self.saved_workspace = Some(serde_yaml::to_string(&state).unwrap_or_default());
+        let Some(root) = self.explorer.as_ref().map(|e| e.root_path().to_path_buf()) else {
+            return;
+        };
+
+        // on the web, the files are still being opened when the buffer states are restored below
+        let mut layout_files = HashSet::new();
+        if let Some(layout) = state.layout.take() {
+            for path in self.buffers.restore_layout(layout, &root) {
+                // files may have been deleted since the layout was saved
+                #[cfg(not(target_arch = "wasm32"))]
+                if !path.is_file() {
+                    continue;
+                }
+                layout_files.insert(path.clone());
+                self.open_file(path);
+            }
+        }
+
+        let Some(explorer) = &mut self.explorer else {
+            return;
+        };
+        explorer.expanded = state.expanded_dirs.iter().map(|dir| root.join(dir)).collect();
+        self.bottom_panel_state = state.bottom_panel;
+
+        for buffer_state in state.buffers {
+            // untitled buffers only exist in the workspace state, so they are recreated from their drafts
+            let Some(path) = buffer_state.path.as_ref().map(|path| root.join(path)) else {
+                let contents = buffer_state.draft.clone().unwrap_or_default();
+                let mut buffer = Buffer::new(contents, None);
+                buffer.restore_view(buffer_state.cursor, buffer_state.scroll);
+
+                let id = buffer.id();
+                self.buffers.add_restored(buffer);
+                self.select_restored(id, &buffer_state);
+                continue;
+            };
+
+            // files in the saved layout have already been opened
+            if self.buffers.get_by_path(&path).is_none() && !layout_files.contains(&path) {
+                // files may have been deleted since the last session
+                #[cfg(not(target_arch = "wasm32"))]
+                if !path.is_file() {
+                    continue;
+                }
+                self.buffers.restore_file(path.clone());
+                self.open_file(path.clone());
+            }
+            self.pending_buffer_states.insert(path.clone(), buffer_state);
+            self.apply_buffer_state(&path);
+        }
+    }
+
+    // Moves a buffer back to where it was in the previous session and restores its unsaved changes,
+    // once its file has been opened
+    fn apply_buffer_state(&mut self, path: &Path) {
+        let Some(buffer) = self.buffers.get_mut_by_path(path) else {
+            return;
+        };
+        let Some(mut state) = self.pending_buffer_states.remove(path) else {
+            return;
+        };
+
+        buffer.restore_view(state.cursor, state.scroll);
+        if let Some(draft) = state.draft.take()
+            && buffer.kind().is_text()
+        {
+            buffer.set_contents(draft);
+        }
+
+        let id = buffer.id();
+        self.select_restored(id, &state);
+    }
+
+    fn select_restored(&mut self, id: Uuid, state: &BufferState) {
+        if state.active {
+            self.buffers.select(id);
+        } else if state.selected {
+            self.buffers.select_tab(id);
+        }
+    }
+
+    // The current state of the workspace, to be restored when the project is next opened
+    fn workspace_state(&self) -> Option<WorkspaceState> {
+        let explorer = self.explorer.as_ref()?;
+        let root = explorer.root_path();
+        let current_id = self.buffers.current_buffer().map(Buffer::id);
+
+        let buffers = self
+            .buffers
+            .iter()
+            .filter_map(|buffer| {
+                let path = match buffer.file_data() {
+                    Some(file) => Some(file.path.strip_prefix(root).ok()?.to_path_buf()),
+                    // empty untitled buffers aren't worth restoring
+                    None if !buffer.is_dirty() => return None,
+                    None => None,
+                };
+
+                Some(BufferState {
+                    path,
+                    cursor: buffer.cursor(),
+                    scroll: buffer.scroll_offset(),
+                    draft: buffer.is_dirty().then(|| buffer.contents().to_string()),
+                    selected: self.buffers.is_selected(buffer.id()),
+                    active: current_id == Some(buffer.id()),
+                })
+            })
+            .collect();
+
+        // sorted so that the state only changes when the open directories do
+        let mut expanded_dirs: Vec<_> = explorer
+            .expanded
+            .iter()
+            .filter_map(|dir| Some(dir.strip_prefix(root).ok()?.to_path_buf()))
+            .collect();
+        expanded_dirs.sort();
+
+        Some(WorkspaceState {
+            buffers,
+            bottom_panel: self.bottom_panel_state,
+            expanded_dirs,
+            layout: Some(self.buffers.saved_layout(root)),
+        })
+    }
+
+    // Saves the state of the workspace every few seconds
+    fn save_workspace(&mut self, ctx: &egui::Context) {
+        let time = ctx.input(|i| i.time);
+        if time - self.last_workspace_save < Self::WORKSPACE_SAVE_INTERVAL {
+            return;
+        }
+        self.last_workspace_save = time;
+
+        self.store_workspace();
+    }
+
+    // Stores the state of the workspace if it has changed since it was last stored
+    // (in eframe's storage on desktop, or on the server for the web editor)
+    fn store_workspace(&mut self) {
+        let Some(saved_workspace) = &self.saved_workspace else {
+            return;
+        };
+        let Some(state) = self.workspace_state() else {
+            return;
+        };
+        let Ok(serialized) = serde_yaml::to_string(&state) else {
+            return;
+        };
+        if &serialized == saved_workspace {
+            return;
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(explorer) = &self.explorer {
+            self.workspaces.insert(explorer.root_path().to_path_buf(), state);
+        }
+        #[cfg(target_arch = "wasm32")]
+        self.backend_handle
+            .send(ws_messages::Command::SaveWorkspace {
+                state: serialized.clone(),
+            });
+
+        self.saved_workspace = Some(serialized);
+    }
+
+    // delete the file (or directory) for the path, and remove the buffers for it in the UI
+    fn delete(&mut self, path: &Path) {
+        let ids: Vec<_> = self
+            .buffers
+            .iter()
+            .filter(|buffer| buffer.file_data().is_some_and(|file| file.path.starts_with(path)))
+            .map(Buffer::id)
+            .collect();
+        for id in ids {
+            self.buffers.delete_buffer(id);
+        }
+
+        if self.check_fs_result(self.fs.delete(path)) {
+            self.refresh_explorer(path.parent().into_iter());
+        }
+    }
+
+    // Checks the result of changing the project's files, showing any error to the user.
+    // Returns whether the change was made (or on the web, whether it has been sent to the server)
+    fn check_fs_result(&mut self, result: std::io::Result<()>) -> bool {
+        match result {
+            Ok(()) => true,
+            Err(err) if err.kind() == ErrorKind::WouldBlock => true,
+            Err(err) => {
+                self.error_message = Some(err.to_string());
+                false
+            }
+        }
+    }
+
+    fn new_file(&mut self, path: &Path) {
+        if self.check_fs_result(self.fs.new_file(path)) {
+            self.refresh_explorer(path.parent().into_iter());
+            self.open_file(path.to_path_buf());
+        }
+    }
+
+    fn new_folder(&mut self, path: &Path) {
+        if self.check_fs_result(self.fs.new_folder(path)) {
+            self.refresh_explorer(path.parent().into_iter());
+        }
+    }
+
+    // Moves the files and directories into the directory `to`, updating the paths of their open buffers
+    fn move_files(&mut self, paths: &[PathBuf], to: &Path) {
+        let Some(explorer) = &self.explorer else {
+            return;
+        };
+        if !to.starts_with(explorer.root_path()) {
+            return;
+        }
+
+        let mut changed_dirs = vec![to.to_path_buf()];
+        for path in paths {
+            // skip entries that are already in the directory, and directories being moved into themselves
+            if path.parent() == Some(to) || to.starts_with(path) {
+                continue;
+            }
+            let Some(name) = path.file_name() else {
+                continue;
+            };
+
+            if self.move_entry(path, &to.join(name)) {
+                changed_dirs.extend(path.parent().map(Path::to_path_buf));
+            }
+        }
+
+        self.refresh_explorer(changed_dirs.iter().map(PathBuf::as_path));
+    }
+
+    // Renames a file or directory (within the same directory), updating the paths of its open buffers
+    fn rename_entry(&mut self, from: &Path, to: &Path) {
+        if self.move_entry(from, to) {
+            self.refresh_explorer(from.parent().into_iter());
+        }
+    }
+
+    // moves a file or directory to `new_path`, unless something is already there
+    fn move_entry(&mut self, path: &Path, new_path: &Path) -> bool {
+        let Some(explorer) = &self.explorer else {
+            return false;
+        };
+        // moving would replace an existing file on desktop
+        #[cfg(not(target_arch = "wasm32"))]
+        let exists = explorer.is_listed(new_path) || new_path.exists();
+        #[cfg(target_arch = "wasm32")]
+        let exists = explorer.is_listed(new_path);
+        if exists {
+            self.error_message = Some(format!(
+                "Can't move {} as {} already exists",
+                path.display(),
+                new_path.display()
+            ));
+            return false;
+        }
+
+        if !self.check_fs_result(self.fs.rename(path, new_path)) {
+            return false;
+        }
+        self.buffers_moved(path, new_path);
+
+        true
+    }
+
+    // Copies the files and directories into the directory `to`,
+    // adding "copy" to their names if they are already in it (e.g. when duplicating a file)
+    fn copy_files(&mut self, paths: &[PathBuf], to: &Path) {
+        let Some(explorer) = &self.explorer else {
+            return;
+        };
+        if !to.starts_with(explorer.root_path()) {
+            return;
+        }
+
+        for path in paths {
+            let Some(explorer) = &self.explorer else {
+                return;
+            };
+            let Some(name) = path.file_name() else {
+                continue;
+            };
+            let new_path = explorer.unused_path(to, &name.to_string_lossy());
+
+            if !self.check_fs_result(self.fs.copy(path, &new_path)) {
+                break;
+            }
+        }
+
+        self.refresh_explorer([to].into_iter());
+    }
+
+    // updates the paths of the open buffers for a file or directory which has been moved
+    fn buffers_moved(&mut self, from: &Path, to: &Path) {
+        for buffer in self.buffers.iter_mut() {
+            let Some(file) = buffer.file_data() else {
+                continue;
+            };
+            let Ok(rest) = file.path.strip_prefix(from) else {
+                continue;
+            };
+
+            let path = if rest.as_os_str().is_empty() {
+                to.to_path_buf()
+            } else {
+                to.join(rest)
+            };
+            let contents = file.contents.clone();
+            buffer.set_file_data(FileData { contents, path });
+        }
+    }
+
+    // Updates the explorer after the contents of the given directories have changed.
+    // On the web, the directories' new contents are requested, and the explorer is refreshed once they are received (in `handle_pending`)
+    fn refresh_explorer<'a>(&mut self, changed_dirs: impl Iterator<Item = &'a Path>) {
+        #[cfg(target_arch = "wasm32")]
+        for dir in changed_dirs {
+            self.fs.refresh_dir(dir);
+        }
+
+        // on desktop, the whole tree is read again, so it doesn't matter which directories changed
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let _ = changed_dirs;
+            if let Some(explorer) = &mut self.explorer
+                && let Err(err) = explorer.refresh(&self.fs)
+            {
+                self.error_message = Some(err.to_string());
+            }
+        }
+    }
+
+    fn show_delete_modal(&mut self, ctx: &egui::Context) {
+        let Some(paths) = &self.delete_modal_state else {
+            return;
+        };
+
+        let mut confirmed = false;
+        let mut cancelled = false;
+        let modal = Modal::new(Id::new("delete_modal")).show(ctx, |ui| {
+            match paths.as_slice() {
+                [path] => ui.label(format!("Are you sure you want to delete {}?", path.display())),
+                paths => ui.label(format!("Are you sure you want to delete these {} items?", paths.len())),
+            };
+            if paths.len() > 1 {
+                ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                    for path in paths {
+                        ui.label(path.display().to_string());
+                    }
+                });
+            }
+            ui.label("This can't be undone.");
+
+            ui.with_layout(Layout::default().with_cross_align(Align::Max), |ui| {
+                ui.horizontal(|ui| {
+                    confirmed = ui.button("Delete").clicked();
+                    cancelled = ui.button("Cancel").clicked();
+                });
+            });
+        });
+
+        if confirmed {
+            let paths = self.delete_modal_state.take().unwrap_or_default();
+            for path in &paths {
+                self.delete(path);
+            }
+            if let Some(explorer) = &mut self.explorer {
+                explorer.selection.paths.clear();
+            }
+        } else if cancelled || modal.should_close() {
+            self.delete_modal_state = None;
+        }
+    }
+
+    // Shows a modal offering to merge the changes on GitHub into the project, after saving failed because of them
+    #[cfg(target_arch = "wasm32")]
+    fn show_merge_modal(&mut self, ctx: &egui::Context) {
+        let Some(message) = &self.merge_modal_state else {
+            return;
+        };
+
+        let mut merge = false;
+        let mut cancelled = false;
+        let modal = Modal::new(Id::new("merge_modal")).show(ctx, |ui| {
+            ui.label(message);
+            ui.label(
+                "Merge their changes into the project, then save again. \
+                Files changed in the same places on GitHub and here will have conflicts to resolve.",
+            );
+
+            ui.with_layout(Layout::default().with_cross_align(Align::Max), |ui| {
+                ui.horizontal(|ui| {
+                    merge = ui.button("Merge").clicked();
+                    cancelled = ui.button("Cancel").clicked();
+                });
+            });
+        });
 
-        if let Err(err) = self.fs.delete(path) {
-            self.error_message = Some(err.to_string());
+        if merge {
+            self.merge_modal_state = None;
+            self.merge_remote();
+        } else if cancelled || modal.should_close() {
+            self.merge_modal_state = None;
         }
     }
 
@@ -886,8 +2654,41 @@ impl App {
                     }
                 });
 
-            // TODO: add options for other settings
-            
+            ui.separator();
+
+            ui.collapsing("Keybindings", |ui| {
+                Self::keybinding_editor(
+                    ui,
+                    &self.commands,
+                    &mut settings_state.keymap,
+                    &mut self.recording_keybinding,
+                );
+            });
+
+            ui.collapsing("Editor", |ui| {
+                ComboBox::from_label("Font")
+                    .selected_text(settings_state.font_family.name())
+                    .show_ui(ui, |ui| {
+                        for family in FontFamily::ALL {
+                            ui.selectable_value(&mut settings_state.font_family, family, family.name());
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    ui.add(DragValue::new(&mut settings_state.font_size).range(8.0..=32.0).speed(0.5));
+                    ui.label("Font size");
+                });
+                ui.horizontal(|ui| {
+                    ui.add(DragValue::new(&mut settings_state.tab_width).range(1..=16));
+                    ui.label("Tab width");
+                });
+                ui.checkbox(&mut settings_state.insert_spaces, "Indent using spaces");
+                ui.checkbox(&mut settings_state.soft_wrap, "Wrap long lines");
+                ui.checkbox(&mut settings_state.line_numbers, "Line numbers");
+                ui.checkbox(&mut settings_state.highlight_current_line, "Highlight current line");
+                ui.checkbox(&mut settings_state.show_whitespace, "Show whitespace");
+                ui.checkbox(&mut settings_state.minimap, "Minimap");
+            });
+
             ui.horizontal(|ui| {
                 if ui.button("Done").clicked() {
                     updated = true;
@@ -907,6 +2708,8 @@ impl App {
             let scheme = self.available_color_schemes.get_scheme(scheme).unwrap();
             self.set_color_scheme(ctx, &scheme.clone());
         }
+        self.commands.apply_keymap(&settings.keymap);
+        self.recording_keybinding = None;
 
         #[cfg(target_arch = "wasm32")]
         self.backend_handle
@@ -917,16 +2720,116 @@ impl App {
         self.editor_settings = settings;
     }
 
+    // show the keybinding of every command, which can be changed by clicking on it then pressing the new shortcut
+    // commands which have the same keybinding as another command are highlighted
+    fn keybinding_editor(
+        ui: &mut egui::Ui,
+        commands: &CommandRegistry<App>,
+        keymap: &mut Keymap,
+        recording: &mut Option<&'static str>,
+    ) {
+        ComboBox::from_label("Preset")
+            .selected_text(commands::preset_name(keymap.preset))
+            .show_ui(ui, |ui| {
+                for preset in [KeymapPreset::VsCode, KeymapPreset::Emacs] {
+                    if ui
+                        .selectable_value(&mut keymap.preset, preset, commands::preset_name(preset))
+                        .changed()
+                    {
+                        // the user's own keybindings are replaced by the new preset
+                        keymap.overrides.clear();
+                    }
+                }
+            });
+
+        // set the keybinding being recorded to the first shortcut pressed (or cancel it if Escape is pressed)
+        if let Some(id) = *recording {
+            let pressed = ui.input(|i| {
+                i.events.iter().find_map(|event| match event {
+                    Event::Key {
+                        key,
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } => Some(KeyboardShortcut::new(*modifiers, *key)),
+                    _ => None,
+                })
+            });
+
+            if let Some(shortcut) = pressed {
+                if shortcut.logical_key != Key::Escape {
+                    keymap
+                        .overrides
+                        .insert(id.to_string(), Some(commands::to_keybinding(&shortcut)));
+                }
+                *recording = None;
+            }
+        }
+
+        let keybindings = commands.resolve(keymap);
+        let conflicts = commands::conflicts(&keybindings);
+
+        ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+            Grid::new("keybindings").striped(true).num_columns(4).show(ui, |ui| {
+                for command in commands.iter() {
+                    ui.label(command.name);
+
+                    let text = if *recording == Some(command.id) {
+                        "Press a shortcut...".to_string()
+                    } else {
+                        keybindings
+                            .get(command.id)
+                            .copied()
+                            .flatten()
+                            .map_or("Unbound".to_string(), |shortcut| ui.ctx().format_shortcut(&shortcut))
+                    };
+                    if ui.button(text).clicked() {
+                        *recording = Some(command.id);
+                    }
+
+                    ui.horizontal(|ui| {
+                        if ui.small_button("Clear").clicked() {
+                            keymap.overrides.insert(command.id.to_string(), None);
+                        }
+                        if keymap.overrides.contains_key(command.id)
+                            && ui.small_button("Reset").clicked()
+                        {
+                            keymap.overrides.remove(command.id);
+                        }
+                    });
+
+                    if conflicts.contains(command.id) {
+                        ui.colored_label(ui.visuals().error_fg_color, "Conflicts with another command");
+                    } else {
+                        ui.label("");
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
     // show modal which allows the user to perform search and replace
     fn show_search_modal(
         ctx: &egui::Context,
         search_state: &mut SearchModalState,
         opened: &mut Option<SearchResult>,
         changed: &mut bool,
+        previewed: &mut bool,
         replaced: &mut bool,
         done: &mut bool,
     ) {
         Modal::new(Id::new("search_modal")).show(ctx, |ui| {
+            // once a replace has been previewed, only show the changes until the user confirms them
+            if let Some(changes) = &mut search_state.replace_preview {
+                let mut back = false;
+                Self::show_replace_preview(ui, changes, replaced, done, &mut back);
+                if back {
+                    search_state.replace_preview = None;
+                }
+                return;
+            }
+
             ui.label("Search");
             // text prompt for search query
             if ui
@@ -935,15 +2838,39 @@ impl App {
             {
                 *changed = true;
             }
+
+            // options for how the search query is matched
+            ui.horizontal(|ui| {
+                *changed |= ui.checkbox(&mut search_state.regex, "Regex").changed();
+                *changed |= ui
+                    .checkbox(&mut search_state.case_sensitive, "Match case")
+                    .changed();
+                *changed |= ui
+                    .checkbox(&mut search_state.whole_word, "Whole word")
+                    .changed();
+                ui.label("Files:");
+                *changed |= ui
+                    .add(TextEdit::singleline(&mut search_state.glob).hint_text("e.g. *.py"))
+                    .changed();
+            });
+            if let Some(err) = &search_state.search_error {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+            }
+
             // toggle for replace mode
             ui.checkbox(&mut search_state.is_replace, "Replace");
             if search_state.is_replace {
                 // text prompt for replace string
                 ui.text_edit_singleline(&mut search_state.replace_text);
                 ui.horizontal(|ui| {
-                    if ui.button("Replace all").clicked() {
-                        *replaced = true;
-                        *done = true;
+                    if ui
+                        .add_enabled(
+                            !search_state.search_text.is_empty(),
+                            Button::new("Replace all..."),
+                        )
+                        .clicked()
+                    {
+                        *previewed = true;
                     }
 
                     if ui.button("Done").clicked() {
@@ -960,36 +2887,433 @@ impl App {
 
             ui.separator();
 
+            if search_state.searching {
+                ui.label("Searching...");
+            }
+
             // display search results in rows, which can be clicked to open the file
-            Grid::new("search_results")
-                .striped(true)
-                .num_columns(1)
-                .show(ui, |ui| {
-                    for res in &search_state.search_results {
-                        if ui
-                            .add(Button::new(format!(
-                                "{}:{}:{}",
-                                res.path.to_string_lossy(),
-                                res.line,
-                                res.col
-                            )))
-                            .clicked()
-                        {
-                            *opened = Some(res.clone());
+            ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                Grid::new("search_results")
+                    .striped(true)
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        for res in &search_state.search_results {
+                            if ui
+                                .add(Button::new(format!(
+                                    "{}:{}:{}",
+                                    res.path.to_string_lossy(),
+                                    res.line,
+                                    res.col
+                                )))
+                                .clicked()
+                            {
+                                *opened = Some(res.clone());
+                            }
+                            ui.label(RichText::new(res.preview.trim()).monospace());
+                            ui.end_row();
                         }
-                        ui.end_row();
-                    }
-                });
+                    });
+            });
+        });
+    }
+
+    // show the change to each match that will be replaced, which can be ticked or unticked,
+    // with buttons to confirm or go back
+    fn show_replace_preview(
+        ui: &mut egui::Ui,
+        changes: &mut [(ReplaceChange, bool)],
+        replaced: &mut bool,
+        done: &mut bool,
+        back: &mut bool,
+    ) {
+        let ticked = changes.iter().filter(|(_, ticked)| *ticked).count();
+        ui.label(format!("Replace {ticked} of {} match(es)?", changes.len()));
+
+        ui.horizontal(|ui| {
+            if ui.button("Select all").clicked() {
+                changes.iter_mut().for_each(|(_, ticked)| *ticked = true);
+            }
+            if ui.button("Select none").clicked() {
+                changes.iter_mut().for_each(|(_, ticked)| *ticked = false);
+            }
+        });
+
+        ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+            for (change, ticked) in changes.iter_mut() {
+                ui.checkbox(
+                    ticked,
+                    format!(
+                        "{}:{}:{}",
+                        change.path.to_string_lossy(),
+                        change.line,
+                        change.col
+                    ),
+                );
+                ui.label(
+                    RichText::new(format!("- {}", change.before.trim()))
+                        .monospace()
+                        .color(ui.visuals().error_fg_color),
+                );
+                ui.label(
+                    RichText::new(format!("+ {}", change.after.trim()))
+                        .monospace()
+                        .color(Color32::GREEN),
+                );
+            }
+        });
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(ticked > 0, Button::new("Confirm"))
+                .clicked()
+            {
+                *replaced = true;
+                *done = true;
+            }
+            if ui.button("Back").clicked() {
+                *back = true;
+            }
         });
     }
 
-    fn update_search_results(&mut self, search: &str) {
+    fn update_search_results(&mut self) {
+        if let Some(project) = &self.project
+            && let Some(search_state) = &mut self.search_modal_state
+        {
+            search_state.search_results.clear();
+            search_state.search_error = None;
+            search_state.searching = false;
+            #[cfg(target_arch = "wasm32")]
+            {
+                self.last_search_id = self.last_search_id.wrapping_add(1);
+                search_state.search_id = self.last_search_id;
+            }
+
+            match self.fs.search_project(project, &search_state.query()) {
+                Ok(results) => search_state.search_results = results,
+                // on the web, the results are received from the server later on
+                Err(err) if err.kind() == ErrorKind::WouldBlock => search_state.searching = true,
+                Err(err) => search_state.search_error = Some(err.to_string()),
+            }
+        }
+    }
+
+    // get a preview of the changes that replacing would make, so that the user can confirm them
+    fn preview_replace(&mut self) {
         if let Some(project) = &self.project
             && let Some(search_state) = &mut self.search_modal_state
         {
-            let results = self.fs.search_project(project, search);
-            search_state.search_results = results;
+            match self.fs.preview_replace(
+                project,
+                &search_state.query(),
+                &search_state.replace_text,
+            ) {
+                Ok(changes) => {
+                    search_state.replace_preview =
+                        Some(changes.into_iter().map(|change| (change, true)).collect());
+                }
+                // on the web, the preview is received from the server later on
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+                Err(err) => self.error_message = Some(err.to_string()),
+            }
+        }
+    }
+
+    fn replace(&mut self) {
+        if let Some(project) = &self.project
+            && let Some(search_state) = &self.search_modal_state
+        {
+            let ticked: Vec<_> = search_state
+                .replace_preview
+                .iter()
+                .flatten()
+                .filter(|(_, ticked)| *ticked)
+                .map(|(change, _)| change.clone())
+                .collect();
+
+            match self.fs.replace(
+                project,
+                &search_state.query(),
+                &search_state.replace_text,
+                &ticked,
+            ) {
+                Ok(()) => {}
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+                Err(err) => self.error_message = Some(format!("Failed to replace: {err}")),
+            }
+        }
+    }
+
+    // show modal prompting the user for a line (and optionally a column) to move to in the current buffer
+    fn show_goto_line_modal(&mut self, ctx: &egui::Context) {
+        let mut target = None;
+        let mut cancelled = false;
+
+        let modal = Modal::new(Id::new("goto_line_modal")).show(ctx, |ui| {
+            let Some(buffer) = self.buffers.current_buffer() else {
+                cancelled = true;
+                return;
+            };
+            let (line_count, current) = (buffer.line_count(), buffer.cursor());
+            let input = self.goto_line_modal_state.as_mut().unwrap();
+
+            ui.label(format!(
+                "Go to line (1 - {line_count}), optionally followed by :column\n\
+                Currently at {}:{}",
+                current.line, current.col
+            ));
+            ui.text_edit_singleline(input).request_focus();
+
+            let position = input.parse::<CursorPosition>().ok();
+            if position.is_none() && !input.trim().is_empty() {
+                ui.colored_label(ui.visuals().error_fg_color, "Invalid line number");
+            }
+
+            ui.horizontal(|ui| {
+                let entered = ui.input(|i| i.key_pressed(Key::Enter));
+                if ui.add_enabled(position.is_some(), Button::new("Go")).clicked() || entered {
+                    target = position;
+                }
+                if ui.button("Cancel").clicked() {
+                    cancelled = true;
+                }
+            });
+        });
+
+        if let Some(position) = target
+            && let Some(buffer) = self.buffers.current_buffer_mut()
+        {
+            buffer.set_cursor(position);
+            self.goto_line_modal_state = None;
+        }
+
+        if cancelled || modal.should_close() {
+            self.goto_line_modal_state = None;
+        }
+    }
+
+    // show the file finder modal, after getting the paths of all of the files in the project
+    fn open_file_finder(&mut self) {
+        let Some(project) = &self.project else {
+            return;
+        };
+
+        let files = match self.fs.list_files(project) {
+            Ok(files) => Some(files),
+            // on the web, the files are received from the server later on
+            Err(err) if err.kind() == ErrorKind::WouldBlock => None,
+            Err(err) => {
+                self.error_message = Some(format!("Failed to list project files: {err}"));
+                return;
+            }
+        };
+
+        self.file_finder_state = Some(FileFinderState {
+            files,
+            ..Default::default()
+        });
+    }
+
+    // show modal where the user can type part of a file's path to find and open it
+    fn show_file_finder_modal(&mut self, ctx: &egui::Context) {
+        let mut opened = None;
+        let root = self.explorer.as_ref().map(|e| e.root_path().to_path_buf());
+        let recent_files = &self.recent_files;
+        let Some(state) = &mut self.file_finder_state else {
+            return;
+        };
+
+        let modal = Modal::new(Id::new("file_finder_modal")).show(ctx, |ui| {
+            ui.label("Go to file");
+
+            let Some(files) = &state.files else {
+                ui.label("Loading files...");
+                return;
+            };
+
+            // recently opened files come first, so they are ranked above other files that match equally well
+            let paths: Vec<_> = recent_files
+                .iter()
+                .filter(|path| files.contains(path))
+                .chain(files.iter().filter(|path| !recent_files.contains(path)))
+                .collect();
+            // show paths relative to the project folder
+            let items: Vec<_> = paths
+                .iter()
+                .map(|path| {
+                    let relative = root
+                        .as_deref()
+                        .and_then(|root| path.strip_prefix(root).ok())
+                        .unwrap_or(path);
+                    let hint = if recent_files.contains(path) { "recently opened" } else { "" };
+
+                    (relative.to_string_lossy().into_owned(), hint.to_string())
+                })
+                .collect();
+
+            if let Some(i) =
+                Self::show_fuzzy_picker(ui, &mut state.query, &mut state.selected, &mut state.matches, &items)
+            {
+                opened = Some(paths[i].clone());
+            }
+        });
+
+        if modal.should_close() {
+            self.file_finder_state = None;
+        }
+
+        if let Some(path) = opened {
+            self.file_finder_state = None;
+            self.open_file(path);
+        }
+    }
+
+    // show the command palette, where any enabled command (or a colour scheme) can be searched for and run
+    fn show_command_palette(&mut self, ctx: &egui::Context) {
+        let Some(picking_color_scheme) = self
+            .command_palette_state
+            .as_ref()
+            .map(|state| state.picking_color_scheme)
+        else {
+            return;
+        };
+
+        // the function to run each command (when not picking a colour scheme), in the same order as the items
+        let mut runs = vec![];
+        let items: Vec<_> = if picking_color_scheme {
+            self.available_color_schemes
+                .schemes
+                .iter()
+                .map(|scheme| (scheme.name().to_string(), String::new()))
+                .collect()
+        } else {
+            self.commands
+                .iter()
+                .filter(|command| (command.enabled)(self))
+                .map(|command| {
+                    runs.push(command.run);
+                    let keybinding = self
+                        .commands
+                        .keybinding(command)
+                        .map(|shortcut| ctx.format_shortcut(&shortcut))
+                        .unwrap_or_default();
+
+                    (command.name.to_string(), keybinding)
+                })
+                .collect()
+        };
+
+        let mut chosen = None;
+        let state = self.command_palette_state.as_mut().unwrap();
+        let modal = Modal::new(Id::new("command_palette")).show(ctx, |ui| {
+            ui.label(if picking_color_scheme {
+                "Choose a colour scheme"
+            } else {
+                "Run a command"
+            });
+
+            chosen =
+                Self::show_fuzzy_picker(ui, &mut state.query, &mut state.selected, &mut state.matches, &items);
+        });
+
+        if modal.should_close() {
+            self.command_palette_state = None;
+        }
+
+        let Some(i) = chosen else {
+            return;
+        };
+        self.command_palette_state = None;
+
+        if picking_color_scheme {
+            let mut settings = self.editor_settings.clone();
+            settings.color_scheme = Some(items[i].0.clone());
+            self.settings_modal_state = Some(settings);
+            self.update_settings(ctx);
+        } else {
+            runs[i](self, ctx);
+        }
+    }
+
+    // show a text box, and a list of the items (pairs of labels and hints shown beside them) that fuzzy match it
+    // an item can be chosen by clicking on it, or by moving to it with the arrow keys and pressing Enter
+    //
+    // returns the index of the chosen item
+    fn show_fuzzy_picker(
+        ui: &mut egui::Ui,
+        query: &mut String,
+        selected: &mut usize,
+        matches: &mut Option<Vec<usize>>,
+        items: &[(String, String)],
+    ) -> Option<usize> {
+        let mut chosen = None;
+
+        // move the highlighted item with the arrow keys (before the text box can use them)
+        let mut moved = false;
+        ui.input_mut(|i| {
+            if i.consume_key(Modifiers::NONE, Key::ArrowDown) {
+                *selected += 1;
+                moved = true;
+            }
+            if i.consume_key(Modifiers::NONE, Key::ArrowUp) {
+                *selected = selected.saturating_sub(1);
+                moved = true;
+            }
+        });
+
+        let response = ui.text_edit_singleline(query);
+        response.request_focus();
+        if response.changed() {
+            *selected = 0;
+        }
+
+        // the items are only matched again when the query changes, as matching every file in a big project is slow
+        if response.changed() || matches.is_none() {
+            *matches = Some(
+                fuzzy::filter(query, items.iter().enumerate(), |(_, (label, _))| label)
+                    .into_iter()
+                    .take(Self::MAX_PICKER_RESULTS)
+                    .map(|(i, _)| i)
+                    .collect(),
+            );
+        }
+        let results: Vec<_> = matches
+            .iter()
+            .flatten()
+            .map(|&i| (i, &items[i]))
+            .collect();
+        *selected = (*selected).min(results.len().saturating_sub(1));
+
+        if ui.input(|i| i.key_pressed(Key::Enter))
+            && let Some((i, _)) = results.get(*selected)
+        {
+            chosen = Some(*i);
+        }
+
+        ui.separator();
+
+        if results.is_empty() {
+            ui.label("No matches");
         }
+
+        ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+            for (n, (i, (label, hint))) in results.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    let response = ui.selectable_label(n == *selected, label);
+                    if response.clicked() {
+                        chosen = Some(*i);
+                    }
+                    if moved && n == *selected {
+                        response.scroll_to_me(None);
+                    }
+                    ui.weak(hint);
+                });
+            }
+        });
+
+        chosen
     }
 
     // display an error message to the user
@@ -1016,20 +3340,16 @@ impl App {
             ui.separator();
 
             ui.label(RichText::new("Keyboard shortcuts:").underline());
-            
-            ui.label("Ctrl + S: Save file\n\
-                Ctrl + Shift + S: Save as\n\
-                Ctrl + Alt + S: Save all\n\
-                Ctrl + N: New file\n\
-                Ctrl + O: Open file\n\
-                Ctrl + Shift + O: Open folder\n\
-                Ctrl + F: Search\n\
-                Ctrl + H: Replace\n\
-                Ctrl + ,: Settings\n\
-                Ctrl + `: Toggle terminal\n\
-                Ctrl + Shift + U: Toggle output\n\
-                F5: Run",
-            );
+
+            Grid::new("help_shortcuts").num_columns(2).show(ui, |ui| {
+                for command in self.commands.iter() {
+                    if let Some(shortcut) = self.commands.keybinding(command) {
+                        ui.label(ctx.format_shortcut(&shortcut));
+                        ui.label(command.name);
+                        ui.end_row();
+                    }
+                }
+            });
         });
 
         if modal.should_close() {
@@ -1074,6 +3394,7 @@ impl App {
             // pattern match agaisnt possible sent commands and their received response
             match resp {
                 (OpenProject, Project { contents, settings }) => {
+                    self.commands.apply_keymap(&settings.keymap);
                     self.editor_settings = settings;
 
                     let path = contents.path().clone();
@@ -1081,6 +3402,7 @@ impl App {
                     log::info!("opened project: {}", path.display());
 
                     self.explorer = Some(Explorer::new(path, &self.fs).unwrap());
+                    self.load_workspace();
                 }
                 (ReadSettings { action }, ProjectSettings { contents }) => {
                     if contents.is_empty() {
@@ -1102,19 +3424,117 @@ impl App {
                         schemes: color_schemes,
                     };
                 }
-                (ReadFile { path }, FileContents { contents }) => self.buffers.add(Buffer::new(
-                    contents.clone(),
-                    Some(FileData {
-                        contents,
-                        path: path.clone(),
-                    }),
-                )),
-                (ReadDir { path }, DirContents { contents_paths }) => {}
+                // the file is already open, so it is being reloaded after being changed (e.g. by a formatter)
+                (ReadFile { path }, FileContents { contents })
+                    if self.buffers.get_by_path(&path).is_some() =>
+                {
+                    self.file_changed(&path, contents);
+                }
+                // the buffer was closed before the file's new contents were received
+                (ReadFile { .. }, FileContents { .. }) => {}
+                // the file is already open, but isn't text (e.g. an image), so its buffer is replaced
+                (ReadBytes { path }, FileBytes { contents })
+                    if self.buffers.get_by_path(&path).is_some() =>
+                {
+                    if let Some(buffer) = self.buffers.get_mut_by_path(&path)
+                        && !buffer.kind().is_text()
+                    {
+                        buffer.reload_bytes(contents);
+                    }
+                }
+                (ReadBytes { path }, FileBytes { contents }) => {
+                    let mut buffer = Buffer::from_bytes(path.clone(), contents);
+
+                    // move to the location the file was opened at (e.g. from a search result)
+                    if let Some(position) = self.pending_cursors.remove(&path) {
+                        buffer.set_cursor(position);
+                    }
+
+                    self.buffers.add(buffer);
+                    self.apply_buffer_state(&path);
+                }
+                (LoadWorkspace, Workspace { state }) => {
+                    // a state which can't be read is replaced with a new one
+                    let state = state
+                        .and_then(|state| serde_yaml::from_str(&state).ok())
+                        .unwrap_or_default();
+                    self.restore_workspace(state);
+                }
+                // the directory's contents have changed, so the explorer is rebuilt with them
+                (ReadDir { path }, DirContents { contents_paths }) => {
+                    self.fs.cache_dir(path, contents_paths);
+                    if let Some(explorer) = &mut self.explorer
+                        && let Err(err) = explorer.refresh(&self.fs)
+                    {
+                        self.error_message = Some(err.to_string());
+                    }
+                }
+                (PollChanges, ChangedFiles { paths }) => self.files_changed(&paths),
+                (ListFiles { .. }, FileList { paths }) => {
+                    if let Some(state) = &mut self.file_finder_state {
+                        state.files = Some(paths);
+                        state.matches = None;
+                    }
+                }
                 (Run { .. }, Output { output }) => {
                     self.runner.set_finished();
                     self.output.lock().unwrap().push_str(&output);
                 }
+                (Search { search_id, .. }, SearchResults { matches, finished }) => {
+                    // ignore results from previous searches that are still being received
+                    // (which may have had the same pattern, but different options)
+                    if let Some(search_state) = &mut self.search_modal_state
+                        && search_state.search_id == search_id
+                    {
+                        search_state
+                            .search_results
+                            .extend(matches.into_iter().map(SearchResult::from));
+                        search_state.searching = !finished;
+                    }
+                }
+                (Search { search_id, .. }, Error { msg }) => {
+                    if let Some(search_state) = &mut self.search_modal_state
+                        && search_state.search_id == search_id
+                    {
+                        search_state.search_error = Some(msg);
+                        search_state.searching = false;
+                    }
+                }
+                (Replace { selected: None, .. }, ReplacePreview { changes }) => {
+                    if let Some(search_state) = &mut self.search_modal_state {
+                        search_state.replace_preview =
+                            Some(changes.into_iter().map(|change| (change, true)).collect());
+                    }
+                }
+                (Format { input: Some(input), .. }, Formatted { contents }) => {
+                    // ignore the formatted contents if the buffer has been edited since it was sent to be formatted
+                    if self
+                        .buffers
+                        .get_by_path(&input.path)
+                        .is_some_and(|buffer| buffer.contents() == input.contents)
+                    {
+                        self.apply_formatted(&input.path, contents);
+                    }
+                }
+                (Format { .. }, Success) => self.reload_buffers(),
+                (GitStatus, GitFiles { files }) => {
+                    if let Some(source_control) = &mut self.source_control {
+                        source_control.set_files(files);
+                    }
+                }
+                (GitStatus, Error { msg }) => {
+                    if let Some(source_control) = &mut self.source_control {
+                        source_control.set_error(msg);
+                    }
+                }
+                (GitDiff { path }, FileDiff { diff }) => {
+                    if let Some(source_control) = &mut self.source_control {
+                        source_control.set_diff(path, diff);
+                    }
+                }
+                (GitStage { .. } | GitUnstage { .. }, Success) => self.refresh_git_status(),
                 (_, Success) => {}
+                (_, Error { msg }) => self.error_message = Some(msg),
                 // the server sent an invalid response to the RPC call
                 resp => {
                     eprintln!("received invalid RPC response from server: {resp:#?}");
@@ -1,28 +1,182 @@
 use std::{
+    collections::HashMap,
     fmt::Debug,
     io,
     ops::Deref,
     path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
 };
 
 use crate::{
     app::ModalAction,
-    platform::{FileSystem, FileSystemTrait as _},
+    markdown,
+    platform::{self, FileSystem, FileSystemTrait as _},
 };
 use color_eyre::Section;
-use egui::{Response, RichText, ScrollArea, TextEdit, Ui};
+use egui::{
+    Align, Align2, CursorIcon, FontFamily, FontId, Frame, Id, Image, Key, Label, Modifiers, Rect,
+    Response, RichText, ScrollArea, Sense, Shape, StrokeKind, TextBuffer, TextEdit, TextStyle, Ui,
+    UiBuilder,
+    containers::scroll_area::ScrollAreaOutput,
+    pos2,
+    text::{CCursor, CCursorRange, LayoutJob, LayoutSection, TAB_SIZE},
+    text_edit::TextEditOutput,
+    vec2,
+};
 use egui_extras::syntax_highlighting::{self, CodeTheme};
 use eyre::{Context, eyre};
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use ws_messages::EditorSettings;
 
+// width of the minimap shown to the right of a buffer
+const MINIMAP_WIDTH: f32 = 80.0;
+// height and character width of each line in the minimap (lines are squashed together if the buffer doesn't fit)
+const MINIMAP_LINE_HEIGHT: f32 = 2.0;
+const MINIMAP_CHAR_WIDTH: f32 = 1.0;
+// space between the line numbers and the text
+const GUTTER_MARGIN: f32 = 16.0;
+// width of the handle between two split panes, which is dragged to resize them
+const SPLIT_HANDLE_WIDTH: f32 = 6.0;
+// smallest fraction of a split that either side can be resized to
+const MIN_SPLIT_RATIO: f32 = 0.1;
+// number of bytes shown on each line of the hex viewer
+const HEX_BYTES_PER_ROW: usize = 16;
+// extensions of the files shown as images (the formats enabled for the `image` crate)
+const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "gif", "bmp", "webp"];
+
 #[derive(Debug)]
 pub struct FileData {
     pub path: PathBuf,
     pub contents: String,
 }
 
+/// A file as it was at a commit, shown in a buffer which can't be edited
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Revision {
+    pub path: PathBuf,
+    /// SHA hash of the commit
+    pub sha: String,
+}
+
+impl Revision {
+    /// The start of the commit's hash, which is enough to identify it
+    pub fn short_sha(&self) -> &str {
+        &self.sha[..self.sha.len().min(7)]
+    }
+}
+
+/// How a buffer's file is shown, chosen from its extension and contents when it is opened
+#[derive(Debug)]
+pub enum BufferKind {
+    /// Text, shown in the editor
+    Text,
+    /// Markdown, which can be switched between the editor and a rendered preview
+    Markdown { preview: bool },
+    /// An image, decoded by egui's image loaders.
+    /// `uri` identifies the image in egui's cache, so it changes whenever the image is reloaded
+    Image { uri: String, bytes: Arc<[u8]> },
+    /// A PDF document, which is opened in the system's (or browser's) PDF viewer
+    Pdf {
+        bytes: Vec<u8>,
+        open_error: Option<String>,
+    },
+    /// Any other file which isn't text, shown as a hex dump
+    Binary { bytes: Vec<u8> },
+}
+
+impl BufferKind {
+    // Chooses how to show a file with the given contents, also returning the file's text if it is shown as text
+    fn detect(path: &Path, bytes: Vec<u8>) -> (Self, String) {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+            let uri = format!("bytes://{}/{}", Uuid::new_v4(), path.display());
+            return (
+                Self::Image {
+                    uri,
+                    bytes: bytes.into(),
+                },
+                String::new(),
+            );
+        }
+        if extension == "pdf" {
+            return (
+                Self::Pdf {
+                    bytes,
+                    open_error: None,
+                },
+                String::new(),
+            );
+        }
+
+        match String::from_utf8(bytes) {
+            // valid UTF-8 can still contain null bytes, which text files never do
+            Ok(text) if text.contains('\0') => (
+                Self::Binary {
+                    bytes: text.into_bytes(),
+                },
+                String::new(),
+            ),
+            Ok(text) if matches!(extension.as_str(), "md" | "markdown") => {
+                (Self::Markdown { preview: true }, text)
+            }
+            Ok(text) => (Self::Text, text),
+            Err(err) => (
+                Self::Binary {
+                    bytes: err.into_bytes(),
+                },
+                String::new(),
+            ),
+        }
+    }
+
+    /// Whether the buffer's contents are text, which can be edited and saved
+    pub fn is_text(&self) -> bool {
+        matches!(self, Self::Text | Self::Markdown { .. })
+    }
+}
+
+/// A position in a buffer's text (both the line and column start from 1)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CursorPosition {
+    pub line: usize,
+    pub col: usize,
+}
+
+impl CursorPosition {
+    pub const START: Self = Self { line: 1, col: 1 };
+
+    pub fn new(line: usize, col: usize) -> Self {
+        Self { line, col }
+    }
+}
+
+impl FromStr for CursorPosition {
+    type Err = ();
+
+    // parses a position written as either "line" or "line:col"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_part = |part: &str| part.trim().parse().ok().filter(|&n| n > 0).ok_or(());
+
+        match s.trim().split_once(':') {
+            Some((line, col)) => Ok(Self::new(parse_part(line)?, parse_part(col)?)),
+            None => Ok(Self::new(parse_part(s)?, 1)),
+        }
+    }
+}
+
+impl Default for CursorPosition {
+    fn default() -> Self {
+        Self::START
+    }
+}
+
 pub enum BufferError {
     /// The buffer doesn't have a file associated with it.
     NoAssociatedFile,
@@ -32,6 +186,10 @@ pub enum BufferError {
 pub struct BuffersOutput {
     pub save_modal_action: Option<ModalAction>,
     pub error_message: Option<String>,
+    /// Paths of the files that were auto-saved
+    pub saved: Vec<PathBuf>,
+    /// Buffer showing an old version of a file, which the user chose to restore the file to
+    pub restore_id: Option<Uuid>,
 }
 
 #[derive(Debug)]
@@ -41,13 +199,158 @@ struct Rename {
     just_started: bool,
 }
 
-#[derive(Debug, Default)]
+/// Direction a pane is split in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SplitDirection {
+    /// The two panes are side by side
+    Horizontal,
+    /// The two panes are above each other
+    Vertical,
+}
+
+/// How the editor area is divided into panes
+#[derive(Debug)]
+enum PaneLayout {
+    Pane(Uuid),
+    Split {
+        direction: SplitDirection,
+        /// Fraction of the space taken up by the first side of the split
+        ratio: f32,
+        first: Box<PaneLayout>,
+        second: Box<PaneLayout>,
+    },
+}
+
+impl PaneLayout {
+    // the first pane in the layout, which is used to identify splits
+    fn first_pane(&self) -> Uuid {
+        match self {
+            Self::Pane(id) => *id,
+            Self::Split { first, .. } => first.first_pane(),
+        }
+    }
+
+    // replaces a pane with a split between it and a new pane, returning whether the pane was found
+    fn split(&mut self, pane_id: Uuid, new_pane_id: Uuid, direction: SplitDirection) -> bool {
+        match self {
+            Self::Pane(id) if *id == pane_id => {
+                *self = Self::Split {
+                    direction,
+                    ratio: 0.5,
+                    first: Box::new(Self::Pane(pane_id)),
+                    second: Box::new(Self::Pane(new_pane_id)),
+                };
+                true
+            }
+            Self::Pane(_) => false,
+            Self::Split { first, second, .. } => {
+                first.split(pane_id, new_pane_id, direction)
+                    || second.split(pane_id, new_pane_id, direction)
+            }
+        }
+    }
+
+    // Removes a pane, so that the other side of its split takes up the space.
+    // Returns the pane which took its place (`None` if it is the only pane, which can't be removed)
+    fn remove(&mut self, pane_id: Uuid) -> Option<Uuid> {
+        let Self::Split { first, second, .. } = self else {
+            return None;
+        };
+
+        let remaining = if matches!(**first, Self::Pane(id) if id == pane_id) {
+            second
+        } else if matches!(**second, Self::Pane(id) if id == pane_id) {
+            first
+        } else {
+            return first.remove(pane_id).or_else(|| second.remove(pane_id));
+        };
+
+        *self = std::mem::replace(&mut **remaining, Self::Pane(pane_id));
+        Some(self.first_pane())
+    }
+}
+
+/// Layout of the panes and the files open in each of them, which is saved with the project's workspace state
+/// so that it can be restored the next time the project is opened
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum SavedLayout {
+    /// The paths of the files open in the pane (relative to the project), in the order of their tabs
+    Pane(Vec<PathBuf>),
+    Split {
+        direction: SplitDirection,
+        ratio: f32,
+        first: Box<SavedLayout>,
+        second: Box<SavedLayout>,
+    },
+}
+
+/// Part of the editor area with its own tabs, showing the selected one of its buffers
+#[derive(Debug)]
+struct Pane {
+    id: Uuid,
+    /// The buffers with tabs in the pane, in the order of the tabs
+    buffer_ids: Vec<Uuid>,
+    selected_id: Option<Uuid>,
+}
+
+impl Pane {
+    fn new() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            buffer_ids: vec![],
+            selected_id: None,
+        }
+    }
+}
+
+/// Payload of a tab being dragged to another pane
+struct TabDrag {
+    buffer_id: Uuid,
+}
+
+/// What happened while the panes were being shown, which is handled once they have all been shown
+#[derive(Default)]
+struct PanesOutput {
+    /// Buffer whose tab was closed
+    delete_id: Option<Uuid>,
+    /// Whether the tab being renamed has lost focus
+    renamed: bool,
+    /// Response of the selected buffer in the active pane
+    active_view: Option<Response>,
+    /// Buffer whose tab was dropped onto a pane, and the pane it was dropped onto
+    moved: Option<(Uuid, Uuid)>,
+    split: Option<(Uuid, SplitDirection)>,
+    closed_pane: Option<Uuid>,
+    restore_id: Option<Uuid>,
+}
+
+#[derive(Debug)]
 pub struct Buffers {
     buffers: Vec<Buffer>,
-    selected_id: Option<Uuid>,
+    panes: Vec<Pane>,
+    layout: PaneLayout,
+    /// The pane that new files are opened in (the one which was last clicked on)
+    active_pane: Uuid,
+    /// Panes to add files to once they have been read, when a saved layout is being restored
+    restoring: HashMap<PathBuf, Uuid>,
     rename: Option<Rename>,
 }
 
+impl Default for Buffers {
+    fn default() -> Self {
+        let pane = Pane::new();
+
+        Self {
+            buffers: vec![],
+            layout: PaneLayout::Pane(pane.id),
+            active_pane: pane.id,
+            panes: vec![pane],
+            restoring: HashMap::new(),
+            rename: None,
+        }
+    }
+}
+
 impl Buffers {
     pub fn show(
         &mut self,
@@ -56,41 +359,57 @@ impl Buffers {
         code_theme: &CodeTheme,
         fs: &FileSystem,
     ) -> BuffersOutput {
-        let (delete_id, renamed) = self.show_tabs(ui);
+        let mut output = PanesOutput::default();
+        // the layout is taken out while it is shown, so that the panes can be changed at the same time
+        let mut layout = std::mem::replace(&mut self.layout, PaneLayout::Pane(Uuid::nil()));
+        self.show_layout(ui, &mut layout, code_theme, settings, &mut output);
+        self.layout = layout;
 
-        if renamed {
+        if output.renamed {
             // can unwrap as `renamed` is only set to true if `rename` is Some
             let rename = self.rename.take().unwrap();
             self.get_mut_by_id(rename.buffer_id)
                 .and_then(|b| b.rename(&rename.name, fs).ok())
                 .expect("failed to rename buffer");
         }
+        if let Some((buffer_id, pane_id)) = output.moved {
+            self.move_to_pane(buffer_id, pane_id);
+        }
+        if let Some((pane_id, direction)) = output.split {
+            self.split_pane(pane_id, direction);
+        }
+        if let Some(pane_id) = output.closed_pane {
+            self.close_pane(pane_id);
+        }
 
-        // show text edit for current buffer
+        // auto save all buffers when the user clicks away from the buffer they are editing
         let mut error_message = None;
-        if let Some(buffer) = self.current_buffer_mut() {
-            let buffer_view = buffer.show(ui, code_theme);
-
-            if buffer_view.clicked_elsewhere() && settings.auto_save && self.is_dirty() {
-                let mut failed_to_save = vec![];
-                for buf in self.buffers.iter_mut() {
+        let mut saved = vec![];
+        if let Some(buffer_view) = output.active_view
+            && buffer_view.clicked_elsewhere()
+            && settings.auto_save
+            && self.is_dirty()
+        {
+            let mut failed_to_save = vec![];
+            for buf in self.buffers.iter_mut() {
+                let was_dirty = buf.is_dirty();
+                match buf.save(fs) {
+                    // can unwrap as the buffer has been saved to its file
+                    Ok(()) if was_dirty => saved.push(buf.file_data().unwrap().path.clone()),
                     // Ignore `BufferError::NoAssociatedFile` as we ignore buffers that don't have files in auto save
-                    if let Err(BufferError::IoError(err)) = buf.save(fs) {
-                        failed_to_save.push((err, &*buf));
-                    }
-                }
-                if !failed_to_save.is_empty() {
-                    error_message = Some(Self::join_save_errors(failed_to_save));
+                    Err(BufferError::IoError(err)) => failed_to_save.push((err, &*buf)),
+                    _ => {}
                 }
             }
-        } else {
-            ui.label("No file open...");
+            if !failed_to_save.is_empty() {
+                error_message = Some(Self::join_save_errors(failed_to_save));
+            }
         }
 
         let mut save_modal_action = None;
 
         // If there is a buffer to delete
-        if let Some(id) = delete_id
+        if let Some(id) = output.delete_id
             && let Some(buffer) = self.get_by_id(id)
         {
             // If buffer is dirty, then firstly show an "unsaved changes" modal, and then continue with deletion
@@ -105,16 +424,176 @@ impl Buffers {
         BuffersOutput {
             save_modal_action,
             error_message,
+            saved,
+            restore_id: output.restore_id,
+        }
+    }
+
+    // shows the panes in the layout, dividing the space between the two sides of each split
+    fn show_layout(
+        &mut self,
+        ui: &mut Ui,
+        layout: &mut PaneLayout,
+        code_theme: &CodeTheme,
+        settings: &EditorSettings,
+        output: &mut PanesOutput,
+    ) {
+        let PaneLayout::Split {
+            direction,
+            ratio,
+            first,
+            second,
+        } = layout
+        else {
+            if let PaneLayout::Pane(pane_id) = layout {
+                self.show_pane(ui, *pane_id, code_theme, settings, output);
+            }
+            return;
+        };
+
+        let rect = ui.available_rect_before_wrap();
+        let (first_rect, handle_rect, second_rect) = match direction {
+            SplitDirection::Horizontal => {
+                let x = rect.left() + (rect.width() - SPLIT_HANDLE_WIDTH) * *ratio;
+                (
+                    Rect::from_min_max(rect.min, pos2(x, rect.bottom())),
+                    Rect::from_min_max(
+                        pos2(x, rect.top()),
+                        pos2(x + SPLIT_HANDLE_WIDTH, rect.bottom()),
+                    ),
+                    Rect::from_min_max(pos2(x + SPLIT_HANDLE_WIDTH, rect.top()), rect.max),
+                )
+            }
+            SplitDirection::Vertical => {
+                let y = rect.top() + (rect.height() - SPLIT_HANDLE_WIDTH) * *ratio;
+                (
+                    Rect::from_min_max(rect.min, pos2(rect.right(), y)),
+                    Rect::from_min_max(
+                        pos2(rect.left(), y),
+                        pos2(rect.right(), y + SPLIT_HANDLE_WIDTH),
+                    ),
+                    Rect::from_min_max(pos2(rect.left(), y + SPLIT_HANDLE_WIDTH), rect.max),
+                )
+            }
+        };
+
+        // the handle between the panes can be dragged to resize them
+        let handle = ui.interact(
+            handle_rect,
+            Id::new("pane_split").with(first.first_pane()),
+            Sense::drag(),
+        );
+        let (delta, size, cursor) = match direction {
+            SplitDirection::Horizontal => (
+                handle.drag_delta().x,
+                rect.width(),
+                CursorIcon::ResizeHorizontal,
+            ),
+            SplitDirection::Vertical => (
+                handle.drag_delta().y,
+                rect.height(),
+                CursorIcon::ResizeVertical,
+            ),
+        };
+        if handle.dragged() {
+            *ratio = (*ratio + delta / (size - SPLIT_HANDLE_WIDTH).max(1.0))
+                .clamp(MIN_SPLIT_RATIO, 1.0 - MIN_SPLIT_RATIO);
         }
+        let stroke = if handle.hovered() || handle.dragged() {
+            ui.ctx().set_cursor_icon(cursor);
+            ui.visuals().widgets.hovered.fg_stroke
+        } else {
+            ui.visuals().widgets.noninteractive.bg_stroke
+        };
+        let center = handle_rect.center();
+        match direction {
+            SplitDirection::Horizontal => {
+                ui.painter().vline(center.x, handle_rect.y_range(), stroke)
+            }
+            SplitDirection::Vertical => ui.painter().hline(handle_rect.x_range(), center.y, stroke),
+        };
+
+        for (rect, child) in [(first_rect, first), (second_rect, second)] {
+            let mut child_ui = ui.new_child(UiBuilder::new().max_rect(rect));
+            // stop long lines in one pane from being drawn over the other pane
+            child_ui.set_clip_rect(rect.intersect(ui.clip_rect()));
+            self.show_layout(&mut child_ui, child, code_theme, settings, output);
+        }
+        ui.allocate_rect(rect, Sense::hover());
     }
 
-    fn show_tabs(&mut self, ui: &mut Ui) -> (Option<Uuid>, bool) {
-        let mut to_delete = None;
-        let mut renamed = false;
+    fn show_pane(
+        &mut self,
+        ui: &mut Ui,
+        pane_id: Uuid,
+        code_theme: &CodeTheme,
+        settings: &EditorSettings,
+        output: &mut PanesOutput,
+    ) {
+        let rect = ui.available_rect_before_wrap();
+        let is_active = pane_id == self.active_pane;
+
+        // clicking anywhere in a pane makes it the active pane
+        if ui.rect_contains_pointer(rect) && ui.input(|i| i.pointer.any_pressed()) {
+            self.active_pane = pane_id;
+        }
+
+        // the whole pane is a drop zone for tabs dragged from other panes
+        let (_, dropped) = ui.dnd_drop_zone::<TabDrag, _>(Frame::new(), |ui| {
+            ui.set_min_size(rect.size());
+            self.show_tabs(ui, pane_id, output);
+
+            let selected_id = self.pane(pane_id).and_then(|pane| pane.selected_id);
+            match selected_id.and_then(|id| self.get_mut_by_id(id)) {
+                Some(buffer) => {
+                    if let Some(revision) = &buffer.revision {
+                        ui.horizontal(|ui| {
+                            ui.label(format!(
+                                "{} at commit {} (read only)",
+                                revision.path.display(),
+                                revision.short_sha()
+                            ));
+                            if ui.button("Restore this version").clicked() {
+                                output.restore_id = Some(buffer.id);
+                            }
+                        });
+                    }
+                    let buffer_view = buffer.show(ui, code_theme, settings);
+                    if is_active {
+                        output.active_view = Some(buffer_view);
+                    }
+                }
+                None => {
+                    ui.label("No file open...");
+                }
+            }
+        });
+        if let Some(tab) = dropped {
+            output.moved = Some((tab.buffer_id, pane_id));
+        }
+
+        // outline the active pane so that it's clear where files will be opened
+        if is_active && self.panes.len() > 1 {
+            ui.painter()
+                .rect_stroke(rect, 0.0, ui.visuals().selection.stroke, StrokeKind::Inside);
+        }
+    }
+
+    fn show_tabs(&mut self, ui: &mut Ui, pane_id: Uuid, output: &mut PanesOutput) {
+        let Some(pane) = self.pane(pane_id) else {
+            return;
+        };
+        let (buffer_ids, selected_id) = (pane.buffer_ids.clone(), pane.selected_id);
+        let can_close = self.panes.len() > 1;
+        let mut clicked = None;
 
         ui.horizontal(|ui| {
             ui.visuals_mut().button_frame = false;
-            for buffer in self.buffers.iter() {
+            for id in buffer_ids {
+                let Some(buffer) = self.buffers.iter().find(|buffer| buffer.id == id) else {
+                    continue;
+                };
+
                 if let Some(rename) = self
                     .rename
                     .as_mut()
@@ -131,20 +610,27 @@ impl Buffers {
                     }
 
                     if text_edit.lost_focus() {
-                        renamed = true;
+                        output.renamed = true;
                     }
                 } else {
                     ui.scope(|ui| {
-                        if let Some(selected_id) = self.selected_id {
-                            if buffer.id == selected_id {
-                                ui.visuals_mut().button_frame = true;
-                            }
+                        if selected_id == Some(buffer.id) {
+                            ui.visuals_mut().button_frame = true;
                         }
 
-                        let tab = ui.button(buffer.file_display_name());
+                        // tabs can be dragged onto another pane to move their buffer there
+                        let tab = ui
+                            .dnd_drag_source(
+                                Id::new("buffer_tab").with(buffer.id),
+                                TabDrag {
+                                    buffer_id: buffer.id,
+                                },
+                                |ui| ui.button(buffer.file_display_name()),
+                            )
+                            .inner;
 
                         if tab.clicked() {
-                            self.selected_id = Some(buffer.id);
+                            clicked = Some(buffer.id);
                         }
 
                         if tab.double_clicked() && buffer.file_data.is_some() {
@@ -162,14 +648,28 @@ impl Buffers {
                     });
 
                     if ui.button("x").clicked() {
-                        to_delete = Some(buffer.id);
+                        output.delete_id = Some(buffer.id);
                     }
                 }
                 ui.separator();
             }
+
+            ui.with_layout(egui::Layout::right_to_left(Align::Center), |ui| {
+                if can_close && ui.button("✖").on_hover_text("Close pane").clicked() {
+                    output.closed_pane = Some(pane_id);
+                }
+                if ui.button("⬍").on_hover_text("Split down").clicked() {
+                    output.split = Some((pane_id, SplitDirection::Vertical));
+                }
+                if ui.button("⬌").on_hover_text("Split right").clicked() {
+                    output.split = Some((pane_id, SplitDirection::Horizontal));
+                }
+            });
         });
 
-        (to_delete, renamed)
+        if let Some(id) = clicked {
+            self.select(id);
+        }
     }
 
     fn join_save_errors(errors: Vec<(io::Error, &Buffer)>) -> String {
@@ -190,20 +690,264 @@ impl Buffers {
             .to_string()
     }
 
+    fn pane(&self, id: Uuid) -> Option<&Pane> {
+        self.panes.iter().find(|pane| pane.id == id)
+    }
+
+    fn pane_mut(&mut self, id: Uuid) -> Option<&mut Pane> {
+        self.panes.iter_mut().find(|pane| pane.id == id)
+    }
+
+    // the pane with the buffer's tab in it
+    fn pane_of_mut(&mut self, buffer_id: Uuid) -> Option<&mut Pane> {
+        self.panes
+            .iter_mut()
+            .find(|pane| pane.buffer_ids.contains(&buffer_id))
+    }
+
+    /// Adds a buffer to the active pane and selects it
+    /// (or to the pane it was in before, if the layout is being restored)
     pub fn add(&mut self, buffer: Buffer) {
-        self.select(buffer.id);
+        let restored_pane = buffer
+            .file_data
+            .as_ref()
+            .and_then(|file| self.restoring.remove(&file.path))
+            .filter(|&id| self.pane(id).is_some());
+
+        match restored_pane {
+            Some(pane_id) => self.add_to_pane(buffer, pane_id),
+            None => {
+                let id = buffer.id;
+                self.add_to_pane(buffer, self.active_pane);
+                self.select(id);
+            }
+        }
+    }
+
+    /// Adds a buffer restored from a previous session to the active pane.
+    /// It is only selected if nothing else in the pane is, so that the tab which was selected before stays selected
+    pub fn add_restored(&mut self, buffer: Buffer) {
+        self.add_to_pane(buffer, self.active_pane);
+    }
+
+    /// Marks a file as being restored from a previous session, so that once it has been read
+    /// it is added to the active pane without taking the selection from the tab which was selected before
+    pub fn restore_file(&mut self, path: PathBuf) {
+        self.restoring.entry(path).or_insert(self.active_pane);
+    }
+
+    fn add_to_pane(&mut self, buffer: Buffer, pane_id: Uuid) {
+        if let Some(pane) = self.pane_mut(pane_id) {
+            pane.buffer_ids.push(buffer.id);
+            pane.selected_id.get_or_insert(buffer.id);
+        }
         self.buffers.push(buffer);
     }
 
+    /// Selects the buffer in its pane, and makes that pane the active pane
     pub fn select(&mut self, id: Uuid) {
-        self.selected_id = Some(id);
+        if let Some(pane) = self.pane_of_mut(id) {
+            pane.selected_id = Some(id);
+            self.active_pane = pane.id;
+        }
+    }
+
+    /// Selects the buffer's tab in its pane, without changing the active pane
+    pub fn select_tab(&mut self, id: Uuid) {
+        if let Some(pane) = self.pane_of_mut(id) {
+            pane.selected_id = Some(id);
+        }
+    }
+
+    /// Whether the buffer's tab is the selected tab in its pane
+    pub fn is_selected(&self, id: Uuid) -> bool {
+        self.panes.iter().any(|pane| pane.selected_id == Some(id))
     }
 
     pub fn delete_buffer(&mut self, id: Uuid) {
         self.buffers.retain(|buffer| id != buffer.id);
 
-        if self.selected_id.is_some_and(|selected| selected == id) {
-            self.selected_id = self.buffers.last().map(|buf| buf.id);
+        let Some(pane) = self.pane_of_mut(id) else {
+            return;
+        };
+        pane.buffer_ids.retain(|&buffer_id| buffer_id != id);
+        if pane.selected_id.is_some_and(|selected| selected == id) {
+            pane.selected_id = pane.buffer_ids.last().copied();
+        }
+
+        // a split pane is closed along with its last tab
+        if pane.buffer_ids.is_empty() {
+            let pane_id = pane.id;
+            self.close_pane(pane_id);
+        }
+    }
+
+    pub fn pane_count(&self) -> usize {
+        self.panes.len()
+    }
+
+    /// Splits the active pane in two, moving its selected buffer into the new pane
+    /// (unless it is the only buffer in the pane)
+    pub fn split_active_pane(&mut self, direction: SplitDirection) {
+        self.split_pane(self.active_pane, direction);
+    }
+
+    fn split_pane(&mut self, pane_id: Uuid, direction: SplitDirection) {
+        let mut new_pane = Pane::new();
+        if !self.layout.split(pane_id, new_pane.id, direction) {
+            return;
+        }
+
+        if let Some(pane) = self.pane_mut(pane_id)
+            && pane.buffer_ids.len() > 1
+            && let Some(selected) = pane.selected_id
+        {
+            pane.buffer_ids.retain(|&id| id != selected);
+            pane.selected_id = pane.buffer_ids.last().copied();
+            new_pane.buffer_ids.push(selected);
+            new_pane.selected_id = Some(selected);
+        }
+
+        self.active_pane = new_pane.id;
+        self.panes.push(new_pane);
+    }
+
+    pub fn close_active_pane(&mut self) {
+        self.close_pane(self.active_pane);
+    }
+
+    // closes a pane (unless it's the only one), moving its buffers into the pane which takes its place
+    fn close_pane(&mut self, pane_id: Uuid) {
+        let Some(neighbour_id) = self.layout.remove(pane_id) else {
+            return;
+        };
+        let Some(index) = self.panes.iter().position(|pane| pane.id == pane_id) else {
+            return;
+        };
+
+        let closed = self.panes.remove(index);
+        if let Some(neighbour) = self.pane_mut(neighbour_id) {
+            neighbour.buffer_ids.extend(closed.buffer_ids);
+            neighbour.selected_id = neighbour.selected_id.or(closed.selected_id);
+        }
+        if self.active_pane == pane_id {
+            self.active_pane = neighbour_id;
+        }
+    }
+
+    // moves a buffer's tab into a different pane, and selects it there
+    fn move_to_pane(&mut self, buffer_id: Uuid, pane_id: Uuid) {
+        let Some(from) = self.pane_of_mut(buffer_id) else {
+            return;
+        };
+        if from.id == pane_id {
+            return;
+        }
+
+        from.buffer_ids.retain(|&id| id != buffer_id);
+        if from.selected_id == Some(buffer_id) {
+            from.selected_id = from.buffer_ids.last().copied();
+        }
+        let (from_id, emptied) = (from.id, from.buffer_ids.is_empty());
+
+        if let Some(to) = self.pane_mut(pane_id) {
+            to.buffer_ids.push(buffer_id);
+        }
+        self.select(buffer_id);
+
+        if emptied {
+            self.close_pane(from_id);
+        }
+    }
+
+    /// Gets the layout of the panes to save, with the paths of their files relative to the project's root
+    pub fn saved_layout(&self, root: &Path) -> SavedLayout {
+        self.saved_layout_of(&self.layout, root)
+    }
+
+    fn saved_layout_of(&self, layout: &PaneLayout, root: &Path) -> SavedLayout {
+        match layout {
+            PaneLayout::Pane(id) => SavedLayout::Pane(
+                self.pane(*id)
+                    .into_iter()
+                    .flat_map(|pane| &pane.buffer_ids)
+                    .filter_map(|&id| self.get_by_id(id)?.file_data.as_ref())
+                    .filter_map(|file| file.path.strip_prefix(root).ok())
+                    .map(Path::to_path_buf)
+                    .collect(),
+            ),
+            PaneLayout::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => SavedLayout::Split {
+                direction: *direction,
+                // rounded so that tiny changes to the size of the panes don't need saving
+                ratio: (ratio * 100.0).round() / 100.0,
+                first: Box::new(self.saved_layout_of(first, root)),
+                second: Box::new(self.saved_layout_of(second, root)),
+            },
+        }
+    }
+
+    /// Replaces the panes with a saved layout, returning the files to open to fill the panes again
+    /// (which are added to their panes as they are opened). Any buffers already open are put in the first pane
+    pub fn restore_layout(&mut self, saved: SavedLayout, root: &Path) -> Vec<PathBuf> {
+        let mut panes = vec![];
+        let mut files = vec![];
+        self.restoring.clear();
+        let layout =
+            Self::restore_layout_of(saved, root, &mut panes, &mut self.restoring, &mut files);
+
+        let first_pane_id = layout.first_pane();
+        if let Some(first_pane) = panes.iter_mut().find(|pane| pane.id == first_pane_id) {
+            first_pane.buffer_ids = self.buffers.iter().map(|buffer| buffer.id).collect();
+            first_pane.selected_id = self.current_buffer().map(|buffer| buffer.id);
+        }
+
+        self.layout = layout;
+        self.panes = panes;
+        self.active_pane = first_pane_id;
+
+        files
+    }
+
+    fn restore_layout_of(
+        saved: SavedLayout,
+        root: &Path,
+        panes: &mut Vec<Pane>,
+        restoring: &mut HashMap<PathBuf, Uuid>,
+        files: &mut Vec<PathBuf>,
+    ) -> PaneLayout {
+        match saved {
+            SavedLayout::Pane(paths) => {
+                let pane = Pane::new();
+                for path in paths {
+                    let path = root.join(path);
+                    restoring.insert(path.clone(), pane.id);
+                    files.push(path);
+                }
+
+                let id = pane.id;
+                panes.push(pane);
+                PaneLayout::Pane(id)
+            }
+            SavedLayout::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => PaneLayout::Split {
+                direction,
+                ratio: ratio.clamp(MIN_SPLIT_RATIO, 1.0 - MIN_SPLIT_RATIO),
+                first: Box::new(Self::restore_layout_of(
+                    *first, root, panes, restoring, files,
+                )),
+                second: Box::new(Self::restore_layout_of(
+                    *second, root, panes, restoring, files,
+                )),
+            },
         }
     }
 
@@ -211,6 +955,10 @@ impl Buffers {
         self.buffers.iter()
     }
 
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Buffer> {
+        self.buffers.iter_mut()
+    }
+
     pub fn get_by_id(&self, id: Uuid) -> Option<&Buffer> {
         self.buffers.iter().find(|buf| buf.id == id)
     }
@@ -219,12 +967,17 @@ impl Buffers {
         self.buffers.iter_mut().find(|buf| buf.id == id)
     }
 
+    // the selected buffer in the active pane
     pub fn current_buffer(&self) -> Option<&Buffer> {
-        self.selected_id.and_then(|id| self.get_by_id(id))
+        self.pane(self.active_pane)
+            .and_then(|pane| pane.selected_id)
+            .and_then(|id| self.get_by_id(id))
     }
 
     pub fn current_buffer_mut(&mut self) -> Option<&mut Buffer> {
-        self.selected_id.and_then(|id| self.get_mut_by_id(id))
+        self.pane(self.active_pane)
+            .and_then(|pane| pane.selected_id)
+            .and_then(|id| self.get_mut_by_id(id))
     }
 
     pub fn get_by_path(&self, path: &Path) -> Option<&Buffer> {
@@ -233,6 +986,12 @@ impl Buffers {
             .find(|buf| buf.file_data.as_ref().map(|f| &*f.path.deref()) == Some(path))
     }
 
+    pub fn get_mut_by_path(&mut self, path: &Path) -> Option<&mut Buffer> {
+        self.buffers
+            .iter_mut()
+            .find(|buf| buf.file_data.as_ref().map(|f| &*f.path.deref()) == Some(path))
+    }
+
     pub fn is_dirty(&self) -> bool {
         self.buffers.iter().any(|buf| buf.is_dirty())
     }
@@ -247,6 +1006,19 @@ pub struct Buffer {
     contents: String,
     /// Optional file data (`None` if the buffer is newly created, and not yet saved to a file)
     file_data: Option<FileData>,
+    /// Current position of the text cursor (updated each time the buffer is shown)
+    cursor: CursorPosition,
+    /// Position to move the cursor to (and scroll to) the next time the buffer is shown
+    pending_cursor: Option<CursorPosition>,
+    /// Vertical scroll offset to move to the next time the buffer is shown (set by clicking on the minimap)
+    pending_scroll: Option<f32>,
+    /// Vertical scroll offset of the text when the buffer was last shown
+    scroll_offset: f32,
+    /// How the buffer is shown (buffers which aren't text have empty `contents`)
+    kind: BufferKind,
+    /// The version of a file the buffer shows, if it shows a file as it was at a commit rather than the file itself.
+    /// These buffers can't be edited or saved
+    revision: Option<Revision>,
 }
 
 impl Buffer {
@@ -256,6 +1028,12 @@ impl Buffer {
             id: Uuid::new_v4(),
             contents,
             file_data,
+            cursor: CursorPosition::START,
+            pending_cursor: None,
+            pending_scroll: None,
+            scroll_offset: 0.0,
+            kind: BufferKind::Text,
+            revision: None,
         }
     }
 
@@ -264,18 +1042,37 @@ impl Buffer {
     /// Constructs a new buffer by reading from the file at the given path.
     /// A reference to the filesystem system is needed to read the file contents
     pub fn from_path(path: PathBuf, fs: &FileSystem) -> eyre::Result<Self> {
-        let contents = fs.read_file(&path).wrap_err("Failed to read file")?;
+        let bytes = fs.read_bytes(&path).wrap_err("Failed to read file")?;
 
-        Ok(Self::new(
-            contents.clone(),
-            Some(FileData { contents, path }),
-        ))
+        Ok(Self::from_bytes(path, bytes))
+    }
+
+    /// Constructs a new buffer for a file with the given contents,
+    /// choosing how to show it (e.g. as text or an image) from its extension and contents
+    pub fn from_bytes(path: PathBuf, bytes: Vec<u8>) -> Self {
+        let (kind, contents) = BufferKind::detect(&path, bytes);
+
+        Self {
+            kind,
+            ..Self::new(contents.clone(), Some(FileData { contents, path }))
+        }
     }
 
     pub fn empty() -> Self {
         Self::new(String::new(), None)
     }
 
+    /// Constructs a read-only buffer showing a file as it was at a commit
+    pub fn from_revision(revision: Revision, bytes: Vec<u8>) -> Self {
+        let (kind, contents) = BufferKind::detect(&revision.path, bytes);
+
+        Self {
+            kind,
+            revision: Some(revision),
+            ..Self::new(contents, None)
+        }
+    }
+
     // Public getters/setters
     
     pub fn id(&self) -> Uuid {
@@ -286,6 +1083,10 @@ impl Buffer {
         &self.contents
     }
 
+    pub fn set_contents(&mut self, contents: String) {
+        self.contents = contents;
+    }
+
     pub fn file_data(&self) -> Option<&FileData> {
         self.file_data.as_ref()
     }
@@ -294,8 +1095,77 @@ impl Buffer {
         self.file_data = Some(file_data);
     }
 
+    pub fn kind(&self) -> &BufferKind {
+        &self.kind
+    }
+
+    pub fn revision(&self) -> Option<&Revision> {
+        self.revision.as_ref()
+    }
+
+    pub fn cursor(&self) -> CursorPosition {
+        self.cursor
+    }
+
+    /// Moves the cursor to the given position, and scrolls the buffer to show it.
+    /// This takes effect the next time the buffer is shown.
+    pub fn set_cursor(&mut self, position: CursorPosition) {
+        self.pending_cursor = Some(position);
+    }
+
+    pub fn scroll_offset(&self) -> f32 {
+        self.scroll_offset
+    }
+
+    /// Moves the cursor and scrolls the buffer back to where they were in a previous session.
+    /// Unlike [`Buffer::set_cursor`], the view isn't centred on the cursor and the buffer isn't focused
+    pub fn restore_view(&mut self, cursor: CursorPosition, scroll_offset: f32) {
+        self.pending_cursor = Some(cursor);
+        self.pending_scroll = Some(scroll_offset);
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.contents.split('\n').count()
+    }
+
+    /// Converts a line and column into a character index into the buffer contents,
+    /// clamping it to the end of the line (or the end of the buffer)
+    fn char_index(&self, position: CursorPosition) -> usize {
+        let mut index = 0;
+        for (i, line) in self.contents.split('\n').enumerate() {
+            let line_len = line.chars().count();
+            if i + 1 == position.line.max(1) {
+                return index + (position.col.max(1) - 1).min(line_len);
+            }
+            // add 1 for the newline character
+            index += line_len + 1;
+        }
+
+        // the line is past the end of the buffer, so go to the end
+        self.contents.chars().count()
+    }
+
+    /// Converts a character index into the buffer contents into a line and column
+    fn position_of(&self, char_index: usize) -> CursorPosition {
+        let mut position = CursorPosition::START;
+        for c in self.contents.chars().take(char_index) {
+            if c == '\n' {
+                position.line += 1;
+                position.col = 1;
+            } else {
+                position.col += 1;
+            }
+        }
+        position
+    }
+
     /// Gets the text to display for the buffer on the tabs at the top of the screen
     fn file_display_name(&self) -> RichText {
+        if let Some(revision) = &self.revision {
+            let name = revision.path.file_name().unwrap_or_default().to_string_lossy();
+            return RichText::new(format!("{name} @ {}", revision.short_sha())).italics();
+        }
+
         self.file_data
             .as_ref()
             .and_then(|f| {
@@ -318,6 +1188,11 @@ impl Buffer {
 
     /// Checks whether the current buffer is dirty (i.e. has unsaved changes)
     pub fn is_dirty(&self) -> bool {
+        // old versions of files can't be changed
+        if self.revision.is_some() {
+            return false;
+        }
+
         match &self.file_data {
             // If buffer is backed by file, return true if current contents don't match file contents
             Some(f) => self.contents != f.contents,
@@ -334,6 +1209,11 @@ impl Buffer {
             // returns an error if the buffer has no associated file
             .ok_or(BufferError::NoAssociatedFile)?;
 
+        // buffers which aren't text can't be edited, and writing their (empty) contents would wipe the file
+        if !self.kind.is_text() {
+            return Ok(());
+        }
+
         let result = fs.write(&file.path, &self.contents);
 
         #[cfg(not(target_arch = "wasm32"))]
@@ -344,6 +1224,30 @@ impl Buffer {
         Ok(())
     }
 
+    /// Replaces the contents of the buffer with the new contents of its file (e.g. after it has been formatted).
+    /// Buffers with unsaved changes are left as they are, so that the changes aren't lost
+    pub fn reload(&mut self, contents: String) {
+        if self.is_dirty() {
+            return;
+        }
+
+        if let Some(file) = &mut self.file_data {
+            file.contents = contents.clone();
+            self.contents = contents;
+        }
+    }
+
+    /// Replaces a buffer which isn't text (e.g. an image) with the new contents of its file
+    pub fn reload_bytes(&mut self, bytes: Vec<u8>) {
+        let Some(file) = &self.file_data else {
+            return;
+        };
+
+        let (kind, contents) = BufferKind::detect(&file.path, bytes);
+        self.kind = kind;
+        self.reload(contents);
+    }
+
     fn rename(&mut self, new_name: &str, fs: &FileSystem) -> Result<(), BufferError> {
         let file = self
             .file_data
@@ -361,37 +1265,564 @@ impl Buffer {
         Ok(())
     }
 
-    fn show(&mut self, ui: &mut Ui, theme: &CodeTheme) -> Response {
-        ScrollArea::vertical()
-            .show(ui, |ui| {
-                let size = ui.available_size();
-
-                let lang = self
-                    .file_data
-                    .as_ref()
-                    .and_then(|f| f.path.extension())
-                    .unwrap_or_default();
-
-                ui.add_sized(
-                    size,
-                    egui::TextEdit::multiline(&mut self.contents)
-                        .code_editor()
-                        .desired_width(f32::INFINITY)
-                        .layouter(&mut |ui: &Ui, contents, wrap_width| {
-                            let mut layout_job = syntax_highlighting::highlight(
-                                ui.ctx(),
-                                ui.style(),
-                                theme,
-                                contents.as_str(),
-                                &lang.to_string_lossy(),
+    fn show(&mut self, ui: &mut Ui, theme: &CodeTheme, settings: &EditorSettings) -> Response {
+        // the path is only used by the PDF viewer, and buffers which aren't text always have files
+        let path = self.file_data.as_ref().map(|file| file.path.as_path());
+
+        match &mut self.kind {
+            BufferKind::Text => self.show_text(ui, theme, settings),
+            BufferKind::Markdown { preview } => {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(preview, false, "Edit");
+                    ui.selectable_value(preview, true, "Preview");
+                });
+
+                if *preview {
+                    markdown::show(
+                        ui,
+                        Id::new("markdown_preview").with(self.id),
+                        &self.contents,
+                        theme,
+                    )
+                } else {
+                    self.show_text(ui, theme, settings)
+                }
+            }
+            BufferKind::Image { uri, bytes } => Self::show_image(ui, uri, bytes),
+            BufferKind::Pdf { bytes, open_error } => Self::show_pdf(ui, path, bytes, open_error),
+            BufferKind::Binary { bytes } => Self::show_hex(ui, bytes),
+        }
+    }
+
+    fn show_image(ui: &mut Ui, uri: &str, bytes: &Arc<[u8]>) -> Response {
+        ui.scope(|ui| {
+            ui.label(format!("Image ({})", format_size(bytes.len())));
+
+            // images are shrunk to fit the panel, but aren't scaled up past their original size
+            let max_size = ui.available_size();
+            ui.centered_and_justified(|ui| {
+                ui.add(
+                    Image::from_bytes(uri.to_string(), bytes.clone())
+                        .max_size(max_size)
+                        .shrink_to_fit(),
+                );
+            });
+        })
+        .response
+    }
+
+    fn show_pdf(
+        ui: &mut Ui,
+        path: Option<&Path>,
+        bytes: &[u8],
+        open_error: &mut Option<String>,
+    ) -> Response {
+        ui.vertical_centered(|ui| {
+            ui.add_space(ui.available_height() / 3.0);
+            ui.heading("PDF document");
+            ui.label(format_size(bytes.len()));
+
+            if ui.button("Open in PDF viewer").clicked()
+                && let Some(path) = path
+            {
+                *open_error = platform::open_in_viewer(path, bytes, "application/pdf")
+                    .err()
+                    .map(|err| format!("Failed to open PDF: {err}"));
+            }
+            if let Some(err) = open_error {
+                ui.colored_label(ui.visuals().error_fg_color, err.as_str());
+            }
+        })
+        .response
+    }
+
+    // Shows the bytes of a binary file in the same layout as `hexdump -C`:
+    // the offset of each row, then its bytes in hex, then the bytes which are printable as ASCII
+    fn show_hex(ui: &mut Ui, bytes: &[u8]) -> Response {
+        ui.scope(|ui| {
+            ui.label(format!(
+                "Binary file ({}), shown as hex",
+                format_size(bytes.len())
+            ));
+
+            let row_height = ui.text_style_height(&TextStyle::Monospace);
+            let rows = bytes.len().div_ceil(HEX_BYTES_PER_ROW);
+            ScrollArea::both().auto_shrink(false).show_rows(
+                ui,
+                row_height,
+                rows,
+                |ui, visible_rows| {
+                    for row in visible_rows {
+                        let start = row * HEX_BYTES_PER_ROW;
+                        let end = (start + HEX_BYTES_PER_ROW).min(bytes.len());
+                        ui.add(
+                            Label::new(
+                                RichText::new(hex_row(start, &bytes[start..end])).monospace(),
+                            )
+                            .extend(),
+                        );
+                    }
+                },
+            );
+        })
+        .response
+    }
+
+    fn show_text(&mut self, ui: &mut Ui, theme: &CodeTheme, settings: &EditorSettings) -> Response {
+        let text_edit_id = Id::new("buffer_text_edit").with(self.id);
+        let font_id = FontId::new(
+            settings.font_size,
+            match settings.font_family {
+                ws_messages::FontFamily::Monospace => FontFamily::Monospace,
+                ws_messages::FontFamily::Proportional => FontFamily::Proportional,
+            },
+        );
+        let tab_width = settings.tab_width.max(1) as usize;
+
+        if settings.insert_spaces && self.revision.is_none() {
+            self.indent_with_spaces(ui, text_edit_id, tab_width);
+        }
+
+        // when the view is being restored, the cursor is moved without scrolling to it
+        let restoring_view = self.pending_cursor.is_some() && self.pending_scroll.is_some();
+
+        // move the cursor in the stored text edit state before the text edit is shown
+        let jump_to = self.pending_cursor.take().map(|position| {
+            let ccursor = CCursor::new(self.char_index(position));
+            let mut state = TextEdit::load_state(ui.ctx(), text_edit_id).unwrap_or_default();
+            state
+                .cursor
+                .set_char_range(Some(CCursorRange::one(ccursor)));
+            state.store(ui.ctx(), text_edit_id);
+            if !restoring_view {
+                ui.memory_mut(|mem| mem.request_focus(text_edit_id));
+            }
+
+            ccursor
+        });
+
+        // the minimap takes up a strip on the right of the buffer
+        let available = ui.available_rect_before_wrap();
+        let (editor_rect, minimap_rect) = if settings.minimap {
+            let (editor_rect, minimap_rect) =
+                available.split_left_right_at_x(available.right() - MINIMAP_WIDTH);
+            (editor_rect, Some(minimap_rect))
+        } else {
+            (available, None)
+        };
+
+        // long lines can only be scrolled to horizontally if they aren't wrapped
+        let mut scroll_area = if settings.soft_wrap {
+            ScrollArea::vertical()
+        } else {
+            ScrollArea::both()
+        }
+        .id_salt(self.id)
+        .auto_shrink(false);
+        if let Some(offset) = self.pending_scroll.take() {
+            scroll_area = scroll_area.vertical_scroll_offset(offset);
+        }
+
+        let line_count = self.line_count();
+        let scroll_output = ui
+            .scope_builder(UiBuilder::new().max_rect(editor_rect), |ui| {
+                scroll_area.show(ui, |ui| {
+                    let lang = self
+                        .file_data
+                        .as_ref()
+                        .map(|f| &f.path)
+                        .or(self.revision.as_ref().map(|r| &r.path))
+                        .and_then(|path| path.extension())
+                        .unwrap_or_default();
+
+                    // the gutter is wide enough to fit the largest line number
+                    let gutter_width = if settings.line_numbers {
+                        let digits = line_count.to_string().len().max(2);
+                        ui.fonts_mut(|f| f.glyph_width(&font_id, '0')) * digits as f32
+                            + GUTTER_MARGIN
+                    } else {
+                        0.0
+                    };
+                    // egui always lays out tabs as `TAB_SIZE` spaces, so the text after each tab is shifted to make up the difference
+                    let tab_shift = (tab_width as f32 - TAB_SIZE as f32)
+                        * ui.fonts_mut(|f| f.glyph_width(&font_id, ' '));
+
+                    ui.horizontal_top(|ui| {
+                        ui.spacing_mut().item_spacing.x = 0.0;
+                        ui.add_space(gutter_width);
+
+                        let size = ui.available_size();
+
+                        // the background is painted behind the text by us rather than the text edit's frame,
+                        // so that the current line can be highlighted underneath the text
+                        let background = ui.painter().add(Shape::Noop);
+
+                        // the text of old versions of files can be selected and copied, but not edited
+                        let mut read_only;
+                        let text: &mut dyn TextBuffer = match &self.revision {
+                            Some(_) => {
+                                read_only = self.contents.as_str();
+                                &mut read_only
+                            }
+                            None => &mut self.contents,
+                        };
+                        let output = TextEdit::multiline(text)
+                            .id(text_edit_id)
+                            .code_editor()
+                            .font(font_id.clone())
+                            .frame(false)
+                            .desired_width(f32::INFINITY)
+                            .min_size(size)
+                            .layouter(&mut |ui: &Ui, contents, wrap_width| {
+                                let mut layout_job = syntax_highlighting::highlight(
+                                    ui.ctx(),
+                                    ui.style(),
+                                    theme,
+                                    contents.as_str(),
+                                    &lang.to_string_lossy(),
+                                );
+                                for section in &mut layout_job.sections {
+                                    section.format.font_id = font_id.clone();
+                                }
+                                if tab_shift != 0.0 {
+                                    shift_after_tabs(&mut layout_job, tab_shift);
+                                }
+                                layout_job.wrap.max_width = if settings.soft_wrap {
+                                    wrap_width
+                                } else {
+                                    f32::INFINITY
+                                };
+                                ui.fonts_mut(|f| f.layout_job(layout_job))
+                            })
+                            .show(ui);
+
+                        let galley_pos = output.galley_pos.to_vec2();
+                        let cursor_rect = output.cursor_range.map(|range| {
+                            output
+                                .galley
+                                .pos_from_cursor(range.primary)
+                                .translate(galley_pos)
+                        });
+
+                        let mut background_shapes = vec![Shape::rect_filled(
+                            output.response.rect,
+                            0.0,
+                            ui.visuals().text_edit_bg_color(),
+                        )];
+                        if settings.highlight_current_line
+                            && let Some(cursor_rect) = cursor_rect
+                        {
+                            let line_rect = Rect::from_x_y_ranges(
+                                output.response.rect.x_range(),
+                                cursor_rect.y_range(),
                             );
-                            layout_job.wrap.max_width = wrap_width;
-                            ui.fonts_mut(|f| f.layout_job(layout_job))
-                        }),
-                )
+                            background_shapes.push(Shape::rect_filled(
+                                line_rect,
+                                0.0,
+                                ui.visuals().code_bg_color.gamma_multiply(0.5),
+                            ));
+                        }
+                        ui.painter().set(background, Shape::Vec(background_shapes));
+
+                        if settings.line_numbers {
+                            Self::paint_line_numbers(ui, &output, &font_id, cursor_rect);
+                        }
+                        if settings.show_whitespace {
+                            Self::paint_whitespace(ui, &output, &font_id);
+                        }
+
+                        // scroll so that the new cursor position is in the middle of the view
+                        if let Some(ccursor) = jump_to
+                            && !restoring_view
+                        {
+                            let cursor_rect =
+                                output.galley.pos_from_cursor(ccursor).translate(galley_pos);
+                            ui.scroll_to_rect(cursor_rect, Some(Align::Center));
+                        }
+
+                        output
+                    })
+                    .inner
+                })
             })
-            .inner
+            .inner;
+
+        if let Some(rect) = minimap_rect
+            && let Some(offset) = self.show_minimap(ui, rect, &scroll_output)
+        {
+            self.pending_scroll = Some(offset);
+            ui.ctx().request_repaint();
+        }
+
+        self.scroll_offset = scroll_output.state.offset.y;
+        let output = scroll_output.inner;
+        if let Some(range) = output.cursor_range {
+            self.cursor = self.position_of(range.primary.index);
+        }
+
+        output.response
+    }
+
+    /// Replaces a press of the tab key with spaces up to the next tab stop
+    /// (the text edit would insert a tab character otherwise)
+    fn indent_with_spaces(&mut self, ui: &Ui, text_edit_id: Id, tab_width: usize) {
+        // shift+tab is left for the text edit to handle
+        let tab_pressed = ui.memory(|mem| mem.has_focus(text_edit_id))
+            && ui.input_mut(|i| !i.modifiers.any() && i.consume_key(Modifiers::NONE, Key::Tab));
+        if !tab_pressed {
+            return;
+        }
+
+        let Some(mut state) = TextEdit::load_state(ui.ctx(), text_edit_id) else {
+            return;
+        };
+        let Some(range) = state.cursor.char_range() else {
+            return;
+        };
+
+        // any selected text is replaced by the indentation
+        let [start, end] = range.sorted_cursors();
+        let col = self.position_of(start.index).col - 1;
+        let spaces = " ".repeat(tab_width - col % tab_width);
+
+        let byte_index = |char_index| {
+            self.contents
+                .char_indices()
+                .nth(char_index)
+                .map_or(self.contents.len(), |(i, _)| i)
+        };
+        let byte_range = byte_index(start.index)..byte_index(end.index);
+        self.contents.replace_range(byte_range, &spaces);
+
+        let cursor = CCursor::new(start.index + spaces.len());
+        state.cursor.set_char_range(Some(CCursorRange::one(cursor)));
+        state.store(ui.ctx(), text_edit_id);
+    }
+
+    /// Paints the number of each line in the gutter to the left of the text,
+    /// with the number of the line containing the cursor in a stronger colour
+    fn paint_line_numbers(
+        ui: &Ui,
+        output: &TextEditOutput,
+        font_id: &FontId,
+        cursor_rect: Option<Rect>,
+    ) {
+        let clip_rect = ui.clip_rect();
+        let right = output.response.rect.left() - GUTTER_MARGIN / 2.0;
+
+        let mut line = 1;
+        let mut row_starts_line = true;
+        for row in &output.galley.rows {
+            let row_rect = row.rect().translate(output.galley_pos.to_vec2());
+
+            // wrapped rows continue the line above, so they don't get a number
+            if row_starts_line && row_rect.intersects(clip_rect) {
+                let is_current = cursor_rect.is_some_and(|c| {
+                    c.center().y >= row_rect.top() && c.center().y <= row_rect.bottom()
+                });
+                let color = if is_current {
+                    ui.visuals().strong_text_color()
+                } else {
+                    ui.visuals().weak_text_color()
+                };
+                ui.painter().text(
+                    pos2(right, row_rect.top()),
+                    Align2::RIGHT_TOP,
+                    line,
+                    font_id.clone(),
+                    color,
+                );
+            }
+
+            if row.ends_with_newline {
+                line += 1;
+            }
+            row_starts_line = row.ends_with_newline;
+        }
+    }
+
+    /// Paints faint `·` and `→` characters over spaces and tabs
+    fn paint_whitespace(ui: &Ui, output: &TextEditOutput, font_id: &FontId) {
+        let clip_rect = ui.clip_rect();
+        let color = ui.visuals().weak_text_color().gamma_multiply(0.6);
+
+        for row in &output.galley.rows {
+            let offset = row.pos.to_vec2() + output.galley_pos.to_vec2();
+            if !row
+                .rect()
+                .translate(output.galley_pos.to_vec2())
+                .intersects(clip_rect)
+            {
+                continue;
+            }
+
+            for glyph in &row.glyphs {
+                let (symbol, align) = match glyph.chr {
+                    ' ' => ("·", Align2::CENTER_CENTER),
+                    '\t' => ("→", Align2::LEFT_CENTER),
+                    _ => continue,
+                };
+                let rect = glyph.logical_rect().translate(offset);
+                let pos = if glyph.chr == '\t' {
+                    rect.left_center()
+                } else {
+                    rect.center()
+                };
+                ui.painter()
+                    .text(pos, align, symbol, font_id.clone(), color);
+            }
+        }
+    }
+
+    /// Shows an overview of the whole buffer to the right of it, with each line drawn as a bar, and the visible part highlighted.
+    /// Returns the offset to scroll the buffer to if the minimap has been clicked or dragged.
+    fn show_minimap(
+        &self,
+        ui: &mut Ui,
+        rect: Rect,
+        scroll_output: &ScrollAreaOutput<TextEditOutput>,
+    ) -> Option<f32> {
+        let response = ui.allocate_rect(rect, Sense::click_and_drag());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, ui.visuals().text_edit_bg_color());
+
+        // lines are squashed together if the whole buffer doesn't fit at the normal height
+        let line_count = self.line_count();
+        let line_height = MINIMAP_LINE_HEIGHT.min(rect.height() / line_count as f32);
+        // skip lines that would be drawn on the same pixel as the previous line
+        let step = (1.0 / line_height).ceil().max(1.0) as usize;
+        let color = ui.visuals().text_color().gamma_multiply(0.4);
+
+        for (i, line) in self.contents.split('\n').enumerate().step_by(step) {
+            let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+            let len = line.trim_end().chars().count();
+            if len <= indent {
+                continue;
+            }
+
+            let top = rect.top() + i as f32 * line_height;
+            let bar = Rect::from_min_max(
+                pos2(rect.left() + indent as f32 * MINIMAP_CHAR_WIDTH, top),
+                pos2(
+                    (rect.left() + len as f32 * MINIMAP_CHAR_WIDTH).min(rect.right()),
+                    top + line_height.max(1.0),
+                ),
+            );
+            painter.rect_filled(bar, 0.0, color);
+        }
+
+        // scale from the buffer's scroll area to the minimap
+        let content_height = scroll_output.content_size.y.max(1.0);
+        let scale = line_height * line_count as f32 / content_height;
+        let viewport_height = scroll_output.inner_rect.height();
+        let viewport = Rect::from_min_size(
+            pos2(
+                rect.left(),
+                rect.top() + scroll_output.state.offset.y * scale,
+            ),
+            vec2(rect.width(), viewport_height * scale),
+        );
+        painter.rect_filled(
+            viewport,
+            0.0,
+            ui.visuals().selection.bg_fill.gamma_multiply(0.3),
+        );
+
+        // centre the view on the clicked line
+        let pointer = response
+            .interact_pointer_pos()
+            .filter(|_| response.clicked() || response.dragged())?;
+        Some(((pointer.y - rect.top()) / scale - viewport_height / 2.0).max(0.0))
+    }
+}
+
+// Moves the text after each tab in the layout job by `shift`, to change how wide tabs are displayed
+fn shift_after_tabs(layout_job: &mut LayoutJob, shift: f32) {
+    let mut sections = Vec::with_capacity(layout_job.sections.len());
+    // shift carried over from a tab at the end of the previous section
+    let mut carried_shift = 0.0;
+
+    for section in std::mem::take(&mut layout_job.sections) {
+        let range = section.byte_range.clone();
+        let mut start = range.start;
+        let mut leading_space = section.leading_space + carried_shift;
+        carried_shift = 0.0;
+
+        for (i, _) in layout_job.text[range.clone()].match_indices('\t') {
+            let end = range.start + i + 1;
+            if end == range.end {
+                carried_shift = shift;
+                break;
+            }
+
+            // split the section after the tab, so that the rest of it can be shifted
+            sections.push(LayoutSection {
+                leading_space,
+                byte_range: start..end,
+                format: section.format.clone(),
+            });
+            start = end;
+            leading_space = shift;
+        }
+
+        sections.push(LayoutSection {
+            leading_space,
+            byte_range: start..range.end,
+            format: section.format,
+        });
+    }
+
+    layout_job.sections = sections;
+}
+
+// formats a row of the hex viewer, starting at the offset `start` into the file
+fn hex_row(start: usize, bytes: &[u8]) -> String {
+    let mut row = format!("{start:08x}  ");
+    for i in 0..HEX_BYTES_PER_ROW {
+        match bytes.get(i) {
+            Some(byte) => row.push_str(&format!("{byte:02x} ")),
+            // the last row is padded so that its ASCII column lines up with the others
+            None => row.push_str("   "),
+        }
+        // an extra space splits the row into two halves
+        if i == HEX_BYTES_PER_ROW / 2 - 1 {
+            row.push(' ');
+        }
+    }
+
+    let ascii: String = bytes
+        .iter()
+        .map(|&byte| {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            }
+        })
+        .collect();
+    row.push_str(&format!(" |{ascii}|"));
+
+    row
+}
+
+// formats a number of bytes with the largest unit which keeps it above 1 (e.g. "1.5 MB")
+fn format_size(bytes: usize) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} bytes");
     }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next_unit in &UNITS[1..] {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next_unit;
+    }
+
+    format!("{size:.1} {unit}")
 }
 
 impl Default for Buffer {
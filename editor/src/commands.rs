@@ -0,0 +1,180 @@
+use std::collections::{HashMap, HashSet};
+
+use egui::{Context, Key, KeyboardShortcut, Modifiers};
+use ws_messages::{Keybinding, Keymap, KeymapPreset};
+
+/// An action which can be performed in the editor, either from the command palette or with its keybinding
+///
+/// `T` is the type the command acts on (the [`App`](crate::App))
+pub struct EditorCommand<T> {
+    /// Unique identifier for the command (e.g. `file.save`), used to store the user's keybindings
+    pub id: &'static str,
+    /// Name of the command shown to the user
+    pub name: &'static str,
+    /// Keybinding in the default (VS Code-like) keymap
+    pub default_keybinding: Option<KeyboardShortcut>,
+    /// Whether the command can currently be run
+    pub enabled: fn(&T) -> bool,
+    pub run: fn(&mut T, &Context),
+}
+
+/// Every command available in the editor, in the order they are listed to the user
+pub struct CommandRegistry<T> {
+    commands: Vec<EditorCommand<T>>,
+    /// Current keybinding of each command, by its id
+    keybindings: HashMap<&'static str, Option<KeyboardShortcut>>,
+}
+
+impl<T> CommandRegistry<T> {
+    pub fn new(commands: Vec<EditorCommand<T>>) -> Self {
+        let mut registry = Self {
+            commands,
+            keybindings: HashMap::new(),
+        };
+        registry.apply_keymap(&Keymap::default());
+
+        registry
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &EditorCommand<T>> {
+        self.commands.iter()
+    }
+
+    pub fn keybinding(&self, command: &EditorCommand<T>) -> Option<KeyboardShortcut> {
+        self.keybindings.get(command.id).copied().flatten()
+    }
+
+    // gets the keybinding each command would have with the keymap
+    pub fn resolve(&self, keymap: &Keymap) -> HashMap<&'static str, Option<KeyboardShortcut>> {
+        let preset = preset_keybindings(keymap.preset);
+
+        self.commands
+            .iter()
+            .map(|command| {
+                let keybinding = match keymap.overrides.get(command.id) {
+                    Some(keybinding) => keybinding.as_ref().and_then(from_keybinding),
+                    None => preset
+                        .get(command.id)
+                        .copied()
+                        .unwrap_or(command.default_keybinding),
+                };
+
+                (command.id, keybinding)
+            })
+            .collect()
+    }
+
+    pub fn apply_keymap(&mut self, keymap: &Keymap) {
+        self.keybindings = self.resolve(keymap);
+    }
+
+    // finds the enabled command whose keybinding has just been pressed (consuming the key press)
+    pub fn triggered(&self, target: &T, ctx: &Context) -> Option<&EditorCommand<T>> {
+        // keybindings with more modifiers are checked first, because shortcuts also match
+        // when extra modifiers are held (e.g. Ctrl+S would otherwise be triggered by Ctrl+Shift+S)
+        let mut bound: Vec<_> = self
+            .commands
+            .iter()
+            .filter_map(|command| self.keybinding(command).map(|shortcut| (shortcut, command)))
+            .collect();
+        bound.sort_by_key(|(shortcut, _)| std::cmp::Reverse(modifier_count(shortcut.modifiers)));
+
+        bound.into_iter().find_map(|(shortcut, command)| {
+            ((command.enabled)(target) && ctx.input_mut(|i| i.consume_shortcut(&shortcut)))
+                .then_some(command)
+        })
+    }
+}
+
+// finds the ids of the commands which have the same keybinding as another command
+pub fn conflicts(
+    keybindings: &HashMap<&'static str, Option<KeyboardShortcut>>,
+) -> HashSet<&'static str> {
+    let mut commands_by_shortcut: HashMap<KeyboardShortcut, Vec<&'static str>> = HashMap::new();
+    for (id, shortcut) in keybindings {
+        if let Some(shortcut) = shortcut {
+            commands_by_shortcut.entry(*shortcut).or_default().push(id);
+        }
+    }
+
+    commands_by_shortcut
+        .into_values()
+        .filter(|ids| ids.len() > 1)
+        .flatten()
+        .collect()
+}
+
+pub fn preset_name(preset: KeymapPreset) -> &'static str {
+    match preset {
+        KeymapPreset::VsCode => "VS Code-like",
+        KeymapPreset::Emacs => "Emacs-like",
+    }
+}
+
+// keybindings in the preset which are different to the commands' default keybindings
+fn preset_keybindings(preset: KeymapPreset) -> HashMap<&'static str, Option<KeyboardShortcut>> {
+    let shortcut = |modifiers, key| Some(KeyboardShortcut::new(modifiers, key));
+    let ctrl_alt = Modifiers::COMMAND | Modifiers::ALT;
+
+    match preset {
+        // the default keybindings are already based on VS Code
+        KeymapPreset::VsCode => HashMap::new(),
+        // Emacs uses sequences of shortcuts for many commands (e.g. C-x C-s to save),
+        // so these are approximated using a single shortcut with Ctrl+Alt
+        KeymapPreset::Emacs => HashMap::from([
+            ("file.new", None),
+            ("file.open", None),
+            ("file.open_folder", shortcut(ctrl_alt, Key::D)),
+            ("file.save", shortcut(ctrl_alt, Key::S)),
+            ("file.save_as", shortcut(ctrl_alt, Key::W)),
+            ("file.save_all", shortcut(ctrl_alt | Modifiers::SHIFT, Key::S)),
+            ("search.find", shortcut(Modifiers::COMMAND, Key::S)),
+            ("search.replace", shortcut(Modifiers::ALT | Modifiers::SHIFT, Key::Num5)),
+            ("go.line", shortcut(Modifiers::ALT, Key::G)),
+            ("go.file", shortcut(ctrl_alt, Key::F)),
+            ("palette", shortcut(Modifiers::ALT, Key::X)),
+            ("settings", None),
+            ("view.terminal", shortcut(ctrl_alt, Key::T)),
+            ("view.output", shortcut(ctrl_alt, Key::U)),
+            ("help", shortcut(Modifiers::COMMAND, Key::H)),
+        ]),
+    }
+}
+
+pub fn to_keybinding(shortcut: &KeyboardShortcut) -> Keybinding {
+    let modifiers = shortcut.modifiers;
+
+    Keybinding {
+        key: shortcut.logical_key.name().to_string(),
+        command: modifiers.command || modifiers.ctrl || modifiers.mac_cmd,
+        alt: modifiers.alt,
+        shift: modifiers.shift,
+    }
+}
+
+pub fn from_keybinding(keybinding: &Keybinding) -> Option<KeyboardShortcut> {
+    let mut modifiers = Modifiers::NONE;
+    if keybinding.command {
+        modifiers |= Modifiers::COMMAND;
+    }
+    if keybinding.alt {
+        modifiers |= Modifiers::ALT;
+    }
+    if keybinding.shift {
+        modifiers |= Modifiers::SHIFT;
+    }
+
+    Some(KeyboardShortcut::new(modifiers, Key::from_name(&keybinding.key)?))
+}
+
+fn modifier_count(modifiers: Modifiers) -> usize {
+    [
+        modifiers.alt,
+        modifiers.shift,
+        modifiers.command || modifiers.ctrl,
+        modifiers.mac_cmd,
+    ]
+    .into_iter()
+    .filter(|&held| held)
+    .count()
+}
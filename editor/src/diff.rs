@@ -0,0 +1,280 @@
+// Line-based diffs and three-way merges, used when a file changes on disk while its buffer has unsaved changes,
+// and to show the changes to files in the source control panel
+
+// the largest number of (old line, new line) pairs compared when diffing,
+// above which every line is treated as changed to avoid using too much memory
+const MAX_DIFF_CELLS: usize = 16_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    /// A line in both the old and new text
+    Same(&'a str),
+    /// A line only in the old text
+    Removed(&'a str),
+    /// A line only in the new text
+    Added(&'a str),
+}
+
+/// Result of merging two sets of changes to the same text
+pub struct Merged {
+    pub text: String,
+    /// Number of places where both sets of changes changed the same lines differently,
+    /// which are marked in the text with conflict markers
+    pub conflicts: usize,
+}
+
+// splits text into lines, keeping the line endings so that the text can be put back together exactly
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+// Finds which lines of `old` are kept in `new`, using the longest common subsequence of lines.
+// The result has an entry for each line of `old`, with the index of the matching line in `new` (if it was kept)
+fn matching_lines(old: &[&str], new: &[&str]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; old.len()];
+
+    // lines at the start and end which are the same don't need to be compared
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    for (i, matched) in matches.iter_mut().enumerate().take(prefix) {
+        *matched = Some(i);
+    }
+    for i in 0..suffix {
+        matches[old.len() - 1 - i] = Some(new.len() - 1 - i);
+    }
+
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+    let (n, m) = (old_middle.len(), new_middle.len());
+    if n == 0 || m == 0 || n * m > MAX_DIFF_CELLS {
+        return matches;
+    }
+
+    // lengths[i][j] is the length of the longest common subsequence of old_middle[i..] and new_middle[j..]
+    let mut lengths = vec![0u32; (n + 1) * (m + 1)];
+    let index = |i: usize, j: usize| i * (m + 1) + j;
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[index(i, j)] = if old_middle[i] == new_middle[j] {
+                lengths[index(i + 1, j + 1)] + 1
+            } else {
+                lengths[index(i + 1, j)].max(lengths[index(i, j + 1)])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old_middle[i] == new_middle[j] {
+            matches[prefix + i] = Some(prefix + j);
+            i += 1;
+            j += 1;
+        } else if lengths[index(i + 1, j)] >= lengths[index(i, j + 1)] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    matches
+}
+
+// Compares two texts line by line
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old = lines(old);
+    let new = lines(new);
+    let matches = matching_lines(&old, &new);
+
+    let mut diff = Vec::with_capacity(old.len().max(new.len()));
+    let mut j = 0;
+    for (i, line) in old.iter().enumerate() {
+        match matches[i] {
+            Some(matched) => {
+                // lines added before the kept line
+                diff.extend(new[j..matched].iter().map(|line| DiffLine::Added(line)));
+                diff.push(DiffLine::Same(line));
+                j = matched + 1;
+            }
+            None => diff.push(DiffLine::Removed(line)),
+        }
+    }
+    diff.extend(new[j..].iter().map(|line| DiffLine::Added(line)));
+
+    diff
+}
+
+// Merges the changes made in `ours` and `theirs` to the text they both started from (`base`).
+// Changes to different lines are both kept, and where the same lines were changed differently,
+// both versions are kept between conflict markers labelled with `our_label` and `their_label`
+pub fn merge(base: &str, ours: &str, theirs: &str, our_label: &str, their_label: &str) -> Merged {
+    let base = lines(base);
+    let ours = lines(ours);
+    let theirs = lines(theirs);
+    let our_matches = matching_lines(&base, &ours);
+    let their_matches = matching_lines(&base, &theirs);
+
+    let mut merged = Merged {
+        text: String::new(),
+        conflicts: 0,
+    };
+    // positions in each text up to which the lines have been merged
+    let (mut b, mut o, mut t) = (0, 0, 0);
+
+    loop {
+        // find the next line of the base which is kept in both texts
+        let stable = (b..base.len()).find_map(|i| match (our_matches[i], their_matches[i]) {
+            (Some(oi), Some(ti)) if oi >= o && ti >= t => Some((i, oi, ti)),
+            _ => None,
+        });
+
+        let (next_b, next_o, next_t) = stable.unwrap_or((base.len(), ours.len(), theirs.len()));
+        if (next_b, next_o, next_t) != (b, o, t) {
+            merge_chunk(
+                &mut merged,
+                &base[b..next_b],
+                &ours[o..next_o],
+                &theirs[t..next_t],
+                our_label,
+                their_label,
+            );
+        }
+
+        let Some((i, oi, ti)) = stable else {
+            break;
+        };
+        merged.text.push_str(base[i]);
+        (b, o, t) = (i + 1, oi + 1, ti + 1);
+    }
+
+    merged
+}
+
+// merges a section of lines where at least one of the texts differs from the base
+fn merge_chunk(
+    merged: &mut Merged,
+    base: &[&str],
+    ours: &[&str],
+    theirs: &[&str],
+    our_label: &str,
+    their_label: &str,
+) {
+    if ours == base || ours == theirs {
+        merged.text.extend(theirs.iter().copied());
+    } else if theirs == base {
+        merged.text.extend(ours.iter().copied());
+    } else {
+        merged.conflicts += 1;
+
+        merged.text.push_str(&format!("<<<<<<< {our_label}\n"));
+        push_lines(&mut merged.text, ours);
+        merged.text.push_str("=======\n");
+        push_lines(&mut merged.text, theirs);
+        merged.text.push_str(&format!(">>>>>>> {their_label}\n"));
+    }
+}
+
+fn push_lines(text: &mut String, lines: &[&str]) {
+    text.extend(lines.iter().copied());
+    // anything after the lines has to start on a new line
+    if lines.last().is_some_and(|line| !line.ends_with('\n')) {
+        text.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merge_texts(base: &str, ours: &str, theirs: &str) -> (String, usize) {
+        let merged = merge(base, ours, theirs, "ours", "theirs");
+        (merged.text, merged.conflicts)
+    }
+
+    #[test]
+    fn diff_keeps_common_lines() {
+        assert_eq!(
+            diff_lines("a\nb\nc\n", "a\nx\nc\nd\n"),
+            [
+                DiffLine::Same("a\n"),
+                DiffLine::Removed("b\n"),
+                DiffLine::Added("x\n"),
+                DiffLine::Same("c\n"),
+                DiffLine::Added("d\n"),
+            ]
+        );
+        assert_eq!(diff_lines("", "a"), [DiffLine::Added("a")]);
+        assert_eq!(diff_lines("a\n", ""), [DiffLine::Removed("a\n")]);
+    }
+
+    #[test]
+    fn merge_unchanged() {
+        let text = "a\nb\nc\n";
+        assert_eq!(merge_texts(text, text, text), (text.to_string(), 0));
+    }
+
+    #[test]
+    fn merge_changes_on_one_side() {
+        let base = "a\nb\nc\n";
+        let changed = "a\nB\nc\nd\n";
+        assert_eq!(merge_texts(base, changed, base), (changed.to_string(), 0));
+        assert_eq!(merge_texts(base, base, changed), (changed.to_string(), 0));
+    }
+
+    #[test]
+    fn merge_changes_to_different_lines() {
+        let (text, conflicts) = merge_texts("a\nb\nc\nd\ne\n", "A\nb\nc\nd\ne\n", "a\nb\nc\nd\nE\nf\n");
+        assert_eq!(text, "A\nb\nc\nd\nE\nf\n");
+        assert_eq!(conflicts, 0);
+
+        // a line removed on one side and another added on the other
+        let (text, conflicts) = merge_texts("a\nb\nc\nd\n", "a\nc\nd\n", "a\nb\nc\nd\ne\n");
+        assert_eq!(text, "a\nc\nd\ne\n");
+        assert_eq!(conflicts, 0);
+    }
+
+    #[test]
+    fn merge_same_change_on_both_sides() {
+        let (text, conflicts) = merge_texts("a\nb\nc\n", "a\nx\nc\n", "a\nx\nc\n");
+        assert_eq!(text, "a\nx\nc\n");
+        assert_eq!(conflicts, 0);
+    }
+
+    #[test]
+    fn merge_conflict() {
+        let (text, conflicts) = merge_texts("a\nb\nc\n", "a\nours\nc\n", "a\ntheirs\nc\n");
+        assert_eq!(text, "a\n<<<<<<< ours\nours\n=======\ntheirs\n>>>>>>> theirs\nc\n");
+        assert_eq!(conflicts, 1);
+
+        let (text, conflicts) = merge_texts(
+            "1\n2\n3\n4\n5\n",
+            "x\n2\n3\n4\ny\n",
+            "z\n2\n3\n4\nw\n",
+        );
+        assert_eq!(conflicts, 2);
+        assert!(text.contains("<<<<<<< ours\nx\n=======\nz\n>>>>>>> theirs\n2\n3\n4\n<<<<<<< ours\ny\n"));
+    }
+
+    #[test]
+    fn merge_conflict_without_final_newline() {
+        // the markers still start on their own lines if a side doesn't end with a newline
+        let (text, conflicts) = merge_texts("a\nb", "a\nours", "a\ntheirs");
+        assert_eq!(text, "a\n<<<<<<< ours\nours\n=======\ntheirs\n>>>>>>> theirs\n");
+        assert_eq!(conflicts, 1);
+    }
+
+    #[test]
+    fn merge_edits_at_the_start_and_end() {
+        let (text, conflicts) = merge_texts("b\nc\n", "a\nb\nc\n", "b\nc\nd\n");
+        assert_eq!(text, "a\nb\nc\nd\n");
+        assert_eq!(conflicts, 0);
+
+        let (text, conflicts) = merge_texts("", "a\n", "b\n");
+        assert_eq!(text, "<<<<<<< ours\na\n=======\nb\n>>>>>>> theirs\n");
+        assert_eq!(conflicts, 1);
+    }
+}
@@ -1,9 +1,11 @@
 use std::{
     cmp::Ordering,
+    collections::HashSet,
+    io::ErrorKind,
     path::{Path, PathBuf},
 };
 
-use egui::{CollapsingHeader, Popup, Response, ScrollArea};
+use egui::{CollapsingHeader, Id, Key, Popup, Response, ScrollArea, Sense, Stroke, TextEdit};
 use eyre::WrapErr;
 
 use crate::platform::{FileSystem, FileSystemTrait as _};
@@ -20,6 +22,68 @@ pub enum TreeNode {
     File {
         path: PathBuf,
     },
+    /// Text box for entering the name of a new file or folder, in the directory at `path`
+    NewFile {
+        path: PathBuf,
+        name: String,
+        is_folder: bool,
+    },
+}
+
+/// Paths of the entries being dragged in the explorer
+struct DragPayload(Vec<PathBuf>);
+
+/// Entries selected in the explorer, and those copied to be pasted into another directory
+#[derive(Debug, Default)]
+pub struct Selection {
+    pub paths: Vec<PathBuf>,
+    copied: Vec<PathBuf>,
+    /// Entry being renamed, and the name entered for it so far
+    renaming: Option<(PathBuf, String)>,
+}
+
+impl Selection {
+    // selects the clicked entry, or adds it to (or removes it from) the selection if Ctrl is held
+    fn click(&mut self, ui: &egui::Ui, path: &Path) {
+        if ui.input(|i| i.modifiers.command) {
+            match self.paths.iter().position(|selected| selected == path) {
+                Some(i) => {
+                    self.paths.remove(i);
+                }
+                None => self.paths.push(path.to_owned()),
+            }
+        } else {
+            self.paths = vec![path.to_owned()];
+        }
+    }
+
+    fn is_selected(&self, path: &Path) -> bool {
+        self.paths.iter().any(|selected| selected == path)
+    }
+
+    // the entries an action on `path` applies to: the whole selection if `path` is part of it, otherwise just `path`
+    fn targets(&self, path: &Path) -> Vec<PathBuf> {
+        if self.is_selected(path) {
+            self.paths.clone()
+        } else {
+            vec![path.to_owned()]
+        }
+    }
+
+    // replaces the entry with a text box for its new name, starting with its current name
+    fn start_rename(&mut self, ui: &egui::Ui, path: &Path) {
+        let name = TreeNode::name_from_path(path).to_string();
+        self.renaming = Some((path.to_owned(), name));
+        ui.memory_mut(|memory| memory.request_focus(TreeNode::rename_id(path)));
+    }
+
+    // the name entered so far if the entry is being renamed
+    fn renaming(&mut self, path: &Path) -> Option<&mut String> {
+        match &mut self.renaming {
+            Some((renaming, name)) if renaming == path => Some(name),
+            _ => None,
+        }
+    }
 }
 
 impl TreeNode {
@@ -37,8 +101,10 @@ impl TreeNode {
         } else if max_depth == 0 {
             TreeNode::UnexploredDir { path }
         } else {
-            let children = Self::read_children(&path, max_depth, fs)?;
-            TreeNode::ExploredDir { path, children }
+            match Self::read_children(&path, max_depth, fs)? {
+                Some(children) => TreeNode::ExploredDir { path, children },
+                None => TreeNode::UnexploredDir { path },
+            }
         })
     }
 
@@ -57,6 +123,7 @@ impl TreeNode {
             TreeNode::UnexploredDir { path } => path,
             TreeNode::ExploredDir { path, .. } => path,
             TreeNode::File { path } => path,
+            TreeNode::NewFile { path, .. } => path,
         }
     }
 
@@ -70,15 +137,48 @@ impl TreeNode {
         Self::name_from_path(self.path())
     }
 
+    // finds the node for the path, if it has been explored
+    fn find(&self, path: &Path) -> Option<&TreeNode> {
+        if self.path() == path {
+            return Some(self);
+        }
+
+        match self {
+            TreeNode::ExploredDir { path: dir, children } if path.starts_with(dir) => {
+                children.iter().find_map(|child| child.find(path))
+            }
+            _ => None,
+        }
+    }
+
+    fn find_mut(&mut self, path: &Path) -> Option<&mut TreeNode> {
+        if self.path() == path {
+            return Some(self);
+        }
+
+        match self {
+            TreeNode::ExploredDir { path: dir, children } if path.starts_with(&*dir) => {
+                children.iter_mut().find_map(|child| child.find_mut(path))
+            }
+            _ => None,
+        }
+    }
+
+    // Reads the directory's children, or returns `None` if its contents aren't available yet
+    // (on the web, where they are requested from the server)
     fn read_children(
         path: &Path,
         max_depth: usize,
         fs: &FileSystem,
-    ) -> eyre::Result<Vec<TreeNode>> {
+    ) -> eyre::Result<Option<Vec<TreeNode>>> {
         let err_msg = || format!("Failed to read directory: {}", path.to_string_lossy());
 
         let mut children = vec![];
-        let dir_paths = fs.read_dir(path).wrap_err_with(err_msg)?;
+        let dir_paths = match fs.read_dir(path) {
+            Ok(dir_paths) => dir_paths,
+            Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(None),
+            Err(err) => return Err(err).wrap_err_with(err_msg),
+        };
         for path in dir_paths {
             children.push(Self::new_recursive(
                 path.wrap_err_with(err_msg)?,
@@ -97,85 +197,232 @@ impl TreeNode {
             ) => Ordering::Greater,
             _ => a.name().cmp(b.name()),
         });
-        Ok(children)
+        Ok(Some(children))
     }
 
     fn ui(
         &mut self,
         ui: &mut egui::Ui,
-        highlighted: &mut Option<PathBuf>,
+        selection: &mut Selection,
+        expanded: &mut HashSet<PathBuf>,
         fs: &FileSystem,
     ) -> eyre::Result<ExplorerResponse> {
         Ok(match self {
-            TreeNode::UnexploredDir { .. } => {
-                unreachable!(); // hopefully
+            // on the web, the directory's contents are still being received from the server
+            TreeNode::UnexploredDir { path } => {
+                let response = CollapsingHeader::new(TreeNode::name_from_path(path))
+                    .id_salt(&*path)
+                    .default_open(expanded.contains(path))
+                    .show(ui, |ui| ui.weak("Loading..."));
+
+                ExplorerResponse {
+                    action: None,
+                    response: response.header_response,
+                }
             }
             TreeNode::ExploredDir { children, path } => Self::directory_ui(
                 ui,
                 TreeNode::name_from_path(path),
                 path,
                 children,
-                highlighted,
+                selection,
+                expanded,
                 fs,
             )?,
             TreeNode::File { path } => {
-                Self::file_ui(ui, TreeNode::name_from_path(path), path, highlighted)
-            } // TreeNode::NewFile { path, name } => {
-              //     self.new_file_ui(ui, path, name)
-              // }
+                Self::file_ui(ui, TreeNode::name_from_path(path), path, selection)
+            }
+            TreeNode::NewFile {
+                path,
+                name,
+                is_folder,
+            } => Self::new_file_ui(ui, path, name, *is_folder),
         })
     }
+
     fn explore(&mut self, fs: &FileSystem) -> eyre::Result<()> {
         if let TreeNode::UnexploredDir { path } = self {
-
-            let children = TreeNode::read_children(path, 1, fs)?;
-            *self = TreeNode::ExploredDir {
-                path: std::mem::take(path),
-                children,
-            };
+            // the directory stays unexplored until its contents are available
+            if let Some(children) = TreeNode::read_children(path, 1, fs)? {
+                *self = TreeNode::ExploredDir {
+                    path: std::mem::take(path),
+                    children,
+                };
+            }
         }
 
         Ok(())
     }
 
+    // adds a text box to the start of the directory for entering the name of a new file or folder
+    fn add_new_file(ui: &egui::Ui, children: &mut Vec<TreeNode>, dir: &Path, is_folder: bool) {
+        children.retain(|child| !matches!(child, TreeNode::NewFile { .. }));
+        children.insert(
+            0,
+            TreeNode::NewFile {
+                path: dir.to_owned(),
+                name: String::new(),
+                is_folder,
+            },
+        );
+        ui.memory_mut(|memory| memory.request_focus(Self::new_file_id(dir)));
+    }
+
+    fn new_file_id(dir: &Path) -> Id {
+        Id::new(("new_file", dir))
+    }
+
+    fn rename_id(path: &Path) -> Id {
+        Id::new(("rename", path))
+    }
+
+    // shows a highlight around an entry while entries are dragged over it,
+    // and gets the dragged entries once they are dropped on it
+    fn drop_target(ui: &egui::Ui, response: &Response) -> Option<Vec<PathBuf>> {
+        if response.dnd_hover_payload::<DragPayload>().is_some() {
+            ui.painter().rect_stroke(
+                response.rect,
+                2.0,
+                Stroke::new(1.0, ui.visuals().selection.stroke.color),
+                egui::StrokeKind::Inside,
+            );
+        }
+
+        response
+            .dnd_release_payload::<DragPayload>()
+            .map(|payload| payload.0.clone())
+    }
+
+    // entries are moved when they are dropped, or copied if Ctrl is held
+    fn drop_action(ui: &egui::Ui, paths: Vec<PathBuf>, to: &Path) -> ExplorerAction {
+        let to = to.to_owned();
+        if ui.input(|i| i.modifiers.command) {
+            ExplorerAction::Copy { paths, to }
+        } else {
+            ExplorerAction::Move { paths, to }
+        }
+    }
+
     fn directory_ui(
         ui: &mut egui::Ui,
         name: &str,
         path: &Path,
-        children: &mut [TreeNode],
-        highlighted: &mut Option<PathBuf>,
+        children: &mut Vec<TreeNode>,
+        selection: &mut Selection,
+        expanded: &mut HashSet<PathBuf>,
         fs: &FileSystem,
     ) -> eyre::Result<ExplorerResponse> {
-        let mut action = None;
-        let response = CollapsingHeader::new(name).show(ui, |ui| {
-            for child in children {
-                child.explore(fs)?;
-                let response = child.ui(ui, highlighted, fs)?;
+        if let Some(response) = Self::rename_ui(ui, path, selection) {
+            return Ok(response);
+        }
 
-                if let Some(child_action) = response.action {
-                    action = Some(child_action);
+        let mut action = None;
+        let mut new_file_finished = false;
+
+        // keep the directory open while a new file is being named in it
+        let naming_new_file = children
+            .iter()
+            .any(|child| matches!(child, TreeNode::NewFile { .. }));
+        let response = CollapsingHeader::new(name)
+            .id_salt(path)
+            .default_open(expanded.contains(path))
+            .open(naming_new_file.then_some(true))
+            .show_background(selection.is_selected(path))
+            .show(ui, |ui| {
+                for child in children.iter_mut() {
+                    child.explore(fs)?;
+                    let response = child.ui(ui, selection, expanded, fs)?;
+
+                    if matches!(child, TreeNode::NewFile { .. }) && response.response.lost_focus() {
+                        new_file_finished = true;
+                    }
+                    if let Some(child_action) = response.action {
+                        action = Some(child_action);
+                    }
                 }
-            }
-            Ok(())
-        });
+                Ok(())
+            });
 
-        if response.header_response.clicked() {
-            *highlighted = Some(path.to_owned());
+        if new_file_finished {
+            children.retain(|child| !matches!(child, TreeNode::NewFile { .. }));
+        }
+
+        // remember which directories are open, so that they can be reopened in the next session
+        if response.openness > 0.0 {
+            expanded.insert(path.to_owned());
+        } else {
+            expanded.remove(path);
+        }
+
+        let header = &response.header_response;
+        if header.clicked() {
+            selection.click(ui, path);
+        }
+        // the header can also be dragged (clicks still go to the header, as this only senses drags)
+        let dragged = DragPayload(selection.targets(path));
+        ui.interact(header.rect, Id::new(("explorer_drag", path)), Sense::drag())
+            .dnd_set_drag_payload(dragged);
+        if let Some(paths) = Self::drop_target(ui, header) {
+            action = Some(Self::drop_action(ui, paths, path));
         }
 
         if let Some(Err(err)) = response.body_returned {
             return Err(err);
         }
 
-        Popup::context_menu(&response.header_response).show(|ui| {
-            // if ui.button("New file").clicked() {
-            //     action = Some(ExplorerAction::NewFile(path.to_owned()));
-            // }
+        Popup::context_menu(header).show(|ui| {
+            if ui.button("New file").clicked() {
+                Self::add_new_file(ui, children, path, false);
+            }
+            if ui.button("New folder").clicked() {
+                Self::add_new_file(ui, children, path, true);
+            }
+            ui.separator();
+            if ui.button("Copy").clicked() {
+                selection.copied = selection.targets(path);
+            }
+            if ui
+                .add_enabled(!selection.copied.is_empty(), egui::Button::new("Paste"))
+                .clicked()
+            {
+                action = Some(ExplorerAction::Copy {
+                    paths: selection.copied.clone(),
+                    to: path.to_owned(),
+                });
+            }
+            if let Some(parent) = path.parent()
+                && ui.button("Duplicate").clicked()
+            {
+                action = Some(ExplorerAction::Copy {
+                    paths: vec![path.to_owned()],
+                    to: parent.to_owned(),
+                });
+            }
+            #[cfg(target_arch = "wasm32")]
+            {
+                ui.separator();
+                if ui.button("Upload files here...").clicked() {
+                    action = Some(ExplorerAction::Upload {
+                        dir: path.to_owned(),
+                        folder: false,
+                    });
+                }
+                if ui.button("Upload folder here...").clicked() {
+                    action = Some(ExplorerAction::Upload {
+                        dir: path.to_owned(),
+                        folder: true,
+                    });
+                }
+                if ui.button("Download").clicked() {
+                    action = Some(ExplorerAction::Download(path.to_owned()));
+                }
+            }
+            ui.separator();
             if ui.button("Rename").clicked() {
-                todo!();
+                selection.start_rename(ui, path);
             }
             if ui.button("Delete").clicked() {
-                action = Some(ExplorerAction::Delete(path.to_owned()));
+                action = Some(ExplorerAction::Delete(selection.targets(path)));
             }
         });
 
@@ -189,35 +436,145 @@ impl TreeNode {
         ui: &mut egui::Ui,
         name: &str,
         path: &Path,
-        highlighted: &mut Option<PathBuf>,
+        selection: &mut Selection,
     ) -> ExplorerResponse {
-        ui.scope(|ui| {
-            if highlighted.as_deref() == Some(path) {
-                ui.visuals_mut().button_frame = true;
-            }
+        if let Some(response) = Self::rename_ui(ui, path, selection) {
+            return response;
+        }
 
-            let button = ui.button(name);
+        let mut action = None;
+        let dragged = DragPayload(selection.targets(path));
 
-            if button.clicked() {
-                *highlighted = Some(path.to_owned());
+        let drag = ui.dnd_drag_source(Id::new(("explorer_drag", path)), dragged, |ui| {
+            ui.scope(|ui| {
+                if selection.is_selected(path) {
+                    ui.visuals_mut().button_frame = true;
+                }
+
+                ui.button(name)
+            })
+            .inner
+        });
+        let button = drag.inner;
+
+        if button.clicked() {
+            selection.click(ui, path);
+        }
+        if button.double_clicked() {
+            action = Some(ExplorerAction::OpenFile(path.to_owned()));
+        }
+
+        // dropping entries on a file moves them into the file's directory
+        if let Some(parent) = path.parent()
+            && let Some(paths) = Self::drop_target(ui, &button)
+        {
+            action = Some(Self::drop_action(ui, paths, parent));
+        }
+
+        Popup::context_menu(&button).show(|ui| {
+            if ui.button("Open").clicked() {
+                action = Some(ExplorerAction::OpenFile(path.to_owned()));
+            }
+            #[cfg(target_arch = "wasm32")]
+            if ui.button("Download").clicked() {
+                action = Some(ExplorerAction::Download(path.to_owned()));
+            }
+            ui.separator();
+            if ui.button("Copy").clicked() {
+                selection.copied = selection.targets(path);
+            }
+            if let Some(parent) = path.parent()
+                && ui.button("Duplicate").clicked()
+            {
+                action = Some(ExplorerAction::Copy {
+                    paths: vec![path.to_owned()],
+                    to: parent.to_owned(),
+                });
             }
+            ui.separator();
+            if ui.button("Rename").clicked() {
+                selection.start_rename(ui, path);
+            }
+            if ui.button("Delete").clicked() {
+                action = Some(ExplorerAction::Delete(selection.targets(path)));
+            }
+        });
 
-            ExplorerResponse {
-                action: button
-                    .double_clicked()
-                    .then(|| ExplorerAction::OpenFile(path.to_owned())),
-                response: button,
+        ExplorerResponse {
+            action,
+            response: button,
+        }
+    }
+
+    // Shows a text box in place of the entry if it is being renamed.
+    // It is renamed once Enter is pressed, and left as it was if the text box loses focus any other way
+    fn rename_ui(ui: &mut egui::Ui, path: &Path, selection: &mut Selection) -> Option<ExplorerResponse> {
+        let name = selection.renaming(path)?;
+        let response = ui.add(
+            TextEdit::singleline(name)
+                .id(Self::rename_id(path))
+                .hint_text("New name"),
+        );
+
+        let name = name.trim();
+        let action = (response.lost_focus()
+            && ui.input(|i| i.key_pressed(Key::Enter))
+            && !name.is_empty()
+            && name != TreeNode::name_from_path(path))
+        .then(|| ExplorerAction::Rename {
+            from: path.to_owned(),
+            to: path.with_file_name(name),
+        });
+        if response.lost_focus() {
+            selection.renaming = None;
+        }
+
+        Some(ExplorerResponse { action, response })
+    }
+
+    // text box for the name of a new file, which is created once Enter is pressed (and cancelled if it loses focus)
+    fn new_file_ui(ui: &mut egui::Ui, dir: &Path, name: &mut String, is_folder: bool) -> ExplorerResponse {
+        let hint = if is_folder { "Folder name" } else { "File name" };
+        let response = ui.add(
+            TextEdit::singleline(name)
+                .id(Self::new_file_id(dir))
+                .hint_text(hint),
+        );
+
+        let name = name.trim();
+        let action = (response.lost_focus()
+            && ui.input(|i| i.key_pressed(Key::Enter))
+            && !name.is_empty())
+        .then(|| {
+            let path = dir.join(name);
+            if is_folder {
+                ExplorerAction::NewFolder(path)
+            } else {
+                ExplorerAction::NewFile(path)
             }
-        })
-        .inner
+        });
+
+        ExplorerResponse { action, response }
     }
 }
 
 pub enum ExplorerAction {
     OpenFile(PathBuf),
-    // NewFile(PathBuf),
+    NewFile(PathBuf),
     NewFolder(PathBuf),
-    Delete(PathBuf),
+    Delete(Vec<PathBuf>),
+    /// Move the entries into the directory `to`
+    Move { paths: Vec<PathBuf>, to: PathBuf },
+    /// Give the entry at `from` a new name (`to` is in the same directory)
+    Rename { from: PathBuf, to: PathBuf },
+    /// Copy the entries into the directory `to` (which duplicates them if it is the directory they are already in)
+    Copy { paths: Vec<PathBuf>, to: PathBuf },
+    /// Choose files (or a folder) from the user's computer to upload into the directory
+    #[cfg(target_arch = "wasm32")]
+    Upload { dir: PathBuf, folder: bool },
+    /// Download the file or directory as an archive
+    #[cfg(target_arch = "wasm32")]
+    Download(PathBuf),
 }
 
 pub struct ExplorerResponse {
@@ -229,29 +586,124 @@ pub struct ExplorerResponse {
 #[derive(Debug)]
 pub struct Explorer {
     pub root_node: TreeNode,
-    pub highlighted: Option<PathBuf>,
+    pub selection: Selection,
+    /// Directories which are open in the tree
+    pub expanded: HashSet<PathBuf>,
 }
 
 impl Explorer {
     pub fn new(path: PathBuf, fs: &FileSystem) -> eyre::Result<Self> {
         Ok(Self {
             root_node: TreeNode::new(path, fs)?,
-            highlighted: None,
+            selection: Selection::default(),
+            expanded: HashSet::new(),
         })
     }
 
     pub fn root_path(&self) -> &Path {
         match &self.root_node {
             TreeNode::UnexploredDir { path } | TreeNode::ExploredDir { path, .. } => path,
-            TreeNode::File { .. } => panic!("explorer root node isn't a directory"),
+            TreeNode::File { .. } | TreeNode::NewFile { .. } => {
+                panic!("explorer root node isn't a directory")
+            }
+        }
+    }
+
+    // Rebuilds the tree after files have been changed, keeping the selected entries which still exist
+    pub fn refresh(&mut self, fs: &FileSystem) -> eyre::Result<()> {
+        self.root_node = TreeNode::new(self.root_path().to_owned(), fs)?;
+
+        let root_node = &self.root_node;
+        self.selection
+            .paths
+            .retain(|path| root_node.find(path).is_some());
+
+        Ok(())
+    }
+
+    // Whether the path is listed in the explorer (only the directories which have been explored are checked)
+    pub fn is_listed(&self, path: &Path) -> bool {
+        self.root_node.find(path).is_some()
+    }
+
+    // Whether the path is in an explored directory, but isn't listed in it (so the explorer is out of date)
+    pub fn is_missing(&self, path: &Path) -> bool {
+        let Some(TreeNode::ExploredDir { children, .. }) =
+            path.parent().and_then(|parent| self.root_node.find(parent))
+        else {
+            return false;
+        };
+
+        !children.iter().any(|child| child.path() == path)
+    }
+
+    // Gets a path for an entry named `name` in the directory `dir` which isn't already used,
+    // adding "copy" to the name if it is (e.g. `main copy.py`, then `main copy 2.py`)
+    pub fn unused_path(&self, dir: &Path, name: &str) -> PathBuf {
+        let name_path = Path::new(name);
+        let stem = name_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy())
+            .unwrap_or_default();
+        let extension = name_path
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_default();
+
+        let mut path = dir.join(name);
+        let mut copies = 0;
+        while self.root_node.find(&path).is_some() {
+            copies += 1;
+            let copy = if copies == 1 {
+                format!("{stem} copy{extension}")
+            } else {
+                format!("{stem} copy {copies}{extension}")
+            };
+            path = dir.join(copy);
+        }
+
+        path
+    }
+
+    // The directory new files are added to (e.g. when created or uploaded):
+    // the selected directory, or the directory of the selected file
+    pub fn target_dir(&self) -> PathBuf {
+        let selected = self
+            .selection
+            .paths
+            .last()
+            .and_then(|path| self.root_node.find(path));
+
+        match selected {
+            Some(TreeNode::ExploredDir { path, .. }) => path.clone(),
+            Some(node) => node
+                .path()
+                .parent()
+                .map_or_else(|| self.root_path().to_owned(), Path::to_owned),
+            None => self.root_path().to_owned(),
         }
     }
 
     pub fn show(&mut self, ui: &mut egui::Ui, fs: &FileSystem) -> eyre::Result<ExplorerResponse> {
+        ui.horizontal(|ui| {
+            let new_file = ui.small_button("+ File").on_hover_text("New file");
+            let new_folder = ui.small_button("+ Folder").on_hover_text("New folder");
+
+            if new_file.clicked() || new_folder.clicked() {
+                let dir = self.target_dir();
+                if let Some(TreeNode::ExploredDir { children, .. }) = self.root_node.find_mut(&dir)
+                {
+                    TreeNode::add_new_file(ui, children, &dir, new_folder.clicked());
+                }
+            }
+        });
+        ui.separator();
+
         let explorer = ScrollArea::vertical()
             .show(ui, |ui| {
                 ui.style_mut().visuals.button_frame = false;
-                self.root_node.ui(ui, &mut self.highlighted, fs)
+                self.root_node
+                    .ui(ui, &mut self.selection, &mut self.expanded, fs)
             })
             .inner?;
 
@@ -0,0 +1,69 @@
+// Fuzzy matching used to filter and rank lists of items (e.g. file paths) by a short pattern typed by the user
+
+// bonus for a matched character directly after the previous matched character
+const CONSECUTIVE_BONUS: i32 = 5;
+// bonus for a matched character at the start of a word (e.g. after `/`, `_` or a lowercase letter followed by a capital)
+const WORD_START_BONUS: i32 = 8;
+// penalty for each character skipped between matched characters
+const GAP_PENALTY: i32 = 1;
+
+fn is_separator(c: char) -> bool {
+    matches!(c, '/' | '\\' | '_' | '-' | '.' | ' ')
+}
+
+// Scores how well `pattern` matches `text`, or returns `None` if it doesn't match.
+// Every character of the pattern must appear in the text in the same order (ignoring case),
+// and higher scores are given when they are consecutive or at the start of words.
+pub fn score(pattern: &str, text: &str) -> Option<i32> {
+    let mut pattern_chars = pattern.chars().filter(|c| !c.is_whitespace()).peekable();
+    let mut score = 0;
+    let mut prev: Option<char> = None;
+    // index of the last matched character in the text
+    let mut last_match: Option<usize> = None;
+
+    for (i, c) in text.chars().enumerate() {
+        let Some(&p) = pattern_chars.peek() else {
+            break;
+        };
+
+        if c.to_lowercase().eq(p.to_lowercase()) {
+            score += 1;
+
+            let word_start = match prev {
+                None => true,
+                Some(prev) => is_separator(prev) || (prev.is_lowercase() && c.is_uppercase()),
+            };
+            if word_start {
+                score += WORD_START_BONUS;
+            }
+
+            match last_match {
+                Some(last) if last + 1 == i => score += CONSECUTIVE_BONUS,
+                Some(last) => score -= GAP_PENALTY * (i - last - 1) as i32,
+                None => {}
+            }
+
+            last_match = Some(i);
+            pattern_chars.next();
+        }
+
+        prev = Some(c);
+    }
+
+    // the pattern only matches if all of its characters were found
+    pattern_chars.peek().is_none().then_some(score)
+}
+
+// Filters the items down to those matching the pattern, sorted from best to worst match.
+// Items with equal scores keep their original order, and all items are kept if the pattern is empty.
+pub fn filter<T>(pattern: &str, items: impl IntoIterator<Item = T>, key: impl Fn(&T) -> &str) -> Vec<T> {
+    let mut scored: Vec<_> = items
+        .into_iter()
+        .filter_map(|item| score(pattern, key(&item)).map(|score| (score, item)))
+        .collect();
+
+    // `sort_by` is stable, so equal scores stay in the same order
+    scored.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    scored.into_iter().map(|(_, item)| item).collect()
+}
@@ -0,0 +1,78 @@
+// The history window, which lists the commits that changed a file,
+// and lets the file be opened as it was at any of them
+
+use std::path::PathBuf;
+
+use egui::{RichText, ScrollArea};
+use ws_messages::CommitInfo;
+
+pub enum HistoryAction {
+    /// Open the file as it was at the commit with this SHA hash
+    ViewFile(String),
+}
+
+pub struct FileHistory {
+    /// The file whose history is shown
+    pub path: PathBuf,
+    /// Commits which changed the file, newest first (`None` until they have been loaded)
+    commits: Option<Vec<CommitInfo>>,
+}
+
+impl FileHistory {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            commits: None,
+        }
+    }
+
+    pub fn set_commits(&mut self, commits: Vec<CommitInfo>) {
+        self.commits = Some(commits);
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.commits.is_none()
+    }
+
+    // shows the window, returning whether it is still open along with anything the user chose to do
+    pub fn show(&mut self, ctx: &egui::Context) -> (bool, Option<HistoryAction>) {
+        let mut open = true;
+        let mut action = None;
+
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        egui::Window::new(format!("History of {name}"))
+            .id(egui::Id::new("file_history"))
+            .open(&mut open)
+            .default_size([500.0, 400.0])
+            .show(ctx, |ui| {
+                let Some(commits) = &self.commits else {
+                    ui.weak("Loading history...");
+                    return;
+                };
+                if commits.is_empty() {
+                    ui.weak("The file hasn't been committed yet");
+                    return;
+                }
+
+                ScrollArea::vertical().auto_shrink(false).show(ui, |ui| {
+                    for commit in commits {
+                        ui.horizontal(|ui| {
+                            if ui.button("View").on_hover_text("Open the file as it was after this commit").clicked() {
+                                action = Some(HistoryAction::ViewFile(commit.sha.clone()));
+                            }
+                            ui.label(RichText::new(&commit.sha[..commit.sha.len().min(7)]).monospace());
+                            // only the first line of the message (its summary) is shown, with the rest when hovered
+                            let summary = commit.message.lines().next().unwrap_or_default();
+                            ui.label(summary).on_hover_text(&commit.message);
+                        });
+                        // the date is shown without the time of day
+                        let date = commit.date.get(..10).unwrap_or(&commit.date);
+                        ui.weak(format!("{} on {date}", commit.author));
+                        ui.separator();
+                    }
+                });
+            });
+
+        (open, action)
+    }
+}
@@ -5,8 +5,14 @@ pub use app::App;
 mod app;
 mod buffer;
 mod color_scheme;
+mod commands;
+mod diff;
 mod explorer;
+mod fuzzy;
+mod history;
+mod markdown;
 mod platform;
+mod source_control;
 
 #[cfg(target_arch = "wasm32")]
 use {
@@ -34,6 +40,12 @@ pub async fn start() {
             panic!("invalid path");
         }
 
+        // the project can be opened at a branch, tag or commit with `?ref=...`
+        let search = window.location().search().unwrap_or_default();
+        let git_ref = web_sys::UrlSearchParams::new_with_str(&search)
+            .ok()
+            .and_then(|params| params.get("ref"));
+
         let document = window.document().expect("no document");
         let canvas = document
             .get_element_by_id("canvas")
@@ -41,10 +53,17 @@ pub async fn start() {
             .dyn_into::<web_sys::HtmlCanvasElement>()
             .expect("element with id canvas was not a HtmlCanvasElement");
 
-        let new_app = app::App::new(user, repo).await;
+        let new_app = app::App::new(user, repo, git_ref).await;
 
         let result = eframe::WebRunner::new()
-            .start(canvas, options, Box::new(move |_| Ok(Box::new(new_app))))
+            .start(
+                canvas,
+                options,
+                Box::new(move |cc| {
+                    egui_extras::install_image_loaders(&cc.egui_ctx);
+                    Ok(Box::new(new_app))
+                }),
+            )
             .await;
 
         if let Some(loading_text) = document.get_element_by_id("loading_text") {
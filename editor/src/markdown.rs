@@ -0,0 +1,276 @@
+// Renders Markdown files (e.g. a project's README) as formatted text, for the preview of Markdown buffers
+
+use egui::{Id, Response, RichText, ScrollArea, Ui};
+use egui_extras::syntax_highlighting::{self, CodeTheme};
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+
+// how far each level of nested lists and block quotes is indented
+const INDENT: f32 = 16.0;
+
+/// A piece of text in a paragraph, with its formatting
+enum Span {
+    Text(RichText),
+    Link { text: RichText, url: String },
+    LineBreak,
+}
+
+/// Formatting applied to the text inside of inline tags (e.g. `**bold**`)
+#[derive(Default)]
+struct InlineStyle {
+    strong: usize,
+    emphasis: usize,
+    strikethrough: usize,
+    heading: Option<HeadingLevel>,
+    link: Option<String>,
+}
+
+struct Renderer<'a> {
+    theme: &'a CodeTheme,
+    style: InlineStyle,
+    /// Text of the paragraph (or heading or list item) being built up
+    spans: Vec<Span>,
+    /// The lists the current item is in, with the number of the next item in each list (`None` if it is a bullet list)
+    lists: Vec<Option<u64>>,
+    /// Marker to show before the next paragraph (e.g. "•" or "1."), when it is the start of a list item
+    item_marker: Option<String>,
+    quote_depth: usize,
+    /// Language and contents of the code block being read (if inside one)
+    code_block: Option<(String, String)>,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(theme: &'a CodeTheme) -> Self {
+        Self {
+            theme,
+            style: InlineStyle::default(),
+            spans: vec![],
+            lists: vec![],
+            item_marker: None,
+            quote_depth: 0,
+            code_block: None,
+        }
+    }
+
+    fn indent(&self) -> f32 {
+        (self.lists.len() + self.quote_depth) as f32 * INDENT
+    }
+
+    fn rich_text(&self, text: &str) -> RichText {
+        let mut rich_text = RichText::new(text);
+
+        if let Some(level) = self.style.heading {
+            let size = match level {
+                HeadingLevel::H1 => 26.0,
+                HeadingLevel::H2 => 22.0,
+                HeadingLevel::H3 => 18.0,
+                _ => 16.0,
+            };
+            rich_text = rich_text.size(size).strong();
+        }
+        if self.style.strong > 0 {
+            rich_text = rich_text.strong();
+        }
+        if self.style.emphasis > 0 {
+            rich_text = rich_text.italics();
+        }
+        if self.style.strikethrough > 0 {
+            rich_text = rich_text.strikethrough();
+        }
+        if self.quote_depth > 0 {
+            rich_text = rich_text.weak();
+        }
+
+        rich_text
+    }
+
+    fn push_text(&mut self, text: &str) {
+        // each word is a separate label, so that the paragraph wraps between words
+        for word in text.split_inclusive(' ') {
+            let rich_text = self.rich_text(word);
+            self.spans.push(match &self.style.link {
+                Some(url) => Span::Link {
+                    text: rich_text,
+                    url: url.clone(),
+                },
+                None => Span::Text(rich_text),
+            });
+        }
+    }
+
+    // shows the paragraph that has been built up (if there is one)
+    fn finish_paragraph(&mut self, ui: &mut Ui) {
+        let marker = self.item_marker.take();
+        if self.spans.is_empty() && marker.is_none() {
+            return;
+        }
+
+        let spans = std::mem::take(&mut self.spans);
+        let indent = self.indent();
+        ui.horizontal_top(|ui| {
+            ui.add_space(indent);
+            if let Some(marker) = marker {
+                ui.label(marker);
+            }
+
+            ui.horizontal_wrapped(|ui| {
+                // the spaces between words are part of the words themselves
+                ui.spacing_mut().item_spacing.x = 0.0;
+                for span in spans {
+                    match span {
+                        Span::Text(text) => {
+                            ui.label(text);
+                        }
+                        Span::Link { text, url } => {
+                            ui.hyperlink_to(text, url);
+                        }
+                        Span::LineBreak => ui.end_row(),
+                    }
+                }
+            });
+        });
+    }
+
+    fn start(&mut self, ui: &mut Ui, tag: Tag) {
+        match tag {
+            Tag::Heading { level, .. } => {
+                self.finish_paragraph(ui);
+                self.style.heading = Some(level);
+            }
+            Tag::BlockQuote(_) => {
+                self.finish_paragraph(ui);
+                self.quote_depth += 1;
+            }
+            Tag::CodeBlock(kind) => {
+                self.finish_paragraph(ui);
+                let language = match kind {
+                    CodeBlockKind::Fenced(language) => language.to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code_block = Some((language, String::new()));
+            }
+            Tag::List(start) => {
+                // a list nested in an item comes after the item's own text
+                self.finish_paragraph(ui);
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.finish_paragraph(ui);
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}.", *number - 1)
+                    }
+                    _ => "•".to_string(),
+                };
+                self.item_marker = Some(marker);
+            }
+            Tag::Emphasis => self.style.emphasis += 1,
+            Tag::Strong => self.style.strong += 1,
+            Tag::Strikethrough => self.style.strikethrough += 1,
+            Tag::Link { dest_url, .. } => self.style.link = Some(dest_url.to_string()),
+            // images would have to be downloaded to be shown, so they are shown as links to the image instead
+            Tag::Image { dest_url, .. } => {
+                self.style.link = Some(dest_url.to_string());
+                self.push_text("🖼 ");
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, ui: &mut Ui, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph => {
+                self.finish_paragraph(ui);
+                ui.add_space(ui.spacing().item_spacing.y);
+            }
+            TagEnd::Heading(level) => {
+                self.finish_paragraph(ui);
+                self.style.heading = None;
+                if level <= HeadingLevel::H2 {
+                    ui.separator();
+                }
+            }
+            TagEnd::BlockQuote(_) => {
+                self.finish_paragraph(ui);
+                self.quote_depth -= 1;
+            }
+            TagEnd::CodeBlock => {
+                if let Some((language, code)) = self.code_block.take() {
+                    self.show_code_block(ui, &language, &code);
+                }
+            }
+            TagEnd::List(_) => {
+                self.finish_paragraph(ui);
+                self.lists.pop();
+            }
+            TagEnd::Item => self.finish_paragraph(ui),
+            TagEnd::Emphasis => self.style.emphasis -= 1,
+            TagEnd::Strong => self.style.strong -= 1,
+            TagEnd::Strikethrough => self.style.strikethrough -= 1,
+            TagEnd::Link | TagEnd::Image => self.style.link = None,
+            _ => {}
+        }
+    }
+
+    fn show_code_block(&self, ui: &mut Ui, language: &str, code: &str) {
+        let indent = self.indent();
+        ui.horizontal_top(|ui| {
+            ui.add_space(indent);
+            egui::Frame::group(ui.style())
+                .fill(ui.visuals().extreme_bg_color)
+                .show(ui, |ui| {
+                    // code blocks without a language are still shown in a monospace font
+                    let language = if language.is_empty() { "txt" } else { language };
+                    syntax_highlighting::code_view_ui(
+                        ui,
+                        self.theme,
+                        code.trim_end_matches('\n'),
+                        language,
+                    );
+                });
+        });
+    }
+
+    fn event(&mut self, ui: &mut Ui, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(ui, tag),
+            Event::End(tag) => self.end(ui, tag),
+            Event::Text(text) => match &mut self.code_block {
+                Some((_, code)) => code.push_str(&text),
+                None => self.push_text(&text),
+            },
+            Event::Code(code) => self
+                .spans
+                .push(Span::Text(RichText::new(code.as_ref()).code())),
+            Event::Html(html) | Event::InlineHtml(html) => self.push_text(&html),
+            Event::SoftBreak => self.push_text(" "),
+            Event::HardBreak => self.spans.push(Span::LineBreak),
+            Event::Rule => {
+                self.finish_paragraph(ui);
+                ui.separator();
+            }
+            Event::TaskListMarker(checked) => self.push_text(if checked { "☑ " } else { "☐ " }),
+            _ => {}
+        }
+    }
+}
+
+// Shows Markdown text rendered with egui widgets (headings, lists, links, code blocks etc.)
+pub fn show(ui: &mut Ui, id: Id, text: &str, theme: &CodeTheme) -> Response {
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+
+    ui.scope(|ui| {
+        ScrollArea::vertical()
+            .id_salt(id)
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                let mut renderer = Renderer::new(theme);
+                for event in Parser::new_ext(text, options) {
+                    renderer.event(ui, event);
+                }
+                // text at the end of the file might not be in a closed paragraph
+                renderer.finish_paragraph(ui);
+            });
+    })
+    .response
+}
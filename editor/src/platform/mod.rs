@@ -5,6 +5,9 @@ use std::{
 };
 use serde::Deserialize;
 use thiserror::Error;
+use ws_messages::{CommitInfo, GitBranches, GitDiff, GitFileStatus, SearchMatch};
+
+pub use ws_messages::ReplaceChange;
 
 #[cfg(not(target_arch = "wasm32"))]
 pub use native::*;
@@ -19,11 +22,44 @@ mod web;
 #[derive(Default, Debug, Deserialize)]
 pub struct ProjectSettings {
     pub run_command: String,
-    pub format_command: Option<String>
+    pub format_command: Option<String>,
+    /// Command to format a single file, where `{file}` is replaced with the file's path.
+    /// This is used instead of `format_command` when formatting files as they are saved
+    pub format_file_command: Option<String>,
+    /// Formatter which reads a file's contents from stdin and writes the formatted contents to stdout (e.g. `black -`).
+    /// `{file}` is replaced with the file's path, for formatters that need it to choose how to format the contents
+    pub format_stdin: Option<String>,
+    /// Globs of files to skip when searching or listing the project's files (on top of `.gitignore`)
+    #[serde(default)]
+    pub ignore: Vec<String>,
 }
 
 impl ProjectSettings {
     const PATH: &str = ".ide/project.toml";
+
+    // Gets the commands to run to format the given files.
+    // If there is no command to format a single file (or no files are given), the whole project is formatted instead
+    pub fn format_commands(&self, files: &[PathBuf]) -> Vec<String> {
+        match &self.format_file_command {
+            Some(command) if !files.is_empty() => files
+                .iter()
+                .map(|file| Self::with_file(command, file))
+                .collect(),
+            _ => self.format_command.iter().cloned().collect(),
+        }
+    }
+
+    // Gets the stdin formatter command for the given file, if the project has one
+    pub fn format_stdin_command(&self, file: &Path) -> Option<String> {
+        self.format_stdin
+            .as_ref()
+            .map(|command| Self::with_file(command, file))
+    }
+
+    // replaces `{file}` in a command with the (quoted) path of the file
+    fn with_file(command: &str, file: &Path) -> String {
+        command.replace("{file}", &shell_words::quote(&file.to_string_lossy()))
+    }
 }
 
 #[derive(Debug, Error)]
@@ -36,28 +72,148 @@ pub enum ProjectSettingsError {
 
 pub trait RunnerTrait {
     fn run(&mut self, project: &mut Project, output: Arc<Mutex<String>>) -> eyre::Result<()>;
-    fn format(&mut self, project: &mut Project) -> eyre::Result<()>;
+    /// Formats the given files (or the whole project if `files` is empty) with the project's formatter
+    fn format(&mut self, project: &mut Project, files: &[PathBuf]) -> eyre::Result<()>;
+    /// Formats the contents of a file by passing them through the project's stdin formatter (`format_stdin`).
+    /// Returns the formatted contents, or `None` if there is no stdin formatter.
+    /// On the web, `None` is always returned as the formatted contents are received later
+    /// (and the file is formatted on disk instead if there is no stdin formatter)
+    fn format_contents(
+        &mut self,
+        project: &mut Project,
+        path: &Path,
+        contents: &str,
+    ) -> eyre::Result<Option<String>>;
     fn stop(&mut self);
     fn update(&mut self);
     fn is_running(&self) -> bool;
 }
 
+pub trait FileWatcherTrait {
+    /// Gets the files that have been created or changed outside of the editor since this was last called.
+    /// On the web, this polls the server and returns `ErrorKind::WouldBlock`, as the changes are received later
+    fn changed_files(&mut self) -> io::Result<Vec<PathBuf>>;
+}
+
+/// Source control for the project's git repository (paths are relative to the project root).
+/// On the web, these send a command (or request) to the server and return `ErrorKind::WouldBlock`,
+/// as the results are received later
+pub trait GitTrait {
+    /// Lists the files which have changed since the last commit
+    fn status(&self) -> io::Result<Vec<GitFileStatus>>;
+    /// Reads a file at the last commit and in the working tree, to show the changes made to it
+    fn diff(&self, path: &Path) -> io::Result<GitDiff>;
+    fn stage(&self, paths: &[PathBuf]) -> io::Result<()>;
+    fn unstage(&self, paths: &[PathBuf]) -> io::Result<()>;
+    /// Commits the staged changes.
+    /// On the web, they are committed to the current branch of the project's GitHub repo
+    fn commit(&mut self, message: &str) -> io::Result<()>;
+    fn branches(&mut self) -> io::Result<GitBranches>;
+    /// Creates a branch from the current commit, and switches to it
+    fn create_branch(&mut self, name: &str) -> io::Result<()>;
+    /// Switches to another branch, changing the project's files to the files on that branch
+    fn switch_branch(&mut self, name: &str) -> io::Result<()>;
+    /// Lists the newest commits on the current branch which changed the file, newest first
+    fn history(&mut self, path: &Path) -> io::Result<Vec<CommitInfo>>;
+    /// Reads a file as it was at a commit
+    fn file_at(&mut self, sha: &str, path: &Path) -> io::Result<Vec<u8>>;
+    /// Gets the result of a request made on the web once it has finished (always `None` on desktop)
+    fn finished(&mut self) -> Option<io::Result<GitOutput>>;
+}
+
+/// The result of a git operation which finishes later on the web
+#[derive(Clone, Debug)]
+pub enum GitOutput {
+    Committed,
+    /// Every change was saved to GitHub
+    #[cfg(target_arch = "wasm32")]
+    Saved,
+    Branches(GitBranches),
+    /// A branch with this name was created
+    CreatedBranch(String),
+    /// The project was switched to the branch with this name
+    SwitchedBranch(String),
+    /// The commits which changed a file
+    History(Vec<CommitInfo>),
+    /// The contents of a file (relative to the project root) at the commit with the SHA hash `sha`
+    FileAtCommit {
+        sha: String,
+        path: PathBuf,
+        contents: Vec<u8>,
+    },
+    /// The changes made on GitHub were merged into the project
+    #[cfg(target_arch = "wasm32")]
+    Merged(ws_messages::GitMerge),
+}
+
+/// A match found when searching the project (`line` and `col` start from 1)
 #[derive(Clone, Debug)]
 pub struct SearchResult {
     pub path: PathBuf,
     pub line: usize,
     pub col: usize,
+    /// The line of text containing the match
+    pub preview: String,
+}
+
+impl From<SearchMatch> for SearchResult {
+    fn from(m: SearchMatch) -> Self {
+        Self {
+            path: m.path,
+            line: m.line,
+            col: m.col,
+            preview: m.preview,
+        }
+    }
+}
+
+/// What to search for in a project, and how to match it
+#[derive(Clone, Debug, Default)]
+pub struct SearchQuery {
+    /// Number of the search, which goes up for each new search (to ignore results from earlier searches)
+    #[cfg(target_arch = "wasm32")]
+    pub search_id: u32,
+    pub pattern: String,
+    /// Whether `pattern` is a regular expression (rather than plain text)
+    pub regex: bool,
+    pub case_sensitive: bool,
+    /// Only match the pattern when it isn't part of a larger word
+    pub whole_word: bool,
+    /// Only search files with names matching this glob (e.g. `*.py`)
+    pub glob: Option<String>,
 }
 
 pub trait FileSystemTrait {
     type ReadDir: Iterator<Item = io::Result<PathBuf>>;
 
-    // fn new_file(&self, path: &Path) -> io::Result<()>;
+    /// Creates an empty file, failing if the path already exists
+    fn new_file(&self, path: &Path) -> io::Result<()>;
+    /// Creates an empty directory, failing if the path already exists
+    fn new_folder(&self, path: &Path) -> io::Result<()>;
     fn read_file(&self, path: &Path) -> io::Result<String>;
+    /// Reads a file without assuming it is text (e.g. an image)
+    fn read_bytes(&self, path: &Path) -> io::Result<Vec<u8>>;
     fn read_dir(&self, path: &Path) -> io::Result<Self::ReadDir>;
-    fn search_project(&self, project: &Project, pattern: &str) -> Vec<SearchResult>;
-    fn replace(&self, paths: &[&Path], pattern: &str, replace: &str) -> io::Result<()>;
+    /// Lists the paths of every file in the project
+    fn list_files(&self, project: &Project) -> io::Result<Vec<PathBuf>>;
+    fn search_project(&self, project: &Project, query: &SearchQuery) -> io::Result<Vec<SearchResult>>;
+    fn preview_replace(
+        &self,
+        project: &Project,
+        query: &SearchQuery,
+        replacement: &str,
+    ) -> io::Result<Vec<ReplaceChange>>;
+    /// Replaces only the given matches (from `preview_replace`), leaving any others unchanged
+    fn replace(
+        &self,
+        project: &Project,
+        query: &SearchQuery,
+        replacement: &str,
+        changes: &[ReplaceChange],
+    ) -> io::Result<()>;
     fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    /// Copies a file, or a directory along with everything in it, failing if `to` already exists
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()>;
     fn write(&self, path: &Path, contents: &str) -> io::Result<()>;
     fn delete(&self, path: &Path) -> io::Result<()>;
 }
@@ -1,65 +1,181 @@
 use std::{
+    collections::HashMap,
     fs,
-    io::Result,
+    io::{Error, ErrorKind, Result},
     path::{Path, PathBuf},
 };
 
-use walkdir::WalkDir;
+use ignore::{WalkBuilder, overrides::OverrideBuilder};
+use regex::Regex;
+use ws_messages::search;
 
-use crate::platform::{FileSystemTrait, Project, SearchResult};
+use crate::platform::{
+    FileSystemTrait, Project, ProjectSettings, ReplaceChange, SearchQuery, SearchResult,
+};
 
 #[derive(Default)]
 pub struct FileSystem;
 
 impl FileSystem {
-    fn get_line_col(contents: &str, offset: usize) -> (usize, usize) {
-        let line = contents[..offset].lines().count();
-        let col = offset - contents[..offset].rfind('\n').unwrap_or(0);
+    // number of bytes at the start of a file which are checked for null bytes to tell if it is binary (the same as git)
+    const BINARY_CHECK_LEN: usize = 8000;
 
-        (line, col)
+    fn search_regex(query: &SearchQuery) -> Result<Regex> {
+        search::search_regex(&query.pattern, query.regex, query.case_sensitive, query.whole_word)
+            .map_err(|err| Error::new(ErrorKind::InvalidInput, err))
+    }
+
+    // gets the paths of the files in the project which match the glob (if there is one),
+    // skipping any files ignored by a `.gitignore` or by the ignore list in the project settings
+    fn walk_project(project: &Project, glob: Option<&str>) -> Result<impl Iterator<Item = PathBuf>> {
+        let to_io_error = |err| Error::new(ErrorKind::InvalidInput, err);
+
+        let ignored = ProjectSettings::read_from(&project.path)
+            .ok()
+            .flatten()
+            .map(|settings| settings.ignore)
+            .unwrap_or_default();
+
+        let mut overrides = OverrideBuilder::new(&project.path);
+        if let Some(glob) = glob {
+            overrides.add(glob).map_err(to_io_error)?;
+        }
+        // globs starting with `!` exclude files rather than include them
+        for glob in ["/.git/"].into_iter().map(String::from).chain(ignored) {
+            overrides.add(&format!("!{glob}")).map_err(to_io_error)?;
+        }
+        let overrides = overrides.build().map_err(to_io_error)?;
+
+        let files = WalkBuilder::new(&project.path)
+            .hidden(false)
+            // use .gitignore files even if the project isn't a git repository
+            .require_git(false)
+            .overrides(overrides)
+            .build()
+            .filter_map(|e| e.ok())
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+            .map(|entry| entry.into_path());
+
+        Ok(files)
+    }
+
+    // gets the paths and contents of the text files in the project which match the query's glob
+    fn project_files(
+        project: &Project,
+        query: &SearchQuery,
+    ) -> Result<impl Iterator<Item = (PathBuf, String)>> {
+        let files = Self::walk_project(project, query.glob.as_deref())?.filter_map(|path| {
+            let bytes = fs::read(&path).ok()?;
+            if bytes[..bytes.len().min(Self::BINARY_CHECK_LEN)].contains(&0) {
+                return None;
+            }
+            let contents = String::from_utf8(bytes).ok()?;
+
+            Some((path, contents))
+        });
+
+        Ok(files)
     }
 }
 
 impl FileSystemTrait for FileSystem {
     type ReadDir = ReadDir;
 
+    fn new_file(&self, path: &Path) -> Result<()> {
+        fs::File::create_new(path).map(|_| ())
+    }
+
+    fn new_folder(&self, path: &Path) -> Result<()> {
+        fs::create_dir(path)
+    }
+
     fn read_file(&self, path: &Path) -> Result<String> {
         fs::read_to_string(path)
     }
 
+    fn read_bytes(&self, path: &Path) -> Result<Vec<u8>> {
+        fs::read(path)
+    }
+
     fn read_dir(&self, path: &Path) -> Result<ReadDir> {
         fs::read_dir(path).map(ReadDir)
     }
 
-    fn search_project(&self, project: &Project, pattern: &str) -> Vec<SearchResult> {
+    fn list_files(&self, project: &Project) -> Result<Vec<PathBuf>> {
+        Ok(Self::walk_project(project, None)?.collect())
+    }
+
+    fn search_project(&self, project: &Project, query: &SearchQuery) -> Result<Vec<SearchResult>> {
         let mut results = Vec::new();
-        for entry in WalkDir::new(&project.path)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            if let Ok(contents) = fs::read_to_string(entry.path()) {
-                let mut start_index = 0;
-                while let Some(pos) = contents[start_index..].find(pattern) {
-                    let byte_offset = start_index + pos;
-                    let (line, col) = Self::get_line_col(&contents, byte_offset);
+        if query.pattern.is_empty() {
+            return Ok(results);
+        }
+        let re = Self::search_regex(query)?;
 
+        for (path, contents) in Self::project_files(project, query)? {
+            for (i, line) in contents.lines().enumerate() {
+                for m in re.find_iter(line) {
                     results.push(SearchResult {
-                        path: entry.path().to_path_buf(),
-                        line,
-                        col,
+                        path: path.clone(),
+                        line: i + 1,
+                        col: line[..m.start()].chars().count() + 1,
+                        preview: line.to_string(),
                     });
-
-                    start_index = byte_offset + pattern.len();
                 }
             }
         }
-        results
+
+        Ok(results)
     }
 
-    fn replace(&self, paths: &[&Path], pattern: &str, replace: &str) -> Result<()> {
-        for path in paths {
-            let contents = fs::read_to_string(path)?.replace(pattern, replace);
-            fs::write(path, contents)?;
+    fn preview_replace(
+        &self,
+        project: &Project,
+        query: &SearchQuery,
+        replacement: &str,
+    ) -> Result<Vec<ReplaceChange>> {
+        let re = Self::search_regex(query)?;
+
+        let changes = self
+            .search_project(project, query)?
+            .into_iter()
+            .map(|res| ReplaceChange {
+                after: search::replace_matches(&re, &res.preview, replacement, query.regex, &[res.col]),
+                path: res.path,
+                line: res.line,
+                col: res.col,
+                before: res.preview,
+            })
+            .collect();
+
+        Ok(changes)
+    }
+
+    fn replace(
+        &self,
+        _project: &Project,
+        query: &SearchQuery,
+        replacement: &str,
+        changes: &[ReplaceChange],
+    ) -> Result<()> {
+        let re = Self::search_regex(query)?;
+
+        // the columns of the selected matches on each line of each file
+        let mut selected: HashMap<&Path, HashMap<usize, Vec<usize>>> = HashMap::new();
+        for change in changes {
+            selected
+                .entry(&change.path)
+                .or_default()
+                .entry(change.line)
+                .or_default()
+                .push(change.col);
+        }
+
+        for (path, lines) in selected {
+            let contents = fs::read_to_string(path)?;
+            let new_contents = search::replace_in_contents(&re, &contents, replacement, query.regex, &lines);
+
+            fs::write(path, new_contents)?;
         }
 
         Ok(())
@@ -69,6 +185,33 @@ impl FileSystemTrait for FileSystem {
         fs::rename(from, to)
     }
 
+    fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        if to.exists() {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("{} already exists", to.display()),
+            ));
+        }
+        // otherwise the copy would keep copying itself
+        if to.starts_with(from) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "can't copy a directory into itself",
+            ));
+        }
+
+        if from.is_dir() {
+            fs::create_dir(to)?;
+            for entry in fs::read_dir(from)? {
+                let entry = entry?;
+                self.copy(&entry.path(), &to.join(entry.file_name()))?;
+            }
+            Ok(())
+        } else {
+            fs::copy(from, to).map(|_| ())
+        }
+    }
+
     fn write(&self, path: &Path, contents: &str) -> Result<()> {
         fs::write(path, contents)
     }
@@ -0,0 +1,166 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use ws_messages::{CommitInfo, GitBranches, GitDiff, GitFileStatus};
+
+use crate::platform::{GitOutput, GitTrait};
+
+/// Runs git in the project's local repository (paths are relative to the project root)
+pub struct Git {
+    root: PathBuf,
+}
+
+impl Git {
+    /// How many commits are listed in a file's history
+    const HISTORY_LENGTH: usize = 100;
+
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new("git");
+        command.current_dir(&self.root);
+        command
+    }
+
+    // git always separates the parts of a path in a commit with `/`
+    fn commit_path(path: &Path) -> String {
+        path.to_string_lossy().replace('\\', "/")
+    }
+
+    // runs the command until it finishes, returning its output if it succeeds (or its error message if not)
+    fn output(command: &mut Command) -> io::Result<Vec<u8>> {
+        let output = command.output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+
+        Ok(output.stdout)
+    }
+}
+
+impl GitTrait for Git {
+    fn status(&self) -> io::Result<Vec<GitFileStatus>> {
+        let output = Self::output(self.command().args(GitFileStatus::STATUS_ARGS))?;
+
+        Ok(GitFileStatus::parse_status(&String::from_utf8_lossy(&output)))
+    }
+
+    fn diff(&self, path: &Path) -> io::Result<GitDiff> {
+        let head_path = format!("HEAD:{}", Self::commit_path(path));
+        let head = Self::output(self.command().args(["show", &head_path])).ok();
+        let working = fs::read(self.root.join(path)).ok();
+
+        Ok(GitDiff {
+            head: head.map(|bytes| String::from_utf8_lossy(&bytes).into_owned()),
+            working: working.map(|bytes| String::from_utf8_lossy(&bytes).into_owned()),
+        })
+    }
+
+    fn stage(&self, paths: &[PathBuf]) -> io::Result<()> {
+        // `-A` stages deleted files as well
+        Self::output(self.command().args(["add", "-A", "--"]).args(paths))?;
+
+        Ok(())
+    }
+
+    fn unstage(&self, paths: &[PathBuf]) -> io::Result<()> {
+        Self::output(self.command().args(["reset", "-q", "--"]).args(paths))?;
+
+        Ok(())
+    }
+
+    // the commit is only made in the local repository, and can be pushed from the terminal
+    fn commit(&mut self, message: &str) -> io::Result<()> {
+        Self::output(self.command().args(["commit", "-q", "-m", message]))?;
+
+        Ok(())
+    }
+
+    fn branches(&mut self) -> io::Result<GitBranches> {
+        let output = Self::output(self.command().args(["branch", "--format=%(refname:short)"]))?;
+        let branches = String::from_utf8_lossy(&output)
+            .lines()
+            .map(str::to_string)
+            .collect();
+
+        // nothing is printed if a commit is checked out rather than a branch
+        let current = Self::output(self.command().args(["branch", "--show-current"]))?;
+        let current = String::from_utf8_lossy(&current).trim().to_string();
+
+        // the default branch is only known if the repository was cloned from a remote
+        let default = Self::output(
+            self.command()
+                .args(["symbolic-ref", "--short", "refs/remotes/origin/HEAD"]),
+        )
+        .ok()
+        .and_then(|output| {
+            let default = String::from_utf8_lossy(&output);
+            Some(default.trim().strip_prefix("origin/")?.to_string())
+        });
+
+        Ok(GitBranches {
+            current: (!current.is_empty()).then_some(current),
+            default,
+            branches,
+        })
+    }
+
+    fn create_branch(&mut self, name: &str) -> io::Result<()> {
+        Self::output(self.command().args(["switch", "-c", name]))?;
+
+        Ok(())
+    }
+
+    // git refuses to switch if it would overwrite changes which haven't been committed
+    fn switch_branch(&mut self, name: &str) -> io::Result<()> {
+        Self::output(self.command().args(["switch", name]))?;
+
+        Ok(())
+    }
+
+    fn history(&mut self, path: &Path) -> io::Result<Vec<CommitInfo>> {
+        // the fields of each commit are separated by the unit separator, and commits by the record separator,
+        // as neither will be in a commit message
+        let output = Self::output(
+            self.command()
+                .args(["log", "--format=%H%x1f%an%x1f%aI%x1f%B%x1e"])
+                .arg(format!("--max-count={}", Self::HISTORY_LENGTH))
+                .arg("--")
+                .arg(path),
+        )?;
+
+        let commits = String::from_utf8_lossy(&output)
+            .split_terminator('\x1e')
+            .filter_map(|commit| {
+                let mut fields = commit.trim_start_matches('\n').splitn(4, '\x1f');
+                Some(CommitInfo {
+                    sha: fields.next()?.to_string(),
+                    author: fields.next()?.to_string(),
+                    date: fields.next()?.to_string(),
+                    message: fields.next()?.trim_end().to_string(),
+                })
+            })
+            .collect();
+
+        Ok(commits)
+    }
+
+    fn file_at(&mut self, sha: &str, path: &Path) -> io::Result<Vec<u8>> {
+        let commit_path = format!("{sha}:{}", Self::commit_path(path));
+
+        Self::output(self.command().args(["show", &commit_path]))
+    }
+
+    fn finished(&mut self) -> Option<io::Result<GitOutput>> {
+        None
+    }
+}
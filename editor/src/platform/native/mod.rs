@@ -1,9 +1,35 @@
+use std::{path::Path, process::Command};
+
 mod filesystem;
+mod git;
 mod pipe_reader;
 mod project;
 mod runner;
+mod watcher;
 
 pub use super::{ProjectSettings, ProjectSettingsError};
 pub use filesystem::FileSystem;
+pub use git::Git;
 pub use project::Project;
 pub use runner::Runner;
+pub use watcher::FileWatcher;
+
+// Opens a file in the program the user's system uses for its type (e.g. their PDF viewer).
+// The file is already on disk, so its contents and mime type aren't needed (unlike on the web)
+pub fn open_in_viewer(path: &Path, _contents: &[u8], _mime: &str) -> eyre::Result<()> {
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        // the empty argument is the title of the window `start` opens
+        command.args(["/C", "start", ""]);
+        command
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut command = Command::new("xdg-open");
+
+    command.arg(path).spawn()?;
+
+    Ok(())
+}
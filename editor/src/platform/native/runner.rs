@@ -1,7 +1,9 @@
 use crossbeam_channel as crossbeam;
 use eyre::OptionExt;
+use eyre::WrapErr as _;
 use eyre::bail;
-use std::path::Path;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
 use std::{
     process::{Child, Stdio},
     sync::{Arc, Mutex},
@@ -28,7 +30,7 @@ struct RunningCommand {
 }
 
 impl Runner {
-    fn execute(&mut self, shell_command: &str, path: &Path) -> eyre::Result<Child> {
+    fn execute(&mut self, shell_command: &str, path: &Path, stdin: Stdio) -> eyre::Result<Child> {
         let mut words = match shell_words::split(shell_command) {
             Ok(words) => words.into_iter(),
             Err(_) => bail!("Invalid command"),
@@ -37,13 +39,14 @@ impl Runner {
 
         let args = words.collect::<Vec<String>>();
 
-        Ok(std::process::Command::new(command)
+        std::process::Command::new(&command)
+            .stdin(stdin)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .current_dir(path)
             .args(args)
             .spawn()
-            .expect("failed to start subprocess"))
+            .wrap_err_with(|| format!("Failed to start `{command}`"))
     }
 }
 
@@ -60,7 +63,7 @@ impl RunnerTrait for Runner {
             .as_ref()
             .ok_or_eyre("No run command set\n\nA project.toml file is needed to set it")?;
 
-        let mut child = self.execute(&settings.run_command, &project.path)?;
+        let mut child = self.execute(&settings.run_command, &project.path, Stdio::inherit())?;
 
         output.lock().expect("failed to lock output").clear();
 
@@ -98,7 +101,7 @@ impl RunnerTrait for Runner {
         Ok(())
     }
 
-    fn format(&mut self, project: &mut Project) -> eyre::Result<()> {
+    fn format(&mut self, project: &mut Project, files: &[PathBuf]) -> eyre::Result<()> {
         // update project settings
         match ProjectSettings::read_from(&project.path) {
             Ok(settings) => project.settings = settings,
@@ -110,15 +113,73 @@ impl RunnerTrait for Runner {
             .as_ref()
             .ok_or_eyre("No format command set\n\nA project.toml file is needed to set it")?;
 
-        if let Some(cmd) = &settings.format_command {
-            let mut child = self.execute(cmd, &project.path)?;
-
-            child.wait().expect("failed to wait for subprocess");
+        for cmd in settings.format_commands(files) {
+            let output = self
+                .execute(&cmd, &project.path, Stdio::null())?
+                .wait_with_output()
+                .wrap_err("Failed to wait for formatter")?;
+
+            // formatters print the reason they failed (e.g. a syntax error) to stderr
+            if !output.status.success() {
+                bail!(
+                    "`{cmd}` failed to format:\n{}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
         }
 
         Ok(())
     }
 
+    fn format_contents(
+        &mut self,
+        project: &mut Project,
+        path: &Path,
+        contents: &str,
+    ) -> eyre::Result<Option<String>> {
+        // update project settings
+        match ProjectSettings::read_from(&project.path) {
+            Ok(settings) => project.settings = settings,
+            Err(err) => Err(err)?,
+        }
+
+        let Some(cmd) = project
+            .settings
+            .as_ref()
+            .and_then(|settings| settings.format_stdin_command(path))
+        else {
+            return Ok(None);
+        };
+
+        let mut child = self.execute(&cmd, &project.path, Stdio::piped())?;
+
+        // the contents are written on another thread, so that the formatter isn't blocked
+        // writing to stdout while we are still writing to its stdin
+        let mut stdin = child.stdin.take().unwrap();
+        let contents = contents.to_string();
+        let writer = thread::spawn(move || stdin.write_all(contents.as_bytes()));
+
+        let output = child
+            .wait_with_output()
+            .wrap_err("Failed to wait for formatter")?;
+        writer
+            .join()
+            .expect("failed to join thread")
+            .wrap_err("Failed to write to formatter")?;
+
+        // formatters print the reason they failed (e.g. a syntax error) to stderr
+        if !output.status.success() {
+            bail!(
+                "`{cmd}` failed to format:\n{}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+
+        let formatted = String::from_utf8(output.stdout).wrap_err("Formatter output isn't valid UTF-8")?;
+
+        Ok(Some(formatted))
+    }
+
     fn update(&mut self) {
         if self
             .running_command
@@ -0,0 +1,52 @@
+use crossbeam_channel::{self as crossbeam, Receiver};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::platform::FileWatcherTrait;
+
+/// Watches a project's directory for changes made to its files outside of the editor
+/// (e.g. by a formatter, or by running `git pull` in the terminal)
+pub struct FileWatcher {
+    // files stop being watched once the watcher is dropped, so it has to be kept
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+}
+
+impl FileWatcher {
+    pub fn new(root: &Path) -> notify::Result<Self> {
+        let (tx, rx) = crossbeam::unbounded();
+
+        // events are sent from the watcher's own thread
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            // the receiver is only dropped along with the watcher, so sending can't fail
+            let _ = tx.send(event);
+        })?;
+        watcher.watch(root, RecursiveMode::Recursive)?;
+
+        Ok(Self {
+            _watcher: watcher,
+            events: rx,
+        })
+    }
+}
+
+impl FileWatcherTrait for FileWatcher {
+    fn changed_files(&mut self) -> io::Result<Vec<PathBuf>> {
+        let mut paths = vec![];
+        for event in self.events.try_iter() {
+            let event = event.map_err(io::Error::other)?;
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                paths.extend(event.paths);
+            }
+        }
+
+        // a single write often causes several events for the same file
+        paths.sort();
+        paths.dedup();
+
+        Ok(paths)
+    }
+}
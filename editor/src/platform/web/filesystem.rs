@@ -1,17 +1,21 @@
 use super::{BackendHandle, PendingOperations, WebSocketHandle};
-use crate::platform::{FileSystemTrait, Project, SearchResult};
+use crate::platform::{FileSystemTrait, Project, ReplaceChange, SearchQuery, SearchResult};
 use std::{
-    collections::HashMap,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
     io::{self, Error, ErrorKind, Result},
     path::{Path, PathBuf},
     vec::IntoIter,
 };
-use ws_messages::{Command, ProjectTree};
+use ws_messages::{Command, ProjectTree, UploadFile};
 
 #[derive(Default, Debug)]
 pub struct FileSystem {
     handle: BackendHandle,
     cached_dirs: HashMap<PathBuf, Vec<PathBuf>>,
+    /// Directories whose contents have been requested from the server, but not received yet
+    /// (so that they are only requested once while the explorer is waiting for them)
+    requested_dirs: RefCell<HashSet<PathBuf>>,
 }
 
 impl FileSystem {
@@ -19,6 +23,7 @@ impl FileSystem {
         Self {
             handle,
             cached_dirs: HashMap::new(),
+            requested_dirs: RefCell::default(),
         }
     }
 
@@ -32,22 +37,85 @@ impl FileSystem {
         }
     }
 
+    // stores the contents of a directory received from the server
+    pub fn cache_dir(&mut self, path: PathBuf, children: Vec<PathBuf>) {
+        self.requested_dirs.get_mut().remove(&path);
+        self.cached_dirs.insert(path, children);
+    }
+
+    // requests the contents of a directory again after they have changed,
+    // and keeps using the cached contents until they are received
+    pub fn refresh_dir(&self, path: &Path) {
+        self.requested_dirs.borrow_mut().insert(path.into());
+        self.handle.send(Command::ReadDir { path: path.into() });
+    }
+
+    // whether the path is listed in the cached contents of its directory
+    pub fn is_cached(&self, path: &Path) -> bool {
+        path.parent()
+            .and_then(|parent| self.cached_dirs.get(parent))
+            .is_some_and(|children| children.iter().any(|child| child == path))
+    }
+
+    // uploads files from the user's computer into the directory (e.g. after they are dropped onto the editor)
+    pub fn upload(&self, dir: &Path, files: Vec<UploadFile>) {
+        self.handle.send(Command::Upload {
+            dir: dir.into(),
+            files,
+        });
+    }
+
     pub fn get_cached(&self, path: &Path) -> Option<ReadDir> {
         self.cached_dirs
             .get(path)
             .map(|p| ReadDir::new(p.clone().into_iter().map(Ok).collect()))
     }
+
+    fn send_replace(
+        &self,
+        query: &SearchQuery,
+        replacement: &str,
+        selected: Option<Vec<ReplaceChange>>,
+    ) {
+        self.handle.send(Command::Replace {
+            pattern: query.pattern.clone(),
+            replacement: replacement.to_string(),
+            regex: query.regex,
+            case_sensitive: query.case_sensitive,
+            whole_word: query.whole_word,
+            glob: query.glob.clone(),
+            selected,
+        });
+    }
 }
 
 impl FileSystemTrait for FileSystem {
     type ReadDir = ReadDir;
 
+    fn new_file(&self, path: &Path) -> Result<()> {
+        self.handle.send(Command::NewFile { path: path.into() });
+
+        Err(ErrorKind::WouldBlock)?
+    }
+
+    fn new_folder(&self, path: &Path) -> Result<()> {
+        self.handle.send(Command::NewFolder { path: path.into() });
+
+        Err(ErrorKind::WouldBlock)?
+    }
+
     fn read_file(&self, path: &Path) -> Result<String> {
         self.handle.send(Command::ReadFile { path: path.into() });
 
         Err(ErrorKind::WouldBlock)?
     }
 
+    fn read_bytes(&self, path: &Path) -> Result<Vec<u8>> {
+        self.handle.send(Command::ReadBytes { path: path.into() });
+
+        Err(ErrorKind::WouldBlock)?
+    }
+
     fn read_dir(&self, path: &Path) -> Result<ReadDir> {
         // info!("reading dir: {}", path.display());
         // info!("looking in cache: {}", self.cached_dirs.keys().map(|p| p.display()).collect::<Vec<_>>().join(", ")););
@@ -55,7 +123,17 @@ impl FileSystemTrait for FileSystem {
             return Ok(read_dir);
         }
 
-        self.handle.send(Command::ReadDir { path: path.into() });
+        if self.requested_dirs.borrow_mut().insert(path.into()) {
+            self.handle.send(Command::ReadDir { path: path.into() });
+        }
+
+        Err(ErrorKind::WouldBlock)?
+    }
+
+    fn list_files(&self, project: &Project) -> Result<Vec<PathBuf>> {
+        self.handle.send(Command::ListFiles {
+            ignore: project.ignored(),
+        });
 
         Err(ErrorKind::WouldBlock)?
     }
@@ -69,6 +147,23 @@ impl FileSystemTrait for FileSystem {
         Err(ErrorKind::WouldBlock)?
     }
 
+    fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        // otherwise the copy would keep copying itself
+        if to.starts_with(from) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "can't copy a directory into itself",
+            ));
+        }
+
+        self.handle.send(Command::Copy {
+            from: from.into(),
+            to: to.into(),
+        });
+
+        Err(ErrorKind::WouldBlock)?
+    }
+
     fn write(&self, path: &Path, contents: &str) -> Result<()> {
         self.handle.send(Command::WriteFile {
             path: path.into(),
@@ -84,12 +179,45 @@ impl FileSystemTrait for FileSystem {
         Err(ErrorKind::WouldBlock)?
     }
 
-    fn search_project(&self, project: &Project, pattern: &str) -> Vec<SearchResult> {
-        todo!()
+    // the search is run on the server, and the results are sent back in batches as they are found
+    fn search_project(&self, _project: &Project, query: &SearchQuery) -> Result<Vec<SearchResult>> {
+        if query.pattern.is_empty() {
+            return Ok(vec![]);
+        }
+
+        self.handle.send(Command::Search {
+            search_id: query.search_id,
+            pattern: query.pattern.clone(),
+            regex: query.regex,
+            case_sensitive: query.case_sensitive,
+            whole_word: query.whole_word,
+            glob: query.glob.clone(),
+        });
+
+        Err(ErrorKind::WouldBlock)?
     }
 
-    fn replace(&self, paths: &[&Path], pattern: &str, replace: &str) -> io::Result<()> {
-        todo!()
+    fn preview_replace(
+        &self,
+        _project: &Project,
+        query: &SearchQuery,
+        replacement: &str,
+    ) -> Result<Vec<ReplaceChange>> {
+        self.send_replace(query, replacement, None);
+
+        Err(ErrorKind::WouldBlock)?
+    }
+
+    fn replace(
+        &self,
+        _project: &Project,
+        query: &SearchQuery,
+        replacement: &str,
+        changes: &[ReplaceChange],
+    ) -> Result<()> {
+        self.send_replace(query, replacement, Some(changes.to_vec()));
+
+        Err(ErrorKind::WouldBlock)?
     }
 }
 
@@ -0,0 +1,296 @@
+use super::{BackendHandle, Task};
+use crate::platform::{GitOutput, GitTrait};
+use gloo_net::http::Request;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    io::{self, ErrorKind, Result},
+    path::{Path, PathBuf},
+};
+use ws_messages::{Command, CommitInfo, GitBranches, GitDiff, GitFileStatus};
+
+#[derive(Serialize)]
+struct CommitRequest<'a> {
+    message: &'a str,
+}
+
+#[derive(Serialize)]
+struct BranchRequest<'a> {
+    name: &'a str,
+}
+
+// body of the server's response when a request fails
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// The reason a request to the server failed
+#[derive(Clone, Debug)]
+struct RequestError {
+    message: String,
+    /// Whether the branch on GitHub has new commits which the project's files need to be merged with first
+    remote_changed: bool,
+}
+
+impl RequestError {
+    // adds what was being done to the start of the message
+    fn context(self, context: &str) -> Self {
+        Self {
+            message: format!("{context}: {}", self.message),
+            ..self
+        }
+    }
+}
+
+impl From<String> for RequestError {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            remote_changed: false,
+        }
+    }
+}
+
+/// Error from saving or committing to GitHub when the branch has new commits since the project was opened
+/// (or last saved), which have to be merged into the project before its changes can be saved
+#[derive(Debug)]
+pub struct RemoteChanged(String);
+
+impl fmt::Display for RemoteChanged {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for RemoteChanged {}
+
+/// Runs git in the project's repository in the container.
+/// The results of each command are received later on (in `App::handle_pending`), and requests which need
+/// the user's GitHub account (e.g. committing) are sent over HTTP, with their results given by `finished`
+#[derive(Default)]
+pub struct Git {
+    handle: BackendHandle,
+    /// Request to the server which is being sent (only one is sent at a time)
+    request: Option<Task<std::result::Result<GitOutput, RequestError>>>,
+    /// URL of the project in the website's API (e.g. `/api/project/user/repo`), which its history is read from
+    project_url: String,
+    /// The branch, tag or commit the project's files are from (`None` for the repo's default branch)
+    git_ref: Option<String>,
+}
+
+impl Git {
+    pub fn new(handle: BackendHandle, username: &str, repo_name: &str, git_ref: Option<String>) -> Self {
+        Self {
+            handle,
+            request: None,
+            project_url: format!("/api/project/{username}/{repo_name}"),
+            git_ref,
+        }
+    }
+
+    // sends a request to the server, failing with the error message from the server if it wasn't successful
+    async fn send(
+        request: std::result::Result<Request, gloo_net::Error>,
+    ) -> std::result::Result<gloo_net::http::Response, RequestError> {
+        let resp = request
+            .map_err(|err| err.to_string())?
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+
+        if resp.ok() {
+            return Ok(resp);
+        }
+        let message = match resp.json::<ErrorResponse>().await {
+            Ok(body) => body.error,
+            Err(_) => resp.status_text(),
+        };
+        Err(RequestError {
+            message,
+            // 409 Conflict
+            remote_changed: resp.status() == 409,
+        })
+    }
+
+    // starts a request to the server, unless one is already being sent
+    // returns the error to give while waiting for its result
+    fn spawn<F>(&mut self, future: F) -> io::Error
+    where
+        F: Future<Output = std::result::Result<GitOutput, RequestError>> + 'static,
+    {
+        if self.request.is_some() {
+            return io::Error::other("Wait for the previous request to finish");
+        }
+        self.request = Some(Task::spawn(future));
+
+        ErrorKind::WouldBlock.into()
+    }
+}
+
+impl Git {
+    // saves every change in the project to the current branch of its GitHub repo, in a single commit
+    pub fn save_to_github(&mut self) -> Result<()> {
+        Err(self.spawn(async {
+            Self::send(Request::post("/api/project/github_save").build())
+                .await
+                .map_err(|err| err.context("Failed to save to GitHub"))?;
+
+            Ok(GitOutput::Saved)
+        }))
+    }
+
+    // merges the commits made on GitHub since the project was opened (or last saved) into the project's files
+    pub fn merge_remote(&mut self) -> Result<()> {
+        Err(self.spawn(async {
+            let resp = Self::send(Request::post("/api/project/merge").build())
+                .await
+                .map_err(|err| err.context("Failed to merge the changes from GitHub"))?;
+            let merge = resp.json().await.map_err(|err| err.to_string())?;
+
+            Ok(GitOutput::Merged(merge))
+        }))
+    }
+}
+
+impl GitTrait for Git {
+    fn status(&self) -> Result<Vec<GitFileStatus>> {
+        self.handle.send(Command::GitStatus);
+
+        Err(ErrorKind::WouldBlock)?
+    }
+
+    fn diff(&self, path: &Path) -> Result<GitDiff> {
+        self.handle.send(Command::GitDiff { path: path.into() });
+
+        Err(ErrorKind::WouldBlock)?
+    }
+
+    fn stage(&self, paths: &[PathBuf]) -> Result<()> {
+        self.handle.send(Command::GitStage {
+            paths: paths.to_vec(),
+        });
+
+        Err(ErrorKind::WouldBlock)?
+    }
+
+    fn unstage(&self, paths: &[PathBuf]) -> Result<()> {
+        self.handle.send(Command::GitUnstage {
+            paths: paths.to_vec(),
+        });
+
+        Err(ErrorKind::WouldBlock)?
+    }
+
+    // commits the staged changes to the project's GitHub repo (through the server, which has the user's GitHub tokens)
+    fn commit(&mut self, message: &str) -> Result<()> {
+        let message = message.to_string();
+        Err(self.spawn(async move {
+            let request = Request::post("/api/project/github_commit").json(&CommitRequest {
+                message: &message,
+            });
+            Self::send(request)
+                .await
+                .map_err(|err| err.context("Failed to commit"))?;
+
+            Ok(GitOutput::Committed)
+        }))
+    }
+
+    fn branches(&mut self) -> Result<GitBranches> {
+        Err(self.spawn(async {
+            let resp = Self::send(Request::get("/api/project/branches").build())
+                .await
+                .map_err(|err| err.context("Failed to list branches"))?;
+            let branches = resp.json().await.map_err(|err| err.to_string())?;
+
+            Ok(GitOutput::Branches(branches))
+        }))
+    }
+
+    fn create_branch(&mut self, name: &str) -> Result<()> {
+        let name = name.to_string();
+        Err(self.spawn(async move {
+            let request =
+                Request::post("/api/project/branches").json(&BranchRequest { name: &name });
+            Self::send(request)
+                .await
+                .map_err(|err| err.context(&format!("Failed to create branch {name}")))?;
+
+            Ok(GitOutput::CreatedBranch(name))
+        }))
+    }
+
+    fn switch_branch(&mut self, name: &str) -> Result<()> {
+        let name = name.to_string();
+        Err(self.spawn(async move {
+            let request =
+                Request::post("/api/project/checkout").json(&BranchRequest { name: &name });
+            Self::send(request)
+                .await
+                .map_err(|err| err.context(&format!("Failed to switch to {name}")))?;
+
+            Ok(GitOutput::SwitchedBranch(name))
+        }))
+    }
+
+    fn history(&mut self, path: &Path) -> Result<Vec<CommitInfo>> {
+        let mut query = vec![("path", path.to_string_lossy().into_owned())];
+        if let Some(git_ref) = &self.git_ref {
+            query.push(("ref", git_ref.clone()));
+        }
+        let request = Request::get(&format!("{}/commits", self.project_url)).query(query);
+
+        Err(self.spawn(async move {
+            let resp = Self::send(request.build())
+                .await
+                .map_err(|err| err.context("Failed to load the file's history"))?;
+            let commits = resp.json().await.map_err(|err| err.to_string())?;
+
+            Ok(GitOutput::History(commits))
+        }))
+    }
+
+    fn file_at(&mut self, sha: &str, path: &Path) -> Result<Vec<u8>> {
+        let request = Request::get(&format!("{}/commits/{sha}/file", self.project_url))
+            .query([("path", path.to_string_lossy())]);
+        let (sha, path) = (sha.to_string(), path.to_path_buf());
+
+        Err(self.spawn(async move {
+            let resp = Self::send(request.build())
+                .await
+                .map_err(|err| err.context(&format!("Failed to read {}", path.display())))?;
+            let contents = resp.binary().await.map_err(|err| err.to_string())?;
+
+            Ok(GitOutput::FileAtCommit {
+                sha,
+                path,
+                contents,
+            })
+        }))
+    }
+
+    fn finished(&mut self) -> Option<Result<GitOutput>> {
+        let result = match self.request.as_ref()?.output()? {
+            Ok(Ok(output)) => {
+                // the history is read from the branch the project is on
+                match output {
+                    GitOutput::Branches(GitBranches {
+                        current: Some(branch),
+                        ..
+                    })
+                    | GitOutput::CreatedBranch(branch)
+                    | GitOutput::SwitchedBranch(branch) => self.git_ref = Some(branch.clone()),
+                    _ => {}
+                }
+                Ok(output.clone())
+            }
+            Ok(Err(err)) if err.remote_changed => Err(io::Error::other(RemoteChanged(err.message.clone()))),
+            Ok(Err(err)) => Err(io::Error::other(err.message.clone())),
+            Err(_) => Err(io::Error::other("Request to the server failed")),
+        };
+        self.request = None;
+
+        Some(result)
+    }
+}
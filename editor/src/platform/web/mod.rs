@@ -21,13 +21,19 @@ use ws_messages::{ClientMessage, Command, Response, ServerMessage};
 use ws_stream_wasm::{WsErr, WsMessage, WsMeta, WsStream};
 
 mod filesystem;
+mod git;
 mod project;
 mod runner;
+mod transfer;
+mod watcher;
 
 pub use super::{ProjectSettings, ProjectSettingsError};
 pub use filesystem::*;
+pub use git::*;
 pub use project::*;
 pub use runner::*;
+pub use transfer::*;
+pub use watcher::*;
 
 pub struct Task<T>(Rc<OnceCell<thread::Result<T>>>);
 
@@ -18,9 +18,18 @@ struct ProjectInfo {
 }
 
 impl Project {
-    pub async fn new(username: String, repo_name: String) -> eyre::Result<Self> {
+    // opens the project at a branch, tag or commit if one is given (otherwise at the repo's default branch)
+    pub async fn new(
+        username: String,
+        repo_name: String,
+        git_ref: Option<String>,
+    ) -> eyre::Result<Self> {
         web_sys::console::log_1(&format!("opening project {username}/{repo_name}").into());
-        let endpoint = format!("/api/project/{username}/{repo_name}/open");
+        let mut endpoint = format!("/api/project/{username}/{repo_name}/open");
+        if let Some(git_ref) = git_ref {
+            let encoded = String::from(js_sys::encode_uri_component(&git_ref));
+            endpoint.push_str(&format!("?ref={encoded}"));
+        }
         let handle = BackendHandle::new(&endpoint)
             .await
             .wrap_err("failed to create websocket")?;
@@ -42,6 +51,14 @@ impl Project {
         &self.handle
     }
 
+    // the globs of files which are skipped when listing the project's files
+    pub fn ignored(&self) -> Vec<String> {
+        self.settings
+            .as_ref()
+            .map(|settings| settings.ignore.clone())
+            .unwrap_or_default()
+    }
+
     pub fn set_settings(&mut self, settings: ProjectSettings) {
         self.settings = Some(settings);
     }
@@ -1,7 +1,10 @@
 use crate::platform::RunnerTrait;
 use super::{BackendHandle, Project, ProjectSettings};
-use std::sync::{Arc, Mutex};
-use ws_messages::{Command, RunAction};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use ws_messages::{Command, FormatInput, RunAction};
 
 #[derive(Default)]
 pub struct Runner {
@@ -22,9 +25,24 @@ impl Runner {
             RunAction::Run => self.handle.send(Command::Run {
                 command: settings.run_command.to_string(),
             }),
-            RunAction::Format => self.handle.send(Command::Format {
-                command: settings.format_command.to_string(),
-            }),
+            RunAction::Format { files } => {
+                for command in settings.format_commands(&files) {
+                    self.handle.send(Command::Format {
+                        command,
+                        input: None,
+                    });
+                }
+            }
+            RunAction::FormatContents { input } => match settings.format_stdin_command(&input.path) {
+                Some(command) => self.handle.send(Command::Format {
+                    command,
+                    input: Some(input),
+                }),
+                // the contents have already been saved, so the file can be formatted instead
+                None => self.run_action(settings, RunAction::Format {
+                    files: vec![input.path],
+                }),
+            },
         }
     }
 
@@ -45,14 +63,34 @@ impl RunnerTrait for Runner {
         Ok(())
     }
 
-    fn format(&mut self, project: &mut Project) -> eyre::Result<()> {
+    fn format(&mut self, _project: &mut Project, files: &[PathBuf]) -> eyre::Result<()> {
         self.handle.send(Command::ReadSettings {
-            action: RunAction::Format,
+            action: RunAction::Format {
+                files: files.to_vec(),
+            },
         });
 
         Ok(())
     }
 
+    fn format_contents(
+        &mut self,
+        _project: &mut Project,
+        path: &Path,
+        contents: &str,
+    ) -> eyre::Result<Option<String>> {
+        self.handle.send(Command::ReadSettings {
+            action: RunAction::FormatContents {
+                input: FormatInput {
+                    path: path.to_path_buf(),
+                    contents: contents.to_string(),
+                },
+            },
+        });
+
+        Ok(None)
+    }
+
     fn update(&mut self) {
         // TODO
     }
@@ -0,0 +1,154 @@
+use super::BackendHandle;
+use eyre::{OptionExt as _, eyre};
+use futures::channel::oneshot;
+use std::path::{Path, PathBuf};
+use wasm_bindgen::{JsCast as _, JsValue, closure::Closure};
+use wasm_bindgen_futures::{JsFuture, spawn_local};
+use web_sys::{Blob, BlobPropertyBag, Document, File, HtmlAnchorElement, HtmlInputElement, Url};
+use ws_messages::{Command, UploadFile};
+
+const DOWNLOAD_ENDPOINT: &str = "/api/project/download";
+/// How long the blob URL of a file opened in a new tab is kept, which is long enough for the tab to load it
+const VIEWER_URL_LIFETIME_MS: i32 = 60_000;
+
+// converts an error thrown by a browser API into a report
+fn js_err(err: JsValue) -> eyre::Report {
+    eyre!("{err:?}")
+}
+
+fn document() -> eyre::Result<Document> {
+    web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_eyre("no document")
+}
+
+// Downloads a file, folder or the whole project (if `path` is `None`) as a tar archive.
+// This clicks a link to the server's download endpoint, so that the browser streams the archive straight to a file
+pub fn download(path: Option<&Path>) -> eyre::Result<()> {
+    let mut url = DOWNLOAD_ENDPOINT.to_string();
+    if let Some(path) = path {
+        let path = String::from(js_sys::encode_uri_component(&path.to_string_lossy()));
+        url.push_str(&format!("?path={path}"));
+    }
+
+    let link: HtmlAnchorElement = document()?
+        .create_element("a")
+        .map_err(js_err)?
+        .unchecked_into();
+    link.set_href(&url);
+    // an empty name lets the server choose the name of the downloaded file
+    link.set_download("");
+    link.click();
+
+    Ok(())
+}
+
+// Lets the user choose files (or a folder, including everything in it) from their computer,
+// then uploads them into the directory `dir` and requests its new contents once they have been read
+pub fn pick_and_upload(handle: &BackendHandle, dir: PathBuf, folder: bool) -> eyre::Result<()> {
+    let input: HtmlInputElement = document()?
+        .create_element("input")
+        .map_err(js_err)?
+        .unchecked_into();
+    input.set_type("file");
+    input.set_multiple(true);
+    if folder {
+        // lets a folder be chosen instead of files (supported by every major browser, despite the prefix)
+        input.set_attribute("webkitdirectory", "").map_err(js_err)?;
+    }
+
+    let (tx, rx) = oneshot::channel();
+    let on_change = Closure::<dyn FnMut()>::once(move || {
+        let _ = tx.send(());
+    });
+    input.set_onchange(Some(on_change.as_ref().unchecked_ref()));
+    // the closure has to live until the user has chosen the files, which never happens if they cancel
+    on_change.forget();
+    input.click();
+
+    let handle = handle.clone();
+    spawn_local(async move {
+        if rx.await.is_err() {
+            return;
+        }
+
+        match read_files(&input, folder).await {
+            Ok(files) if files.is_empty() => {}
+            Ok(files) => {
+                handle.send(Command::Upload {
+                    dir: dir.clone(),
+                    files,
+                });
+                handle.send(Command::ReadDir { path: dir });
+            }
+            Err(err) => log::error!("failed to read files to upload: {err}"),
+        }
+    });
+
+    Ok(())
+}
+
+// Opens a file in a new browser tab, so that it can be shown with the browser's own viewer (e.g. for PDFs).
+// The file only exists in the container, so its contents are given to the browser as a blob.
+// Browsers only allow new tabs to be opened while handling a click, so this has to be called straight away
+// when the button is clicked (eframe runs the app's logic inside the browser's pointer events for this reason)
+pub fn open_in_viewer(_path: &Path, contents: &[u8], mime: &str) -> eyre::Result<()> {
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(contents));
+    let options = BlobPropertyBag::new();
+    options.set_type(mime);
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options).map_err(js_err)?;
+
+    let window = web_sys::window().ok_or_eyre("no window")?;
+    let url = Url::create_object_url_with_blob(&blob).map_err(js_err)?;
+    let opened = window.open_with_url_and_target(&url, "_blank").map_err(js_err)?;
+    if opened.is_none() {
+        let _ = Url::revoke_object_url(&url);
+        return Err(eyre!("the browser blocked the new tab, allow pop-ups for this site to open it"));
+    }
+
+    // the URL keeps the blob in memory, so it is revoked once the tab has had time to load the file
+    let revoke = Closure::<dyn FnMut()>::once(move || {
+        let _ = Url::revoke_object_url(&url);
+    });
+    window
+        .set_timeout_with_callback_and_timeout_and_arguments_0(
+            revoke.as_ref().unchecked_ref(),
+            VIEWER_URL_LIFETIME_MS,
+        )
+        .map_err(js_err)?;
+    // the closure is freed by wasm-bindgen after it has been called once
+    revoke.forget();
+
+    Ok(())
+}
+
+async fn read_files(input: &HtmlInputElement, folder: bool) -> eyre::Result<Vec<UploadFile>> {
+    let Some(list) = input.files() else {
+        return Ok(vec![]);
+    };
+
+    let mut files = vec![];
+    for i in 0..list.length() {
+        let Some(file) = list.get(i) else {
+            continue;
+        };
+
+        let buffer = JsFuture::from(file.array_buffer()).await.map_err(js_err)?;
+        files.push(UploadFile {
+            path: relative_path(&file, folder),
+            contents: js_sys::Uint8Array::new(&buffer).to_vec(),
+        });
+    }
+
+    Ok(files)
+}
+
+// files in a chosen folder keep their path within it (starting with the folder's name)
+fn relative_path(file: &File, folder: bool) -> PathBuf {
+    let relative = folder
+        .then(|| js_sys::Reflect::get(file, &"webkitRelativePath".into()).ok()?.as_string())
+        .flatten()
+        .filter(|path| !path.is_empty());
+
+    relative.unwrap_or_else(|| file.name()).into()
+}
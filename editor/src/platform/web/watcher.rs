@@ -0,0 +1,29 @@
+use super::BackendHandle;
+use crate::platform::FileWatcherTrait;
+use std::{
+    io::{ErrorKind, Result},
+    path::PathBuf,
+};
+use ws_messages::Command;
+
+/// Polls the server for changes made to the project's files outside of the editor
+/// (e.g. by a formatter, or by commands run in the container)
+#[derive(Default)]
+pub struct FileWatcher {
+    handle: BackendHandle,
+}
+
+impl FileWatcher {
+    pub fn new(handle: BackendHandle) -> Self {
+        Self { handle }
+    }
+}
+
+impl FileWatcherTrait for FileWatcher {
+    // the server sends back the files that have changed since it was last polled
+    fn changed_files(&mut self) -> Result<Vec<PathBuf>> {
+        self.handle.send(Command::PollChanges);
+
+        Err(ErrorKind::WouldBlock)?
+    }
+}
@@ -0,0 +1,292 @@
+// The source control side panel, which shows the files changed since the last commit,
+// and lets them be staged, compared with the last commit and committed (and lets the branch be changed)
+
+use std::path::PathBuf;
+
+use egui::{Button, Color32, ComboBox, RichText, ScrollArea, TextEdit, Ui};
+use ws_messages::{GitBranches, GitChange, GitDiff, GitFileStatus};
+
+use crate::diff::{self, DiffLine};
+
+pub enum SourceControlAction {
+    /// Reload the status of the repository
+    Refresh,
+    Stage(Vec<PathBuf>),
+    Unstage(Vec<PathBuf>),
+    /// Commit the staged changes with the message
+    Commit(String),
+    /// Compare the file with its contents at the last commit
+    ShowDiff(PathBuf),
+    OpenFile(PathBuf),
+    SwitchBranch(String),
+    /// Create a branch from the current commit, and switch to it
+    CreateBranch(String),
+}
+
+/// The changes to a file since the last commit, as shown in the diff window
+struct FileDiff {
+    path: PathBuf,
+    /// Lines of the diff, each starting with ` `, `-` or `+`
+    lines: Vec<(char, String)>,
+}
+
+#[derive(Default)]
+pub struct SourceControl {
+    /// Files which have changed since the last commit (paths are relative to the project root)
+    files: Vec<GitFileStatus>,
+    /// Message for the next commit
+    pub message: String,
+    /// Whether a commit is being made (so that another can't be started until it has finished)
+    pub committing: bool,
+    /// Error from reading the status of the repository (e.g. if the project isn't a git repository)
+    error: Option<String>,
+    /// The diff currently shown (`None` if the diff window is closed)
+    diff: Option<FileDiff>,
+    /// Branches of the repository (`None` until they have been loaded)
+    branches: Option<GitBranches>,
+    /// Name entered for a new branch
+    new_branch: String,
+}
+
+impl SourceControl {
+    pub fn set_files(&mut self, files: Vec<GitFileStatus>) {
+        self.files = files;
+        self.error = None;
+    }
+
+    pub fn set_error(&mut self, error: String) {
+        self.files.clear();
+        self.error = Some(error);
+    }
+
+    pub fn set_branches(&mut self, branches: GitBranches) {
+        self.branches = Some(branches);
+    }
+
+    // shows the branch that has been switched to (or created) straight away, before the branches are reloaded
+    pub fn set_current_branch(&mut self, branch: String) {
+        if let Some(branches) = &mut self.branches {
+            if !branches.branches.contains(&branch) {
+                branches.branches.push(branch.clone());
+            }
+            branches.current = Some(branch);
+        }
+    }
+
+    pub fn set_diff(&mut self, path: PathBuf, diff: GitDiff) {
+        let head = diff.head.unwrap_or_default();
+        let working = diff.working.unwrap_or_default();
+        let lines = diff::diff_lines(&head, &working)
+            .into_iter()
+            .map(|line| match line {
+                DiffLine::Same(text) => (' ', text.to_string()),
+                DiffLine::Removed(text) => ('-', text.to_string()),
+                DiffLine::Added(text) => ('+', text.to_string()),
+            })
+            .collect();
+
+        self.diff = Some(FileDiff { path, lines });
+    }
+
+    fn has_staged(&self) -> bool {
+        self.files.iter().any(|file| file.staged.is_some())
+    }
+
+    // shows a list of changed files, with a button to stage or unstage each one
+    fn file_list(
+        ui: &mut Ui,
+        files: &[(&PathBuf, GitChange)],
+        staged: bool,
+        action: &mut Option<SourceControlAction>,
+    ) {
+        for (path, change) in files {
+            ui.horizontal(|ui| {
+                let color = match change {
+                    GitChange::Added => Color32::from_rgb(0x4c, 0xaf, 0x50),
+                    GitChange::Modified => ui.visuals().warn_fg_color,
+                    GitChange::Deleted => ui.visuals().error_fg_color,
+                };
+                ui.label(RichText::new(change.letter().to_string()).monospace().color(color));
+
+                let (button, hover) = if staged {
+                    ("−", "Unstage changes")
+                } else {
+                    ("+", "Stage changes")
+                };
+                if ui.small_button(button).on_hover_text(hover).clicked() {
+                    *action = Some(if staged {
+                        SourceControlAction::Unstage(vec![(*path).clone()])
+                    } else {
+                        SourceControlAction::Stage(vec![(*path).clone()])
+                    });
+                }
+
+                let response = ui
+                    .selectable_label(false, path.to_string_lossy())
+                    .on_hover_text("Click to compare with the last commit, double click to open");
+                if response.double_clicked() && *change != GitChange::Deleted {
+                    *action = Some(SourceControlAction::OpenFile((*path).clone()));
+                } else if response.clicked() {
+                    *action = Some(SourceControlAction::ShowDiff((*path).clone()));
+                }
+            });
+        }
+    }
+
+    // shows the current branch, which can be changed to another branch or a new one
+    fn branch_ui(&mut self, ui: &mut Ui, action: &mut Option<SourceControlAction>) {
+        let Some(branches) = &self.branches else {
+            ui.weak("Loading branches...");
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.label("Branch:");
+            let current = branches.current.as_deref();
+            ComboBox::from_id_salt("source_control_branch")
+                .selected_text(current.unwrap_or("(no branch)"))
+                .show_ui(ui, |ui| {
+                    for branch in &branches.branches {
+                        let mut text = branch.clone();
+                        if branches.default.as_ref() == Some(branch) {
+                            text.push_str(" (default)");
+                        }
+                        if ui.selectable_label(current == Some(branch), text).clicked()
+                            && current != Some(branch)
+                        {
+                            *action = Some(SourceControlAction::SwitchBranch(branch.clone()));
+                        }
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            let name = self.new_branch.trim();
+            let can_create = !name.is_empty() && !branches.branches.iter().any(|b| b == name);
+            let button = ui.add_enabled(can_create, Button::new("Create branch"));
+            ui.add(
+                TextEdit::singleline(&mut self.new_branch)
+                    .hint_text("New branch")
+                    .desired_width(f32::INFINITY),
+            );
+            if button.clicked() {
+                *action = Some(SourceControlAction::CreateBranch(
+                    self.new_branch.trim().to_string(),
+                ));
+                self.new_branch.clear();
+            }
+        });
+    }
+
+    pub fn show(&mut self, ui: &mut Ui) -> Option<SourceControlAction> {
+        let mut action = None;
+
+        ui.horizontal(|ui| {
+            ui.heading("Source control");
+            if ui.small_button("⟳").on_hover_text("Refresh").clicked() {
+                action = Some(SourceControlAction::Refresh);
+            }
+        });
+        ui.separator();
+
+        if let Some(error) = &self.error {
+            ui.label(RichText::new(error).color(ui.visuals().error_fg_color));
+            return action;
+        }
+
+        self.branch_ui(ui, &mut action);
+        ui.separator();
+
+        ui.add(
+            TextEdit::multiline(&mut self.message)
+                .hint_text("Commit message")
+                .desired_rows(3)
+                .desired_width(f32::INFINITY),
+        );
+        let can_commit = !self.committing && self.has_staged() && !self.message.trim().is_empty();
+        let label = if self.committing { "Committing..." } else { "Commit" };
+        if ui
+            .add_enabled(can_commit, Button::new(label).min_size([ui.available_width(), 0.0].into()))
+            .on_disabled_hover_text("Stage some changes and enter a message to commit")
+            .clicked()
+        {
+            action = Some(SourceControlAction::Commit(self.message.trim().to_string()));
+        }
+        ui.separator();
+
+        let staged: Vec<_> = self
+            .files
+            .iter()
+            .filter_map(|file| Some((&file.path, file.staged?)))
+            .collect();
+        let unstaged: Vec<_> = self
+            .files
+            .iter()
+            .filter_map(|file| Some((&file.path, file.unstaged?)))
+            .collect();
+
+        ScrollArea::vertical().auto_shrink(false).show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.strong(format!("Staged changes ({})", staged.len()));
+                if !staged.is_empty() && ui.small_button("Unstage all").clicked() {
+                    let paths = staged.iter().map(|(path, _)| (*path).clone()).collect();
+                    action = Some(SourceControlAction::Unstage(paths));
+                }
+            });
+            Self::file_list(ui, &staged, true, &mut action);
+
+            ui.add_space(ui.spacing().item_spacing.y);
+            ui.horizontal(|ui| {
+                ui.strong(format!("Changes ({})", unstaged.len()));
+                if !unstaged.is_empty() && ui.small_button("Stage all").clicked() {
+                    let paths = unstaged.iter().map(|(path, _)| (*path).clone()).collect();
+                    action = Some(SourceControlAction::Stage(paths));
+                }
+            });
+            Self::file_list(ui, &unstaged, false, &mut action);
+
+            if self.files.is_empty() {
+                ui.weak("No changes since the last commit");
+            }
+        });
+
+        action
+    }
+
+    // shows the window comparing a file with the last commit (if one is open)
+    pub fn show_diff(&mut self, ctx: &egui::Context) {
+        let Some(diff) = &self.diff else {
+            return;
+        };
+
+        let mut open = true;
+        egui::Window::new(format!("Changes to {}", diff.path.display()))
+            .id(egui::Id::new("source_control_diff"))
+            .open(&mut open)
+            .default_size([600.0, 400.0])
+            .show(ctx, |ui| {
+                ui.label("Lines removed since the last commit are marked with -, and added lines with +");
+                let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+                ScrollArea::both().auto_shrink(false).show_rows(
+                    ui,
+                    row_height,
+                    diff.lines.len(),
+                    |ui, rows| {
+                        for (kind, text) in &diff.lines[rows] {
+                            let color = match kind {
+                                '-' => ui.visuals().error_fg_color,
+                                '+' => Color32::from_rgb(0x4c, 0xaf, 0x50),
+                                _ => ui.visuals().weak_text_color(),
+                            };
+                            let line = format!("{kind} {}", text.trim_end_matches(['\r', '\n']));
+                            ui.label(RichText::new(line).monospace().color(color));
+                        }
+                    },
+                );
+            });
+
+        if !open {
+            self.diff = None;
+        }
+    }
+}
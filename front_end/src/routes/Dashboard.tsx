@@ -1,12 +1,14 @@
 import { FontAwesomeIcon } from "@fortawesome/react-fontawesome";
 import ProjectView from "../components/ProjectView";
-import { faPlus, faXmark } from "@fortawesome/free-solid-svg-icons";
+import { faFileImport, faPlus, faXmark } from "@fortawesome/free-solid-svg-icons";
 import { FormEvent, useState } from "react";
 import { fetchApi, useApi } from "../utils";
 import { ProjectInfo } from "../types";
+import ImportModal from "./ImportModal";
 
 function Dashboard() {
     const [showModal, setShowModal] = useState(false);
+    const [showImportModal, setShowImportModal] = useState(false);
 
     async function createProject(e: FormEvent<HTMLFormElement>) {
         e.preventDefault();
@@ -35,11 +37,15 @@ function Dashboard() {
 
     return <>
         <div className="container mx-auto">
-            <div className="flex justify-center items-center">
+            <div className="flex justify-center items-center gap-10">
                 <button onClick={() => setShowModal(true)} className="outline-2 rounded-md text-2xl p-5 my-32 hover:bg-white hover:text-black">
                     <FontAwesomeIcon className="mr-2" icon={faPlus} />
                     Create new project
                 </button>
+                <button onClick={() => setShowImportModal(true)} className="outline-2 rounded-md text-2xl p-5 my-32 hover:bg-white hover:text-black">
+                    <FontAwesomeIcon className="mr-2" icon={faFileImport} />
+                    Import from GitHub
+                </button>
             </div>
             <h2 className="text-4xl mb-5">Your projects</h2>
             <ProjectView projects={projects} error={error} horizontal />
@@ -83,6 +89,7 @@ function Dashboard() {
                 </form>
             </div>
         }
+        {showImportModal && <ImportModal onClose={() => setShowImportModal(false)} />}
     </>;
 }
 
@@ -0,0 +1,156 @@
+import { FontAwesomeIcon } from "@fortawesome/react-fontawesome";
+import { faCircleNotch, faFileImport, faLock, faXmark } from "@fortawesome/free-solid-svg-icons";
+import { FormEvent, useState } from "react";
+import { fetchApi, useApi } from "../utils";
+import { ImportableRepo } from "../types";
+
+const LANGUAGES: [string, string][] = [
+    ["py", "Python"],
+    ["js", "JavaScript"],
+    ["ts", "TypeScript"],
+    ["rs", "Rust"],
+    ["c", "C"],
+    ["cpp", "C++"],
+    ["cs", "C#"],
+    ["sh", "Bash"],
+    ["java", "Java"],
+];
+
+// modal for importing one of the user's existing GitHub repos as a project
+function ImportModal({ onClose }: { onClose: () => void }) {
+    const [repos, error] = useApi<ImportableRepo[]>("/project/import");
+    // the repo chosen to import
+    const [selected, setSelected] = useState<ImportableRepo | undefined>(undefined);
+    // the language of the project, which is worked out from the repo's files if empty
+    const [lang, setLang] = useState("");
+    const [importing, setImporting] = useState(false);
+    const [importError, setImportError] = useState<string | undefined>(undefined);
+    // link to the pull request which adds the project settings, when one was opened
+    const [pullRequest, setPullRequest] = useState<{ url: string, editorUrl: string } | undefined>(undefined);
+
+    function selectRepo(repo: ImportableRepo) {
+        setSelected(repo);
+        setLang(repo.lang ?? "");
+        setImportError(undefined);
+    }
+
+    async function importProject(e: FormEvent<HTMLFormElement>) {
+        e.preventDefault();
+        if (!selected) {
+            return;
+        }
+
+        const projectData = new FormData(e.target as HTMLFormElement);
+        const title = projectData.get("title") as string;
+        const usePullRequest = projectData.get("pullRequest") == "on";
+
+        setImporting(true);
+        setImportError(undefined);
+        const response = await fetchApi("/project/import", {
+            method: "POST",
+            headers: {
+                "Content-Type": "application/json",
+            },
+            body: JSON.stringify({
+                repoName: selected.name,
+                title: title || null,
+                lang: lang || null,
+                pullRequest: usePullRequest,
+            })
+        });
+        setImporting(false);
+
+        if (response.ok) {
+            const { username, repoName, pullRequestUrl } = await response.json();
+            const editorUrl = `/editor/${username}/${repoName}`;
+            // the user is shown the pull request first, as it has to be merged for the project to run
+            if (pullRequestUrl) {
+                setPullRequest({ url: pullRequestUrl, editorUrl });
+            } else {
+                window.location.href = editorUrl;
+            }
+        } else if (response.status == 422) {
+            setImportError("You already have a project with that title or repo");
+        } else {
+            const body = await response.json().catch(() => undefined);
+            setImportError(body?.error ?? "Failed to import the repo");
+        }
+    }
+
+    let repoList;
+    if (error) {
+        repoList = <p className="text-red-400">Failed to load your GitHub repos</p>;
+    } else if (!repos) {
+        repoList = <FontAwesomeIcon icon={faCircleNotch} size="2x" className="animate-spin mx-auto my-5" />;
+    } else if (repos.length == 0) {
+        repoList = <p>You don't have any GitHub repos</p>;
+    } else {
+        repoList = <ul className="overflow-y-auto max-h-72 rounded-lg bg-dark-gray">
+            {repos.map(repo =>
+                <li key={repo.name}>
+                    <button
+                        type="button"
+                        disabled={repo.imported}
+                        onClick={() => selectRepo(repo)}
+                        className={`w-full text-left px-3 py-2 disabled:opacity-50 enabled:hover:bg-white/10 ${selected?.name == repo.name ? "bg-white/20" : ""}`}
+                    >
+                        <span className="text-lg">{repo.name}</span>
+                        {repo.private && <FontAwesomeIcon icon={faLock} className="ml-2 text-sm" />}
+                        {repo.imported && <span className="ml-2 text-sm">(already a project)</span>}
+                        {repo.description && <p className="text-sm opacity-75">{repo.description}</p>}
+                    </button>
+                </li>
+            )}
+        </ul>;
+    }
+
+    return <div className="fixed top-0 left-0 w-full h-full bg-black/45">
+        <form onSubmit={importProject} className="flex flex-col py-8 px-10 rounded-3xl m-10 bg-blue-gray">
+            <button type="button" onClick={onClose} className="absolute right-16 top-16 text-2xl">
+                <FontAwesomeIcon icon={faXmark} className="cursor-pointer" />
+            </button>
+
+            <h2 className="text-3xl mb-3">Import from GitHub</h2>
+
+            {
+                pullRequest ?
+                <>
+                    <p className="text-xl mt-3">
+                        A <a href={pullRequest.url} target="_blank" className="underline">pull request</a> has
+                        been opened to add the project's settings. Merge it so that the project can be run in the editor.
+                    </p>
+                    <a href={pullRequest.editorUrl} className="outline-2 rounded-md text-2xl p-5 mt-10 text-center hover:bg-white hover:text-blue-gray">
+                        Open in the editor
+                    </a>
+                </> :
+                <>
+                    <label className="text-xl mt-3">Repository</label>
+                    {repoList}
+
+                    <label className="text-xl mt-3" htmlFor="title">Project title</label>
+                    <input className="h-10 px-3 rounded-lg bg-dark-gray" type="text" name="title" placeholder={selected?.name} />
+
+                    <label className="text-xl mt-3" htmlFor="lang">Language</label>
+                    <select className="h-10 px-3 rounded-lg bg-dark-gray" name="lang" value={lang} onChange={e => setLang(e.target.value)}>
+                        <option value="">Detect from the repo's files</option>
+                        {LANGUAGES.map(([value, name]) => <option key={value} value={value}>{name}</option>)}
+                    </select>
+
+                    <label className="text-xl mt-3">
+                        <input className="mr-2" type="checkbox" name="pullRequest" />
+                        Add the project's settings with a pull request, instead of committing them straight to the default branch
+                    </label>
+
+                    {importError && <p className="text-red-400 mt-3">{importError}</p>}
+
+                    <button type="submit" disabled={!selected || importing} className="outline-2 rounded-md text-2xl p-5 mt-10 enabled:hover:bg-white enabled:hover:text-blue-gray disabled:opacity-50">
+                        <FontAwesomeIcon className="mr-2" icon={importing ? faCircleNotch : faFileImport} spin={importing} />
+                        {importing ? "Importing..." : "Import project"}
+                    </button>
+                </>
+            }
+        </form>
+    </div>;
+}
+
+export default ImportModal;
@@ -2,7 +2,9 @@ import GithubLogo from '../../assets/github.png';
 
 function GithubButton() {
   const clientId = import.meta.env.VITE_GITHUB_CLIENT_ID;
-  const url = "https://github.com/login/oauth/authorize?client_id=" + clientId;
+  // GitHub Enterprise (or a mock GitHub when testing) can be used by setting its URL
+  const githubUrl = import.meta.env.VITE_GITHUB_URL ?? "https://github.com";
+  const url = githubUrl + "/login/oauth/authorize?client_id=" + clientId;
 
     return (
         <a href={url} className="flex items-center bg-github-gray w-[340px] rounded-xl px-10 py-1">
@@ -35,4 +35,11 @@ export interface ProjectComment {
 export interface Category {
     name: string,
     projects: ProjectInfo[],
-}
\ No newline at end of file
+}
+export interface ImportableRepo {
+    name: string,
+    description: string | null,
+    private: boolean,
+    lang: string | null,
+    imported: boolean,
+}
@@ -3,5 +3,6 @@
 interface ImportMeta {
     readonly env: {
         readonly VITE_GITHUB_CLIENT_ID: string,
+        readonly VITE_GITHUB_URL?: string,
     }
 }
\ No newline at end of file
@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     fmt::Display,
     fs::File,
     path::{Path, PathBuf},
@@ -12,6 +13,9 @@ use uuid::Uuid;
 
 pub use bincode::error::{DecodeError, EncodeError};
 
+#[cfg(feature = "search")]
+pub mod search;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ClientMessage {
     pub id: Uuid,
@@ -39,17 +43,61 @@ impl ClientMessage {
     }
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum RunAction {
     Run,
-    Format,
+    /// Formats the given files, or the whole project if there are none
+    Format { files: Vec<PathBuf> },
+    /// Formats the contents of a buffer with the project's stdin formatter
+    /// (or formats the file instead if there isn't one)
+    FormatContents { input: FormatInput },
+}
+
+/// Contents of a file to pass to a formatter on stdin
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FormatInput {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+/// A file uploaded from the user's computer
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UploadFile {
+    /// Path relative to the directory the file is uploaded into (e.g. `data/scores.csv` when uploading a folder)
+    pub path: PathBuf,
+    pub contents: Vec<u8>,
+}
+
+// the contents aren't printed, as commands are logged when they are sent
+impl std::fmt::Debug for UploadFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UploadFile")
+            .field("path", &self.path)
+            .field("size", &self.contents.len())
+            .finish()
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct EditorSettings {
     pub color_scheme: Option<String>,
     pub auto_save: bool,
     pub format_on_save: bool,
+    pub keymap: Keymap,
+    pub font_family: FontFamily,
+    pub font_size: f32,
+    /// Number of columns a tab takes up, and the number of spaces inserted when indenting with spaces
+    pub tab_width: u32,
+    /// Whether pressing tab inserts spaces rather than a tab character
+    pub insert_spaces: bool,
+    /// Whether long lines are wrapped to fit the width of the editor
+    pub soft_wrap: bool,
+    pub line_numbers: bool,
+    pub highlight_current_line: bool,
+    /// Whether spaces and tabs are shown as faint `·` and `→` characters
+    pub show_whitespace: bool,
+    pub minimap: bool,
 }
 
 impl Default for EditorSettings {
@@ -58,8 +106,69 @@ impl Default for EditorSettings {
             color_scheme: None,
             auto_save: true,
             format_on_save: false,
+            keymap: Keymap::default(),
+            font_family: FontFamily::default(),
+            font_size: 14.0,
+            tab_width: 4,
+            insert_spaces: true,
+            soft_wrap: false,
+            line_numbers: true,
+            highlight_current_line: true,
+            show_whitespace: false,
+            minimap: false,
+        }
+    }
+}
+
+/// Font used to display the contents of buffers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FontFamily {
+    #[default]
+    Monospace,
+    Proportional,
+}
+
+impl FontFamily {
+    pub const ALL: [Self; 2] = [Self::Monospace, Self::Proportional];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Monospace => "Monospace",
+            Self::Proportional => "Proportional",
         }
     }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|family| family.name() == name)
+    }
+}
+
+/// A keyboard shortcut for an editor command
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Keybinding {
+    /// Name of the key that is pressed (as named by egui, e.g. `S` or `F5`)
+    pub key: String,
+    /// Ctrl on Windows and Linux, or Cmd on Mac
+    pub command: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+/// Set of default keybindings, which the user's own keybindings are based on
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeymapPreset {
+    #[default]
+    VsCode,
+    Emacs,
+}
+
+/// The keybindings used for the editor's commands
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keymap {
+    pub preset: KeymapPreset,
+    /// Keybindings the user has changed from the preset, by the id of the command (`None` if it has been unbound)
+    pub overrides: BTreeMap<String, Option<Keybinding>>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -69,13 +178,60 @@ pub enum Command {
     ColorSchemes,
     UpdateSettings { settings: EditorSettings },
     ReadFile { path: PathBuf },
+    /// Reads a file without assuming it is text (e.g. an image)
+    ReadBytes { path: PathBuf },
     ReadDir { path: PathBuf },
+    /// Lists every file in the project (recursively), apart from those ignored by a `.gitignore`
+    /// or matching one of the `ignore` globs from the project settings
+    ListFiles { ignore: Vec<String> },
     Rename { from: PathBuf, to: PathBuf },
     WriteFile { path: PathBuf, contents: String },
+    /// Creates an empty file, failing if the path already exists
+    NewFile { path: PathBuf },
+    /// Creates an empty directory, failing if the path already exists
+    NewFolder { path: PathBuf },
+    /// Copies a file or directory (with everything in it), failing if `to` already exists
+    Copy { from: PathBuf, to: PathBuf },
+    /// Adds files from the user's computer to the directory `dir`, replacing any files with the same paths
+    Upload { dir: PathBuf, files: Vec<UploadFile> },
     Delete { path: PathBuf },
-    Format { command: String },
+    /// Lists the project's files which have changed since the last time this was sent
+    PollChanges,
+    /// Runs a formatter in the project directory.
+    /// If `input` is set, its contents are passed to the formatter on stdin and the formatted contents are sent back
+    Format { command: String, input: Option<FormatInput> },
     Run { command: String },
     StopRunning,
+    Search {
+        /// Identifies the search, so that the editor can ignore results from earlier searches still being received
+        search_id: u32,
+        pattern: String,
+        regex: bool,
+        case_sensitive: bool,
+        whole_word: bool,
+        glob: Option<String>,
+    },
+    /// Replaces matches of the pattern in the project.
+    /// If `selected` is None, nothing is changed and a preview of every match is sent back instead,
+    /// otherwise only the selected matches (from a previous preview) are replaced.
+    Replace {
+        pattern: String,
+        replacement: String,
+        regex: bool,
+        case_sensitive: bool,
+        whole_word: bool,
+        glob: Option<String>,
+        selected: Option<Vec<ReplaceChange>>,
+    },
+    /// Stores the state of the editor for the project (its open tabs, panels etc.), serialized as yaml
+    SaveWorkspace { state: String },
+    /// Gets the state of the editor last stored for the project
+    LoadWorkspace,
+    /// Lists the files changed since the last commit (paths in git commands are relative to the project root)
+    GitStatus,
+    GitDiff { path: PathBuf },
+    GitStage { paths: Vec<PathBuf> },
+    GitUnstage { paths: Vec<PathBuf> },
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -129,6 +285,160 @@ impl ProjectTree {
     }
 }
 
+/// A single match found when searching a project (`line` and `col` start from 1)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SearchMatch {
+    pub path: PathBuf,
+    pub line: usize,
+    pub col: usize,
+    /// The full line of text containing the match
+    pub preview: String,
+}
+
+/// A single match that would be changed by replacing in a project (`line` and `col` start from 1)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplaceChange {
+    pub path: PathBuf,
+    pub line: usize,
+    pub col: usize,
+    /// The line containing the match, before and after only this match is replaced
+    pub before: String,
+    pub after: String,
+}
+
+/// How a file has changed since the last commit
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GitChange {
+    /// A new file (including files which aren't tracked by git yet)
+    Added,
+    Modified,
+    Deleted,
+}
+
+impl GitChange {
+    // reads a status letter from `git status --porcelain`
+    fn from_status(status: char) -> Option<Self> {
+        match status {
+            'A' | '?' => Some(Self::Added),
+            // files with merge conflicts (`U`) are shown as modified
+            'M' | 'T' | 'U' => Some(Self::Modified),
+            'D' => Some(Self::Deleted),
+            _ => None,
+        }
+    }
+
+    /// The letter shown next to a changed file (as in `git status --short`)
+    pub fn letter(self) -> char {
+        match self {
+            Self::Added => 'A',
+            Self::Modified => 'M',
+            Self::Deleted => 'D',
+        }
+    }
+}
+
+/// A file which has changed since the last commit
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitFileStatus {
+    /// Path of the file relative to the root of the repository
+    pub path: PathBuf,
+    /// The change which has been staged to be committed (`None` if nothing is staged)
+    pub staged: Option<GitChange>,
+    /// The change in the working tree which hasn't been staged yet
+    pub unstaged: Option<GitChange>,
+}
+
+impl GitFileStatus {
+    /// Arguments to `git` which list the changed files in the format read by [`GitFileStatus::parse_status`].
+    /// Renames are shown as a deleted and an added file, so that each entry has a single path
+    pub const STATUS_ARGS: [&str; 5] = [
+        "status",
+        "--porcelain=v1",
+        "-z",
+        "--no-renames",
+        "--untracked-files=all",
+    ];
+
+    /// Parses the output of `git status --porcelain=v1 -z`
+    pub fn parse_status(output: &str) -> Vec<Self> {
+        output
+            .split('\0')
+            .filter_map(|entry| {
+                // each entry is two status letters (staged then unstaged), a space and then the path
+                let mut chars = entry.chars();
+                let staged = chars.next()?;
+                let unstaged = chars.next()?;
+                let path = entry.get(3..).filter(|path| !path.is_empty())?;
+
+                // untracked files are only shown as unstaged
+                let staged = if staged == '?' { ' ' } else { staged };
+                Some(Self {
+                    path: PathBuf::from(path),
+                    staged: GitChange::from_status(staged),
+                    unstaged: GitChange::from_status(unstaged),
+                })
+            })
+            .collect()
+    }
+}
+
+/// The branches of a project's repository
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GitBranches {
+    /// The branch the project's files are from (`None` if they are from a tag or commit instead)
+    pub current: Option<String>,
+    /// The repository's default branch (if it is known)
+    pub default: Option<String>,
+    pub branches: Vec<String>,
+}
+
+/// The result of merging the changes made on GitHub into a project (paths are relative to the project root)
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GitMerge {
+    /// Files which were changed on GitHub
+    pub changed: Vec<PathBuf>,
+    /// Files which were changed in the same places on GitHub and in the project, and have conflict markers to resolve
+    pub conflicts: Vec<PathBuf>,
+}
+
+/// A commit in the history of a project's repository
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CommitInfo {
+    pub sha: String,
+    pub message: String,
+    pub author: String,
+    /// When the commit was made, as an RFC 3339 timestamp
+    pub date: String,
+}
+
+/// A file changed by a commit
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CommitFile {
+    pub path: PathBuf,
+    /// How the file was changed (`added`, `modified`, `removed` or `renamed`)
+    pub status: String,
+    pub additions: u32,
+    pub deletions: u32,
+    /// The changes as a unified diff (`None` if the file isn't text, or the diff is too large)
+    pub patch: Option<String>,
+}
+
+/// A commit, with the changes it made to each file
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CommitDetails {
+    pub commit: CommitInfo,
+    pub files: Vec<CommitFile>,
+}
+
+/// A file's contents at the last commit and in the working tree, to show the changes between them
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GitDiff {
+    /// Contents at the last commit (`None` if the file is new)
+    pub head: Option<String>,
+    /// Contents in the working tree (`None` if the file has been deleted)
+    pub working: Option<String>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ColorScheme {
     pub name: String,
@@ -175,8 +485,19 @@ pub enum Response {
     ProjectSettings { contents: String },
     AvailableSchemes { color_schemes: Vec<ColorScheme> },
     FileContents { contents: String },
+    FileBytes { contents: Vec<u8> },
+    Formatted { contents: String },
     DirContents { contents_paths: Vec<PathBuf> },
+    FileList { paths: Vec<PathBuf> },
+    ChangedFiles { paths: Vec<PathBuf> },
     Output { output: String },
+    // search results are sent in batches, and `finished` is set on the final batch
+    SearchResults { matches: Vec<SearchMatch>, finished: bool },
+    ReplacePreview { changes: Vec<ReplaceChange> },
+    // `None` if the state has never been stored for the project
+    Workspace { state: Option<String> },
+    GitFiles { files: Vec<GitFileStatus> },
+    FileDiff { diff: GitDiff },
     Success,
     Error { msg: String },
 }
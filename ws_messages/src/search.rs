@@ -0,0 +1,78 @@
+// Finding and replacing the matches of a project search, shared by the server (which searches for the web editor)
+// and the desktop editor, so that both find and replace the same matches
+use std::collections::HashMap;
+
+use regex::{Regex, RegexBuilder};
+
+// Builds the regex which finds the matches of a search in each line
+pub fn search_regex(
+    pattern: &str,
+    regex: bool,
+    case_sensitive: bool,
+    whole_word: bool,
+) -> Result<Regex, regex::Error> {
+    let mut pattern = if regex {
+        pattern.to_string()
+    } else {
+        regex::escape(pattern)
+    };
+    if whole_word {
+        pattern = format!(r"\b(?:{pattern})\b");
+    }
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!case_sensitive)
+        .build()
+}
+
+// Replaces the matches in a line which start at one of the given columns
+// Replacements are only expanded (e.g. `$1` for the first capture group) when using regex
+pub fn replace_matches(re: &Regex, line: &str, replacement: &str, expand: bool, cols: &[usize]) -> String {
+    let mut result = String::new();
+    let mut last_end = 0;
+
+    for caps in re.captures_iter(line) {
+        let Some(m) = caps.get(0) else {
+            continue;
+        };
+        let col = line[..m.start()].chars().count() + 1;
+        if !cols.contains(&col) {
+            continue;
+        }
+
+        result.push_str(&line[last_end..m.start()]);
+        if expand {
+            caps.expand(replacement, &mut result);
+        } else {
+            result.push_str(replacement);
+        }
+        last_end = m.end();
+    }
+    result.push_str(&line[last_end..]);
+
+    result
+}
+
+// Replaces the selected matches in the contents of a file, given the columns of the matches on each line
+pub fn replace_in_contents(
+    re: &Regex,
+    contents: &str,
+    replacement: &str,
+    expand: bool,
+    lines: &HashMap<usize, Vec<usize>>,
+) -> String {
+    let mut new_contents = String::with_capacity(contents.len());
+
+    for (i, line) in contents.split_inclusive('\n').enumerate() {
+        let Some(cols) = lines.get(&(i + 1)) else {
+            new_contents.push_str(line);
+            continue;
+        };
+        // only replace within the line itself, keeping its line ending
+        let text = line.trim_end_matches(['\n', '\r']);
+        new_contents.push_str(&replace_matches(re, text, replacement, expand, cols));
+        new_contents.push_str(&line[text.len()..]);
+    }
+
+    new_contents
+}